@@ -4,16 +4,33 @@ extern crate limn;
 
 mod util;
 
+use std::any::TypeId;
+
 use limn::prelude::*;
-use limn::widgets::button::ToggleButtonStyle;
+use limn::widgets::button::{ButtonStyle, ToggleButtonStyle};
+use limn::draw::rect::RectStyle;
+use limn::draw::nine_patch::{NinePatch, Insets};
 
 fn main() {
     let window_builder = glutin::WindowBuilder::new()
         .with_title("Limn button demo")
         .with_min_dimensions(100, 100);
     let app = util::init(window_builder);
+    resources().image_loader.load_image("button_skin", include_bytes!("../assets/images/button_skin.svg").to_vec());
     let mut root = Widget::new("root");
 
+    // the skin image corners are kept at their size, the rest stretches to fit the button
+    let skin = NinePatch::new(ImageSource::bundled("button_skin"), Insets::uniform(8.0));
+    {
+        let mut res = resources();
+        res.theme.register_class_style("skinned_button_rect", style!(RectStyle {
+            image: Some(skin.clone()),
+        }));
+        res.theme.register_class_prop_style("skinned_button_rect", PRESSED.clone(), style!(RectStyle {
+            image: Some(skin.fill(false)),
+        }));
+    }
+
     let mut button = ToggleButtonStyle::default();
     button.toggle_text("ON", "OFF");
     let mut button = Widget::from_modifier_style(button);
@@ -21,7 +38,16 @@ fn main() {
         center(&root),
         bound_by(&root).padding(50.0).strength(WEAK),
     ]);
+    button.set_tooltip("Toggles the button");
     root.add_child(button);
 
+    let mut skinned_button = Widget::from_modifier_style(ButtonStyle::from_text("Skinned"));
+    skinned_button.set_style_class(TypeId::of::<RectStyle>(), "skinned_button_rect");
+    skinned_button.layout().add(constraints![
+        center_horizontal(&root),
+        align_bottom(&root).padding(10.0),
+    ]);
+    root.add_child(skinned_button);
+
     app.main_loop(root);
 }
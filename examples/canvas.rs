@@ -0,0 +1,72 @@
+#[allow(unused_imports)]
+#[macro_use]
+extern crate limn;
+
+mod util;
+
+use std::f32;
+
+use limn::prelude::*;
+use limn::widgets::canvas::{CanvasBuilder, Painter, Path, Stroke, LineJoin, LineCap};
+
+fn draw_chart(painter: &mut Painter, size: Size) {
+    let grid = Stroke::new(1.0, GRAY_70).dashes(vec![4.0, 4.0], 0.0);
+    for step in 1..4 {
+        let y = size.height * step as f32 / 4.0;
+        painter.stroke(Path::line(Point::new(0.0, y), Point::new(size.width, y)), grid.clone());
+    }
+    let points: Vec<Point> = (0..101).map(|step| {
+        let fraction = step as f32 / 100.0;
+        let value = (fraction * 4.0 * f32::consts::PI).sin() * (1.0 - fraction * 0.5);
+        Point::new(size.width * fraction, size.height * (0.5 - value * 0.4))
+    }).collect();
+    let mut area = points.clone();
+    area.push(Point::new(size.width, size.height));
+    area.push(Point::new(0.0, size.height));
+    painter.fill(Path::polygon(&area), Color(0x3399FF40));
+    painter.stroke(Path::polyline(&points), Stroke::new(2.0, BLUE).join(LineJoin::Round));
+}
+
+fn draw_gauge(painter: &mut Painter, size: Size) {
+    let center = Point::new(size.width / 2.0, size.height * 0.6);
+    let radius = size.width.min(size.height) * 0.4;
+    let (start, end) = (f32::consts::PI * 0.75, f32::consts::PI * 2.25);
+    let track = Stroke::new(12.0, GRAY_80).cap(LineCap::Round);
+    let value = Stroke::new(12.0, RED).cap(LineCap::Round);
+    painter.stroke(Path::new().arc(center, radius, start, end), track);
+    painter.stroke(Path::new().arc(center, radius, start, start + (end - start) * 0.65), value);
+    let needle = Path::new()
+        .move_to(center + Vector::new(0.0, -4.0))
+        .quadratic_to(center + Vector::new(radius * 0.5, -4.0), center + Vector::new(radius * 0.8, 0.0))
+        .quadratic_to(center + Vector::new(radius * 0.5, 4.0), center + Vector::new(0.0, 4.0))
+        .close();
+    painter.fill(needle, BLACK);
+    painter.fill_and_stroke(Path::circle(center, 8.0), WHITE, Stroke::new(2.0, BLACK));
+}
+
+fn main() {
+    let window_builder = glutin::WindowBuilder::new()
+        .with_title("Limn canvas demo")
+        .with_min_dimensions(100, 100);
+    let app = util::init(window_builder);
+    let mut root = Widget::new("root");
+
+    let mut chart = CanvasBuilder::new(draw_chart);
+    chart.layout().add(constraints![
+        min_size(Size::new(300.0, 200.0)),
+        align_top(&root).padding(20.0),
+        align_left(&root).padding(20.0),
+        align_bottom(&root).padding(20.0),
+    ]);
+    let mut gauge = CanvasBuilder::new(draw_gauge);
+    gauge.layout().add(constraints![
+        size(Size::new(200.0, 200.0)),
+        to_right_of(&chart).padding(20.0),
+        align_right(&root).padding(20.0),
+        center_vertical(&root),
+    ]);
+    root.add_child(chart);
+    root.add_child(gauge);
+
+    app.main_loop(root);
+}
@@ -1,7 +1,6 @@
 #[allow(unused_imports)]
 #[macro_use]
 extern crate limn;
-extern crate euclid;
 
 extern crate chrono;
 
@@ -12,11 +11,10 @@ use std::{thread, time};
 
 use chrono::{Local, Timelike};
 
-use limn::webrender::api::*;
+use limn::webrender::api::PrimitiveInfo;
 use limn::prelude::*;
 use limn::draw::ellipse::EllipseState;
-
-type Radians = euclid::Radians<f32>;
+use limn::widget::transform::Radians;
 
 struct ClockTick;
 
@@ -25,7 +23,6 @@ pub struct ClockHand {
     color: Color,
     width: f32,
     length: f32,
-    rotation: Radians,
 }
 
 impl Default for ClockHand {
@@ -34,7 +31,6 @@ impl Default for ClockHand {
             color: BLACK,
             width: 0.0,
             length: 0.0,
-            rotation: Radians::new(0.0),
         }
     }
 }
@@ -46,49 +42,41 @@ impl Component for ClockHand {
 }
 
 impl ClockHand {
-    pub fn new(color: Color, width: f32, length: f32, rotation: Radians) -> Self {
+    pub fn new(color: Color, width: f32, length: f32) -> Self {
         ClockHand {
             color: color,
             width: width,
             length: length,
-            rotation: rotation,
         }
     }
 }
 
 impl Draw for ClockHand {
+    // the hand is drawn pointing at twelve, and rotated by the widget transform
     fn draw(&mut self, bounds: Rect, _: Rect, renderer: &mut RenderBuilder) {
-        let transform = rotation_transform(&bounds.center(),
-            self.rotation + Radians::new(f32::consts::PI));
-        renderer.builder.push_stacking_context(
-            &PrimitiveInfo::new(Rect::zero()),
-            ScrollPolicy::Fixed,
-            Some(PropertyBinding::Value(transform)),
-            TransformStyle::Flat,
-            None,
-            MixBlendMode::Normal,
-            Vec::new(),
-        );
         let rect = Rect::new(
-            bounds.center() + Size::new(-self.width / 2.0, 0.0),
+            bounds.center() + Size::new(-self.width / 2.0, -self.length),
             Size::new(self.width, self.length)
         );
         renderer.builder.push_rect(
             &PrimitiveInfo::new(rect),
             self.color.into());
-        renderer.builder.pop_stacking_context();
     }
 }
 
-fn rotation_transform(origin: &LayoutPoint, rotation: Radians) -> LayoutTransform {
-    let pre_transform = LayoutTransform::create_translation(origin.x, origin.y, 0.0);
-    let post_transform = LayoutTransform::create_translation(-origin.x, -origin.y, -0.0);
-    let transform = LayoutTransform::identity().pre_rotate(0.0, 0.0, 1.0, -rotation);
-    pre_transform.pre_mul(&transform).pre_mul(&post_transform)
+fn rotation(fraction: f32) -> Transform {
+    Transform::default().rotate(Radians::new(2.0 * f32::consts::PI * fraction))
 }
 
-fn rotation(fraction: f32) -> Radians {
-    Radians::new(2.0 * f32::consts::PI * fraction)
+fn clock_hand<F: Fn() -> Transform + 'static>(name: &str, hand: ClockHand, angle: F) -> Widget {
+    let mut widget = Widget::new(name);
+    widget
+        .set_draw_state(hand)
+        .set_transform(angle())
+        .add_handler(move |_: &ClockTick, mut args: EventArgs| {
+            args.widget.set_transform(angle());
+        });
+    widget
 }
 
 struct ClockBuilder {
@@ -108,24 +96,9 @@ impl ClockBuilder {
         let hour_angle = || rotation((Local::now().hour() % 12) as f32 / 12.0);
         let minute_angle = || rotation(Local::now().minute() as f32 / 60.0);
         let second_angle = || rotation(Local::now().second() as f32 / 60.0);
-        let mut hour_widget = Widget::new("hours");
-        hour_widget
-            .set_draw_state(ClockHand::new(BLACK, 4.0, 60.0, hour_angle()))
-            .add_handler(DrawEventHandler::new(ClockTick, move |state: &mut ClockHand| {
-                state.rotation = hour_angle()
-            }));
-        let mut minute_widget = Widget::new("minutes");
-        minute_widget
-            .set_draw_state(ClockHand::new(BLACK, 3.0, 90.0, minute_angle()))
-            .add_handler(DrawEventHandler::new(ClockTick, move |state: &mut ClockHand| {
-                state.rotation = minute_angle()
-            }));
-        let mut second_widget = Widget::new("seconds");
-        second_widget
-            .set_draw_state(ClockHand::new(RED, 2.0, 80.0, second_angle()))
-            .add_handler(DrawEventHandler::new(ClockTick, move |state: &mut ClockHand| {
-                state.rotation = second_angle()
-            }));
+        let hour_widget = clock_hand("hours", ClockHand::new(BLACK, 4.0, 60.0), hour_angle);
+        let minute_widget = clock_hand("minutes", ClockHand::new(BLACK, 3.0, 90.0), minute_angle);
+        let second_widget = clock_hand("seconds", ClockHand::new(RED, 2.0, 80.0), second_angle);
 
         widget
             .add_child(hour_widget)
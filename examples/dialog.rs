@@ -0,0 +1,88 @@
+#[allow(unused_imports)]
+#[macro_use]
+extern crate limn;
+
+mod util;
+
+use limn::prelude::*;
+
+use limn::widgets::button::ButtonStyle;
+use limn::widgets::edit_text::{EditText, TextUpdated};
+use limn::widgets::modal::ModalResult;
+use limn::widgets::text::StaticTextStyle;
+use limn::draw::rect::RectStyle;
+
+fn confirm_dialog() -> Widget {
+    let mut dialog = Widget::new("dialog");
+    dialog.set_draw_style(style!(RectStyle {
+        background_color: WHITE,
+        corner_radius: Some(5.0),
+    }));
+    let mut layout_settings = LinearLayoutSettings::new(Orientation::Vertical);
+    layout_settings.spacing = Spacing::Around;
+    dialog.linear_layout(layout_settings);
+    dialog.layout().add(min_size(Size::new(300.0, 200.0)));
+
+    let text = Widget::from_modifier_style(StaticTextStyle::from_text("Delete item?"));
+    let mut reason = Widget::from_modifier(EditText::default());
+    reason.layout().add(min_size(Size::new(250.0, 35.0)));
+
+    let mut buttons = Widget::new("buttons");
+    let mut layout_settings = LinearLayoutSettings::new(Orientation::Horizontal);
+    layout_settings.spacing = Spacing::Around;
+    buttons.linear_layout(layout_settings);
+    let mut ok_button = Widget::from_modifier_style(ButtonStyle::from_text("Delete"));
+    ok_button.add_handler(|_: &ClickEvent, args: EventArgs| {
+        args.widget.event_bubble_up(ModalResult::<()>::Ok);
+    });
+    let mut cancel_button = Widget::from_modifier_style(ButtonStyle::from_text("Cancel"));
+    cancel_button.add_handler(|_: &ClickEvent, args: EventArgs| {
+        args.widget.event_bubble_up(ModalResult::<()>::Cancel);
+    });
+    buttons
+        .add_child(ok_button)
+        .add_child(cancel_button);
+
+    dialog
+        .add_child(text)
+        .add_child(reason)
+        .add_child(buttons);
+    dialog
+}
+
+fn main() {
+    let window_builder = glutin::WindowBuilder::new()
+        .with_title("Limn dialog demo")
+        .with_min_dimensions(100, 100);
+    let app = util::init(window_builder);
+
+    let mut root = Widget::new("root");
+    root.layout().add(min_size(Size::new(500.0, 400.0)));
+    let mut layout_settings = LinearLayoutSettings::new(Orientation::Vertical);
+    layout_settings.spacing = Spacing::Around;
+    root.linear_layout(layout_settings);
+
+    let mut status = Widget::from_modifier_style(StaticTextStyle::from_text("Item present"));
+    status.layout().add(center_horizontal(&root));
+
+    let mut button = Widget::from_modifier_style(ButtonStyle::from_text("Delete item"));
+    button.layout().add(center_horizontal(&root));
+    let status_ref = status.clone();
+    button
+        .add_handler(|_: &ClickEvent, args: EventArgs| {
+            args.widget.open_modal::<()>(confirm_dialog());
+        })
+        .add_handler(move |event: &ModalResult<()>, _: EventArgs| {
+            let text = match *event {
+                ModalResult::Ok => "Item deleted",
+                _ => "Delete cancelled",
+            };
+            status_ref.event(TextUpdated(text.to_owned()));
+        });
+
+    root
+        .add_child(status)
+        .add_child(button);
+
+    app.main_loop(root);
+}
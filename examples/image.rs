@@ -5,7 +5,23 @@ extern crate limn;
 mod util;
 
 use limn::prelude::*;
-use limn::widgets::image::Image;
+use limn::widgets::image::{Image, AnimationEvent};
+use limn::draw::image::{ImageStyle, ImageFit, ImageRotation};
+use limn::resources::pixels::{RawPixels, PixelFormat};
+
+const GRADIENT_SIZE: u32 = 64;
+
+/// A gradient of straight alpha RGBA pixels, shifted by `offset` pixels.
+fn gradient(width: u32, height: u32, offset: u32) -> RawPixels {
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let value = (((x + y + offset) * 255) / (width + height)) as u8;
+            data.extend_from_slice(&[value, 128, 255 - value, 255]);
+        }
+    }
+    RawPixels::new(data, width, height, PixelFormat::Rgba8)
+}
 
 fn main() {
     let window_builder = glutin::WindowBuilder::new()
@@ -13,15 +29,74 @@ fn main() {
         .with_min_dimensions(100, 100);
     let app = util::init(window_builder);
     resources().image_loader.load_image("rust", include_bytes!("../assets/images/rust.png").to_vec());
+    resources().image_loader.load_image("clock", include_bytes!("../assets/images/clock.svg").to_vec());
+    resources().image_loader.load_image("spinner", include_bytes!("../assets/images/spinner.gif").to_vec());
+    resources().image_loader.create_raw_image("gradient", gradient(GRADIENT_SIZE, GRADIENT_SIZE, 0)).unwrap();
+
+    resources().theme.register_class_style("icon", style!(ImageStyle {
+        tint: Some(BLUE_HIGHLIGHT),
+    }));
 
     let mut root = Widget::new("root");
 
-    let mut image_widget = Widget::from_modifier(Image::new(ImageSource::bundled("rust")));
+    // the image shrinks with the window, keeping it's aspect ratio
+    let image = Image::new(ImageSource::bundled("rust")).style(style!(ImageStyle {
+        fit: ImageFit::Contain,
+    }));
+    let mut image_widget = Widget::from_modifier(image);
     image_widget.layout().add(constraints![
         center(&root),
         bound_by(&root).padding(50.0),
     ]);
+    // svg images are rasterized at the size they're drawn at, the icon class tints the image
+    let mut icon_widget = Widget::from_modifier(Image::new(ImageSource::bundled("clock")).style_class("icon"));
+    icon_widget.layout().add(constraints![
+        align_top(&root).padding(10.0),
+        align_left(&root).padding(10.0),
+    ]);
+    // animated images play when created, click the spinner to pause and resume it
+    let mut spinner_widget = Widget::from_modifier(Image::new(ImageSource::bundled("spinner")));
+    spinner_widget.layout().add(constraints![
+        align_top(&root).padding(10.0),
+        align_right(&root).padding(10.0),
+    ]);
+    let mut playing = true;
+    spinner_widget.add_handler(move |_: &ClickEvent, args: EventArgs| {
+        playing = !playing;
+        args.widget.event(if playing { AnimationEvent::Play } else { AnimationEvent::Pause });
+    });
+    // images loaded from a file are decoded on a worker thread, a placeholder is shown until they're ready
+    let file_image = Image::new(ImageSource::absolute(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/images/rust.png")))
+        .placeholder_size(Size::new(100.0, 100.0));
+    let mut file_image_widget = Widget::from_modifier(file_image);
+    file_image_widget.layout().add(constraints![
+        align_bottom(&root).padding(10.0),
+        align_left(&root).padding(10.0),
+    ]);
+    // images can be created from pixels generated at runtime, click the gradient to update a stripe of it
+    let gradient_image = Image::new(ImageSource::raw("gradient")).style(style!(ImageStyle {
+        rotation: ImageRotation::Clockwise90,
+        flip_horizontal: true,
+    }));
+    let mut gradient_widget = Widget::from_modifier(gradient_image);
+    gradient_widget.layout().add(constraints![
+        align_bottom(&root).padding(10.0),
+        align_right(&root).padding(10.0),
+    ]);
+    let mut row = 0;
+    let mut offset = 0;
+    gradient_widget.add_handler(move |_: &ClickEvent, args: EventArgs| {
+        offset += 16;
+        let stripe = gradient(GRADIENT_SIZE, 8, offset);
+        resources().image_loader.update_raw_image_rect("gradient", 0, row, stripe).unwrap();
+        row = (row + 8) % GRADIENT_SIZE;
+        args.ui.redraw();
+    });
     root.add_child(image_widget);
+    root.add_child(file_image_widget);
+    root.add_child(icon_widget);
+    root.add_child(spinner_widget);
+    root.add_child(gradient_widget);
 
     app.main_loop(root);
 }
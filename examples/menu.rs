@@ -0,0 +1,94 @@
+#[allow(unused_imports)]
+#[macro_use]
+extern crate limn;
+
+mod util;
+
+use std::collections::HashMap;
+
+use limn::prelude::*;
+
+use limn::widgets::edit_text::TextUpdated;
+use limn::widgets::menu::*;
+use limn::input::shortcut::{KeyChord, ShortcutScope};
+use limn::widgets::text::StaticTextStyle;
+use limn::draw::rect::RectStyle;
+
+fn main() {
+    let window_builder = glutin::WindowBuilder::new()
+        .with_title("Limn menu demo")
+        .with_min_dimensions(100, 100);
+    let mut app = util::init(window_builder);
+
+    let shortcuts = [
+        ("new", glutin::VirtualKeyCode::N),
+        ("open", glutin::VirtualKeyCode::O),
+        ("save", glutin::VirtualKeyCode::S),
+    ];
+    let mut accelerators = HashMap::new();
+    for &(command, key) in shortcuts.iter() {
+        let chord = KeyChord::new(key).ctrl();
+        accelerators.insert(command, chord.to_string());
+        let menu_command = MenuCommand {
+            command: command.to_owned(),
+            checked: None,
+        };
+        app.shortcuts().bind(chord, ShortcutScope::Global, command, menu_command).unwrap();
+    }
+
+    let mut root = Widget::new("root");
+    root.layout().add(min_size(Size::new(500.0, 400.0)));
+    let mut layout_settings = LinearLayoutSettings::new(Orientation::Vertical);
+    layout_settings.item_align = ItemAlignment::Fill;
+    root.linear_layout(layout_settings);
+
+    let mut menu_bar = Widget::from_modifier_style(MenuBarStyle::from_entries(vec![
+        MenuEntry::submenu("File", vec![
+            MenuItem::new("New", "new").accelerator(&accelerators["new"]).into(),
+            MenuItem::new("Open", "open").accelerator(&accelerators["open"]).into(),
+            MenuEntry::submenu("Open Recent", vec![
+                MenuEntry::item("notes.txt", "open_recent_notes"),
+                MenuEntry::item("todo.txt", "open_recent_todo"),
+            ]),
+            MenuItem::new("Save", "save").accelerator(&accelerators["save"]).enabled(false).into(),
+            MenuEntry::Separator,
+            MenuEntry::item("Quit", "quit"),
+        ]),
+        MenuEntry::submenu("View", vec![
+            MenuItem::new("Show Toolbar", "toolbar").checkable(true).into(),
+            MenuItem::new("Show Status Bar", "status_bar").checkable(false).into(),
+        ]),
+    ]));
+
+    let mut content = Widget::new("content");
+    content.set_draw_style(RectStyle::default());
+    content.layout().add(align_bottom(&root));
+    let mut status = Widget::from_modifier_style(StaticTextStyle::from_text("Right click for a context menu"));
+    status.layout().add(center(&content));
+    content.add_child(status.clone());
+    content.set_context_menu(vec![
+        MenuItem::new("Cut", "cut").accelerator("Ctrl+X").into(),
+        MenuItem::new("Copy", "copy").accelerator("Ctrl+C").into(),
+        MenuItem::new("Paste", "paste").accelerator("Ctrl+V").into(),
+    ]);
+
+    // shortcut commands are sent to the root of the ui, menu commands bubble up to it
+    let status_ref = status.clone();
+    app.add_handler(move |event: &MenuCommand, args: EventArgs| {
+        if event.command == "quit" {
+            args.ui.close();
+            return;
+        }
+        let text = match event.checked {
+            Some(checked) => format!("{}: {}", event.command, checked),
+            None => event.command.clone(),
+        };
+        status_ref.event(TextUpdated(text));
+    });
+
+    root
+        .add_child(menu_bar)
+        .add_child(content);
+
+    app.main_loop(root);
+}
@@ -0,0 +1,70 @@
+#[allow(unused_imports)]
+#[macro_use]
+extern crate limn;
+
+mod util;
+
+use limn::prelude::*;
+
+use limn::widgets::text::{RichText, RichTextUpdated};
+use limn::draw::text::{AttributedText, TextSpan};
+
+/// A message, with each occurrence of `search` highlighted.
+fn highlight(message: &str, search: &str) -> AttributedText {
+    let mut text = AttributedText::new();
+    for (index, part) in message.split(search).enumerate() {
+        if index > 0 {
+            text.push(TextSpan::new(search).bold().background(YELLOW));
+        }
+        text.push(TextSpan::new(part));
+    }
+    text
+}
+
+fn main() {
+    let window_builder = glutin::WindowBuilder::new()
+        .with_title("Limn rich text demo")
+        .with_min_dimensions(100, 100);
+    let app = util::init(window_builder);
+    let mut root = Widget::new("root");
+    root.layout().add(min_size(Size::new(400.0, 300.0)));
+
+    let label = AttributedText::new()
+        .span(TextSpan::new("Rich text can be "))
+        .span(TextSpan::new("bold").bold())
+        .span(TextSpan::new(", "))
+        .span(TextSpan::new("italic").italic())
+        .span(TextSpan::new(", "))
+        .span(TextSpan::new("large").font_size(40.0))
+        .span(TextSpan::new(", "))
+        .span(TextSpan::new("colored").color(RED))
+        .span(TextSpan::new(" or "))
+        .span(TextSpan::new("underlined").underline())
+        .span(TextSpan::new(", and wraps across spans."));
+    let mut label_widget = Widget::from_modifier(RichText::new(label));
+    label_widget.layout().add(constraints![
+        align_top(&root).padding(20.0),
+        align_left(&root).padding(20.0),
+        align_right(&root).padding(20.0),
+    ]);
+
+    // click the message to highlight the next word
+    let message = "the quick brown fox jumps over the lazy dog";
+    let mut message_widget = Widget::from_modifier(RichText::new(highlight(message, "the")));
+    message_widget.layout().add(constraints![
+        below(&label_widget).padding(20.0),
+        align_left(&root).padding(20.0),
+        align_right(&root).padding(20.0),
+    ]);
+    let words: Vec<&str> = message.split(' ').collect();
+    let mut word = 0;
+    message_widget.add_handler(move |_: &ClickEvent, args: EventArgs| {
+        word = (word + 1) % words.len();
+        args.widget.event(RichTextUpdated(highlight(message, words[word])));
+        args.ui.redraw();
+    });
+
+    root.add_child(label_widget);
+    root.add_child(message_widget);
+    app.main_loop(root);
+}
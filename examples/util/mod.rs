@@ -5,17 +5,22 @@ use limn::input::{EscKeyCloseHandler, DebugSettingsHandler};
 use limn::resources;
 use limn::resources::font::FontDescriptor;
 use limn::draw::rect::RectStyle;
-use limn::draw::text::TextStyle;
+use limn::draw::text::{TextStyle, RichTextStyle};
 use limn::draw::ellipse::EllipseStyle;
+use limn::draw::image::ImageStyle;
 use limn::widgets::slider::*;
 
 pub fn default_style() {
     let mut res = resources::resources();
 
     res.font_loader.register_font_data(FontDescriptor::from_family("NotoSans"), include_bytes!("../../assets/fonts/NotoSans/NotoSans-Regular.ttf").to_vec()).unwrap();
+    res.font_loader.register_font_data(FontDescriptor { bold: true, ..FontDescriptor::from_family("NotoSans") }, include_bytes!("../../assets/fonts/NotoSans/NotoSans-Bold.ttf").to_vec()).unwrap();
+    res.font_loader.register_font_data(FontDescriptor { italic: true, ..FontDescriptor::from_family("NotoSans") }, include_bytes!("../../assets/fonts/NotoSans/NotoSans-Italic.ttf").to_vec()).unwrap();
+    res.font_loader.register_font_data(FontDescriptor { bold: true, italic: true, ..FontDescriptor::from_family("NotoSans") }, include_bytes!("../../assets/fonts/NotoSans/NotoSans-BoldItalic.ttf").to_vec()).unwrap();
 
     res.theme.register_type_style(EllipseStyle::default());
     res.theme.register_type_style(RectStyle::default());
+    res.theme.register_type_style(ImageStyle::default());
     res.theme.register_type_style(style!(TextStyle {
         font: FontDescriptor::from_family("NotoSans"),
         font_size: 24.0,
@@ -24,6 +29,11 @@ pub fn default_style() {
         wrap: Wrap::Whitespace,
         align: Align::Start,
     }));
+    res.theme.register_type_style(style!(RichTextStyle {
+        font: FontDescriptor::from_family("NotoSans"),
+        font_size: 24.0,
+        text_color: BLACK,
+    }));
     res.theme.register_class_prop_style("static_text", INACTIVE.clone(), style!(TextStyle {
         text_color: GRAY_50,
     }));
@@ -60,6 +70,13 @@ pub fn default_style() {
     res.theme.register_class_prop_style("button_rect", MOUSEOVER.clone(), style!(RectStyle {
         background_color: GRAY_90,
     }));
+    res.theme.register_class_style("image_loading", style!(RectStyle {
+        background_color: GRAY_90,
+    }));
+    res.theme.register_class_style("image_error", style!(RectStyle {
+        background_color: GRAY_90,
+        border: Some((1.0, RED)),
+    }));
     res.theme.register_modifier_class_style("scrollbar_slider", style!(SliderStyle {
         variable_handle_size: true,
         handle_style: HandleStyle::Square,
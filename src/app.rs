@@ -9,6 +9,7 @@ use glutin;
 use window::Window;
 use ui::Ui;
 use input::InputEvent;
+use input::shortcut::ShortcutRegistry;
 use widget::Widget;
 use event::{self, EventHandler};
 use geometry::Size;
@@ -39,7 +40,8 @@ impl App {
     /// desktop app:
     ///
     /// `ui_handlers`, `layout_handlers`, `input_handlers`,
-    /// `mouse_handlers`, `keyboard_handlers` and `drag_handlers`
+    /// `mouse_handlers`, `keyboard_handlers`, `drag_handlers`, `touch_handlers`, `modal_handlers` and
+    /// `tooltip_handlers`
     pub fn new(window: Window, events_loop: glutin::EventsLoop) -> Self {
         event::queue_set_events_loop(&events_loop);
         let ui = Ui::new(window, &events_loop);
@@ -63,6 +65,10 @@ impl App {
         self.add_mouse_handlers();
         self.add_keyboard_handlers();
         self.add_drag_handlers();
+        self.add_touch_handlers();
+        self.add_modal_handlers();
+        self.add_tooltip_handlers();
+        self.add_image_handlers();
     }
 
     fn handle_window_event(&mut self, event: glutin::Event) {
@@ -141,6 +147,11 @@ impl App {
     pub fn window(&self) -> ::std::cell::Ref<Window> {
         self.ui.window.borrow()
     }
+
+    /// The registry of keyboard shortcuts, to bind shortcuts before the main loop starts.
+    pub fn shortcuts(&mut self) -> &mut ShortcutRegistry {
+        &mut self.ui.shortcuts
+    }
 }
 
 /// Event emitted after every frame is rendered.
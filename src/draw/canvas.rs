@@ -0,0 +1,45 @@
+use std::fmt;
+use std::rc::Rc;
+
+use render::RenderBuilder;
+use widget::draw::Draw;
+use geometry::{Rect, Size};
+use style::Component;
+use draw::path::{Painter, PathImage};
+
+/// Draw state for a canvas, drawn by a callback that builds the paths to draw whenever the
+/// UI is redrawn. The rasterized paths are reused if the callback draws the same paths again.
+#[derive(Clone)]
+pub struct CanvasState {
+    callback: Rc<Fn(&mut Painter, Size)>,
+    image: PathImage,
+}
+
+impl CanvasState {
+    pub fn new<F: Fn(&mut Painter, Size) + 'static>(callback: F) -> Self {
+        CanvasState {
+            callback: Rc::new(callback),
+            image: PathImage::default(),
+        }
+    }
+}
+
+impl Component for CanvasState {
+    fn name() -> String {
+        String::from("canvas")
+    }
+}
+
+impl fmt::Debug for CanvasState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CanvasState")
+    }
+}
+
+impl Draw for CanvasState {
+    fn draw(&mut self, bounds: Rect, _: Rect, renderer: &mut RenderBuilder) {
+        let mut painter = Painter::new();
+        (self.callback)(&mut painter, bounds.size);
+        self.image.draw(&painter.into_paths(), bounds, renderer);
+    }
+}
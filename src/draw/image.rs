@@ -1,21 +1,126 @@
+use std::f32;
+
 use webrender::api::*;
 
 use render::RenderBuilder;
 use widget::draw::Draw;
 use resources::resources;
-use resources::image::ImageSource;
-use geometry::{Rect, Size};
-use style::Component;
+use resources::image::{self, ImageSource, OwnedImage};
+use resources::animation::Animation;
+use resources::cache::ResourceHandle;
+use geometry::{Rect, RectExt, Point, Size};
+use widget::transform::Radians;
+use color::Color;
+
+/// How an image is sized to fit the bounds it's drawn in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ImageFit {
+    /// Stretched to the bounds, ignoring it's aspect ratio
+    Fill,
+    /// Scaled to fit inside the bounds, keeping it's aspect ratio
+    Contain,
+    /// Scaled to cover the bounds, keeping it's aspect ratio, cropped to the bounds
+    Cover,
+    /// Drawn at it's own size, cropped to the bounds
+    None,
+    /// Drawn at it's own size, or scaled down like `Contain` if it doesn't fit
+    ScaleDown,
+    /// Repeated at it's own size to fill the bounds
+    Tile,
+}
+
+impl ImageFit {
+    /// The size an image of the given size is drawn at, in bounds of the given size.
+    fn fit(&self, image: Size, bounds: Size) -> Size {
+        if image.width <= 0.0 || image.height <= 0.0 {
+            return image;
+        }
+        let contain = (bounds.width / image.width).min(bounds.height / image.height);
+        let cover = (bounds.width / image.width).max(bounds.height / image.height);
+        match *self {
+            ImageFit::Fill | ImageFit::Tile => bounds,
+            ImageFit::Contain => image * contain,
+            ImageFit::Cover => image * cover,
+            ImageFit::None => image,
+            ImageFit::ScaleDown => image * contain.min(1.0),
+        }
+    }
+}
 
-#[derive(Clone, Debug)]
-pub struct ImageState {
-    pub image: ImageSource,
-    pub scale: Size,
+/// Clockwise rotation of an image by quarter turns, the widget `Transform` can rotate by any angle.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ImageRotation {
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
 }
 
-impl Component for ImageState {
-    fn name() -> String {
-        String::from("image")
+impl ImageRotation {
+    /// True if the image's width and height are swapped when it's rotated.
+    fn is_quarter_turn(&self) -> bool {
+        *self == ImageRotation::Clockwise90 || *self == ImageRotation::Clockwise270
+    }
+    fn angle(&self) -> f32 {
+        match *self {
+            ImageRotation::None => 0.0,
+            ImageRotation::Clockwise90 => f32::consts::FRAC_PI_2,
+            ImageRotation::Clockwise180 => f32::consts::PI,
+            ImageRotation::Clockwise270 => 3.0 * f32::consts::FRAC_PI_2,
+        }
+    }
+}
+
+component_style!{pub struct ImageState<name="image", style=ImageStyle> {
+    image: ImageSource = ImageSource::bundled(""),
+    fit: ImageFit = ImageFit::Fill,
+    /// Where the image is placed if it's smaller or larger than the bounds, where (0.0, 0.0)
+    /// aligns the top left corners of the image and bounds, and (1.0, 1.0) the bottom right
+    align: (f32, f32) = (0.5, 0.5),
+    /// Applied before fitting, so a rotated image fits the bounds
+    rotation: ImageRotation = ImageRotation::None,
+    flip_horizontal: bool = false,
+    flip_vertical: bool = false,
+    /// Replaces the color of every pixel, keeping it's alpha, to color monochrome icons
+    tint: Option<Color> = None,
+} state {
+    /// Keeps the image drawn last from being evicted from the cache, set when it's drawn
+    handle: Option<ResourceHandle> = None,
+    /// The frame shown if the image is animated, set by the image widget while it plays
+    frame: usize = 0,
+    frame_image: FrameImage = FrameImage::default(),
+}}
+
+/// The frame of an animated image last drawn, the image resource is updated when the frame changes.
+#[derive(Debug, Clone, Default)]
+pub struct FrameImage {
+    image: OwnedImage,
+    uploaded: Option<(usize, Option<Color>)>,
+}
+
+impl FrameImage {
+    fn update(&mut self, animation: &Animation, frame: usize, tint: Option<Color>) -> ImageKey {
+        let frame = frame.min(animation.frames.len() - 1);
+        if self.uploaded != Some((frame, tint)) || self.image.info().is_none() {
+            let pixels = &animation.frames[frame].pixels;
+            let pixels = match tint {
+                Some(tint) => image::tint_pixels(pixels, tint),
+                None => pixels.clone(),
+            };
+            let descriptor = ImageDescriptor::new(animation.width, animation.height, ImageFormat::BGRA8, false);
+            self.image.update(&mut resources().image_loader, ImageData::new(pixels), descriptor);
+            self.uploaded = Some((frame, tint));
+        }
+        self.image.info().unwrap().key
+    }
+}
+
+impl ImageStyle {
+    pub fn from_source(source: ImageSource) -> Self {
+        ImageStyle {
+            image: Some(source),
+            ..ImageStyle::default()
+        }
     }
 }
 
@@ -23,28 +128,119 @@ impl ImageState {
     pub fn new(source: ImageSource) -> Self {
         ImageState {
             image: source,
-            scale: Size::new(1.0, 1.0),
+            ..ImageState::default()
         }
     }
-    pub fn measure(&self) -> Size {
-        let descriptor = resources().image_loader.get_image(&self.image).unwrap().descriptor;
-        Size::new(descriptor.width as f32, descriptor.height as f32)
+    /// The size of the image once it's rotated, `None` if it can't be loaded.
+    pub fn measure(&self) -> Option<Size> {
+        let size = match resources().image_loader.get_image_size(&self.image) {
+            Ok(size) => size,
+            Err(_) => return None,
+        };
+        if self.rotation.is_quarter_turn() {
+            Some(Size::new(size.height, size.width))
+        } else {
+            Some(size)
+        }
     }
-    pub fn scale(&mut self, scale: Size) {
-        self.scale = scale;
+    /// Rotates and flips the image about it's center.
+    fn orientation(&self, center: Point) -> Option<LayoutTransform> {
+        if self.rotation == ImageRotation::None && !self.flip_horizontal && !self.flip_vertical {
+            return None;
+        }
+        let scale_x = if self.flip_horizontal { -1.0 } else { 1.0 };
+        let scale_y = if self.flip_vertical { -1.0 } else { 1.0 };
+        // y increases moving down, so a negative angle is clockwise on screen
+        let transform = LayoutTransform::identity()
+            .pre_rotate(0.0, 0.0, 1.0, Radians::new(-self.rotation.angle()))
+            .pre_scale(scale_x, scale_y, 1.0);
+        Some(LayoutTransform::create_translation(center.x, center.y, 0.0)
+            .pre_mul(&transform)
+            .pre_mul(&LayoutTransform::create_translation(-center.x, -center.y, 0.0)))
     }
 }
 
 impl Draw for ImageState {
     fn draw(&mut self, bounds: Rect, _: Rect, renderer: &mut RenderBuilder) {
-        let key = resources().image_loader.get_image(&self.image).unwrap().key;
-        let info = PrimitiveInfo::new(bounds);
+        let image_size = match self.measure() {
+            Some(image_size) => image_size,
+            None => return,
+        };
+        // the area covered by the image, once it's rotated
+        let fitted_size = self.fit.fit(image_size, bounds.size);
+        let fitted = Rect::new(
+            Point::new(bounds.left() + (bounds.width() - fitted_size.width) * self.align.0,
+                       bounds.top() + (bounds.height() - fitted_size.height) * self.align.1),
+            fitted_size);
+        // the image is drawn unrotated, centered on the same point, then rotated into place
+        let unrotated_size = if self.rotation.is_quarter_turn() {
+            Size::new(fitted_size.height, fitted_size.width)
+        } else {
+            fitted_size
+        };
+        let center = fitted.center();
+        let unrotated = Rect::new(
+            Point::new(center.x - unrotated_size.width / 2.0, center.y - unrotated_size.height / 2.0),
+            unrotated_size);
+        // tiles are drawn at the image's own size, unrotated
+        let tile_size = if self.fit == ImageFit::Tile {
+            if self.rotation.is_quarter_turn() { Size::new(image_size.height, image_size.width) } else { image_size }
+        } else {
+            unrotated_size
+        };
+        let animation = {
+            let mut res = resources();
+            let animation = res.image_loader.get_animation(&self.image);
+            if animation.is_some() {
+                // the frames are cached with the still image, which is kept while it's drawn
+                self.handle = res.image_loader.image_handle(&self.image, tile_size, None);
+            }
+            animation
+        };
+        let key = if let Some(animation) = animation {
+            // frames are drawn at the animation's own size, and scaled like raster images
+            self.frame_image.update(&animation, self.frame, self.tint)
+        } else {
+            let mut res = resources();
+            let key = match self.tint {
+                Some(tint) => res.image_loader.get_tinted_image(&self.image, tile_size, tint).map(|info| info.key),
+                None => res.image_loader.get_sized_image(&self.image, tile_size).map(|info| info.key),
+            };
+            self.handle = res.image_loader.image_handle(&self.image, tile_size, self.tint);
+            match key {
+                Ok(key) => key,
+                Err(_) => return,
+            }
+        };
+        let clipped = !bounds.contains_rect(&fitted);
+        if clipped {
+            let clip_id = renderer.builder.define_clip(None, bounds, vec![], None);
+            renderer.builder.push_clip_id(clip_id);
+        }
+        let orientation = self.orientation(center);
+        if let Some(transform) = orientation {
+            renderer.builder.push_stacking_context(
+                &PrimitiveInfo::new(Rect::zero()),
+                ScrollPolicy::Fixed,
+                Some(PropertyBinding::Value(transform)),
+                TransformStyle::Flat,
+                None,
+                MixBlendMode::Normal,
+                Vec::new(),
+            );
+        }
         renderer.builder.push_image(
-            &info,
-            bounds.size,
+            &PrimitiveInfo::new(unrotated),
+            tile_size,
             LayoutSize::zero(),
             ImageRendering::Auto,
             key,
         );
+        if orientation.is_some() {
+            renderer.builder.pop_stacking_context();
+        }
+        if clipped {
+            renderer.builder.pop_clip_id();
+        }
     }
 }
@@ -5,3 +5,6 @@ pub mod ellipse;
 pub mod text;
 pub mod image;
 pub mod glcanvas;
+pub mod path;
+pub mod canvas;
+pub mod nine_patch;
@@ -0,0 +1,146 @@
+//! Nine-patch images, used to draw skins that scale to any size.
+//!
+//! The image is split into nine regions by four insets. The corners are drawn at a fixed size,
+//! the edges are stretched or tiled along their length, and the center in both directions.
+
+use webrender::api::{PrimitiveInfo, BorderWidths, BorderDetails, ImageBorder, NinePatchDescriptor, RepeatMode};
+use euclid::SideOffsets2D;
+
+use render::RenderBuilder;
+use resources::resources;
+use resources::image::ImageSource;
+use geometry::Rect;
+
+/// Distances in from each edge of a rect.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Insets {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl Insets {
+    pub fn new(top: f32, right: f32, bottom: f32, left: f32) -> Self {
+        Insets {
+            top: top,
+            right: right,
+            bottom: bottom,
+            left: left,
+        }
+    }
+    pub fn uniform(inset: f32) -> Self {
+        Insets::new(inset, inset, inset, inset)
+    }
+}
+
+/// How the edges and center of a nine-patch fill the space between the corners.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PatchRepeat {
+    Stretch,
+    /// Tiled at the image size, the last tile is cut off
+    Repeat,
+    /// Tiled, with the tiles scaled so a whole number of them fit
+    Round,
+}
+
+impl Into<RepeatMode> for PatchRepeat {
+    fn into(self) -> RepeatMode {
+        match self {
+            PatchRepeat::Stretch => RepeatMode::Stretch,
+            PatchRepeat::Repeat => RepeatMode::Repeat,
+            PatchRepeat::Round => RepeatMode::Round,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NinePatch {
+    pub image: ImageSource,
+    /// Where the image is split, in the image's own size
+    pub insets: Insets,
+    /// The size the corners and edges are drawn at, the same as `insets` if not set
+    pub widths: Option<Insets>,
+    pub repeat_horizontal: PatchRepeat,
+    pub repeat_vertical: PatchRepeat,
+    /// If false, the center region isn't drawn
+    pub fill: bool,
+}
+
+impl NinePatch {
+    pub fn new(image: ImageSource, insets: Insets) -> Self {
+        NinePatch {
+            image: image,
+            insets: insets,
+            widths: None,
+            repeat_horizontal: PatchRepeat::Stretch,
+            repeat_vertical: PatchRepeat::Stretch,
+            fill: true,
+        }
+    }
+    pub fn widths(mut self, widths: Insets) -> Self {
+        self.widths = Some(widths);
+        self
+    }
+    pub fn repeat(mut self, horizontal: PatchRepeat, vertical: PatchRepeat) -> Self {
+        self.repeat_horizontal = horizontal;
+        self.repeat_vertical = vertical;
+        self
+    }
+    pub fn fill(mut self, fill: bool) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// Draws the image once it's loaded, images that aren't are loaded on a worker thread,
+    /// and the UI is redrawn when they're ready.
+    pub fn draw(&self, bounds: Rect, renderer: &mut RenderBuilder) {
+        let mut res = resources();
+        if !res.image_loader.is_loaded(&self.image) {
+            if !res.image_loader.failed_to_load(&self.image) {
+                res.image_loader.load_image_async(&self.image);
+            }
+            return;
+        }
+        let size = match res.image_loader.get_image_size(&self.image) {
+            Ok(size) => size,
+            Err(_) => return,
+        };
+        let info = match res.image_loader.get_sized_image(&self.image, size) {
+            Ok(info) => info.clone(),
+            Err(_) => return,
+        };
+        if size.width <= 0.0 || size.height <= 0.0 {
+            return;
+        }
+        // insets are in the image's size, SVG images may be rasterized at a higher resolution
+        let scale_x = info.descriptor.width as f32 / size.width;
+        let scale_y = info.descriptor.height as f32 / size.height;
+        let insets = self.insets;
+        let slice = SideOffsets2D::new(
+            (insets.top * scale_y).round() as u32,
+            (insets.right * scale_x).round() as u32,
+            (insets.bottom * scale_y).round() as u32,
+            (insets.left * scale_x).round() as u32);
+        let widths = self.widths.unwrap_or(insets);
+        let widths = BorderWidths {
+            left: widths.left,
+            top: widths.top,
+            right: widths.right,
+            bottom: widths.bottom,
+        };
+        let details = BorderDetails::Image(ImageBorder {
+            image_key: info.key,
+            patch: NinePatchDescriptor {
+                width: info.descriptor.width,
+                height: info.descriptor.height,
+                slice: slice,
+            },
+            fill: self.fill,
+            outset: SideOffsets2D::zero(),
+            repeat_horizontal: self.repeat_horizontal.into(),
+            repeat_vertical: self.repeat_vertical.into(),
+        });
+        renderer.builder.push_border(&PrimitiveInfo::new(bounds), widths, details);
+    }
+}
@@ -0,0 +1,291 @@
+//! Approximates paths with polylines, and splits polylines into dashes.
+
+use std::mem;
+
+use geometry::{Point, Vector};
+use super::{Path, PathEvent};
+
+/// Maximum distance between a curve and the line segments approximating it.
+const TOLERANCE: f32 = 0.1;
+/// Dashes and gaps shorter than this, apart from zero length dashes, are lengthened to it,
+/// anything shorter isn't visible, and would split a line into an unbounded number of dashes.
+const MIN_DASH_LENGTH: f32 = 0.01;
+
+/// The points of a single subpath, after flattening.
+#[derive(Debug, Clone)]
+pub(super) struct Polyline {
+    pub points: Vec<Point>,
+    pub closed: bool,
+}
+
+pub(super) fn flatten(path: &Path) -> Vec<Polyline> {
+    let mut polylines = Vec::new();
+    let mut points: Vec<Point> = Vec::new();
+    // the end of the last segment, where the next one starts
+    let mut last = Point::zero();
+    let mut subpath_start = Point::zero();
+    for event in &path.events {
+        if points.is_empty() {
+            match *event {
+                PathEvent::MoveTo(_) | PathEvent::Arc { .. } | PathEvent::Close => (),
+                _ => {
+                    subpath_start = last;
+                    points.push(last);
+                }
+            }
+        }
+        match *event {
+            PathEvent::MoveTo(point) => {
+                finish(&mut polylines, &mut points, false);
+                points.push(point);
+                subpath_start = point;
+                last = point;
+            }
+            PathEvent::LineTo(point) => {
+                points.push(point);
+                last = point;
+            }
+            PathEvent::QuadraticTo(control, point) => {
+                let segments = curve_segments(&[last, control, point]);
+                for step in 1..segments + 1 {
+                    let t = step as f32 / segments as f32;
+                    let mt = 1.0 - t;
+                    points.push(weighted(&[(last, mt * mt), (control, 2.0 * mt * t), (point, t * t)]));
+                }
+                last = point;
+            }
+            PathEvent::CubicTo(control_1, control_2, point) => {
+                let segments = curve_segments(&[last, control_1, control_2, point]);
+                for step in 1..segments + 1 {
+                    let t = step as f32 / segments as f32;
+                    let mt = 1.0 - t;
+                    points.push(weighted(&[
+                        (last, mt * mt * mt),
+                        (control_1, 3.0 * mt * mt * t),
+                        (control_2, 3.0 * mt * t * t),
+                        (point, t * t * t),
+                    ]));
+                }
+                last = point;
+            }
+            PathEvent::Arc { center, radius, start_angle, end_angle } => {
+                let sweep = end_angle - start_angle;
+                let segments = arc_segments(radius, sweep);
+                if points.is_empty() {
+                    subpath_start = center + Vector::new(start_angle.cos(), start_angle.sin()) * radius;
+                }
+                for step in 0..segments + 1 {
+                    let angle = start_angle + sweep * step as f32 / segments as f32;
+                    points.push(center + Vector::new(angle.cos(), angle.sin()) * radius);
+                }
+                last = *points.last().unwrap();
+            }
+            PathEvent::Close => {
+                finish(&mut polylines, &mut points, true);
+                last = subpath_start;
+            }
+        }
+    }
+    finish(&mut polylines, &mut points, false);
+    polylines
+}
+
+/// The number of line segments needed to approximate an arc of a circle.
+pub(super) fn arc_segments(radius: f32, sweep: f32) -> usize {
+    if radius <= TOLERANCE {
+        return 1;
+    }
+    // the largest angle where the segment doesn't stray further than the tolerance from the arc
+    let max_angle = 2.0 * (1.0 - TOLERANCE / radius).acos();
+    ((sweep.abs() / max_angle).ceil() as usize).max(1).min(1000)
+}
+
+/// Estimates the number of segments for a Bézier curve from the length of it's control polygon.
+fn curve_segments(points: &[Point]) -> usize {
+    let length: f32 = points.windows(2).map(|pair| (pair[1] - pair[0]).length()).sum();
+    ((length / TOLERANCE).sqrt().ceil() as usize / 2).max(1).min(1000)
+}
+
+fn weighted(points: &[(Point, f32)]) -> Point {
+    let mut sum = Vector::zero();
+    for &(point, weight) in points {
+        sum = sum + point.to_vector() * weight;
+    }
+    sum.to_point()
+}
+
+fn finish(polylines: &mut Vec<Polyline>, points: &mut Vec<Point>, closed: bool) {
+    let points = mem::replace(points, Vec::new());
+    // a lone move to draws nothing, unless it's closed
+    if points.len() > 1 || (points.len() == 1 && closed) {
+        polylines.push(Polyline { points: points, closed: closed });
+    }
+}
+
+/// Splits polylines into dashes, following the pattern of alternating dash and gap lengths.
+pub(super) fn dash(polylines: Vec<Polyline>, pattern: &[f32], offset: f32) -> Vec<Polyline> {
+    let total: f32 = pattern.iter().sum();
+    if pattern.is_empty() || total <= 0.0 || pattern.iter().any(|length| *length < 0.0) {
+        return polylines;
+    }
+    // an odd number of lengths is repeated, so dashes and gaps alternate
+    let pattern: Vec<f32> = if pattern.len() % 2 == 1 {
+        pattern.iter().chain(pattern.iter()).cloned().collect()
+    } else {
+        pattern.to_vec()
+    };
+    let pattern: Vec<f32> = pattern.into_iter()
+        .map(|length| if length > 0.0 { length.max(MIN_DASH_LENGTH) } else { 0.0 })
+        .collect();
+    let total: f32 = pattern.iter().sum();
+
+    let mut dashes = Vec::new();
+    for polyline in polylines {
+        let mut points = polyline.points.clone();
+        if polyline.closed {
+            points.push(polyline.points[0]);
+        }
+        // find where in the pattern the line starts
+        let mut index = 0;
+        let mut remaining = pattern[0];
+        let mut skip = ((offset % total) + total) % total;
+        while skip > 0.0 {
+            if skip >= remaining {
+                skip -= remaining;
+                index = (index + 1) % pattern.len();
+                remaining = pattern[index];
+            } else {
+                remaining -= skip;
+                skip = 0.0;
+            }
+        }
+        let mut current = if index % 2 == 0 { vec![points[0]] } else { Vec::new() };
+        for pair in points.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            let length = (end - start).length();
+            let mut position = 0.0;
+            while length - position > remaining {
+                let next = position + remaining;
+                // far along a long segment, a short dash can be lost to rounding, and would never end
+                if next <= position && remaining > 0.0 {
+                    break;
+                }
+                position = next;
+                let point = start + (end - start) * (position / length);
+                // ends the current dash, or starts the next one
+                current.push(point);
+                if index % 2 == 0 {
+                    dashes.push(Polyline { points: mem::replace(&mut current, Vec::new()), closed: false });
+                }
+                index = (index + 1) % pattern.len();
+                remaining = pattern[index];
+            }
+            remaining = (remaining - (length - position)).max(0.0);
+            if index % 2 == 0 {
+                current.push(end);
+            }
+        }
+        if index % 2 == 0 && current.len() > 1 {
+            dashes.push(Polyline { points: current, closed: false });
+        }
+    }
+    dashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polyline(points: &[(f32, f32)], closed: bool) -> Polyline {
+        Polyline {
+            points: points.iter().map(|&(x, y)| Point::new(x, y)).collect(),
+            closed: closed,
+        }
+    }
+
+    fn points(polyline: &Polyline) -> Vec<(f32, f32)> {
+        polyline.points.iter().map(|point| (point.x, point.y)).collect()
+    }
+
+    #[test]
+    fn flatten_lines() {
+        let path = Path::polygon(&[Point::new(0.0, 0.0), Point::new(10.0, 0.0), Point::new(10.0, 10.0)])
+            .move_to(Point::new(20.0, 20.0))
+            .move_to(Point::new(30.0, 30.0))
+            .line_to(Point::new(40.0, 30.0));
+        let polylines = flatten(&path);
+        // the lone move to draws nothing
+        assert_eq!(polylines.len(), 2);
+        assert_eq!(points(&polylines[0]), vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]);
+        assert!(polylines[0].closed);
+        assert_eq!(points(&polylines[1]), vec![(30.0, 30.0), (40.0, 30.0)]);
+        assert!(!polylines[1].closed);
+    }
+
+    #[test]
+    fn flatten_curves() {
+        let path = Path::new()
+            .move_to(Point::new(0.0, 0.0))
+            .quadratic_to(Point::new(50.0, 100.0), Point::new(100.0, 0.0));
+        let polylines = flatten(&path);
+        assert_eq!(polylines.len(), 1);
+        let points = &polylines[0].points;
+        assert!(points.len() > 3);
+        assert_eq!(points[0], Point::new(0.0, 0.0));
+        assert_eq!(*points.last().unwrap(), Point::new(100.0, 0.0));
+        // the curve peaks half way between the control point and the line between it's ends
+        let peak = points.iter().map(|point| point.y).fold(0.0, f32::max);
+        assert!((peak - 50.0).abs() < TOLERANCE);
+
+        let circle = flatten(&Path::circle(Point::new(0.0, 0.0), 10.0));
+        assert_eq!(circle.len(), 1);
+        assert!(circle[0].closed);
+        assert!((circle[0].points[0] - Point::new(10.0, 0.0)).length() < 1e-4);
+        for point in &circle[0].points {
+            assert!((point.to_vector().length() - 10.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn dash_lines() {
+        let line = vec![polyline(&[(0.0, 0.0), (10.0, 0.0)], false)];
+        let dashes = dash(line.clone(), &[2.0, 3.0], 0.0);
+        let dashes: Vec<_> = dashes.iter().map(points).collect();
+        assert_eq!(dashes, vec![vec![(0.0, 0.0), (2.0, 0.0)], vec![(5.0, 0.0), (7.0, 0.0)]]);
+
+        let dashes = dash(line.clone(), &[2.0, 3.0], 1.0);
+        let dashes: Vec<_> = dashes.iter().map(points).collect();
+        assert_eq!(dashes, vec![
+            vec![(0.0, 0.0), (1.0, 0.0)],
+            vec![(4.0, 0.0), (6.0, 0.0)],
+            vec![(9.0, 0.0), (10.0, 0.0)],
+        ]);
+
+        // an odd number of lengths is used for both dashes and gaps
+        assert_eq!(dash(line.clone(), &[4.0], 0.0).len(), 2);
+        // invalid patterns leave the line solid
+        assert_eq!(dash(line.clone(), &[2.0, -1.0], 0.0).len(), 1);
+        assert_eq!(dash(line, &[0.0, 0.0], 0.0).len(), 1);
+    }
+
+    #[test]
+    fn dash_around_corners() {
+        let corner = vec![polyline(&[(0.0, 0.0), (3.0, 0.0), (3.0, 3.0)], false)];
+        let dashes = dash(corner, &[4.0, 10.0], 0.0);
+        assert_eq!(dashes.len(), 1);
+        assert_eq!(points(&dashes[0]), vec![(0.0, 0.0), (3.0, 0.0), (3.0, 1.0)]);
+
+        // a closed polyline is dashed back to it's start
+        let square = vec![polyline(&[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)], true)];
+        let dashes = dash(square, &[1.0, 1.0], 0.0);
+        assert_eq!(dashes.len(), 4);
+        assert_eq!(points(&dashes[3]), vec![(0.0, 2.0), (0.0, 1.0)]);
+    }
+
+    #[test]
+    fn dash_short_pattern() {
+        let line = vec![polyline(&[(0.0, 0.0), (100.0, 0.0)], false)];
+        let dashes = dash(line, &[0.000_001, 0.000_001], 0.0);
+        assert!(dashes.len() >= 4990 && dashes.len() <= 5010);
+    }
+}
@@ -0,0 +1,267 @@
+//! Vector paths, made of lines, Bézier curves and arcs, that can be filled and stroked.
+//!
+//! WebRender has no path primitive, so paths are flattened into polygons and rasterized into an
+//! image on the CPU, which is cached and only redrawn when the paths or the widget size change.
+//! Path coordinates are relative to the top left corner of the widget.
+
+mod flatten;
+mod stroke;
+mod raster;
+
+use webrender::api::{PrimitiveInfo, ImageRendering, ImageDescriptor, ImageFormat, ImageData, LayoutSize};
+
+use render::RenderBuilder;
+use widget::draw::Draw;
+use resources::resources;
+use resources::image::OwnedImage;
+use geometry::{Rect, RectExt, Point, Size};
+use style::Component;
+use color::Color;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PathEvent {
+    MoveTo(Point),
+    LineTo(Point),
+    QuadraticTo(Point, Point),
+    CubicTo(Point, Point, Point),
+    /// Angles are in radians, clockwise from the positive x axis
+    Arc {
+        center: Point,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+    },
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Path {
+    pub events: Vec<PathEvent>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Path::default()
+    }
+    /// Starts a new subpath at `point`.
+    pub fn move_to(mut self, point: Point) -> Self {
+        self.events.push(PathEvent::MoveTo(point));
+        self
+    }
+    pub fn line_to(mut self, point: Point) -> Self {
+        self.events.push(PathEvent::LineTo(point));
+        self
+    }
+    pub fn quadratic_to(mut self, control: Point, point: Point) -> Self {
+        self.events.push(PathEvent::QuadraticTo(control, point));
+        self
+    }
+    pub fn cubic_to(mut self, control_1: Point, control_2: Point, point: Point) -> Self {
+        self.events.push(PathEvent::CubicTo(control_1, control_2, point));
+        self
+    }
+    /// Adds a circular arc, connected to the current subpath with a line. The arc is drawn
+    /// clockwise if `end_angle` is greater than `start_angle`, counter clockwise otherwise.
+    pub fn arc(mut self, center: Point, radius: f32, start_angle: f32, end_angle: f32) -> Self {
+        self.events.push(PathEvent::Arc {
+            center: center,
+            radius: radius,
+            start_angle: start_angle,
+            end_angle: end_angle,
+        });
+        self
+    }
+    /// Closes the current subpath with a line back to it's start.
+    pub fn close(mut self) -> Self {
+        self.events.push(PathEvent::Close);
+        self
+    }
+
+    pub fn line(start: Point, end: Point) -> Self {
+        Path::new().move_to(start).line_to(end)
+    }
+    pub fn polyline(points: &[Point]) -> Self {
+        let mut path = Path::new();
+        for (index, point) in points.iter().enumerate() {
+            path = if index == 0 { path.move_to(*point) } else { path.line_to(*point) };
+        }
+        path
+    }
+    pub fn polygon(points: &[Point]) -> Self {
+        Path::polyline(points).close()
+    }
+    pub fn rect(rect: Rect) -> Self {
+        Path::polygon(&[
+            Point::new(rect.left(), rect.top()),
+            Point::new(rect.right(), rect.top()),
+            Point::new(rect.right(), rect.bottom()),
+            Point::new(rect.left(), rect.bottom()),
+        ])
+    }
+    pub fn circle(center: Point, radius: f32) -> Self {
+        Path::new().arc(center, radius, 0.0, 2.0 * ::std::f32::consts::PI).close()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stroke {
+    pub width: f32,
+    pub color: Color,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// Miter joins longer than this multiple of half the stroke width are drawn beveled
+    pub miter_limit: f32,
+    /// Alternating lengths of dashes and gaps, a solid line if empty
+    pub dashes: Vec<f32>,
+    /// Distance into the dash pattern the line starts at
+    pub dash_offset: f32,
+}
+
+impl Stroke {
+    pub fn new(width: f32, color: Color) -> Self {
+        Stroke {
+            width: width,
+            color: color,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+    pub fn join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+    pub fn cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+    pub fn miter_limit(mut self, miter_limit: f32) -> Self {
+        self.miter_limit = miter_limit;
+        self
+    }
+    pub fn dashes(mut self, dashes: Vec<f32>, offset: f32) -> Self {
+        self.dashes = dashes;
+        self.dash_offset = offset;
+        self
+    }
+}
+
+/// A path with it's fill and stroke, the fill is drawn first.
+/// Filling uses the non-zero winding rule, every subpath is implicitly closed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyledPath {
+    pub path: Path,
+    pub fill: Option<Color>,
+    pub stroke: Option<Stroke>,
+}
+
+/// Collects the paths to draw, in order, back to front.
+#[derive(Debug, Clone, Default)]
+pub struct Painter {
+    paths: Vec<StyledPath>,
+}
+
+impl Painter {
+    pub fn new() -> Self {
+        Painter::default()
+    }
+    pub fn fill(&mut self, path: Path, color: Color) -> &mut Self {
+        self.draw(StyledPath { path: path, fill: Some(color), stroke: None })
+    }
+    pub fn stroke(&mut self, path: Path, stroke: Stroke) -> &mut Self {
+        self.draw(StyledPath { path: path, fill: None, stroke: Some(stroke) })
+    }
+    pub fn fill_and_stroke(&mut self, path: Path, color: Color, stroke: Stroke) -> &mut Self {
+        self.draw(StyledPath { path: path, fill: Some(color), stroke: Some(stroke) })
+    }
+    pub fn draw(&mut self, path: StyledPath) -> &mut Self {
+        self.paths.push(path);
+        self
+    }
+    pub fn into_paths(self) -> Vec<StyledPath> {
+        self.paths
+    }
+}
+
+/// Rasterizes the paths into an image of premultiplied BGRA pixels.
+pub(crate) fn rasterize(paths: &[StyledPath], width: u32, height: u32) -> Vec<u8> {
+    raster::render(paths, width, height)
+}
+
+/// Draws a fixed list of paths.
+#[derive(Debug, Clone)]
+pub struct PathState {
+    pub paths: Vec<StyledPath>,
+    image: PathImage,
+}
+
+impl Component for PathState {
+    fn name() -> String {
+        String::from("path")
+    }
+}
+
+impl PathState {
+    pub fn new(paths: Vec<StyledPath>) -> Self {
+        PathState {
+            paths: paths,
+            image: PathImage::default(),
+        }
+    }
+}
+
+impl Draw for PathState {
+    fn draw(&mut self, bounds: Rect, _: Rect, renderer: &mut RenderBuilder) {
+        self.image.draw(&self.paths, bounds, renderer);
+    }
+}
+
+/// The rasterized image of a list of paths, along with the paths and size it was drawn for.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PathImage {
+    image: OwnedImage,
+    paths: Vec<StyledPath>,
+    size: (u32, u32),
+}
+
+impl PathImage {
+    pub(crate) fn draw(&mut self, paths: &[StyledPath], bounds: Rect, renderer: &mut RenderBuilder) {
+        let size = (bounds.width().ceil() as u32, bounds.height().ceil() as u32);
+        if size.0 == 0 || size.1 == 0 {
+            return;
+        }
+        if self.image.info().is_none() || self.size != size || self.paths[..] != *paths {
+            let pixels = rasterize(paths, size.0, size.1);
+            let descriptor = ImageDescriptor::new(size.0, size.1, ImageFormat::BGRA8, false);
+            self.image.update(&mut resources().image_loader, ImageData::new(pixels), descriptor);
+            self.paths = paths.to_vec();
+            self.size = size;
+        }
+        if let Some(info) = self.image.info() {
+            let image_size = Size::new(size.0 as f32, size.1 as f32);
+            renderer.builder.push_image(
+                &PrimitiveInfo::new(Rect::new(bounds.origin, image_size)),
+                image_size,
+                LayoutSize::zero(),
+                ImageRendering::Auto,
+                info.key,
+            );
+        }
+    }
+}
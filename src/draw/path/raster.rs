@@ -0,0 +1,142 @@
+//! Anti-aliased polygon rasterization, using the signed area accumulation approach of font-rs.
+//!
+//! Each polygon edge adds the signed area it covers to a buffer, per pixel, a running sum along
+//! each row then gives the coverage of every pixel. Polygon winding directions add up, so the
+//! coverage follows the non-zero fill rule.
+
+use webrender::api::ColorF;
+
+use geometry::Point;
+use super::StyledPath;
+use super::flatten::{flatten, dash};
+use super::stroke::stroke;
+
+/// Renders the paths into premultiplied BGRA pixels.
+pub(super) fn render(paths: &[StyledPath], width: u32, height: u32) -> Vec<u8> {
+    let mut pixels = vec![0; (width * height * 4) as usize];
+    for path in paths {
+        let polylines = flatten(&path.path);
+        if let Some(color) = path.fill {
+            let mut coverage = Coverage::new(width as usize, height as usize);
+            for polyline in &polylines {
+                coverage.add_polygon(&polyline.points);
+            }
+            coverage.composite(color.into(), &mut pixels);
+        }
+        if let Some(ref style) = path.stroke {
+            let mut coverage = Coverage::new(width as usize, height as usize);
+            for polyline in dash(polylines, &style.dashes, style.dash_offset) {
+                for polygon in stroke(&polyline, style) {
+                    coverage.add_polygon(&polygon);
+                }
+            }
+            coverage.composite(style.color.into(), &mut pixels);
+        }
+    }
+    pixels
+}
+
+struct Coverage {
+    width: usize,
+    height: usize,
+    /// Rows are two values longer than the image, area to the right of the image lands there
+    area: Vec<f32>,
+}
+
+impl Coverage {
+    fn new(width: usize, height: usize) -> Self {
+        Coverage {
+            width: width,
+            height: height,
+            area: vec![0.0; (width + 2) * height],
+        }
+    }
+
+    fn add_polygon(&mut self, points: &[Point]) {
+        for index in 0..points.len() {
+            self.add_line(points[index], points[(index + 1) % points.len()]);
+        }
+    }
+
+    fn add_line(&mut self, start: Point, end: Point) {
+        if start.y == end.y {
+            return;
+        }
+        let (direction, top, bottom) = if start.y < end.y { (1.0, start, end) } else { (-1.0, end, start) };
+        let y_start = top.y.max(0.0);
+        let y_end = bottom.y.min(self.height as f32);
+        if y_start >= y_end {
+            return;
+        }
+        let dxdy = (bottom.x - top.x) / (bottom.y - top.y);
+        let stride = self.width + 2;
+        let max_x = self.width as f32;
+        let mut x = top.x + (y_start - top.y) * dxdy;
+        let mut y = y_start.floor() as usize;
+        while (y as f32) < y_end {
+            let row = y * stride;
+            let dy = ((y + 1) as f32).min(y_end) - (y as f32).max(y_start);
+            let x_next = x + dxdy * dy;
+            let d = dy * direction;
+            // area left of the image is accumulated at it's left edge, and
+            // area to the right after the last pixel, where it's ignored
+            let clamp = |x: f32| x.max(0.0).min(max_x);
+            let (x0, x1) = if x < x_next { (clamp(x), clamp(x_next)) } else { (clamp(x_next), clamp(x)) };
+            let x0_floor = x0.floor();
+            let x0_index = x0_floor as usize;
+            let x1_ceil = x1.ceil();
+            let x1_index = x1_ceil as usize;
+            if x1_index <= x0_index + 1 {
+                // the line stays within one pixel of this row
+                let x_mid = 0.5 * (x0 + x1) - x0_floor;
+                self.area[row + x0_index] += d - d * x_mid;
+                self.area[row + x0_index + 1] += d * x_mid;
+            } else {
+                let s = (x1 - x0).recip();
+                let x0_fract = x0 - x0_floor;
+                let a0 = 0.5 * s * (1.0 - x0_fract) * (1.0 - x0_fract);
+                let x1_fract = x1 - x1_ceil + 1.0;
+                let am = 0.5 * s * x1_fract * x1_fract;
+                self.area[row + x0_index] += d * a0;
+                if x1_index == x0_index + 2 {
+                    self.area[row + x0_index + 1] += d * (1.0 - a0 - am);
+                } else {
+                    let a1 = s * (1.5 - x0_fract);
+                    self.area[row + x0_index + 1] += d * (a1 - a0);
+                    for index in x0_index + 2..x1_index - 1 {
+                        self.area[row + index] += d * s;
+                    }
+                    let a2 = a1 + (x1_index - x0_index - 3) as f32 * s;
+                    self.area[row + x1_index - 1] += d * (1.0 - a2 - am);
+                }
+                self.area[row + x1_index] += d * am;
+            }
+            x = x_next;
+            y += 1;
+        }
+    }
+
+    /// Draws the color over the pixels, weighted by coverage.
+    fn composite(&self, color: ColorF, pixels: &mut [u8]) {
+        let stride = self.width + 2;
+        for y in 0..self.height {
+            let mut accumulated = 0.0;
+            for x in 0..self.width {
+                accumulated += self.area[y * stride + x];
+                let coverage = accumulated.abs().min(1.0);
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let alpha = color.a * coverage;
+                let pixel = &mut pixels[(y * self.width + x) * 4..(y * self.width + x + 1) * 4];
+                let blend = |source: f32, dest: u8| {
+                    (source * 255.0 + f32::from(dest) * (1.0 - alpha)).round().min(255.0) as u8
+                };
+                pixel[0] = blend(color.b * alpha, pixel[0]);
+                pixel[1] = blend(color.g * alpha, pixel[1]);
+                pixel[2] = blend(color.r * alpha, pixel[2]);
+                pixel[3] = blend(alpha, pixel[3]);
+            }
+        }
+    }
+}
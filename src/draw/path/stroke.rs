@@ -0,0 +1,248 @@
+//! Converts polylines into the polygons covered by their stroke.
+//!
+//! Each segment, join and cap becomes a separate polygon, all wound in the same direction, so
+//! where they overlap they add up under the non-zero fill rule instead of cancelling out.
+
+use std::f32;
+
+use geometry::{Point, Vector};
+use super::{Stroke, LineJoin, LineCap};
+use super::flatten::{Polyline, arc_segments};
+
+pub(super) fn stroke(polyline: &Polyline, stroke: &Stroke) -> Vec<Vec<Point>> {
+    let half_width = stroke.width / 2.0;
+    let mut polygons = Vec::new();
+    if half_width <= 0.0 {
+        return polygons;
+    }
+    let mut points: Vec<Point> = Vec::new();
+    for point in &polyline.points {
+        if points.last().map_or(true, |last| *last != *point) {
+            points.push(*point);
+        }
+    }
+    if polyline.closed && points.len() > 1 && points[0] == points[points.len() - 1] {
+        points.pop();
+    }
+
+    if points.len() == 1 {
+        // a zero length line is only visible with round or square caps
+        let point = points[0];
+        match stroke.cap {
+            LineCap::Round => polygons.push(circle(point, half_width)),
+            LineCap::Square => polygons.push(vec![
+                point + Vector::new(-half_width, -half_width),
+                point + Vector::new(half_width, -half_width),
+                point + Vector::new(half_width, half_width),
+                point + Vector::new(-half_width, half_width),
+            ]),
+            LineCap::Butt => (),
+        }
+        return polygons;
+    }
+
+    let closed = polyline.closed && points.len() > 2;
+    let mut segments: Vec<(Point, Point)> = points.windows(2).map(|pair| (pair[0], pair[1])).collect();
+    if closed {
+        segments.push((points[points.len() - 1], points[0]));
+    }
+    if !closed && stroke.cap == LineCap::Square {
+        // square caps extend the line by half it's width at both ends
+        let last = segments.len() - 1;
+        let (start, end) = segments[0];
+        segments[0].0 = start - direction(start, end) * half_width;
+        let (start, end) = segments[last];
+        segments[last].1 = end + direction(start, end) * half_width;
+    }
+
+    for &(start, end) in &segments {
+        let normal = normal(start, end) * half_width;
+        polygons.push(oriented(vec![start + normal, end + normal, end - normal, start - normal]));
+    }
+
+    let joins = if closed { segments.len() } else { segments.len() - 1 };
+    for index in 0..joins {
+        let (start, point) = segments[index];
+        let (_, end) = segments[(index + 1) % segments.len()];
+        if let Some(polygon) = join(start, point, end, half_width, stroke) {
+            polygons.push(polygon);
+        }
+    }
+
+    if !closed && stroke.cap == LineCap::Round {
+        polygons.push(circle(points[0], half_width));
+        polygons.push(circle(points[points.len() - 1], half_width));
+    }
+    polygons
+}
+
+/// The polygon filling the gap on the outside of the corner at `point`.
+fn join(start: Point, point: Point, end: Point, half_width: f32, stroke: &Stroke) -> Option<Vec<Point>> {
+    let incoming = direction(start, point);
+    let outgoing = direction(point, end);
+    let cross = incoming.cross(outgoing);
+    if cross.abs() < 1e-6 && incoming.dot(outgoing) > 0.0 {
+        return None;
+    }
+    if stroke.join == LineJoin::Round {
+        return Some(circle(point, half_width));
+    }
+    // the outside of the corner is on the opposite side to the turn
+    let side = if cross > 0.0 { -half_width } else { half_width };
+    let outer_in = normal(start, point) * side;
+    let outer_out = normal(point, end) * side;
+    if stroke.join == LineJoin::Miter {
+        let bisector = outer_in + outer_out;
+        if bisector.length() > 1e-6 {
+            let bisector = bisector / bisector.length();
+            let cos_half_angle = bisector.dot(outer_in / half_width);
+            let miter_length = half_width / cos_half_angle;
+            if miter_length / half_width <= stroke.miter_limit {
+                return Some(oriented(vec![point, point + outer_in, point + bisector * miter_length, point + outer_out]));
+            }
+        }
+    }
+    Some(oriented(vec![point, point + outer_in, point + outer_out]))
+}
+
+fn direction(start: Point, end: Point) -> Vector {
+    let vector = end - start;
+    vector / vector.length()
+}
+
+/// Unit vector perpendicular to the line from start to end.
+fn normal(start: Point, end: Point) -> Vector {
+    let direction = direction(start, end);
+    Vector::new(-direction.y, direction.x)
+}
+
+fn circle(center: Point, radius: f32) -> Vec<Point> {
+    let segments = arc_segments(radius, 2.0 * f32::consts::PI).max(8);
+    let polygon = (0..segments).map(|step| {
+        let angle = 2.0 * f32::consts::PI * step as f32 / segments as f32;
+        center + Vector::new(angle.cos(), angle.sin()) * radius
+    }).collect();
+    oriented(polygon)
+}
+
+/// Reverses the polygon if needed, so it's wound clockwise on screen.
+fn oriented(mut polygon: Vec<Point>) -> Vec<Point> {
+    let mut area = 0.0;
+    for index in 0..polygon.len() {
+        let (a, b) = (polygon[index], polygon[(index + 1) % polygon.len()]);
+        area += a.x * b.y - b.x * a.y;
+    }
+    if area < 0.0 {
+        polygon.reverse();
+    }
+    polygon
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color::BLACK;
+
+    fn polyline(points: &[(f32, f32)], closed: bool) -> Polyline {
+        Polyline {
+            points: points.iter().map(|&(x, y)| Point::new(x, y)).collect(),
+            closed: closed,
+        }
+    }
+
+    /// The left, top, right and bottom of the area covered by the polygons.
+    fn extent(polygons: &[Vec<Point>]) -> (f32, f32, f32, f32) {
+        let points = polygons.iter().flat_map(|polygon| polygon.iter());
+        points.fold((f32::MAX, f32::MAX, f32::MIN, f32::MIN), |(left, top, right, bottom), point| {
+            (left.min(point.x), top.min(point.y), right.max(point.x), bottom.max(point.y))
+        })
+    }
+
+    fn has_vertex(polygon: &[Point], x: f32, y: f32) -> bool {
+        polygon.iter().any(|point| (*point - Point::new(x, y)).length() < 1e-4)
+    }
+
+    fn assert_extent(polygons: &[Vec<Point>], expected: (f32, f32, f32, f32)) {
+        let (left, top, right, bottom) = extent(polygons);
+        let error = (left - expected.0).abs() + (top - expected.1).abs() +
+            (right - expected.2).abs() + (bottom - expected.3).abs();
+        assert!(error < 1e-3, "extent {:?}, expected {:?}", (left, top, right, bottom), expected);
+    }
+
+    #[test]
+    fn caps() {
+        let line = polyline(&[(0.0, 0.0), (10.0, 0.0)], false);
+        let butt = stroke(&line, &Stroke::new(2.0, BLACK));
+        assert_eq!(butt.len(), 1);
+        assert_extent(&butt, (0.0, -1.0, 10.0, 1.0));
+        let square = stroke(&line, &Stroke::new(2.0, BLACK).cap(LineCap::Square));
+        assert_eq!(square.len(), 1);
+        assert_extent(&square, (-1.0, -1.0, 11.0, 1.0));
+        let round = stroke(&line, &Stroke::new(2.0, BLACK).cap(LineCap::Round));
+        assert_eq!(round.len(), 3);
+        assert_extent(&round, (-1.0, -1.0, 11.0, 1.0));
+
+        // a zero length line only has caps
+        let point = polyline(&[(5.0, 5.0), (5.0, 5.0)], false);
+        assert!(stroke(&point, &Stroke::new(2.0, BLACK)).is_empty());
+        let square = stroke(&point, &Stroke::new(2.0, BLACK).cap(LineCap::Square));
+        assert_eq!(square.len(), 1);
+        assert_extent(&square, (4.0, 4.0, 6.0, 6.0));
+        let round = stroke(&point, &Stroke::new(2.0, BLACK).cap(LineCap::Round));
+        assert_eq!(round.len(), 1);
+        assert_extent(&round, (4.0, 4.0, 6.0, 6.0));
+
+        assert!(stroke(&line, &Stroke::new(0.0, BLACK)).is_empty());
+    }
+
+    #[test]
+    fn joins() {
+        let corner = polyline(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)], false);
+        let miter = stroke(&corner, &Stroke::new(2.0, BLACK));
+        assert_eq!(miter.len(), 3);
+        assert!(has_vertex(&miter[2], 11.0, -1.0));
+        assert_extent(&miter, (0.0, -1.0, 11.0, 10.0));
+
+        // a right angle miter is longer than a limit of 1.0
+        let limited = stroke(&corner, &Stroke::new(2.0, BLACK).miter_limit(1.0));
+        assert_eq!(limited[2].len(), 3);
+        let bevel = stroke(&corner, &Stroke::new(2.0, BLACK).join(LineJoin::Bevel));
+        assert_eq!(bevel[2].len(), 3);
+        assert!(has_vertex(&bevel[2], 10.0, -1.0));
+        assert!(has_vertex(&bevel[2], 11.0, 0.0));
+        assert!(!has_vertex(&bevel[2], 11.0, -1.0));
+
+        let round = stroke(&corner, &Stroke::new(2.0, BLACK).join(LineJoin::Round));
+        assert_eq!(round.len(), 3);
+        assert!(round[2].len() >= 8);
+
+        // no join is needed where the line continues straight on
+        let straight = polyline(&[(0.0, 0.0), (5.0, 0.0), (10.0, 0.0)], false);
+        assert_eq!(stroke(&straight, &Stroke::new(2.0, BLACK)).len(), 2);
+    }
+
+    #[test]
+    fn closed_polylines() {
+        let square = polyline(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0), (0.0, 0.0)], true);
+        let polygons = stroke(&square, &Stroke::new(2.0, BLACK));
+        // a segment and a join at each corner, the repeated start point is ignored
+        assert_eq!(polygons.len(), 8);
+        assert_extent(&polygons, (-1.0, -1.0, 11.0, 11.0));
+    }
+
+    #[test]
+    fn winding() {
+        let corner = polyline(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 5.0)], false);
+        for join in &[LineJoin::Miter, LineJoin::Round, LineJoin::Bevel] {
+            let stroke_style = Stroke::new(3.0, BLACK).join(*join).cap(LineCap::Round);
+            for polygon in stroke(&corner, &stroke_style) {
+                let mut area = 0.0;
+                for index in 0..polygon.len() {
+                    let (a, b) = (polygon[index], polygon[(index + 1) % polygon.len()]);
+                    area += a.x * b.y - b.x * a.y;
+                }
+                assert!(area >= 0.0);
+            }
+        }
+    }
+}
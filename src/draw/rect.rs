@@ -1,41 +1,335 @@
-use webrender::api::{LocalClip, BorderRadius, ComplexClipRegion, PrimitiveInfo, ClipMode};
+use webrender::api::{self, LocalClip, BorderRadius, ComplexClipRegion, PrimitiveInfo, ClipMode,
+    BoxShadowClipMode, BorderWidths, BorderDetails, NormalBorder, GradientStop, ExtendMode, LayoutPrimitiveInfo};
 
 use render::RenderBuilder;
 use widget::draw::Draw;
-use geometry::{Rect, RectExt};
+use draw::nine_patch::NinePatch;
+use geometry::{Rect, RectExt, Point, Size, Vector};
 use color::*;
 
+/// A gradient fill. Positions and sizes are relative to the rect bounds, where (0.0, 0.0) is
+/// the top left corner and (1.0, 1.0) the bottom right, so a style can be shared by rects of
+/// any size. Stops are pairs of an offset along the gradient, from 0.0 to 1.0, and a color.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gradient {
+    Linear {
+        start: (f32, f32),
+        end: (f32, f32),
+        stops: Vec<(f32, Color)>,
+    },
+    Radial {
+        center: (f32, f32),
+        radius: (f32, f32),
+        stops: Vec<(f32, Color)>,
+    },
+}
+
+impl Gradient {
+    pub fn vertical(top: Color, bottom: Color) -> Self {
+        Gradient::Linear {
+            start: (0.0, 0.0),
+            end: (0.0, 1.0),
+            stops: vec![(0.0, top), (1.0, bottom)],
+        }
+    }
+    pub fn horizontal(left: Color, right: Color) -> Self {
+        Gradient::Linear {
+            start: (0.0, 0.0),
+            end: (1.0, 0.0),
+            stops: vec![(0.0, left), (1.0, right)],
+        }
+    }
+    /// A radial gradient from the center of the rect, reaching it's edges.
+    pub fn radial(center: Color, edge: Color) -> Self {
+        Gradient::Radial {
+            center: (0.5, 0.5),
+            radius: (0.5, 0.5),
+            stops: vec![(0.0, center), (1.0, edge)],
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BorderStyle {
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl Into<api::BorderStyle> for BorderStyle {
+    fn into(self) -> api::BorderStyle {
+        match self {
+            BorderStyle::Solid => api::BorderStyle::Solid,
+            BorderStyle::Dashed => api::BorderStyle::Dashed,
+            BorderStyle::Dotted => api::BorderStyle::Dotted,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BorderSide {
+    pub width: f32,
+    pub color: Color,
+    pub style: BorderStyle,
+}
+
+impl BorderSide {
+    pub fn new(width: f32, color: Color, style: BorderStyle) -> Self {
+        BorderSide {
+            width: width,
+            color: color,
+            style: style,
+        }
+    }
+    pub fn solid(width: f32, color: Color) -> Self {
+        BorderSide::new(width, color, BorderStyle::Solid)
+    }
+    /// A side with no border.
+    pub fn none() -> Self {
+        BorderSide::solid(0.0, TRANSPARENT)
+    }
+}
+
+/// Border widths, colors and styles for each side of a rect.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BorderSides {
+    pub top: BorderSide,
+    pub right: BorderSide,
+    pub bottom: BorderSide,
+    pub left: BorderSide,
+}
+
+impl BorderSides {
+    pub fn uniform(side: BorderSide) -> Self {
+        BorderSides {
+            top: side,
+            right: side,
+            bottom: side,
+            left: side,
+        }
+    }
+}
+
+/// Radii of each corner of a rect.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CornerRadii {
+    pub top_left: f32,
+    pub top_right: f32,
+    pub bottom_right: f32,
+    pub bottom_left: f32,
+}
+
+impl CornerRadii {
+    pub fn new(top_left: f32, top_right: f32, bottom_right: f32, bottom_left: f32) -> Self {
+        CornerRadii {
+            top_left: top_left,
+            top_right: top_right,
+            bottom_right: bottom_right,
+            bottom_left: bottom_left,
+        }
+    }
+    pub fn uniform(radius: f32) -> Self {
+        CornerRadii::new(radius, radius, radius, radius)
+    }
+    /// The radii of the inner edge of a border with the given widths.
+    fn inner(&self, sides: &BorderSides) -> Self {
+        let inner = |radius: f32, width: f32| (radius - width).max(0.0);
+        CornerRadii {
+            top_left: inner(self.top_left, sides.top.width.max(sides.left.width)),
+            top_right: inner(self.top_right, sides.top.width.max(sides.right.width)),
+            bottom_right: inner(self.bottom_right, sides.bottom.width.max(sides.right.width)),
+            bottom_left: inner(self.bottom_left, sides.bottom.width.max(sides.left.width)),
+        }
+    }
+}
+
+impl Into<BorderRadius> for CornerRadii {
+    fn into(self) -> BorderRadius {
+        BorderRadius {
+            top_left: Size::new(self.top_left, self.top_left),
+            top_right: Size::new(self.top_right, self.top_right),
+            bottom_right: Size::new(self.bottom_right, self.bottom_right),
+            bottom_left: Size::new(self.bottom_left, self.bottom_left),
+        }
+    }
+}
+
+/// A shadow cast by a rect, outside it's bounds, or inside them if `inset` is set.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoxShadow {
+    pub offset: Vector,
+    pub color: Color,
+    pub blur_radius: f32,
+    /// Grows the shadow in every direction, before it's blurred
+    pub spread_radius: f32,
+    pub inset: bool,
+}
+
+impl BoxShadow {
+    /// A drop shadow, drawn outside the rect.
+    pub fn new(offset: Vector, blur_radius: f32, color: Color) -> Self {
+        BoxShadow {
+            offset: offset,
+            color: color,
+            blur_radius: blur_radius,
+            spread_radius: 0.0,
+            inset: false,
+        }
+    }
+    /// The area covered by the shadow, for a rect with the given bounds.
+    fn extent(&self, bounds: Rect) -> Rect {
+        if self.inset {
+            bounds
+        } else {
+            let size = self.blur_radius + self.spread_radius;
+            bounds.translate(&self.offset).inflate(size, size)
+        }
+    }
+}
+
 component_style!{pub struct RectState<name="rect", style=RectStyle> {
     background_color: Color = WHITE,
+    gradient: Option<Gradient> = None,
     corner_radius: Option<f32> = None,
+    corner_radii: Option<CornerRadii> = None,
     border: Option<(f32, Color)> = None,
+    border_sides: Option<BorderSides> = None,
+    shadow: Option<BoxShadow> = None,
+    /// Drawn instead of the background and border, if set
+    image: Option<NinePatch> = None,
 }}
 
+impl RectState {
+    /// Per corner radii, if set, otherwise the uniform `corner_radius`.
+    fn radii(&self) -> Option<CornerRadii> {
+        self.corner_radii.or(self.corner_radius.map(CornerRadii::uniform))
+    }
+    /// Fills the rect with the gradient, if set, otherwise the background color.
+    fn push_background(&self, renderer: &mut RenderBuilder, bounds: Rect, clip_rect: Rect, radii: Option<CornerRadii>) {
+        if let Some(ref gradient) = self.gradient {
+            push_gradient(renderer, bounds, gradient, clip_rect, radii);
+        } else {
+            push_rect(renderer, bounds, self.background_color, clip_rect, radii);
+        }
+    }
+}
+
 impl Draw for RectState {
     fn draw(&mut self, bounds: Rect, _: Rect, renderer: &mut RenderBuilder) {
         // rounding is a hack to prevent bug in webrender that produces artifacts around the corners
         //let bounds = Rect::new(bounds.origin.round(), bounds.size.round());
         let bounds = bounds.round();
-        if let Some((width, color)) = self.border {
+        let radii = self.radii();
+        if let Some(shadow) = self.shadow {
+            if !shadow.inset {
+                push_shadow(renderer, bounds, shadow, radii);
+            }
+        }
+        if let Some(ref image) = self.image {
+            image.draw(bounds, renderer);
+        } else if let Some(sides) = self.border_sides {
+            let inner = Rect::new(
+                Point::new(bounds.left() + sides.left.width, bounds.top() + sides.top.width),
+                Size::new(bounds.width() - sides.left.width - sides.right.width,
+                          bounds.height() - sides.top.width - sides.bottom.width));
+            // the background is drawn under the border, so it doesn't show through gaps in dashed and dotted sides
+            self.push_background(renderer, bounds, inner, radii.map(|radii| radii.inner(&sides)));
+            push_border(renderer, bounds, sides, radii);
+        } else if let Some((width, color)) = self.border {
             let width = if width < 2.0 { 2.0 } else { width };
-            push_rect(renderer, bounds, color, bounds, self.corner_radius);
-            push_rect(renderer, bounds, self.background_color, bounds.shrink_bounds(width), self.corner_radius);
+            push_rect(renderer, bounds, color, bounds, radii);
+            self.push_background(renderer, bounds, bounds.shrink_bounds(width), radii);
         } else {
-            push_rect(renderer, bounds, self.background_color, bounds, self.corner_radius);
+            self.push_background(renderer, bounds, bounds, radii);
         };
+        if let Some(shadow) = self.shadow {
+            if shadow.inset {
+                push_shadow(renderer, bounds, shadow, radii);
+            }
+        }
+    }
+    fn draw_bounds(&self, bounds: Rect) -> Rect {
+        let mut draw_bounds = bounds;
+        if let Some(shadow) = self.shadow {
+            draw_bounds = draw_bounds.union(&shadow.extent(bounds));
+        }
+        draw_bounds
     }
 }
 
-fn clip_rounded(rect: Rect, radius: f32) -> LocalClip {
-    let clip_region = ComplexClipRegion::new(rect, BorderRadius::uniform(radius), ClipMode::Clip);
+fn clip_rounded(rect: Rect, radii: CornerRadii) -> LocalClip {
+    let clip_region = ComplexClipRegion::new(rect, radii.into(), ClipMode::Clip);
     LocalClip::RoundedRect(rect, clip_region)
 }
 
-fn push_rect(renderer: &mut RenderBuilder, rect: Rect, color: Color, clip_rect: Rect, radius: Option<f32>) {
-    let info = if let Some(radius) = radius {
-        PrimitiveInfo::with_clip(rect, clip_rounded(clip_rect, radius))
+fn primitive_info(rect: Rect, clip_rect: Rect, radii: Option<CornerRadii>) -> LayoutPrimitiveInfo {
+    if let Some(radii) = radii {
+        PrimitiveInfo::with_clip(rect, clip_rounded(clip_rect, radii))
     } else {
-        PrimitiveInfo::new(rect)
-    };
+        PrimitiveInfo::with_clip_rect(rect, clip_rect)
+    }
+}
+
+fn push_rect(renderer: &mut RenderBuilder, rect: Rect, color: Color, clip_rect: Rect, radii: Option<CornerRadii>) {
+    let info = primitive_info(rect, clip_rect, radii);
     renderer.builder.push_rect(&info, color.into());
 }
+
+fn push_gradient(renderer: &mut RenderBuilder, rect: Rect, gradient: &Gradient, clip_rect: Rect, radii: Option<CornerRadii>) {
+    let info = primitive_info(rect, clip_rect, radii);
+    let relative = |point: (f32, f32)| Point::new(rect.width() * point.0, rect.height() * point.1);
+    // gradient positions are relative to the primitive origin
+    match *gradient {
+        Gradient::Linear { start, end, stops: ref gradient_stops } => {
+            let gradient = renderer.builder.create_gradient(
+                relative(start), relative(end), gradient_stops(gradient_stops), ExtendMode::Clamp);
+            renderer.builder.push_gradient(&info, gradient, rect.size, Size::zero());
+        }
+        Gradient::Radial { center, radius, stops: ref gradient_stops } => {
+            let radius = Size::new(rect.width() * radius.0, rect.height() * radius.1);
+            let gradient = renderer.builder.create_radial_gradient(
+                relative(center), radius, gradient_stops(gradient_stops), ExtendMode::Clamp);
+            renderer.builder.push_radial_gradient(&info, gradient, rect.size, Size::zero());
+        }
+    }
+}
+
+fn gradient_stops(stops: &[(f32, Color)]) -> Vec<GradientStop> {
+    stops.iter().map(|&(offset, color)| GradientStop { offset: offset, color: color.into() }).collect()
+}
+
+fn push_border(renderer: &mut RenderBuilder, rect: Rect, sides: BorderSides, radii: Option<CornerRadii>) {
+    let widths = BorderWidths {
+        left: sides.left.width,
+        top: sides.top.width,
+        right: sides.right.width,
+        bottom: sides.bottom.width,
+    };
+    let side = |side: BorderSide| api::BorderSide {
+        color: side.color.into(),
+        style: side.style.into(),
+    };
+    let details = BorderDetails::Normal(NormalBorder {
+        left: side(sides.left),
+        right: side(sides.right),
+        top: side(sides.top),
+        bottom: side(sides.bottom),
+        radius: radii.map(|radii| radii.into()).unwrap_or_else(BorderRadius::zero),
+    });
+    renderer.builder.push_border(&PrimitiveInfo::new(rect), widths, details);
+}
+
+fn push_shadow(renderer: &mut RenderBuilder, bounds: Rect, shadow: BoxShadow, radii: Option<CornerRadii>) {
+    let clip_mode = if shadow.inset { BoxShadowClipMode::Inset } else { BoxShadowClipMode::Outset };
+    // webrender box shadows only support a uniform radius
+    let radius = radii.map(|radii| radii.top_left).unwrap_or(0.0);
+    renderer.builder.push_box_shadow(
+        &PrimitiveInfo::new(shadow.extent(bounds)),
+        bounds,
+        shadow.offset,
+        shadow.color.into(),
+        shadow.blur_radius,
+        shadow.spread_radius,
+        radius,
+        clip_mode);
+}
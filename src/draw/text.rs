@@ -1,11 +1,17 @@
+use std::ops::Range;
+
 use webrender::api::{LayoutPoint, GlyphInstance, PrimitiveInfo, FontInstanceKey};
 use rusttype::{Scale, GlyphId, VMetrics};
 
 use render::RenderBuilder;
-use text_layout::{self, Wrap, Align};
+use text_layout::{self, Wrap, Align, FontFallback, LayoutFont};
+use text_layout::cursor;
+use text_layout::line::{LineInfo, LineInfos, LineRects, SelectedLineRects};
+use text_layout::rich::{self, Span};
 use resources::resources;
-use resources::font::FontDescriptor;
-use geometry::{Size, Rect, RectExt, Vector};
+use resources::font::{FontDescriptor, FontLoader};
+use resources::cache::ResourceHandle;
+use geometry::{Size, Rect, RectExt, Point, Vector};
 use render;
 use widget::draw::Draw;
 use color::*;
@@ -15,11 +21,19 @@ const DEBUG_LINE_BOUNDS: bool = false;
 component_style!{pub struct TextState<name="text", style=TextStyle> {
     text: String = String::from(""),
     font: FontDescriptor = FontDescriptor::from_family("Verdana"),
+    /// Fonts used, in order, for characters `font` has no glyphs for, before the font loader's `fallback_fonts`
+    fallback_fonts: Vec<FontDescriptor> = Vec::new(),
     font_size: f32 = 24.0,
     text_color: Color = BLACK,
     background_color: Color = TRANSPARENT,
     wrap: Wrap = Wrap::Whitespace,
     align: Align = Align::Start,
+    underline: Option<Range<usize>> = None,
+    /// Draw a caret before the char at this index
+    cursor: Option<usize> = None,
+} state {
+    /// Keeps the font instances drawn last from being evicted from the cache, set when it's drawn
+    font_instances: Vec<ResourceHandle> = Vec::new(),
 }}
 
 impl TextStyle {
@@ -34,14 +48,12 @@ impl TextStyle {
 impl TextState {
     pub fn measure(&self) -> Size {
         let line_height = self.line_height();
-        let mut resources = resources();
-        let font = resources.font_loader.get_font(&self.font).unwrap();
-        Size::from_untyped(&text_layout::get_text_size(
+        self.with_fonts(&self.text, |fonts| Size::from_untyped(&text_layout::get_text_size(
             &self.text,
-            &font.info,
+            fonts,
             self.font_size,
             line_height,
-            self.wrap))
+            self.wrap)))
     }
     pub fn min_height(&self) -> f32 {
         self.line_height()
@@ -51,68 +63,135 @@ impl TextState {
     }
     pub fn text_fits(&self, text: &str, bounds: Rect) -> bool {
         let line_height = self.line_height();
-        let mut resources = resources();
-        let font = resources.font_loader.get_font(&self.font).unwrap();
-        let height = text_layout::get_text_height(
+        let height = self.with_fonts(text, |fonts| text_layout::get_text_height(
             text,
-            &font.info,
+            fonts,
             self.font_size,
             line_height,
             self.wrap,
-            bounds.width());
+            bounds.width()));
         height <= bounds.height()
     }
+    /// The caret rectangle before the char at `char_index`, one pixel wide and the height of it's line.
+    pub fn cursor_rect(&self, bounds: Rect, char_index: usize) -> Option<Rect> {
+        let line_height = self.line_height();
+        self.with_fonts(&self.text, |fonts| {
+            let line_infos: Vec<LineInfo> = LineInfos::new(&self.text, fonts, self.font_size, self.wrap, bounds.width())
+                .collect();
+            cursor::index_before_char(line_infos.iter().cloned(), char_index).and_then(|index| {
+                let xys_per_line = cursor::xys_per_line_from_text(
+                    &line_infos, self.font_size, self.align, line_height, bounds.to_untyped());
+                cursor::xy_at(xys_per_line, index)
+            })
+        }).map(|(x, y_range)| {
+            Rect::new(Point::new(x, y_range.start), Size::new(1.0, y_range.end - y_range.start))
+        })
+    }
+    /// The char index of the caret position visually to the left or right of the caret before
+    /// the char at `char_index`, which in right to left text is after or before it. From the
+    /// edge of a line it moves to the line before or after it.
+    pub fn move_cursor(&self, bounds: Rect, char_index: usize, right: bool) -> Option<usize> {
+        self.with_fonts(&self.text, |fonts| {
+            let line_infos: Vec<LineInfo> = LineInfos::new(&self.text, fonts, self.font_size, self.wrap, bounds.width())
+                .collect();
+            cursor::index_before_char(line_infos.iter().cloned(), char_index).and_then(|index| {
+                if right {
+                    index.right(line_infos.iter().cloned())
+                } else {
+                    index.left(line_infos.iter().cloned())
+                }
+            }).and_then(|index| cursor::char_index(line_infos.iter().cloned(), index))
+        })
+    }
+    /// The rectangles covering the chars in `range`, one for each line the range is on, or more
+    /// if the range is split by a change in text direction.
+    pub fn range_rects(&self, bounds: Rect, range: Range<usize>) -> Vec<Rect> {
+        let line_height = self.line_height();
+        self.with_fonts(&self.text, |fonts| {
+            let line_infos: Vec<LineInfo> = LineInfos::new(&self.text, fonts, self.font_size, self.wrap, bounds.width())
+                .collect();
+            let start = cursor::index_before_char(line_infos.iter().cloned(), range.start);
+            let end = cursor::index_before_char(line_infos.iter().cloned(), range.end);
+            match (start, end) {
+                (Some(start), Some(end)) => {
+                    let line_rects = LineRects::new(
+                        line_infos.iter().cloned(), self.font_size, bounds.to_untyped(), self.align, line_height);
+                    let lines_with_rects = line_infos.iter().cloned().zip(line_rects);
+                    SelectedLineRects::new(lines_with_rects, start, end).map(|rect| Rect::from_untyped(&rect)).collect()
+                }
+                _ => Vec::new(),
+            }
+        })
+    }
     fn get_line_rects(&self, bounds: Rect) -> Vec<Rect> {
         let line_height = self.line_height();
-        let mut resources = resources();
-        let font = resources.font_loader.get_font(&self.font).unwrap();
-        text_layout::get_line_rects(
+        self.with_fonts(&self.text, |fonts| text_layout::get_line_rects(
             &self.text,
             bounds.to_untyped(),
-            &font.info,
+            fonts,
             self.font_size,
             line_height,
             self.wrap,
-            self.align).iter().map(|rect| Rect::from_untyped(rect)).collect()
+            self.align)).iter().map(|rect| Rect::from_untyped(rect)).collect()
     }
-    fn position_glyphs(&self, bounds: Rect) -> Vec<GlyphInstance> {
+    /// Position the glyphs of the text, grouped into runs of consecutive glyphs drawn with the
+    /// same font.
+    fn position_glyphs(&self, bounds: Rect) -> Vec<(FontDescriptor, Vec<GlyphInstance>)> {
         let line_height = self.line_height();
         let descent = self.v_metrics().descent;
         let mut resources = resources();
-        let font = resources.font_loader.get_font(&self.font).unwrap();
-        text_layout::get_positioned_glyphs(
+        let chain = resources.font_loader.get_font_chain(&self.font, &self.fallback_fonts, &self.text).unwrap();
+        let fonts = resources.font_loader.loaded_fonts(&chain);
+        let glyphs = text_layout::get_positioned_glyphs(
             &self.text,
             bounds.to_untyped(),
-            &font.info,
+            FontFallback::new(&fonts),
             self.font_size,
             line_height,
             self.wrap,
-            self.align).iter().map(|glyph| {
-                let position = glyph.position();
-                GlyphInstance {
-                    index: glyph.id().0,
-                    point: LayoutPoint::new(position.x, position.y + descent),
-                }
-            }).collect()
+            self.align);
+        let mut runs: Vec<(usize, Vec<GlyphInstance>)> = Vec::new();
+        for (font_index, glyph) in glyphs {
+            let position = glyph.position();
+            let glyph = GlyphInstance {
+                index: glyph.id().0,
+                point: LayoutPoint::new(position.x, position.y + descent),
+            };
+            let new_run = runs.last().map_or(true, |&(run_font, _)| run_font != font_index);
+            if new_run {
+                runs.push((font_index, Vec::new()));
+            }
+            runs.last_mut().unwrap().1.push(glyph);
+        }
+        runs.into_iter().map(|(font_index, glyphs)| (chain[font_index].clone(), glyphs)).collect()
     }
-    fn font_instance_key(&self) -> FontInstanceKey {
-        *resources().font_loader.get_font_instance(&self.font, self.font_size).unwrap()
+    /// Call `f` with the text's font, followed by the fonts used for characters of `text` it's missing.
+    fn with_fonts<F: FnOnce(FontFallback) -> R, R>(&self, text: &str, f: F) -> R {
+        let mut resources = resources();
+        let chain = resources.font_loader.get_font_chain(&self.font, &self.fallback_fonts, text).unwrap();
+        let fonts = resources.font_loader.loaded_fonts(&chain);
+        f(FontFallback::new(&fonts))
+    }
+    fn font_instance_key(&self, font: &FontDescriptor) -> FontInstanceKey {
+        *resources().font_loader.get_font_instance(font, self.font_size).unwrap()
+    }
+    fn font_instance_handle(&self, font: &FontDescriptor) -> Option<ResourceHandle> {
+        resources().font_loader.font_instance_handle(font, self.font_size)
     }
     fn v_metrics(&self) -> VMetrics {
         let mut resources = resources();
         let font = resources.font_loader.get_font(&self.font).unwrap();
-        font.info.v_metrics(Scale::uniform(self.font_size))
+        font.font().v_metrics(Scale::uniform(self.font_size))
     }
 }
 
 impl Draw for TextState {
     fn draw(&mut self, bounds: Rect, _: Rect, renderer: &mut RenderBuilder) {
-        let glyphs = self.position_glyphs(bounds);
+        let runs = self.position_glyphs(bounds);
         if DEBUG_LINE_BOUNDS {
             let line_rects = self.get_line_rects(bounds);
             let v_metrics = self.v_metrics();
             let mut resources = resources();
-            let font = resources.font_loader.get_font(&self.font).unwrap();
             for mut rect in line_rects {
                 render::draw_rect_outline(rect, CYAN, renderer);
                 rect.origin.y = rect.bottom() + v_metrics.descent;
@@ -120,26 +199,283 @@ impl Draw for TextState {
                 render::draw_rect_outline(rect, RED, renderer);
             }
             let scale = Scale::uniform(self.font_size);
-            for glyph in &glyphs {
-                let scaled_glyph = font.info.glyph(GlyphId(glyph.index)).unwrap().scaled(scale);
-                if let Some(rect) = scaled_glyph.exact_bounding_box() {
-                    let origin = glyph.point.to_vector() + Vector::new(0.0, -1.0);
-                    let rect = Rect::from_rusttype(rect).translate(&origin);
-                    render::draw_rect_outline(rect, BLUE, renderer);
+            for &(ref font, ref glyphs) in &runs {
+                let font = resources.font_loader.get_font(font).unwrap();
+                for glyph in glyphs {
+                    let scaled_glyph = font.font().glyph(GlyphId(glyph.index)).unwrap().scaled(scale);
+                    if let Some(rect) = scaled_glyph.exact_bounding_box() {
+                        let origin = glyph.point.to_vector() + Vector::new(0.0, -1.0);
+                        let rect = Rect::from_rusttype(rect).translate(&origin);
+                        render::draw_rect_outline(rect, BLUE, renderer);
+                    }
                 }
             }
         }
         if self.background_color != TRANSPARENT {
             renderer.builder.push_rect(&PrimitiveInfo::new(bounds.clone()), self.background_color.into());
         }
-        let key = self.font_instance_key();
+        if let Some(ref underline) = self.underline {
+            let descent = self.v_metrics().descent;
+            for rect in self.range_rects(bounds, underline.clone()) {
+                let underline_rect = Rect::new(
+                    Point::new(rect.left(), rect.top() + self.font_size + descent + 1.0),
+                    Size::new(rect.width(), 1.0));
+                renderer.builder.push_rect(&PrimitiveInfo::new(underline_rect), self.text_color.into());
+            }
+        }
+        if let Some(cursor_rect) = self.cursor.and_then(|cursor| self.cursor_rect(bounds, cursor)) {
+            renderer.builder.push_rect(&PrimitiveInfo::new(cursor_rect), self.text_color.into());
+        }
+        let info = PrimitiveInfo::new(bounds);
+        let mut font_instances = Vec::new();
+        for (font, glyphs) in runs {
+            let key = self.font_instance_key(&font);
+            renderer.builder.push_text(
+                &info,
+                &glyphs,
+                key,
+                self.text_color.into(),
+                None,
+            );
+            font_instances.extend(self.font_instance_handle(&font));
+        }
+        self.font_instances = font_instances;
+    }
+}
+
+/// Attributes of a span of rich text, attributes that aren't set are taken from the `RichTextState`.
+#[derive(Clone, Debug, Default)]
+pub struct TextAttributes {
+    pub font: Option<FontDescriptor>,
+    pub font_size: Option<f32>,
+    pub color: Option<Color>,
+    /// Use the bold or regular variant of the font
+    pub bold: Option<bool>,
+    /// Use the italic or upright variant of the font
+    pub italic: Option<bool>,
+    pub underline: bool,
+    pub background: Option<Color>,
+}
+
+/// A run of rich text with it's own attributes.
+#[derive(Clone, Debug)]
+pub struct TextSpan {
+    pub text: String,
+    pub attributes: TextAttributes,
+}
+
+impl TextSpan {
+    pub fn new(text: &str) -> Self {
+        TextSpan {
+            text: String::from(text),
+            attributes: TextAttributes::default(),
+        }
+    }
+    pub fn font(mut self, font: FontDescriptor) -> Self {
+        self.attributes.font = Some(font);
+        self
+    }
+    pub fn font_size(mut self, font_size: f32) -> Self {
+        self.attributes.font_size = Some(font_size);
+        self
+    }
+    pub fn color(mut self, color: Color) -> Self {
+        self.attributes.color = Some(color);
+        self
+    }
+    pub fn bold(mut self) -> Self {
+        self.attributes.bold = Some(true);
+        self
+    }
+    pub fn italic(mut self) -> Self {
+        self.attributes.italic = Some(true);
+        self
+    }
+    pub fn underline(mut self) -> Self {
+        self.attributes.underline = true;
+        self
+    }
+    pub fn background(mut self, color: Color) -> Self {
+        self.attributes.background = Some(color);
+        self
+    }
+}
+
+/// Text made up of spans, each with their own attributes.
+#[derive(Clone, Debug, Default)]
+pub struct AttributedText {
+    pub spans: Vec<TextSpan>,
+}
+
+impl AttributedText {
+    pub fn new() -> Self {
+        AttributedText::default()
+    }
+    /// Add a span to the end of the text.
+    pub fn span(mut self, span: TextSpan) -> Self {
+        self.spans.push(span);
+        self
+    }
+    pub fn push(&mut self, span: TextSpan) {
+        self.spans.push(span);
+    }
+    /// The text of all the spans, without attributes.
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+}
+
+impl<'a> From<&'a str> for AttributedText {
+    fn from(text: &'a str) -> Self {
+        AttributedText::new().span(TextSpan::new(text))
+    }
+}
+
+component_style!{pub struct RichTextState<name="rich_text", style=RichTextStyle> {
+    text: AttributedText = AttributedText::new(),
+    /// The font of spans that don't set one, spans that set `bold` or `italic` use that variant of it
+    font: FontDescriptor = FontDescriptor::from_family("Verdana"),
+    /// Fonts used, in order, for characters missing from each span's font
+    fallback_fonts: Vec<FontDescriptor> = Vec::new(),
+    font_size: f32 = 24.0,
+    text_color: Color = BLACK,
+    background_color: Color = TRANSPARENT,
+    wrap: Wrap = Wrap::Whitespace,
+    align: Align = Align::Start,
+} state {
+    /// Keeps the font instances drawn last from being evicted from the cache, set when it's drawn
+    font_instances: Vec<ResourceHandle> = Vec::new(),
+}}
+
+impl RichTextStyle {
+    pub fn from_text(text: AttributedText) -> Self {
+        Self {
+            text: Some(text),
+            ..Self::default()
+        }
+    }
+}
+
+/// The attributes of a span of rich text, resolved against the `RichTextState`.
+struct SpanStyle {
+    /// The byte index the span starts at
+    start: usize,
+    /// The span's font, followed by the fonts to fall back to
+    fonts: Vec<FontDescriptor>,
+    font_size: f32,
+    color: Color,
+    underline: bool,
+    background: Option<Color>,
+}
+
+impl RichTextState {
+    pub fn measure(&self) -> Size {
+        self.with_spans(|text, spans, _| Size::from_untyped(&rich::get_rich_text_size(text, spans, self.wrap)))
+    }
+    pub fn text_fits(&self, bounds: Rect) -> bool {
+        let height = self.with_spans(|text, spans, _| rich::get_rich_text_height(text, spans, self.wrap, bounds.width()));
+        height <= bounds.height()
+    }
+    /// The style of each non empty span, or of an empty span if the text is empty.
+    fn span_styles(&self, font_loader: &mut FontLoader) -> Vec<SpanStyle> {
+        let mut start = 0;
+        let mut styles = Vec::new();
+        for span in &self.text.spans {
+            if span.text.is_empty() {
+                continue;
+            }
+            styles.push(self.span_style(start, &span.text, &span.attributes, font_loader));
+            start += span.text.len();
+        }
+        if styles.is_empty() {
+            styles.push(self.span_style(0, "", &TextAttributes::default(), font_loader));
+        }
+        styles
+    }
+    fn span_style(&self, start: usize, text: &str, attributes: &TextAttributes, font_loader: &mut FontLoader) -> SpanStyle {
+        let mut font = attributes.font.clone().unwrap_or_else(|| self.font.clone());
+        if let Some(bold) = attributes.bold {
+            font.bold = bold;
+        }
+        if let Some(italic) = attributes.italic {
+            font.italic = italic;
+        }
+        // if the font doesn't have the variant, draw the span in the regular font
+        let fonts = font_loader.get_font_chain(&font, &self.fallback_fonts, text).or_else(|_| {
+            font_loader.get_font_chain(&FontDescriptor::from_family(&font.family_name), &self.fallback_fonts, text)
+        }).unwrap();
+        SpanStyle {
+            start: start,
+            fonts: fonts,
+            font_size: attributes.font_size.unwrap_or(self.font_size),
+            color: attributes.color.unwrap_or(self.text_color),
+            underline: attributes.underline,
+            background: attributes.background,
+        }
+    }
+    /// Call `f` with the text, the layout spans of the text and the style of each span.
+    fn with_spans<F: FnOnce(&str, &[Span], &[SpanStyle]) -> R, R>(&self, f: F) -> R {
+        let text = self.text.text();
+        let mut resources = resources();
+        let styles = self.span_styles(&mut resources.font_loader);
+        let fonts: Vec<Vec<LayoutFont>> = styles.iter().map(|style| resources.font_loader.loaded_fonts(&style.fonts)).collect();
+        let spans: Vec<Span> = styles.iter().zip(&fonts).map(|(style, fonts)| {
+            Span::new(style.start, FontFallback::new(fonts), style.font_size)
+        }).collect();
+        f(&text, &spans, &styles)
+    }
+}
+
+impl Draw for RichTextState {
+    fn draw(&mut self, bounds: Rect, _: Rect, renderer: &mut RenderBuilder) {
+        if self.background_color != TRANSPARENT {
+            renderer.builder.push_rect(&PrimitiveInfo::new(bounds), self.background_color.into());
+        }
+        // runs of glyphs in the same span and font, with the font, size and color they're drawn in
+        let mut runs: Vec<(FontDescriptor, f32, Color, Vec<GlyphInstance>)> = Vec::new();
+        self.with_spans(|text, spans, styles| {
+            let lines = rich::get_rich_lines(text, spans, bounds.to_untyped(), self.wrap, self.align);
+            for span_rect in rich::get_span_rects(spans, &lines) {
+                let style = &styles[span_rect.span];
+                let rect = Rect::from_untyped(&span_rect.rect);
+                if let Some(background) = style.background {
+                    renderer.builder.push_rect(&PrimitiveInfo::new(rect), background.into());
+                }
+                if style.underline {
+                    let underline_rect = Rect::new(
+                        Point::new(rect.left(), span_rect.baseline + 1.0),
+                        Size::new(rect.width(), 1.0));
+                    renderer.builder.push_rect(&PrimitiveInfo::new(underline_rect), style.color.into());
+                }
+            }
+            let mut last_run = None;
+            for glyph in rich::get_rich_positioned_glyphs(spans, &lines) {
+                let style = &styles[glyph.span];
+                if last_run != Some((glyph.span, glyph.font)) {
+                    runs.push((style.fonts[glyph.font].clone(), style.font_size, style.color, Vec::new()));
+                    last_run = Some((glyph.span, glyph.font));
+                }
+                let position = glyph.glyph.position();
+                runs.last_mut().unwrap().3.push(GlyphInstance {
+                    index: glyph.glyph.id().0,
+                    point: LayoutPoint::new(position.x, position.y),
+                });
+            }
+        });
         let info = PrimitiveInfo::new(bounds);
-        renderer.builder.push_text(
-            &info,
-            &glyphs,
-            key,
-            self.text_color.into(),
-            None,
-        );
+        let mut font_instances = Vec::new();
+        for (font, font_size, color, glyphs) in runs {
+            let mut resources = resources();
+            let key = *resources.font_loader.get_font_instance(&font, font_size).unwrap();
+            renderer.builder.push_text(
+                &info,
+                &glyphs,
+                key,
+                color.into(),
+                None,
+            );
+            font_instances.extend(resources.font_loader.font_instance_handle(&font, font_size));
+        }
+        self.font_instances = font_instances;
     }
 }
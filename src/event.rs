@@ -30,17 +30,20 @@
 //! UI state. This means to keep your app responsive, any event handler that needs to block or do long running work must do
 //! it on another thread, either by spawning or notifying a thread, which can then send an event back to the UI when it's
 //! ready. The `event_global` helper method makes this easier, it is equivalent to `Ui::event` but requires the event be
-//! `Send` and can be called from any thread, without a reference to the `Ui`. `Widget` and any other types that can
-//! modify the UI are not thread safe, so can't currently be referenced from other threads, so if any specific widgets need
-//! to be notified from another thread, it's necessary to add a handler to the root widget to forward events.
+//! `Send` and can be called from any thread, without a reference to the `Ui`. `event_global_after` does the same once a
+//! delay has passed, without a thread of it's own for each timeout. `Widget` and any other types that can modify the UI
+//! are not thread safe, so can't currently be referenced from other threads, so if any specific widgets need to be
+//! notified from another thread, it's necessary to add a handler to the root widget to forward events.
 //!
 //! For further explanation of the single threaded event architecture see
 //! https://github.com/christolliday/limn/pull/20#discussion_r145373568
 
 use std::any::{Any, TypeId};
 use std::cell::{Cell, RefCell};
-use std::sync::Mutex;
-use std::collections::VecDeque;
+use std::sync::{Mutex, Condvar};
+use std::collections::{VecDeque, BTreeMap};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use glutin::{EventsLoop, EventsLoopProxy};
 
@@ -159,6 +162,7 @@ impl EventHandlerWrapper {
 lazy_static! {
     static ref FIRST_THREAD: Mutex<Cell<bool>> = Mutex::new(Cell::new(true));
     static ref GLOBAL_QUEUE: Mutex<GlobalQueue> = Mutex::new(GlobalQueue::new());
+    static ref TIMER: Timer = Timer::new();
 }
 
 thread_local! {
@@ -205,6 +209,65 @@ pub fn event_global<T: 'static + Send>(data: T) {
     GLOBAL_QUEUE.lock().unwrap().push(data);
 }
 
+/// Send message to UI from any thread, once the delay has passed.
+/// Timed events can't be cancelled, a handler that no longer wants one should ignore it when it arrives.
+pub fn event_global_after<T: 'static + Send>(delay: Duration, data: T) {
+    TIMER.schedule(Instant::now() + delay, TypeId::of::<T>(), Box::new(data));
+}
+
+/// Holds the events sent with `event_global_after`, until they are due.
+/// All timed events share a single thread, started when the first one is sent.
+struct Timer {
+    pending: Mutex<TimerQueue>,
+    condvar: Condvar,
+}
+
+struct TimerQueue {
+    /// Ordered by when they are due, then by when they were sent
+    events: BTreeMap<(Instant, u64), (TypeId, Box<Any + Send>)>,
+    next_id: u64,
+    started: bool,
+}
+
+impl Timer {
+    fn new() -> Self {
+        Timer {
+            pending: Mutex::new(TimerQueue {
+                events: BTreeMap::new(),
+                next_id: 0,
+                started: false,
+            }),
+            condvar: Condvar::new(),
+        }
+    }
+    fn schedule(&self, due: Instant, type_id: TypeId, data: Box<Any + Send>) {
+        let mut pending = self.pending.lock().unwrap();
+        let id = pending.next_id;
+        pending.next_id += 1;
+        pending.events.insert((due, id), (type_id, data));
+        if !pending.started {
+            pending.started = true;
+            thread::spawn(|| TIMER.run());
+        }
+        self.condvar.notify_one();
+    }
+    fn run(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            let next = pending.events.keys().next().cloned();
+            match next {
+                Some(key) if key.0 <= now => {
+                    let (type_id, data) = pending.events.remove(&key).unwrap();
+                    GLOBAL_QUEUE.lock().unwrap().push_any(type_id, data);
+                }
+                Some(key) => pending = self.condvar.wait_timeout(pending, key.0 - now).unwrap().0,
+                None => pending = self.condvar.wait(pending).unwrap(),
+            }
+        }
+    }
+}
+
 struct GlobalQueue {
     queue: VecDeque<(TypeId, Box<Any + Send>)>,
     events_loop_proxy: Option<EventsLoopProxy>,
@@ -222,8 +285,10 @@ impl GlobalQueue {
     }
     /// Push a new event on the queue and wake the window up if it is asleep
     pub fn push<T: 'static + Send>(&mut self, data: T) {
-        let type_id = TypeId::of::<T>();
-        self.queue.push_back((type_id, Box::new(data)));
+        self.push_any(TypeId::of::<T>(), Box::new(data));
+    }
+    fn push_any(&mut self, type_id: TypeId, data: Box<Any + Send>) {
+        self.queue.push_back((type_id, data));
         if let Some(ref events_loop_proxy) = self.events_loop_proxy {
             events_loop_proxy.wakeup().unwrap();
         }
@@ -2,14 +2,15 @@ use glutin;
 
 use event::{EventHandler, EventArgs};
 use widget::Widget;
-use input::mouse::{MouseMoved, MouseButton, WidgetMouseButton};
+use input::mouse::{MouseMoved, MouseButton, WidgetMouseButton, CursorLeftWindow};
+use input::touch::{TouchEvent, TouchPhase, WidgetTouchEvent};
 use geometry::{Point, Vector};
 use app::App;
 
 #[derive(Debug, Copy, Clone)]
 pub struct DragEvent {
     pub state: DragState,
-    /// mouse position
+    /// mouse or touch position
     pub position: Point,
     /// offset from drag start
     pub offset: Vector,
@@ -27,6 +28,12 @@ pub enum DragState {
 #[derive(Debug, Clone)]
 struct DragInputHandler {
     widget: Option<Widget>,
+    /// The touch dragging the widget, `None` if it's dragged by the mouse
+    touch: Option<u64>,
+    /// The number of mouse buttons held, a press on a widget is only seen after the button
+    /// event, so it may arrive after the button has been released again
+    mouse_buttons: usize,
+    mouse_position: Point,
     position: Point,
     start_position: Point,
     prev_position: Point,
@@ -36,6 +43,9 @@ impl DragInputHandler {
     pub fn new() -> Self {
         DragInputHandler {
             widget: None,
+            touch: None,
+            mouse_buttons: 0,
+            mouse_position: Point::new(0.0, 0.0),
             position: Point::new(0.0, 0.0),
             start_position: Point::new(0.0, 0.0),
             prev_position: Point::new(0.0, 0.0),
@@ -55,50 +65,119 @@ impl EventHandler<DragInputEvent> for DragInputHandler {
     fn handle(&mut self, event: &DragInputEvent, _: EventArgs) {
         match *event {
             DragInputEvent::WidgetPressed(ref widget) => {
-                self.widget = Some(widget.clone());
-                self.start_position = self.position;
-                widget.event(self.drag_event(DragState::Start));
+                if self.widget.is_none() && self.mouse_buttons > 0 {
+                    self.widget = Some(widget.clone());
+                    self.position = self.mouse_position;
+                    self.start_position = self.position;
+                    self.prev_position = self.position;
+                    widget.event(self.drag_event(DragState::Start));
+                }
+            }
+            DragInputEvent::WidgetTouched(ref widget, id, point) => {
+                if self.widget.is_none() {
+                    self.widget = Some(widget.clone());
+                    self.touch = Some(id);
+                    self.position = point;
+                    self.start_position = point;
+                    self.prev_position = point;
+                    widget.event(self.drag_event(DragState::Start));
+                }
             }
             DragInputEvent::MouseMoved(point) => {
-                self.prev_position = self.position;
-                self.position = point;
-                if let Some(ref widget) = self.widget {
-                    widget.event(self.drag_event(DragState::Moved));
+                self.mouse_position = point;
+                if self.touch.is_none() {
+                    self.moved(point);
                 }
             }
+            DragInputEvent::MousePressed => {
+                self.mouse_buttons += 1;
+            }
             DragInputEvent::MouseReleased => {
-                if let Some(widget) = self.widget.take() {
-                    widget.event(self.drag_event(DragState::End));
+                self.mouse_buttons = self.mouse_buttons.saturating_sub(1);
+                if self.touch.is_none() {
+                    self.end();
+                }
+            }
+            DragInputEvent::MouseCancelled => {
+                self.mouse_buttons = 0;
+                if self.touch.is_none() {
+                    self.end();
+                }
+            }
+            DragInputEvent::TouchMoved(id, point) => {
+                if self.touch == Some(id) {
+                    self.moved(point);
+                }
+            }
+            DragInputEvent::TouchEnded(id) => {
+                if self.touch == Some(id) {
+                    self.touch = None;
+                    self.end();
                 }
             }
         }
     }
 }
 
+impl DragInputHandler {
+    fn moved(&mut self, point: Point) {
+        self.prev_position = self.position;
+        self.position = point;
+        if let Some(ref widget) = self.widget {
+            widget.event(self.drag_event(DragState::Moved));
+        }
+    }
+    fn end(&mut self) {
+        if let Some(widget) = self.widget.take() {
+            widget.event(self.drag_event(DragState::End));
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 enum DragInputEvent {
     WidgetPressed(Widget),
+    WidgetTouched(Widget, u64, Point),
     MouseMoved(Point),
+    MousePressed,
     MouseReleased,
+    MouseCancelled,
+    TouchMoved(u64, Point),
+    TouchEnded(u64),
 }
 
 impl Widget {
-    /// Make a widget receive drag events.
+    /// Make a widget receive drag events. The widget captures the mouse while it is dragged.
     pub fn make_draggable(&mut self) -> &mut Self {
+        // keep receiving mouse input when the cursor moves off the widget
+        self.set_captures_mouse(true);
         self.add_handler(|event: &WidgetMouseButton, args: EventArgs| {
             if let WidgetMouseButton(glutin::ElementState::Pressed, _) = *event {
                 let event = DragInputEvent::WidgetPressed(args.widget);
                 args.ui.event(event);
             }
         });
+        self.add_handler(|event: &WidgetTouchEvent, args: EventArgs| {
+            let &WidgetTouchEvent(touch) = event;
+            match touch.phase {
+                TouchPhase::Start => {
+                    let event = DragInputEvent::WidgetTouched(args.widget, touch.id, touch.position);
+                    args.ui.event(event);
+                }
+                // the drag owns the touch, so the recognizers of ancestors, eg. a scroll container,
+                // don't pan at the same time, the end isn't handled so they see it and reset
+                TouchPhase::Moved => *args.handled = true,
+                TouchPhase::End | TouchPhase::Cancelled => (),
+            }
+        });
         self
     }
 }
 
 impl App {
     /// Add handlers to UI to enable drag detection
-    /// UI receives messages from draggable widgets when they are clicked,
-    /// and combines that with mouse move and mouse release events to
+    /// UI receives messages from draggable widgets when they are clicked or touched,
+    /// and combines that with mouse or touch move and release events to
     /// synthesize `DragEvent`s
     pub fn add_drag_handlers(&mut self) {
         self.add_handler(DragInputHandler::new());
@@ -106,8 +185,20 @@ impl App {
             args.ui.event(DragInputEvent::MouseMoved(event.0));
         });
         self.add_handler(|event: &MouseButton, args: EventArgs| {
-            if let MouseButton(glutin::ElementState::Released, _) = *event {
-                args.ui.event(DragInputEvent::MouseReleased);
+            match *event {
+                MouseButton(glutin::ElementState::Pressed, _) => args.ui.event(DragInputEvent::MousePressed),
+                MouseButton(glutin::ElementState::Released, _) => args.ui.event(DragInputEvent::MouseReleased),
+            }
+        });
+        // leaving the window cancels the mouse capture, ending the drag
+        self.add_handler(|_: &CursorLeftWindow, args: EventArgs| {
+            args.ui.event(DragInputEvent::MouseCancelled);
+        });
+        self.add_handler(|event: &TouchEvent, args: EventArgs| {
+            match event.phase {
+                TouchPhase::Moved => args.ui.event(DragInputEvent::TouchMoved(event.id, event.position)),
+                TouchPhase::End | TouchPhase::Cancelled => args.ui.event(DragInputEvent::TouchEnded(event.id)),
+                TouchPhase::Start => (),
             }
         });
     }
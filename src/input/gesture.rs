@@ -0,0 +1,743 @@
+//! Gesture recognizers, turning the touch events received by a widget into gesture events.
+//!
+//! Recognizers are event handlers for `WidgetTouchEvent`, added to a widget with `add_handler`.
+//! Any number of them can be combined on one widget, eg. a `TapRecognizer` and a `PanRecognizer`,
+//! each sees every touch and sends it's own gesture events to the widget. Touches that start on a
+//! widget's descendants bubble up, so are seen by it's recognizers too.
+//!
+//! A recognizer marks the touch moves that are part of a pan or pinch it recognized as handled,
+//! so the recognizers of an ancestor, eg. of nested scroll containers, don't pan at the same time.
+//!
+//! The state machine of each recognizer is exposed as it's `recognize` method, which takes a
+//! `TouchEvent` and returns the gesture events it completes, without needing a widget or `Ui`, so
+//! gestures can be tested with synthesized touch events. The recognizers only depend on the
+//! position and time of those events, apart from `LongPressRecognizer`, which also uses a timer.
+
+use std::time::{Duration, Instant};
+
+use event::{self, EventHandler, EventArgs};
+use widget::Widget;
+use input::touch::{TouchEvent, TouchPhase, WidgetTouchEvent};
+use input::drag::DragState;
+use resources::WidgetId;
+use geometry::{Point, Vector};
+
+#[derive(Debug, Copy, Clone)]
+pub struct TapEvent {
+    pub position: Point,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DoubleTapEvent {
+    pub position: Point,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct LongPressEvent {
+    pub position: Point,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct PanEvent {
+    pub state: DragState,
+    /// touch position
+    pub position: Point,
+    /// offset from the touch start
+    pub offset: Vector,
+    /// change since last PanEvent
+    pub change: Vector,
+    /// in pixels per second
+    pub velocity: Vector,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct PinchEvent {
+    pub state: DragState,
+    /// midpoint of the two touches
+    pub center: Point,
+    /// distance between the touches, relative to the distance when the pinch started
+    pub scale: f32,
+    /// ratio of the scale to the scale of the last PinchEvent
+    pub change: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct SwipeEvent {
+    pub direction: SwipeDirection,
+    /// in pixels per second, when the touch ended
+    pub velocity: Vector,
+}
+
+fn seconds(duration: Duration) -> f32 {
+    duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000.0
+}
+
+/// Follows the position and velocity of a single touch.
+#[derive(Debug, Copy, Clone)]
+struct TouchTracker {
+    id: u64,
+    start: Point,
+    start_time: Instant,
+    position: Point,
+    time: Instant,
+    velocity: Vector,
+}
+
+impl TouchTracker {
+    fn new(event: &TouchEvent) -> Self {
+        TouchTracker {
+            id: event.id,
+            start: event.position,
+            start_time: event.time,
+            position: event.position,
+            time: event.time,
+            velocity: Vector::zero(),
+        }
+    }
+    /// Update with a new position, returns the change since the last position.
+    fn moved(&mut self, event: &TouchEvent) -> Vector {
+        let change = event.position - self.position;
+        let elapsed = seconds(event.time.duration_since(self.time));
+        if elapsed > 0.0 {
+            // smooth out jitter in the sampled positions
+            self.velocity = change / elapsed * 0.7 + self.velocity * 0.3;
+        }
+        self.position = event.position;
+        self.time = event.time;
+        change
+    }
+    fn offset(&self) -> Vector {
+        self.position - self.start
+    }
+}
+
+/// Recognizes a touch that is released quickly without moving, sends `TapEvent`.
+pub struct TapRecognizer {
+    /// How far the touch can move and still be a tap
+    pub slop: f32,
+    pub max_duration: Duration,
+    touch: Option<(u64, Point, Instant)>,
+    active_touches: usize,
+}
+
+impl Default for TapRecognizer {
+    fn default() -> Self {
+        TapRecognizer {
+            slop: 10.0,
+            max_duration: Duration::from_millis(300),
+            touch: None,
+            active_touches: 0,
+        }
+    }
+}
+
+impl TapRecognizer {
+    /// Returns the tap the event completes, if any.
+    pub fn recognize(&mut self, event: &TouchEvent) -> Option<TapEvent> {
+        match event.phase {
+            TouchPhase::Start => {
+                self.active_touches += 1;
+                // a touch with more than one finger isn't a tap
+                self.touch = if self.active_touches == 1 {
+                    Some((event.id, event.position, event.time))
+                } else {
+                    None
+                };
+            }
+            TouchPhase::Moved => {
+                if let Some((id, start, _)) = self.touch {
+                    if id == event.id && (event.position - start).length() > self.slop {
+                        self.touch = None;
+                    }
+                }
+            }
+            TouchPhase::End | TouchPhase::Cancelled => {
+                self.active_touches = self.active_touches.saturating_sub(1);
+                if let Some((id, start, time)) = self.touch {
+                    if id == event.id {
+                        self.touch = None;
+                        let quick = event.time.duration_since(time) <= self.max_duration;
+                        // moves handled by a descendant's recognizer aren't seen here
+                        let still = (event.position - start).length() <= self.slop;
+                        if event.phase == TouchPhase::End && quick && still {
+                            return Some(TapEvent { position: start });
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl EventHandler<WidgetTouchEvent> for TapRecognizer {
+    fn handle(&mut self, event: &WidgetTouchEvent, args: EventArgs) {
+        if let Some(tap) = self.recognize(&event.0) {
+            args.widget.event(tap);
+        }
+    }
+}
+
+/// Recognizes two taps in quick succession, close together, sends `DoubleTapEvent`.
+pub struct DoubleTapRecognizer {
+    pub tap: TapRecognizer,
+    /// Maximum time between the end of the first tap and the end of the second
+    pub interval: Duration,
+    /// How far apart the two taps can be
+    pub distance: f32,
+    last_tap: Option<(Point, Instant)>,
+}
+
+impl Default for DoubleTapRecognizer {
+    fn default() -> Self {
+        DoubleTapRecognizer {
+            tap: TapRecognizer::default(),
+            interval: Duration::from_millis(300),
+            distance: 30.0,
+            last_tap: None,
+        }
+    }
+}
+
+impl DoubleTapRecognizer {
+    /// Returns the double tap the event completes, if any.
+    pub fn recognize(&mut self, event: &TouchEvent) -> Option<DoubleTapEvent> {
+        if let Some(TapEvent { position }) = self.tap.recognize(event) {
+            let double_tap = self.last_tap.map_or(false, |(last_position, last_time)| {
+                event.time.duration_since(last_time) <= self.interval &&
+                    (position - last_position).length() <= self.distance
+            });
+            if double_tap {
+                self.last_tap = None;
+                return Some(DoubleTapEvent { position: position });
+            }
+            self.last_tap = Some((position, event.time));
+        }
+        None
+    }
+}
+
+impl EventHandler<WidgetTouchEvent> for DoubleTapRecognizer {
+    fn handle(&mut self, event: &WidgetTouchEvent, args: EventArgs) {
+        if let Some(double_tap) = self.recognize(&event.0) {
+            args.widget.event(double_tap);
+        }
+    }
+}
+
+/// Sent from the timer thread to the root widget, and forwarded to the widget as `LongPressTimeout`.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct GestureTimeout {
+    pub widget: WidgetId,
+    pub touch: u64,
+    pub start: Instant,
+}
+
+/// Identifies the touch by it's id and start time.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct LongPressTimeout(pub u64, pub Instant);
+
+multi_event!{impl EventHandler<LongPressRecognizerEvent> for LongPressRecognizer {
+    WidgetTouchEvent => touch,
+    LongPressTimeout => timeout,
+}}
+
+/// Recognizes a touch held without moving, sends `LongPressEvent`.
+/// Unlike other recognizers, this must be added with `Widget::add_long_press_recognizer`.
+pub struct LongPressRecognizer {
+    /// How far the touch can move and still be a long press
+    pub slop: f32,
+    pub duration: Duration,
+    touch: Option<TouchTracker>,
+}
+
+impl Default for LongPressRecognizer {
+    fn default() -> Self {
+        LongPressRecognizer {
+            slop: 10.0,
+            duration: Duration::from_millis(500),
+            touch: None,
+        }
+    }
+}
+
+impl LongPressRecognizer {
+    /// Returns true if the event starts a touch that can become a long press, in which case
+    /// `recognize_timeout` should be called with the touch's id and start time once `duration` has passed.
+    pub fn recognize(&mut self, event: &TouchEvent) -> bool {
+        match event.phase {
+            TouchPhase::Start => {
+                if self.touch.is_none() {
+                    self.touch = Some(TouchTracker::new(event));
+                    return true;
+                }
+            }
+            TouchPhase::Moved => {
+                let slop = self.slop;
+                let moved_away = self.touch.as_mut().map_or(false, |touch| {
+                    touch.id == event.id && {
+                        touch.moved(event);
+                        touch.offset().length() > slop
+                    }
+                });
+                if moved_away {
+                    self.touch = None;
+                }
+            }
+            TouchPhase::End | TouchPhase::Cancelled => {
+                if self.touch.map_or(false, |touch| touch.id == event.id) {
+                    self.touch = None;
+                }
+            }
+        }
+        false
+    }
+
+    /// Returns the long press, if the touch started at `start` is still held without moving.
+    pub fn recognize_timeout(&mut self, id: u64, start: Instant) -> Option<LongPressEvent> {
+        if let Some(touch) = self.touch {
+            // the timer may be for an earlier touch with the same id
+            if touch.id == id && touch.start_time == start {
+                self.touch = None;
+                return Some(LongPressEvent { position: touch.start });
+            }
+        }
+        None
+    }
+
+    fn touch(&mut self, event: &WidgetTouchEvent, args: EventArgs) {
+        let event = &event.0;
+        if self.recognize(event) {
+            let timeout = GestureTimeout {
+                widget: args.widget.id(),
+                touch: event.id,
+                start: event.time,
+            };
+            event::event_global_after(self.duration, timeout);
+        }
+    }
+
+    fn timeout(&mut self, event: &LongPressTimeout, args: EventArgs) {
+        let &LongPressTimeout(id, start) = event;
+        if let Some(long_press) = self.recognize_timeout(id, start) {
+            args.widget.event(long_press);
+        }
+    }
+}
+
+/// Recognizes a touch moving across the widget, sends `PanEvent`s as it moves.
+pub struct PanRecognizer {
+    /// How far the touch has to move before panning starts
+    pub threshold: f32,
+    touch: Option<(TouchTracker, bool)>,
+}
+
+impl Default for PanRecognizer {
+    fn default() -> Self {
+        PanRecognizer {
+            threshold: 10.0,
+            touch: None,
+        }
+    }
+}
+
+impl PanRecognizer {
+    fn pan_event(&self, touch: &TouchTracker, state: DragState, change: Vector) -> PanEvent {
+        PanEvent {
+            state: state,
+            position: touch.position,
+            offset: touch.offset(),
+            change: change,
+            velocity: touch.velocity,
+        }
+    }
+
+    /// Returns the pan events caused by the event, none until the touch has moved past the threshold.
+    pub fn recognize(&mut self, event: &TouchEvent) -> Vec<PanEvent> {
+        let mut events = Vec::new();
+        match event.phase {
+            TouchPhase::Start => {
+                if self.touch.is_none() {
+                    self.touch = Some((TouchTracker::new(event), false));
+                }
+            }
+            TouchPhase::Moved => {
+                if let Some((mut touch, mut panning)) = self.touch {
+                    if touch.id != event.id {
+                        return events;
+                    }
+                    let change = touch.moved(event);
+                    if panning {
+                        events.push(self.pan_event(&touch, DragState::Moved, change));
+                    } else if touch.offset().length() > self.threshold {
+                        panning = true;
+                        events.push(self.pan_event(&touch, DragState::Start, Vector::zero()));
+                        events.push(self.pan_event(&touch, DragState::Moved, touch.offset()));
+                    }
+                    self.touch = Some((touch, panning));
+                }
+            }
+            TouchPhase::End | TouchPhase::Cancelled => {
+                if let Some((touch, panning)) = self.touch {
+                    if touch.id == event.id {
+                        self.touch = None;
+                        if panning {
+                            events.push(self.pan_event(&touch, DragState::End, Vector::zero()));
+                        }
+                    }
+                }
+            }
+        }
+        events
+    }
+}
+
+impl EventHandler<WidgetTouchEvent> for PanRecognizer {
+    fn handle(&mut self, event: &WidgetTouchEvent, args: EventArgs) {
+        let events = self.recognize(&event.0);
+        // the end of the touch isn't handled, so ancestors' recognizers see it too and reset
+        if event.0.phase == TouchPhase::Moved && !events.is_empty() {
+            *args.handled = true;
+        }
+        for pan in events {
+            args.widget.event(pan);
+        }
+    }
+}
+
+/// Recognizes a quick movement of a touch in one direction, sends `SwipeEvent` when the touch ends.
+pub struct SwipeRecognizer {
+    pub min_distance: f32,
+    /// In pixels per second
+    pub min_velocity: f32,
+    touch: Option<TouchTracker>,
+}
+
+impl Default for SwipeRecognizer {
+    fn default() -> Self {
+        SwipeRecognizer {
+            min_distance: 50.0,
+            min_velocity: 300.0,
+            touch: None,
+        }
+    }
+}
+
+impl SwipeRecognizer {
+    /// Returns the swipe the event completes, if any.
+    pub fn recognize(&mut self, event: &TouchEvent) -> Option<SwipeEvent> {
+        match event.phase {
+            TouchPhase::Start => {
+                if self.touch.is_none() {
+                    self.touch = Some(TouchTracker::new(event));
+                }
+            }
+            TouchPhase::Moved => {
+                if let Some(ref mut touch) = self.touch {
+                    if touch.id == event.id {
+                        touch.moved(event);
+                    }
+                }
+            }
+            TouchPhase::End | TouchPhase::Cancelled => {
+                if let Some(touch) = self.touch {
+                    if touch.id != event.id {
+                        return None;
+                    }
+                    self.touch = None;
+                    // the end is usually where the last move was, so the velocity is taken from
+                    // the moves, a zero length change would slow it down
+                    let velocity = touch.velocity;
+                    let is_swipe = event.phase == TouchPhase::End &&
+                        (event.position - touch.start).length() >= self.min_distance &&
+                        velocity.length() >= self.min_velocity;
+                    if is_swipe {
+                        let direction = if velocity.x.abs() > velocity.y.abs() {
+                            if velocity.x > 0.0 { SwipeDirection::Right } else { SwipeDirection::Left }
+                        } else {
+                            if velocity.y > 0.0 { SwipeDirection::Down } else { SwipeDirection::Up }
+                        };
+                        return Some(SwipeEvent {
+                            direction: direction,
+                            velocity: velocity,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl EventHandler<WidgetTouchEvent> for SwipeRecognizer {
+    fn handle(&mut self, event: &WidgetTouchEvent, args: EventArgs) {
+        if let Some(swipe) = self.recognize(&event.0) {
+            args.widget.event(swipe);
+        }
+    }
+}
+
+/// Recognizes two touches moving closer together or further apart, sends `PinchEvent`s.
+#[derive(Default)]
+pub struct PinchRecognizer {
+    touches: Vec<(u64, Point)>,
+    start_distance: f32,
+    scale: f32,
+}
+
+impl PinchRecognizer {
+    fn pinching(&self) -> bool {
+        self.touches.len() == 2
+    }
+    fn distance(&self) -> f32 {
+        (self.touches[1].1 - self.touches[0].1).length()
+    }
+    fn pinch_event(&self, state: DragState, scale: f32) -> PinchEvent {
+        let center = self.touches[0].1 + (self.touches[1].1 - self.touches[0].1) / 2.0;
+        PinchEvent {
+            state: state,
+            center: center,
+            scale: scale,
+            change: scale / self.scale,
+        }
+    }
+
+    /// Returns the pinch event caused by the event, if two touches are down.
+    pub fn recognize(&mut self, event: &TouchEvent) -> Option<PinchEvent> {
+        match event.phase {
+            TouchPhase::Start => {
+                if self.touches.len() < 2 {
+                    self.touches.push((event.id, event.position));
+                    if self.pinching() {
+                        self.start_distance = self.distance();
+                        self.scale = 1.0;
+                        return Some(self.pinch_event(DragState::Start, 1.0));
+                    }
+                }
+            }
+            TouchPhase::Moved => {
+                if let Some(index) = self.touches.iter().position(|&(id, _)| id == event.id) {
+                    self.touches[index].1 = event.position;
+                    if self.pinching() && self.start_distance > 0.0 {
+                        let scale = self.distance() / self.start_distance;
+                        let pinch = self.pinch_event(DragState::Moved, scale);
+                        self.scale = scale;
+                        return Some(pinch);
+                    }
+                }
+            }
+            TouchPhase::End | TouchPhase::Cancelled => {
+                if let Some(index) = self.touches.iter().position(|&(id, _)| id == event.id) {
+                    let pinch = if self.pinching() {
+                        let scale = self.scale;
+                        Some(self.pinch_event(DragState::End, scale))
+                    } else {
+                        None
+                    };
+                    self.touches.remove(index);
+                    return pinch;
+                }
+            }
+        }
+        None
+    }
+}
+
+impl EventHandler<WidgetTouchEvent> for PinchRecognizer {
+    fn handle(&mut self, event: &WidgetTouchEvent, args: EventArgs) {
+        if let Some(pinch) = self.recognize(&event.0) {
+            if event.0.phase == TouchPhase::Moved {
+                *args.handled = true;
+            }
+            args.widget.event(pinch);
+        }
+    }
+}
+
+impl Widget {
+    pub fn add_long_press_recognizer(&mut self, recognizer: LongPressRecognizer) -> &mut Self {
+        self.add_handler(recognizer);
+        LongPressRecognizer::add_adapters(self);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A touch event `ms` milliseconds after `start`.
+    fn touch(start: Instant, ms: u64, id: u64, phase: TouchPhase, x: f32, y: f32) -> TouchEvent {
+        TouchEvent {
+            id: id,
+            phase: phase,
+            position: Point::new(x, y),
+            time: start + Duration::from_millis(ms),
+        }
+    }
+
+    #[test]
+    fn tap() {
+        let start = Instant::now();
+        let mut recognizer = TapRecognizer::default();
+        assert!(recognizer.recognize(&touch(start, 0, 1, TouchPhase::Start, 10.0, 10.0)).is_none());
+        assert!(recognizer.recognize(&touch(start, 50, 1, TouchPhase::Moved, 12.0, 10.0)).is_none());
+        let tap = recognizer.recognize(&touch(start, 100, 1, TouchPhase::End, 12.0, 10.0)).unwrap();
+        assert_eq!(tap.position, Point::new(10.0, 10.0));
+    }
+
+    #[test]
+    fn tap_too_slow_or_moved() {
+        let start = Instant::now();
+        let mut recognizer = TapRecognizer::default();
+        recognizer.recognize(&touch(start, 0, 1, TouchPhase::Start, 10.0, 10.0));
+        assert!(recognizer.recognize(&touch(start, 400, 1, TouchPhase::End, 10.0, 10.0)).is_none());
+
+        recognizer.recognize(&touch(start, 500, 2, TouchPhase::Start, 10.0, 10.0));
+        recognizer.recognize(&touch(start, 550, 2, TouchPhase::Moved, 40.0, 10.0));
+        recognizer.recognize(&touch(start, 560, 2, TouchPhase::Moved, 10.0, 10.0));
+        assert!(recognizer.recognize(&touch(start, 600, 2, TouchPhase::End, 10.0, 10.0)).is_none());
+
+        // the moves were handled by another recognizer, only the end is seen
+        recognizer.recognize(&touch(start, 700, 3, TouchPhase::Start, 10.0, 10.0));
+        assert!(recognizer.recognize(&touch(start, 750, 3, TouchPhase::End, 60.0, 10.0)).is_none());
+    }
+
+    #[test]
+    fn tap_with_two_fingers() {
+        let start = Instant::now();
+        let mut recognizer = TapRecognizer::default();
+        recognizer.recognize(&touch(start, 0, 1, TouchPhase::Start, 10.0, 10.0));
+        recognizer.recognize(&touch(start, 10, 2, TouchPhase::Start, 50.0, 10.0));
+        assert!(recognizer.recognize(&touch(start, 50, 2, TouchPhase::End, 50.0, 10.0)).is_none());
+        assert!(recognizer.recognize(&touch(start, 60, 1, TouchPhase::End, 10.0, 10.0)).is_none());
+    }
+
+    #[test]
+    fn double_tap() {
+        let start = Instant::now();
+        let mut recognizer = DoubleTapRecognizer::default();
+        recognizer.recognize(&touch(start, 0, 1, TouchPhase::Start, 10.0, 10.0));
+        assert!(recognizer.recognize(&touch(start, 50, 1, TouchPhase::End, 10.0, 10.0)).is_none());
+        recognizer.recognize(&touch(start, 150, 1, TouchPhase::Start, 15.0, 10.0));
+        let double_tap = recognizer.recognize(&touch(start, 200, 1, TouchPhase::End, 15.0, 10.0)).unwrap();
+        assert_eq!(double_tap.position, Point::new(15.0, 10.0));
+
+        // a third tap starts a new double tap
+        recognizer.recognize(&touch(start, 300, 1, TouchPhase::Start, 15.0, 10.0));
+        assert!(recognizer.recognize(&touch(start, 350, 1, TouchPhase::End, 15.0, 10.0)).is_none());
+    }
+
+    #[test]
+    fn long_press() {
+        let start = Instant::now();
+        let mut recognizer = LongPressRecognizer::default();
+        let down = touch(start, 0, 1, TouchPhase::Start, 10.0, 10.0);
+        assert!(recognizer.recognize(&down));
+        assert!(!recognizer.recognize(&touch(start, 100, 1, TouchPhase::Moved, 14.0, 10.0)));
+        let long_press = recognizer.recognize_timeout(1, down.time).unwrap();
+        assert_eq!(long_press.position, Point::new(10.0, 10.0));
+        assert!(recognizer.recognize_timeout(1, down.time).is_none());
+    }
+
+    #[test]
+    fn long_press_moved_or_released() {
+        let start = Instant::now();
+        let mut recognizer = LongPressRecognizer::default();
+        let down = touch(start, 0, 1, TouchPhase::Start, 10.0, 10.0);
+        recognizer.recognize(&down);
+        recognizer.recognize(&touch(start, 100, 1, TouchPhase::Moved, 40.0, 10.0));
+        assert!(recognizer.recognize_timeout(1, down.time).is_none());
+
+        // the timer of an earlier touch with the same id is ignored
+        let down = touch(start, 200, 1, TouchPhase::Start, 10.0, 10.0);
+        recognizer.recognize(&down);
+        recognizer.recognize(&touch(start, 300, 1, TouchPhase::End, 10.0, 10.0));
+        let down_again = touch(start, 400, 1, TouchPhase::Start, 10.0, 10.0);
+        recognizer.recognize(&down_again);
+        assert!(recognizer.recognize_timeout(1, down.time).is_none());
+        assert!(recognizer.recognize_timeout(1, down_again.time).is_some());
+    }
+
+    #[test]
+    fn pan() {
+        let start = Instant::now();
+        let mut recognizer = PanRecognizer::default();
+        assert!(recognizer.recognize(&touch(start, 0, 1, TouchPhase::Start, 0.0, 0.0)).is_empty());
+        assert!(recognizer.recognize(&touch(start, 16, 1, TouchPhase::Moved, 5.0, 0.0)).is_empty());
+        let events = recognizer.recognize(&touch(start, 32, 1, TouchPhase::Moved, 20.0, 0.0));
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].state, DragState::Start);
+        assert_eq!(events[1].state, DragState::Moved);
+        assert_eq!(events[1].change, Vector::new(20.0, 0.0));
+        let events = recognizer.recognize(&touch(start, 48, 1, TouchPhase::Moved, 30.0, 5.0));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].change, Vector::new(10.0, 5.0));
+        assert_eq!(events[0].offset, Vector::new(30.0, 5.0));
+        assert!(events[0].velocity.x > 0.0);
+        // other touches are ignored
+        assert!(recognizer.recognize(&touch(start, 50, 2, TouchPhase::Moved, 100.0, 0.0)).is_empty());
+        let events = recognizer.recognize(&touch(start, 64, 1, TouchPhase::End, 30.0, 5.0));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state, DragState::End);
+    }
+
+    /// A touch moving from (0, 0) to `end`, in `steps` moves 16ms apart, ending where the last move was.
+    fn swipe(recognizer: &mut SwipeRecognizer, end: Vector, steps: u64) -> Option<SwipeEvent> {
+        let start = Instant::now();
+        recognizer.recognize(&touch(start, 0, 1, TouchPhase::Start, 0.0, 0.0));
+        for step in 1..(steps + 1) {
+            let position = end * (step as f32 / steps as f32);
+            recognizer.recognize(&touch(start, step * 16, 1, TouchPhase::Moved, position.x, position.y));
+        }
+        recognizer.recognize(&touch(start, steps * 16 + 8, 1, TouchPhase::End, end.x, end.y))
+    }
+
+    #[test]
+    fn swipe_directions() {
+        let mut recognizer = SwipeRecognizer::default();
+        let right = swipe(&mut recognizer, Vector::new(200.0, 10.0), 5).unwrap();
+        assert_eq!(right.direction, SwipeDirection::Right);
+        assert!(right.velocity.x >= recognizer.min_velocity);
+        let up = swipe(&mut recognizer, Vector::new(-10.0, -200.0), 5).unwrap();
+        assert_eq!(up.direction, SwipeDirection::Up);
+        assert_eq!(swipe(&mut recognizer, Vector::new(-200.0, 0.0), 5).unwrap().direction, SwipeDirection::Left);
+        assert_eq!(swipe(&mut recognizer, Vector::new(0.0, 200.0), 5).unwrap().direction, SwipeDirection::Down);
+    }
+
+    #[test]
+    fn swipe_too_slow_or_short() {
+        let mut recognizer = SwipeRecognizer::default();
+        // 200 pixels over 1.6 seconds
+        assert!(swipe(&mut recognizer, Vector::new(200.0, 0.0), 100).is_none());
+        assert!(swipe(&mut recognizer, Vector::new(30.0, 0.0), 1).is_none());
+    }
+
+    #[test]
+    fn pinch() {
+        let start = Instant::now();
+        let mut recognizer = PinchRecognizer::default();
+        assert!(recognizer.recognize(&touch(start, 0, 1, TouchPhase::Start, 0.0, 0.0)).is_none());
+        let pinch = recognizer.recognize(&touch(start, 10, 2, TouchPhase::Start, 100.0, 0.0)).unwrap();
+        assert_eq!(pinch.state, DragState::Start);
+        assert_eq!(pinch.center, Point::new(50.0, 0.0));
+        let pinch = recognizer.recognize(&touch(start, 20, 2, TouchPhase::Moved, 200.0, 0.0)).unwrap();
+        assert_eq!(pinch.state, DragState::Moved);
+        assert_eq!(pinch.scale, 2.0);
+        assert_eq!(pinch.change, 2.0);
+        let pinch = recognizer.recognize(&touch(start, 30, 1, TouchPhase::Moved, 100.0, 0.0)).unwrap();
+        assert_eq!(pinch.scale, 1.0);
+        assert_eq!(pinch.change, 0.5);
+        let pinch = recognizer.recognize(&touch(start, 40, 1, TouchPhase::End, 100.0, 0.0)).unwrap();
+        assert_eq!(pinch.state, DragState::End);
+        assert!(recognizer.recognize(&touch(start, 50, 2, TouchPhase::Moved, 300.0, 0.0)).is_none());
+    }
+}
@@ -1,14 +1,14 @@
 //! Keyboard input handlers.
 
-use std::collections::HashMap;
-
-use stable_bst::map::TreeMap;
-use stable_bst::Bound::{Excluded, Unbounded};
+use std::cmp::Ordering;
+use std::collections::{HashMap, BTreeMap};
 
 use widget::Widget;
 use widget::property::Property;
 use input::mouse::ClickEvent;
-use event::{EventHandler, EventArgs};
+use event::{self, Target, EventHandler, EventArgs};
+use geometry::{Vector, RectExt};
+use ui::WidgetDetachedEvent;
 use app::App;
 
 use glutin;
@@ -22,45 +22,276 @@ pub struct WidgetKeyboardInput(pub glutin::KeyboardInput);
 #[derive(Debug, Copy, Clone)]
 pub struct WidgetReceivedCharacter(pub char);
 
+/// Input method composition, used to enter text that takes several key presses per character,
+/// like CJK text. glutin doesn't report composition yet, so these events are sent to the root
+/// widget by a platform integration, eg. using `event_global`, and passed on to the focused widget.
+#[derive(Debug, Clone)]
+pub enum CompositionEvent {
+    Start,
+    /// The preedit text, being composed, and the position of the caret within it, in chars
+    Update(String, Option<usize>),
+    /// The composed text, ending the composition
+    Commit(String),
+    Cancel,
+}
+#[derive(Debug, Clone)]
+pub struct WidgetCompositionEvent(pub CompositionEvent);
+
+
+/// Sent to a widget when it receives keyboard focus.
+#[derive(Debug, Copy, Clone)]
+pub struct FocusGained;
+/// Sent to a widget when it loses keyboard focus.
+#[derive(Debug, Copy, Clone)]
+pub struct FocusLost;
+
+/// How the tab key orders focusable widgets with the same tab index.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FocusOrder {
+    /// The order the widgets were registered as focusable
+    Registration,
+    /// Reading order of the widgets' bounds, rows from top to bottom, left to right within a row
+    Geometry,
+}
+
+impl Default for FocusOrder {
+    fn default() -> Self {
+        FocusOrder::Registration
+    }
+}
+
+struct FocusGroup {
+    widget: Widget,
+    arrow_navigation: bool,
+}
+
+/// A focusable widget, or a focus group containing focusable widgets, ordered as a unit.
+struct FocusUnit {
+    widget: Widget,
+    members: Option<Vec<Widget>>,
+    registration: usize,
+}
 
 /// Note on focus:
-/// The tab key iterates through the widgets that have registered as focusable.
-/// Currently the order of this iteration is just based on the order the widgets
-/// are registered as focusable.
-/// Later on maybe it should be based on the relative positioning of widgets (could get
-/// ugly updating the treemap as widgets change position), or some user defined ordering.
+/// The tab key iterates through the widgets that have registered as focusable, Shift+Tab iterates
+/// backwards. Widgets are ordered by their tab index (0 by default, see `Widget::set_tab_index`),
+/// lower first, then either by the order they were registered as focusable, or by their position
+/// on screen, depending on the `FocusOrder`, set with `KeyboardInputEvent::SetFocusOrder`.
+///
+/// A focus group, created with `Widget::make_focus_group`, keeps the focusable widgets inside it
+/// together in the tab order, the group is ordered as a unit, using it's own tab index and bounds.
+/// Groups can also enable navigating between the focusable widgets inside them with the arrow keys,
+/// moving to the nearest widget in the direction of the arrow.
+///
+/// Focus can be restricted to a subtree by pushing a focus scope, eg. while a modal dialog is open.
+/// Only focusable widgets inside the topmost scope can receive focus, and the previous focus is
+/// restored when the scope is popped.
 #[derive(Default)]
 pub struct FocusHandler {
-    focusable_map: HashMap<Widget, usize>,
-    // can replace TreeMap with std BTreeMap once the range API or similar is stable
-    focusable: TreeMap<usize, Widget>,
+    /// Focusable widgets, in the order they were registered
+    focusable: Vec<Widget>,
+    groups: Vec<FocusGroup>,
+    /// Registration order of focusable widgets and groups
+    registration: HashMap<Widget, usize>,
+    registration_max: usize,
+    order: FocusOrder,
     focused: Option<Widget>,
-    focus_index_max: usize,
+    /// Each scope, along with the widget that was focused when it was pushed
+    focus_scopes: Vec<(Widget, Option<Widget>)>,
 }
 impl FocusHandler {
     pub fn new() -> Self {
         Self::default()
     }
+    fn in_focus_scope(&self, widget: &Widget) -> bool {
+        if let Some(&(ref scope, _)) = self.focus_scopes.last() {
+            widget.is_in_subtree(scope)
+        } else {
+            true
+        }
+    }
+    fn first_in_scope(&self) -> Option<Widget> {
+        self.focus_order().into_iter().next()
+    }
+    fn register(&mut self, widget: &Widget) {
+        if !self.registration.contains_key(widget) {
+            self.registration.insert(widget.clone(), self.registration_max);
+            self.registration_max += 1;
+        }
+    }
     fn set_focus(&mut self, new_focus: Option<Widget>) {
         if new_focus != self.focused {
             if let Some(ref mut focused) = self.focused {
                 focused.remove_prop(Property::Focused);
+                focused.event(FocusLost);
             }
             self.focused = new_focus;
             if let Some(ref mut focused) = self.focused {
                 focused.add_prop(Property::Focused);
+                focused.event(FocusGained);
+            }
+        }
+    }
+
+    /// The focusable widgets in the current focus scope, in tab order.
+    fn focus_order(&self) -> Vec<Widget> {
+        let widgets = self.focusable.iter().filter(|widget| self.in_focus_scope(widget)).cloned().collect();
+        self.order_units(widgets, None)
+    }
+
+    /// Order `widgets`, which are all inside `group`, or any widgets if `group` is `None`.
+    fn order_units(&self, widgets: Vec<Widget>, group: Option<&Widget>) -> Vec<Widget> {
+        // the outermost groups inside `group`
+        let inner_groups: Vec<&Widget> = self.groups.iter()
+            .map(|inner| &inner.widget)
+            .filter(|inner| group.map_or(true, |group| *inner != group && inner.is_in_subtree(group)))
+            .collect();
+        let outer_groups: Vec<&Widget> = inner_groups.iter()
+            .filter(|inner| !inner_groups.iter().any(|other| other != *inner && inner.is_in_subtree(other)))
+            .cloned()
+            .collect();
+
+        let mut units: Vec<FocusUnit> = Vec::new();
+        for widget in widgets {
+            let registration = self.registration[&widget];
+            if let Some(inner) = outer_groups.iter().find(|inner| widget.is_in_subtree(inner)) {
+                if let Some(unit) = units.iter_mut().find(|unit| unit.widget == **inner) {
+                    // a group is ordered by it's earliest registered member
+                    unit.registration = unit.registration.min(registration);
+                    unit.members.as_mut().unwrap().push(widget);
+                    continue;
+                }
+                units.push(FocusUnit {
+                    widget: (*inner).clone(),
+                    members: Some(vec![widget]),
+                    registration: registration,
+                });
+            } else {
+                units.push(FocusUnit {
+                    widget: widget,
+                    members: None,
+                    registration: registration,
+                });
+            }
+        }
+
+        let mut by_tab_index: BTreeMap<i32, Vec<FocusUnit>> = BTreeMap::new();
+        for unit in units {
+            let tab_index = unit.widget.tab_index();
+            by_tab_index.entry(tab_index).or_insert_with(Vec::new).push(unit);
+        }
+        let mut order = Vec::new();
+        for (_, mut units) in by_tab_index {
+            match self.order {
+                FocusOrder::Registration => units.sort_by_key(|unit| unit.registration),
+                FocusOrder::Geometry => units = reading_order(units),
+            }
+            for unit in units {
+                match unit.members {
+                    Some(members) => order.extend(self.order_units(members, Some(&unit.widget))),
+                    None => order.push(unit.widget),
+                }
             }
         }
+        order
+    }
+
+    fn focus_next(&mut self, reverse: bool) {
+        let order = self.focus_order();
+        if order.is_empty() {
+            return;
+        }
+        let position = self.focused.as_ref().and_then(|focused| order.iter().position(|widget| widget == focused));
+        let index = match position {
+            Some(position) if reverse => (position + order.len() - 1) % order.len(),
+            Some(position) => (position + 1) % order.len(),
+            // focus on first or last
+            None if reverse => order.len() - 1,
+            None => 0,
+        };
+        self.set_focus(Some(order[index].clone()));
+    }
+
+    /// Move focus to the nearest widget in the direction of `key`, within the innermost group
+    /// containing the focused widget that has arrow navigation enabled.
+    /// Returns false if focus wasn't moved.
+    fn focus_direction(&mut self, key: glutin::VirtualKeyCode) -> bool {
+        let focused = match self.focused {
+            Some(ref focused) => focused.clone(),
+            None => return false,
+        };
+        let group = {
+            let groups: Vec<&Widget> = self.groups.iter()
+                .filter(|group| group.arrow_navigation && focused.is_in_subtree(&group.widget))
+                .map(|group| &group.widget)
+                .collect();
+            match groups.iter().find(|group| !groups.iter().any(|other| other != *group && other.is_in_subtree(group))) {
+                Some(group) => (*group).clone(),
+                None => return false,
+            }
+        };
+        let direction = match key {
+            glutin::VirtualKeyCode::Left => Vector::new(-1.0, 0.0),
+            glutin::VirtualKeyCode::Right => Vector::new(1.0, 0.0),
+            glutin::VirtualKeyCode::Up => Vector::new(0.0, -1.0),
+            glutin::VirtualKeyCode::Down => Vector::new(0.0, 1.0),
+            _ => return false,
+        };
+        let from = focused.bounds().center();
+        let nearest = self.focusable.iter()
+            .filter(|widget| **widget != focused && widget.is_in_subtree(&group) && self.in_focus_scope(widget))
+            .filter_map(|widget| {
+                let offset = widget.bounds().center() - from;
+                let along = offset.dot(direction);
+                if along <= 0.0 {
+                    return None;
+                }
+                // prefer widgets in line with the focused widget
+                let across = (offset - direction * along).length();
+                Some((along + across * 2.0, widget))
+            })
+            .min_by(|&(a, _), &(b, _)| a.partial_cmp(&b).unwrap_or(Ordering::Equal))
+            .map(|(_, widget)| widget.clone());
+        if nearest.is_some() {
+            self.set_focus(nearest);
+            true
+        } else {
+            false
+        }
     }
 }
+
+/// Order units by their bounds, in rows from top to bottom, and left to right within a row.
+/// A unit starts a new row if it's top is below the bottom of the first unit in the current row.
+fn reading_order(mut units: Vec<FocusUnit>) -> Vec<FocusUnit> {
+    units.sort_by(|a, b| a.widget.bounds().top().partial_cmp(&b.widget.bounds().top()).unwrap_or(Ordering::Equal));
+    let mut rows: Vec<(f32, Vec<FocusUnit>)> = Vec::new();
+    for unit in units {
+        let bounds = unit.widget.bounds();
+        let new_row = rows.last().map_or(true, |&(row_bottom, _)| bounds.top() >= row_bottom);
+        if new_row {
+            rows.push((bounds.bottom(), vec![unit]));
+        } else {
+            rows.last_mut().unwrap().1.push(unit);
+        }
+    }
+    let mut order = Vec::new();
+    for (_, mut row) in rows {
+        row.sort_by(|a, b| a.widget.bounds().left().partial_cmp(&b.widget.bounds().left()).unwrap_or(Ordering::Equal));
+        order.extend(row);
+    }
+    order
+}
+
 impl EventHandler<KeyboardInputEvent> for FocusHandler {
-    fn handle(&mut self, event: &KeyboardInputEvent, _: EventArgs) {
+    fn handle(&mut self, event: &KeyboardInputEvent, args: EventArgs) {
         match *event {
             KeyboardInputEvent::AddFocusable(ref widget_id) => {
-                self.focusable.insert(self.focus_index_max, widget_id.clone());
-                self.focusable_map.insert(widget_id.clone(), self.focus_index_max);
-                self.focus_index_max += 1;
-                if self.focused.is_none() {
+                if !self.focusable.contains(widget_id) {
+                    self.register(widget_id);
+                    self.focusable.push(widget_id.clone());
+                }
+                if self.focused.is_none() && self.in_focus_scope(widget_id) {
                     self.set_focus(Some(widget_id.clone()));
                 }
             }
@@ -70,46 +301,105 @@ impl EventHandler<KeyboardInputEvent> for FocusHandler {
                         self.set_focus(None);
                     }
                 }
-                let index = self.focusable_map.remove(widget_id).unwrap();
-                self.focusable.remove(&index);
+                self.focusable.retain(|widget| widget != widget_id);
+                self.registration.remove(widget_id);
+            }
+            KeyboardInputEvent::AddFocusGroup(ref widget_id, arrow_navigation) => {
+                self.register(widget_id);
+                self.groups.retain(|group| group.widget != *widget_id);
+                self.groups.push(FocusGroup {
+                    widget: widget_id.clone(),
+                    arrow_navigation: arrow_navigation,
+                });
+            }
+            KeyboardInputEvent::RemoveFocusGroup(ref widget_id) => {
+                self.groups.retain(|group| group.widget != *widget_id);
+                self.registration.remove(widget_id);
+            }
+            KeyboardInputEvent::SetFocusOrder(order) => {
+                self.order = order;
             }
             KeyboardInputEvent::FocusChange(ref new_focus) => {
-                self.set_focus(new_focus.clone());
+                let in_scope = new_focus.as_ref().map_or(true, |widget| self.in_focus_scope(widget));
+                if in_scope {
+                    self.set_focus(new_focus.clone());
+                }
+            }
+            KeyboardInputEvent::PushFocusScope(ref scope) => {
+                self.focus_scopes.push((scope.clone(), self.focused.clone()));
+                let new_focus = self.first_in_scope();
+                self.set_focus(new_focus);
+            }
+            KeyboardInputEvent::PopFocusScope(ref scope) => {
+                if let Some(index) = self.focus_scopes.iter().position(|&(ref widget, _)| widget == scope) {
+                    let (_, prev_focus) = self.focus_scopes.remove(index);
+                    // only restore focus if the popped scope was the topmost
+                    if index == self.focus_scopes.len() {
+                        let prev_focus = prev_focus.and_then(|widget| {
+                            if self.focusable.contains(&widget) { Some(widget) } else { None }
+                        });
+                        self.set_focus(prev_focus);
+                    }
+                }
             }
             KeyboardInputEvent::KeyboardInput(ref key_input) => {
+                let &KeyboardInput(input) = key_input;
+                let pressed = input.state == glutin::ElementState::Pressed;
+                let used = match input.virtual_keycode {
+                    Some(glutin::VirtualKeyCode::Tab) => {
+                        if pressed {
+                            self.focus_next(input.modifiers.shift);
+                        }
+                        true
+                    }
+                    Some(key @ glutin::VirtualKeyCode::Left) |
+                    Some(key @ glutin::VirtualKeyCode::Right) |
+                    Some(key @ glutin::VirtualKeyCode::Up) |
+                    Some(key @ glutin::VirtualKeyCode::Down) if pressed => self.focus_direction(key),
+                    _ => false,
+                };
+                if !used {
+                    if let Some(ref focused) = self.focused {
+                        let event = WidgetKeyboardInput(input);
+                        focused.event_subtree(event);
+                    }
+                }
+            }
+            KeyboardInputEvent::Composition(ref composition) => {
                 if let Some(ref focused) = self.focused {
-                    let &KeyboardInput(input) = key_input;
-                    let event = WidgetKeyboardInput(input);
-                    focused.event_subtree(event);
+                    focused.event_subtree(WidgetCompositionEvent(composition.clone()));
                 }
             }
             KeyboardInputEvent::ReceivedCharacter(ref received_char) => {
                 let &ReceivedCharacter(char) = received_char;
-                if char == '\t' {
-                    let mut new_focus = self.focused.clone().and_then(|focused| {
-                        let index = &self.focusable_map[&focused];
-                        self.focusable.range(Excluded(index), Unbounded).next().map(|(_, v)| v.clone())
-                    });
-                    if new_focus.is_none() {
-                        // focus on first, if any
-                        new_focus = self.focusable.iter().next().map(|(_, v)| v.clone());
+                // tab is handled as keyboard input
+                if char != '\t' {
+                    if let Some(ref focused) = self.focused {
+                        let event = WidgetReceivedCharacter(char);
+                        focused.event_subtree(event);
                     }
-                    self.set_focus(new_focus);
-                } else if let Some(ref focused) = self.focused {
-                    let event = WidgetReceivedCharacter(char);
-                    focused.event_subtree(event);
                 }
             }
         }
+        args.ui.focused = self.focused.clone();
     }
 }
 
 pub enum KeyboardInputEvent {
     AddFocusable(Widget),
     RemoveFocusable(Widget),
+    /// Keep the focusable widgets inside the given widget together in the tab order,
+    /// and enable arrow key navigation between them if the flag is set.
+    AddFocusGroup(Widget, bool),
+    RemoveFocusGroup(Widget),
+    SetFocusOrder(FocusOrder),
     FocusChange(Option<Widget>),
+    /// Restrict focus to the given widget and it's descendants, until the scope is popped.
+    PushFocusScope(Widget),
+    PopFocusScope(Widget),
     KeyboardInput(KeyboardInput),
     ReceivedCharacter(ReceivedCharacter),
+    Composition(CompositionEvent),
 }
 
 impl Widget {
@@ -118,15 +408,33 @@ impl Widget {
             args.ui.get_root().event(KeyboardInputEvent::FocusChange(Some(args.widget)));
         })
     }
+
+    /// Make this widget a focus group, see `FocusHandler`.
+    pub fn make_focus_group(&mut self, arrow_navigation: bool) -> &mut Self {
+        event::event(Target::Root, KeyboardInputEvent::AddFocusGroup(self.clone(), arrow_navigation));
+        self.add_handler(|_: &WidgetDetachedEvent, args: EventArgs| {
+            args.ui.event(KeyboardInputEvent::RemoveFocusGroup(args.widget));
+        })
+    }
 }
 
 impl App {
     pub fn add_keyboard_handlers(&mut self) {
         self.add_handler(|event: &KeyboardInput, args: EventArgs| {
-            args.widget.event(KeyboardInputEvent::KeyboardInput(*event));
+            let &KeyboardInput(input) = event;
+            let focused = args.ui.focused.clone();
+            // input used by a shortcut isn't passed on to the focused widget
+            if !args.ui.shortcuts.key_input(input, focused.as_ref()) {
+                args.widget.event(KeyboardInputEvent::KeyboardInput(*event));
+            }
         });
         self.add_handler(|event: &ReceivedCharacter, args: EventArgs| {
-            args.widget.event(KeyboardInputEvent::ReceivedCharacter(*event));
+            if !args.ui.shortcuts.received_char() {
+                args.widget.event(KeyboardInputEvent::ReceivedCharacter(*event));
+            }
+        });
+        self.add_handler(|event: &CompositionEvent, args: EventArgs| {
+            args.widget.event(KeyboardInputEvent::Composition(event.clone()));
         });
         self.add_handler(FocusHandler::new());
     }
@@ -4,6 +4,9 @@
 pub mod mouse;
 pub mod keyboard;
 pub mod drag;
+pub mod touch;
+pub mod gesture;
+pub mod shortcut;
 
 use glutin;
 use glutin::ElementState;
@@ -12,6 +15,7 @@ use webrender;
 use event::{EventHandler, EventArgs};
 use input::mouse::{MouseMoved, MouseButton, MouseWheel, CursorLeftWindow};
 use input::keyboard::{KeyboardInput, ReceivedCharacter};
+use input::touch::TouchEvent;
 use geometry::Point;
 use app::App;
 
@@ -34,9 +38,11 @@ impl App {
                 }
                 glutin::WindowEvent::CursorMoved { position, .. } => {
                     let point = Point::new(position.0 as f32, position.1 as f32);
+                    args.ui.cursor = Some(point);
                     args.widget.event(MouseMoved(point));
                 }
                 glutin::WindowEvent::CursorLeft { .. } => {
+                    args.ui.cursor = None;
                     args.widget.event(CursorLeftWindow);
                 }
                 glutin::WindowEvent::KeyboardInput { input, .. } => {
@@ -46,20 +52,26 @@ impl App {
                 glutin::WindowEvent::ReceivedCharacter(char) => {
                     args.widget.event(ReceivedCharacter(char));
                 }
+                glutin::WindowEvent::Touch(touch) => {
+                    let point = Point::new(touch.location.0 as f32, touch.location.1 as f32);
+                    args.widget.event(TouchEvent::new(touch.id, touch.phase.into(), point));
+                }
                 _ => (),
             }
         });
     }
 }
 
-/// Triggers exit on Esc key press.
+/// Triggers exit on Esc key press, unless a modal dialog is open.
 #[derive(Debug, Copy, Clone)]
 pub struct EscKeyCloseHandler;
 
 impl EventHandler<KeyboardInput> for EscKeyCloseHandler {
     fn handle(&mut self, event: &KeyboardInput, args: EventArgs) {
         if let Some(glutin::VirtualKeyCode::Escape) = event.0.virtual_keycode {
-            args.ui.close();
+            if !args.ui.has_modal() {
+                args.ui.close();
+            }
         }
     }
 }
@@ -99,6 +111,7 @@ impl EventHandler<KeyboardInput> for DebugSettingsHandler {
                 Some(glutin::VirtualKeyCode::F4) => ui.solver.debug_variables(),
                 Some(glutin::VirtualKeyCode::F5) => ui.render.toggle_flags(webrender::DebugFlags::PROFILER_DBG),
                 Some(glutin::VirtualKeyCode::F6) => ui.print_widgets(),
+                Some(glutin::VirtualKeyCode::F7) => ui.debug_resource_caches(),
                 _ => {}
             }
         }
@@ -1,11 +1,14 @@
 //! Mouse input handlers.
 
+use std::time::{Duration, Instant};
+
 use glutin;
 
-use event::{EventHandler, EventArgs};
+use event::{self, EventHandler, EventArgs};
 use geometry::Point;
 use widget::Widget;
 use widget::property::Property;
+use input::keyboard::KeyboardInput;
 use layout::LayoutChanged;
 use app::App;
 
@@ -22,21 +25,107 @@ pub struct CursorLeftWindow;
 pub struct WidgetMouseWheel(pub glutin::MouseScrollDelta);
 #[derive(Debug, Copy, Clone)]
 pub struct WidgetMouseButton(pub glutin::ElementState, pub glutin::MouseButton);
+/// Sent to the widget that has captured the mouse, see `Ui::capture_mouse`.
+#[derive(Debug, Copy, Clone)]
+pub struct WidgetMouseMoved(pub Point);
+/// Sent to the widget that had captured the mouse, when the capture is cancelled by the cursor
+/// leaving the window, or by another widget capturing the mouse.
+#[derive(Debug, Copy, Clone)]
+pub struct MouseCaptureLost;
 
+/// Sent to the widget under the mouse when the left button is released, if it was pressed on the same widget.
 #[derive(Debug, Copy, Clone)]
 pub struct ClickEvent {
     pub position: Point,
+    /// The modifier keys held when the button was released
+    pub modifiers: glutin::ModifiersState,
+    /// 1 for a single click, 2 for a double click etc.
+    pub click_count: u32,
+}
+
+/// Like `ClickEvent`, for the right, middle and any other buttons.
+#[derive(Debug, Copy, Clone)]
+pub struct AuxClickEvent {
+    pub position: Point,
+    pub button: glutin::MouseButton,
+    /// The modifier keys held when the button was released
+    pub modifiers: glutin::ModifiersState,
+    /// 1 for a single click, 2 for a double click etc.
+    pub click_count: u32,
+}
+
+/// Sent to the widget under the mouse when a button is held on it, without moving, for the
+/// `ClickSettings`' long press duration. The following release doesn't produce a `ClickEvent`.
+#[derive(Debug, Copy, Clone)]
+pub struct MouseLongPressEvent {
+    pub position: Point,
+    pub button: glutin::MouseButton,
+    pub modifiers: glutin::ModifiersState,
+}
+
+/// Determines how mouse button presses and releases are combined into clicks, available as `Ui::click_settings`.
+#[derive(Debug, Copy, Clone)]
+pub struct ClickSettings {
+    /// Maximum time between clicks counted as a double or triple click
+    pub multi_click_interval: Duration,
+    /// How far apart clicks counted as a double or triple click can be, also how far the mouse
+    /// can move during a long press
+    pub multi_click_distance: f32,
+    pub long_press_duration: Duration,
+}
+
+impl Default for ClickSettings {
+    fn default() -> Self {
+        ClickSettings {
+            multi_click_interval: Duration::from_millis(500),
+            multi_click_distance: 5.0,
+            long_press_duration: Duration::from_millis(800),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Press {
+    id: u64,
+    button: glutin::MouseButton,
+    widget: Widget,
+    position: Point,
+    long_pressed: bool,
+}
+
+#[derive(Debug, Clone)]
+struct LastClick {
+    button: glutin::MouseButton,
+    widget: Widget,
+    position: Point,
+    time: Instant,
+    click_count: u32,
 }
 
+/// Sent from the timer thread when a button has been held for the long press duration.
+#[derive(Debug, Copy, Clone)]
+struct LongPressTimeout(u64);
+
 #[derive(Default, Debug, Clone)]
 struct MouseController {
     pub mouse: Option<Point>,
     pub widget_under_mouse: Option<Widget>,
+    modifiers: glutin::ModifiersState,
+    /// The buttons currently held, with the widget each was pressed on
+    pressed: Vec<Press>,
+    press_id: u64,
+    last_click: Option<LastClick>,
+    /// The button that captured the mouse when it was pressed, on a widget that captures the mouse
+    capture_button: Option<glutin::MouseButton>,
 }
 
 impl MouseController {
     fn check_widget_under_cursor(&mut self, args: EventArgs) {
-        let widget_under_mouse = self.mouse.and_then(|mouse| args.ui.widget_under_cursor(mouse));
+        // while the mouse is captured, the capturing widget stays hovered
+        let widget_under_mouse = match args.ui.mouse_capture() {
+            Some(capture) => Some(capture),
+            None => self.mouse.and_then(|mouse| args.ui.widget_under_cursor(mouse)),
+        };
         if widget_under_mouse != self.widget_under_mouse {
             if let Some(ref old_widget) = self.widget_under_mouse {
                 old_widget.event_bubble_up(MouseOverEvent::Out);
@@ -55,21 +144,119 @@ impl MouseController {
     fn mouse_moved(&mut self, event: &MouseMoved, args: EventArgs) {
         let &MouseMoved(mouse) = event;
         self.mouse = Some(mouse);
+        if let Some(capture) = args.ui.mouse_capture() {
+            capture.event(WidgetMouseMoved(mouse));
+        }
         self.check_widget_under_cursor(args);
     }
 
     fn mouse_left(&mut self, _: &CursorLeftWindow, args: EventArgs) {
         self.mouse = None;
+        // leaving the window cancels any presses and the capture
+        self.pressed.clear();
+        self.capture_button = None;
+        if let Some(capture) = args.ui.mouse_capture.take() {
+            capture.event(MouseCaptureLost);
+        }
         self.check_widget_under_cursor(args);
     }
 
-    fn mouse_button(&mut self, event: &MouseButton, _: EventArgs) {
+    fn mouse_button(&mut self, event: &MouseButton, args: EventArgs) {
         let &MouseButton(state, button) = event;
+        let settings = args.ui.click_settings;
         if let Some(ref widget_under) = self.widget_under_mouse {
             widget_under.event_bubble_up(WidgetMouseButton(state, button));
-            if (state == glutin::ElementState::Released) && (button == glutin::MouseButton::Left) && self.mouse.is_some() {
-                let event = ClickEvent { position: self.mouse.unwrap() };
-                widget_under.event_bubble_up(event);
+        }
+        match state {
+            glutin::ElementState::Pressed => {
+                self.pressed.retain(|press| press.button != button);
+                // captured here rather than by a handler of the press, so the capture is in place
+                // before any release queued behind it
+                if args.ui.mouse_capture.is_none() {
+                    if let Some(widget) = self.widget_under_mouse.as_ref().and_then(capturing_ancestor) {
+                        args.ui.capture_mouse(widget);
+                        self.capture_button = Some(button);
+                    }
+                }
+                if let (Some(widget), Some(position)) = (self.widget_under_mouse.clone(), self.mouse) {
+                    self.press_id += 1;
+                    self.pressed.push(Press {
+                        id: self.press_id,
+                        button: button,
+                        widget: widget,
+                        position: position,
+                        long_pressed: false,
+                    });
+                    event::event_global_after(settings.long_press_duration, LongPressTimeout(self.press_id));
+                }
+            }
+            glutin::ElementState::Released => {
+                if let Some(index) = self.pressed.iter().position(|press| press.button == button) {
+                    let press = self.pressed.remove(index);
+                    let under_cursor = self.mouse.and_then(|mouse| args.ui.widget_under_cursor(mouse));
+                    // a click needs the press and release to be on the same widget
+                    if under_cursor.as_ref() == Some(&press.widget) && !press.long_pressed {
+                        self.click(press, settings);
+                    }
+                }
+                // the capture ends when the button that took it is released, or once all buttons are released
+                if self.capture_button == Some(button) || self.pressed.is_empty() {
+                    self.capture_button = None;
+                    if args.ui.mouse_capture.take().is_some() {
+                        self.check_widget_under_cursor(args);
+                    }
+                }
+            }
+        }
+    }
+
+    fn click(&mut self, press: Press, settings: ClickSettings) {
+        if let Some(position) = self.mouse {
+            let now = Instant::now();
+            let click_count = match self.last_click {
+                Some(ref last) if last.button == press.button && last.widget == press.widget &&
+                    now.duration_since(last.time) <= settings.multi_click_interval &&
+                    (position - last.position).length() <= settings.multi_click_distance => last.click_count + 1,
+                _ => 1,
+            };
+            self.last_click = Some(LastClick {
+                button: press.button,
+                widget: press.widget.clone(),
+                position: position,
+                time: now,
+                click_count: click_count,
+            });
+            if press.button == glutin::MouseButton::Left {
+                press.widget.event_bubble_up(ClickEvent {
+                    position: position,
+                    modifiers: self.modifiers,
+                    click_count: click_count,
+                });
+            } else {
+                press.widget.event_bubble_up(AuxClickEvent {
+                    position: position,
+                    button: press.button,
+                    modifiers: self.modifiers,
+                    click_count: click_count,
+                });
+            }
+        }
+    }
+
+    fn long_press_timeout(&mut self, event: &LongPressTimeout, args: EventArgs) {
+        let &LongPressTimeout(id) = event;
+        let distance = args.ui.click_settings.multi_click_distance;
+        let (mouse, modifiers) = (self.mouse, self.modifiers);
+        let widget_under_mouse = self.widget_under_mouse.clone();
+        if let Some(press) = self.pressed.iter_mut().find(|press| press.id == id) {
+            let held_still = mouse.map_or(false, |mouse| (mouse - press.position).length() <= distance);
+            if held_still && widget_under_mouse.as_ref() == Some(&press.widget) {
+                press.long_pressed = true;
+                press.widget.event_bubble_up(MouseLongPressEvent {
+                    position: press.position,
+                    button: press.button,
+                    modifiers: modifiers,
+                });
             }
         }
     }
@@ -80,6 +267,33 @@ impl MouseController {
             widget_under.event_bubble_up(WidgetMouseWheel(mouse_scroll_delta));
         }
     }
+
+    fn keyboard_input(&mut self, event: &KeyboardInput, _: EventArgs) {
+        let &KeyboardInput(input) = event;
+        let mut modifiers = input.modifiers;
+        // the modifiers may not yet include a modifier key being pressed or released
+        let pressed = input.state == glutin::ElementState::Pressed;
+        match input.virtual_keycode {
+            Some(glutin::VirtualKeyCode::LShift) | Some(glutin::VirtualKeyCode::RShift) => modifiers.shift = pressed,
+            Some(glutin::VirtualKeyCode::LControl) | Some(glutin::VirtualKeyCode::RControl) => modifiers.ctrl = pressed,
+            Some(glutin::VirtualKeyCode::LAlt) | Some(glutin::VirtualKeyCode::RAlt) => modifiers.alt = pressed,
+            Some(glutin::VirtualKeyCode::LWin) | Some(glutin::VirtualKeyCode::RWin) => modifiers.logo = pressed,
+            _ => (),
+        }
+        self.modifiers = modifiers;
+    }
+}
+
+/// The widget, or it's nearest ancestor, that captures the mouse when it's pressed.
+fn capturing_ancestor(widget: &Widget) -> Option<Widget> {
+    let mut current = Some(widget.clone());
+    while let Some(widget) = current {
+        if widget.captures_mouse() {
+            return Some(widget);
+        }
+        current = widget.parent();
+    }
+    None
 }
 
 multi_event!{impl EventHandler<MouseControllerEvent> for MouseController {
@@ -87,7 +301,9 @@ multi_event!{impl EventHandler<MouseControllerEvent> for MouseController {
     MouseMoved => mouse_moved,
     CursorLeftWindow => mouse_left,
     MouseButton => mouse_button,
+    LongPressTimeout => long_press_timeout,
     MouseWheel => mouse_wheel,
+    KeyboardInput => keyboard_input,
 }}
 
 impl App {
@@ -0,0 +1,568 @@
+//! Keyboard shortcuts.
+//!
+//! A shortcut is a key chord, like Ctrl+S, or a sequence of chords, like Ctrl+K Ctrl+S, bound to
+//! a command event that is sent when the shortcut is typed. Bindings are stored in the
+//! `ShortcutRegistry`, available as `Ui::shortcuts`, or `App::shortcuts` while initializing.
+//!
+//! Each binding has a scope, determining when it is active, relative to the focused widget.
+//! Binding the same shortcut twice in one scope, or binding a shortcut that is the start of
+//! another in the same scope, or in a scope that can be active at the same time, is a conflict.
+//! Bindings in more specific scopes take priority over the same shortcut in less specific ones.
+//! Bindings scoped to a widget are removed when the widget, or one of it's ancestors, is removed.
+//!
+//! Key presses that complete or continue a shortcut are not passed on to the focused widget.
+//! Keys that type or edit text, without Ctrl, Alt or Logo held, don't start a shortcut while
+//! the focused widget accepts text, see `Widget::set_accepts_text`.
+
+use std::fmt;
+use std::mem;
+
+use glutin::{self, VirtualKeyCode, ElementState};
+
+use event::{self, Target};
+use widget::Widget;
+
+/// The modifier keys held for a `KeyChord`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+impl From<glutin::ModifiersState> for Modifiers {
+    fn from(modifiers: glutin::ModifiersState) -> Self {
+        Modifiers {
+            shift: modifiers.shift,
+            ctrl: modifiers.ctrl,
+            alt: modifiers.alt,
+            logo: modifiers.logo,
+        }
+    }
+}
+
+/// A key, pressed while holding a set of modifiers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: VirtualKeyCode,
+    pub modifiers: Modifiers,
+}
+
+impl KeyChord {
+    pub fn new(key: VirtualKeyCode) -> Self {
+        KeyChord {
+            key: key,
+            modifiers: Modifiers::default(),
+        }
+    }
+    pub fn shift(mut self) -> Self {
+        self.modifiers.shift = true;
+        self
+    }
+    pub fn ctrl(mut self) -> Self {
+        self.modifiers.ctrl = true;
+        self
+    }
+    pub fn alt(mut self) -> Self {
+        self.modifiers.alt = true;
+        self
+    }
+    pub fn logo(mut self) -> Self {
+        self.modifiers.logo = true;
+        self
+    }
+}
+
+fn key_name(key: VirtualKeyCode) -> String {
+    match key {
+        VirtualKeyCode::Return => "Enter".to_owned(),
+        VirtualKeyCode::Escape => "Esc".to_owned(),
+        VirtualKeyCode::Back => "Backspace".to_owned(),
+        _ => {
+            let name = format!("{:?}", key);
+            // number keys are named Key1, Key2 etc.
+            if name.len() == 4 && name.starts_with("Key") {
+                name[3..].to_owned()
+            } else {
+                name
+            }
+        }
+    }
+}
+
+/// True if `chord` types a character or edits text, in a widget that accepts text.
+fn edits_text(chord: &KeyChord) -> bool {
+    if chord.modifiers.ctrl || chord.modifiers.alt || chord.modifiers.logo {
+        return false;
+    }
+    match chord.key {
+        VirtualKeyCode::Key1 | VirtualKeyCode::Key2 | VirtualKeyCode::Key3 | VirtualKeyCode::Key4 |
+        VirtualKeyCode::Key5 | VirtualKeyCode::Key6 | VirtualKeyCode::Key7 | VirtualKeyCode::Key8 |
+        VirtualKeyCode::Key9 | VirtualKeyCode::Key0 |
+        VirtualKeyCode::A | VirtualKeyCode::B | VirtualKeyCode::C | VirtualKeyCode::D |
+        VirtualKeyCode::E | VirtualKeyCode::F | VirtualKeyCode::G | VirtualKeyCode::H |
+        VirtualKeyCode::I | VirtualKeyCode::J | VirtualKeyCode::K | VirtualKeyCode::L |
+        VirtualKeyCode::M | VirtualKeyCode::N | VirtualKeyCode::O | VirtualKeyCode::P |
+        VirtualKeyCode::Q | VirtualKeyCode::R | VirtualKeyCode::S | VirtualKeyCode::T |
+        VirtualKeyCode::U | VirtualKeyCode::V | VirtualKeyCode::W | VirtualKeyCode::X |
+        VirtualKeyCode::Y | VirtualKeyCode::Z |
+        VirtualKeyCode::Numpad0 | VirtualKeyCode::Numpad1 | VirtualKeyCode::Numpad2 |
+        VirtualKeyCode::Numpad3 | VirtualKeyCode::Numpad4 | VirtualKeyCode::Numpad5 |
+        VirtualKeyCode::Numpad6 | VirtualKeyCode::Numpad7 | VirtualKeyCode::Numpad8 |
+        VirtualKeyCode::Numpad9 |
+        VirtualKeyCode::Space | VirtualKeyCode::Apostrophe | VirtualKeyCode::Backslash |
+        VirtualKeyCode::Comma | VirtualKeyCode::Equals | VirtualKeyCode::Grave |
+        VirtualKeyCode::LBracket | VirtualKeyCode::RBracket | VirtualKeyCode::Minus |
+        VirtualKeyCode::Period | VirtualKeyCode::Semicolon | VirtualKeyCode::Slash |
+        VirtualKeyCode::Add | VirtualKeyCode::Subtract | VirtualKeyCode::Multiply |
+        VirtualKeyCode::Divide | VirtualKeyCode::Decimal |
+        VirtualKeyCode::Back | VirtualKeyCode::Delete |
+        VirtualKeyCode::Left | VirtualKeyCode::Right | VirtualKeyCode::Home | VirtualKeyCode::End => true,
+        _ => false,
+    }
+}
+
+fn is_modifier(key: VirtualKeyCode) -> bool {
+    match key {
+        VirtualKeyCode::LShift | VirtualKeyCode::RShift |
+        VirtualKeyCode::LControl | VirtualKeyCode::RControl |
+        VirtualKeyCode::LAlt | VirtualKeyCode::RAlt |
+        VirtualKeyCode::LWin | VirtualKeyCode::RWin => true,
+        _ => false,
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.modifiers.logo {
+            write!(f, "Logo+")?;
+        }
+        write!(f, "{}", key_name(self.key))
+    }
+}
+
+/// A sequence of one or more key chords.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shortcut(pub Vec<KeyChord>);
+
+impl Shortcut {
+    fn starts_with(&self, chords: &[KeyChord]) -> bool {
+        self.0.starts_with(chords)
+    }
+}
+
+impl From<KeyChord> for Shortcut {
+    fn from(chord: KeyChord) -> Self {
+        Shortcut(vec![chord])
+    }
+}
+
+impl From<Vec<KeyChord>> for Shortcut {
+    fn from(chords: Vec<KeyChord>) -> Self {
+        Shortcut(chords)
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, chord) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", chord)?;
+        }
+        Ok(())
+    }
+}
+
+/// Determines when a shortcut is active, and where it's command is sent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShortcutScope {
+    /// Always active, the command is sent to the root widget
+    Global,
+    /// Active while the focused widget is this widget or one of it's descendants,
+    /// the command is sent to this widget, bubbling up
+    Subtree(Widget),
+    /// Active while this widget is focused, the command is sent to it, bubbling up
+    Focused(Widget),
+}
+
+impl ShortcutScope {
+    fn is_active(&self, focused: Option<&Widget>) -> bool {
+        match *self {
+            ShortcutScope::Global => true,
+            ShortcutScope::Subtree(ref widget) => focused.map_or(false, |focused| focused.is_in_subtree(widget)),
+            ShortcutScope::Focused(ref widget) => focused.map_or(false, |focused| focused == widget),
+        }
+    }
+
+    /// True if both scopes can be active at the same time.
+    fn overlaps(&self, other: &ShortcutScope) -> bool {
+        match (self, other) {
+            (&ShortcutScope::Global, _) | (_, &ShortcutScope::Global) => true,
+            (&ShortcutScope::Subtree(ref a), &ShortcutScope::Subtree(ref b)) => a.is_in_subtree(b) || b.is_in_subtree(a),
+            (&ShortcutScope::Subtree(ref subtree), &ShortcutScope::Focused(ref focused)) |
+            (&ShortcutScope::Focused(ref focused), &ShortcutScope::Subtree(ref subtree)) => focused.is_in_subtree(subtree),
+            (&ShortcutScope::Focused(ref a), &ShortcutScope::Focused(ref b)) => a == b,
+        }
+    }
+
+    /// Higher for more specific scopes, deeper subtrees are more specific than their ancestors.
+    fn priority(&self) -> usize {
+        match *self {
+            ShortcutScope::Global => 0,
+            ShortcutScope::Subtree(ref widget) => {
+                let mut depth = 1;
+                let mut parent = widget.parent();
+                while let Some(widget) = parent {
+                    depth += 1;
+                    parent = widget.parent();
+                }
+                depth
+            }
+            ShortcutScope::Focused(_) => usize::max_value(),
+        }
+    }
+}
+
+/// A shortcut bound to a command.
+pub struct ShortcutBinding {
+    pub shortcut: Shortcut,
+    pub scope: ShortcutScope,
+    /// Describes the command, for display in menus and help screens
+    pub name: String,
+    send: Box<Fn()>,
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "shortcut {} conflicts with {}, bound to {}", shortcut, existing, name)]
+pub struct ShortcutConflict {
+    pub shortcut: Shortcut,
+    /// The shortcut already bound in the same scope, or the start of it or the shortcut it
+    /// starts in a scope that can be active at the same time
+    pub existing: Shortcut,
+    /// The name of the existing binding
+    pub name: String,
+}
+
+#[derive(Default)]
+pub struct ShortcutRegistry {
+    bindings: Vec<ShortcutBinding>,
+    /// The chords typed so far of a shortcut sequence
+    pending: Vec<KeyChord>,
+    /// Set when a key press is used by a shortcut, so the character it produces is ignored
+    suppress_char: bool,
+}
+
+impl ShortcutRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind `shortcut` to send `command` when typed while `scope` is active.
+    /// `name` describes the command, and can be used to look up the shortcut with `shortcut_for`.
+    pub fn bind<S, T>(&mut self, shortcut: S, scope: ShortcutScope, name: &str, command: T) -> Result<(), ShortcutConflict>
+        where S: Into<Shortcut>,
+              T: Clone + 'static,
+    {
+        let shortcut = shortcut.into();
+        if let Some(existing) = self.bindings.iter().find(|binding| {
+            let prefix = binding.shortcut.starts_with(&shortcut.0) || shortcut.starts_with(&binding.shortcut.0);
+            // the same shortcut in another scope overrides it, but a shortcut that starts another
+            // would stop it being typed while both are active
+            if binding.scope == scope {
+                prefix
+            } else {
+                prefix && binding.shortcut != shortcut && binding.scope.overlaps(&scope)
+            }
+        }) {
+            return Err(ShortcutConflict {
+                shortcut: shortcut.clone(),
+                existing: existing.shortcut.clone(),
+                name: existing.name.clone(),
+            });
+        }
+        let target = scope.clone();
+        let send = move || {
+            match target {
+                ShortcutScope::Global => event::event(Target::Root, command.clone()),
+                ShortcutScope::Subtree(ref widget) |
+                ShortcutScope::Focused(ref widget) => widget.event_bubble_up(command.clone()),
+            }
+        };
+        self.bindings.push(ShortcutBinding {
+            shortcut: shortcut,
+            scope: scope,
+            name: name.to_owned(),
+            send: Box::new(send),
+        });
+        Ok(())
+    }
+
+    /// Remove the binding for `shortcut` in `scope`, returns false if there was none.
+    pub fn unbind(&mut self, shortcut: &Shortcut, scope: &ShortcutScope) -> bool {
+        let len = self.bindings.len();
+        self.bindings.retain(|binding| !(binding.shortcut == *shortcut && binding.scope == *scope));
+        self.bindings.len() != len
+    }
+
+    /// Remove all bindings in `scope`.
+    pub fn unbind_scope(&mut self, scope: &ShortcutScope) {
+        self.bindings.retain(|binding| binding.scope != *scope);
+    }
+
+    /// Remove the bindings scoped to `widget` or it's descendants, once it's removed from the UI,
+    /// so they don't keep it alive.
+    pub(crate) fn unbind_subtree(&mut self, widget: &Widget) {
+        self.bindings.retain(|binding| {
+            match binding.scope {
+                ShortcutScope::Global => true,
+                ShortcutScope::Subtree(ref scope) |
+                ShortcutScope::Focused(ref scope) => !scope.is_in_subtree(widget),
+            }
+        });
+    }
+
+    /// All bindings, in the order they were bound.
+    pub fn bindings(&self) -> &[ShortcutBinding] {
+        &self.bindings
+    }
+
+    /// The shortcut of the first binding with the given name, eg. to display as a menu item's accelerator.
+    pub fn shortcut_for(&self, name: &str) -> Option<&Shortcut> {
+        self.bindings.iter().find(|binding| binding.name == name).map(|binding| &binding.shortcut)
+    }
+
+    /// Handle keyboard input, returns true if it was used by a shortcut,
+    /// and shouldn't be passed on to the focused widget.
+    pub(crate) fn key_input(&mut self, input: glutin::KeyboardInput, focused: Option<&Widget>) -> bool {
+        if input.state != ElementState::Pressed {
+            return false;
+        }
+        let key = match input.virtual_keycode {
+            Some(key) => key,
+            None => {
+                self.pending.clear();
+                self.suppress_char = false;
+                return false;
+            }
+        };
+        // modifiers pressed in the middle of a sequence don't interrupt it
+        if is_modifier(key) {
+            return false;
+        }
+        let chord = KeyChord {
+            key: key,
+            modifiers: input.modifiers.into(),
+        };
+        let mut sequence = mem::replace(&mut self.pending, Vec::new());
+        sequence.push(chord);
+        let can_start = !(edits_text(&chord) && focused.map_or(false, |focused| focused.accepts_text()));
+        // if an unfinished sequence doesn't match, the chord may still start a new one
+        let used = if sequence.len() > 1 {
+            self.match_sequence(sequence, focused) || (can_start && self.match_sequence(vec![chord], focused))
+        } else {
+            can_start && self.match_sequence(sequence, focused)
+        };
+        self.suppress_char = used;
+        used
+    }
+
+    /// Returns true if the character produced by the last key press should be ignored.
+    pub(crate) fn received_char(&mut self) -> bool {
+        let suppress = self.suppress_char;
+        self.suppress_char = false;
+        suppress
+    }
+
+    /// Sends the command of the binding completed by `sequence`, or waits for the next chord if
+    /// it's the start of a binding. The most specific active scope with a binding starting with
+    /// `sequence` decides which, so a longer sequence in it isn't cut short by a shorter one in a
+    /// less specific scope.
+    fn match_sequence(&mut self, sequence: Vec<KeyChord>, focused: Option<&Widget>) -> bool {
+        let matched = self.bindings.iter()
+            .filter(|binding| binding.scope.is_active(focused) && binding.shortcut.starts_with(&sequence))
+            .max_by_key(|binding| (binding.scope.priority(), binding.shortcut.0.len() == sequence.len()));
+        match matched {
+            Some(binding) if binding.shortcut.0.len() == sequence.len() => {
+                (binding.send)();
+                true
+            }
+            Some(_) => {
+                self.pending = sequence;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::*;
+
+    type Fired = Rc<RefCell<Vec<&'static str>>>;
+
+    /// Binds `shortcut`, recording `name` in `fired` when it's typed, rather than sending a command.
+    fn bind<S: Into<Shortcut>>(registry: &mut ShortcutRegistry, fired: &Fired, shortcut: S, scope: ShortcutScope, name: &'static str)
+        -> Result<(), ShortcutConflict>
+    {
+        registry.bind(shortcut, scope, name, ())?;
+        let fired = fired.clone();
+        registry.bindings.last_mut().unwrap().send = Box::new(move || fired.borrow_mut().push(name));
+        Ok(())
+    }
+
+    fn press(chord: KeyChord) -> glutin::KeyboardInput {
+        glutin::KeyboardInput {
+            scancode: 0,
+            state: ElementState::Pressed,
+            virtual_keycode: Some(chord.key),
+            modifiers: glutin::ModifiersState {
+                shift: chord.modifiers.shift,
+                ctrl: chord.modifiers.ctrl,
+                alt: chord.modifiers.alt,
+                logo: chord.modifiers.logo,
+            },
+        }
+    }
+
+    fn ctrl(key: VirtualKeyCode) -> KeyChord {
+        KeyChord::new(key).ctrl()
+    }
+
+    #[test]
+    fn conflicts() {
+        let (mut registry, fired) = (ShortcutRegistry::new(), Fired::default());
+        let (widget, other) = (Widget::new("widget"), Widget::new("other"));
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::S), ShortcutScope::Global, "save").unwrap();
+        let conflict = bind(&mut registry, &fired, ctrl(VirtualKeyCode::S), ShortcutScope::Global, "store").unwrap_err();
+        assert_eq!(conflict.existing, Shortcut::from(ctrl(VirtualKeyCode::S)));
+        assert_eq!(conflict.name, "save");
+        assert!(bind(&mut registry, &fired, vec![ctrl(VirtualKeyCode::S), ctrl(VirtualKeyCode::A)], ShortcutScope::Global, "save all").is_err());
+        // the same shortcut in a more specific scope overrides it
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::S), ShortcutScope::Focused(widget.clone()), "save widget").unwrap();
+
+        // a longer sequence would be cut short by a shorter one in a scope active at the same time
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::K), ShortcutScope::Global, "kill").unwrap();
+        let sequence = vec![ctrl(VirtualKeyCode::K), ctrl(VirtualKeyCode::S)];
+        assert!(bind(&mut registry, &fired, sequence.clone(), ShortcutScope::Focused(widget.clone()), "save as").is_err());
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::D), ShortcutScope::Focused(widget.clone()), "delete").unwrap();
+        let sequence = vec![ctrl(VirtualKeyCode::D), ctrl(VirtualKeyCode::D)];
+        assert!(bind(&mut registry, &fired, sequence.clone(), ShortcutScope::Subtree(widget.clone()), "delete line").is_err());
+        // unless the scopes are never active at the same time
+        bind(&mut registry, &fired, sequence, ShortcutScope::Focused(other), "delete line").unwrap();
+    }
+
+    #[test]
+    fn more_specific_scope_wins() {
+        let (mut registry, fired) = (ShortcutRegistry::new(), Fired::default());
+        let mut parent = Widget::new("parent");
+        let widget = Widget::new("widget");
+        parent.add_child(widget.clone());
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::S), ShortcutScope::Global, "global").unwrap();
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::S), ShortcutScope::Subtree(parent.clone()), "subtree").unwrap();
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::S), ShortcutScope::Focused(widget.clone()), "focused").unwrap();
+        assert!(registry.key_input(press(ctrl(VirtualKeyCode::S)), Some(&widget)));
+        assert!(registry.key_input(press(ctrl(VirtualKeyCode::S)), Some(&parent)));
+        assert!(registry.key_input(press(ctrl(VirtualKeyCode::S)), None));
+        assert_eq!(*fired.borrow(), vec!["focused", "subtree", "global"]);
+        assert!(!registry.key_input(press(ctrl(VirtualKeyCode::A)), None));
+    }
+
+    #[test]
+    fn sequences() {
+        let (mut registry, fired) = (ShortcutRegistry::new(), Fired::default());
+        let sequence = vec![ctrl(VirtualKeyCode::K), ctrl(VirtualKeyCode::S)];
+        bind(&mut registry, &fired, sequence, ShortcutScope::Global, "save all").unwrap();
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::X), ShortcutScope::Global, "cut").unwrap();
+        assert!(registry.key_input(press(ctrl(VirtualKeyCode::K)), None));
+        assert!(fired.borrow().is_empty());
+        // modifiers pressed in between don't interrupt the sequence
+        assert!(!registry.key_input(press(KeyChord::new(VirtualKeyCode::LControl)), None));
+        assert!(registry.key_input(press(ctrl(VirtualKeyCode::S)), None));
+        assert_eq!(*fired.borrow(), vec!["save all"]);
+        // a chord that doesn't continue the sequence can start another
+        assert!(registry.key_input(press(ctrl(VirtualKeyCode::K)), None));
+        assert!(registry.key_input(press(ctrl(VirtualKeyCode::X)), None));
+        assert!(!registry.key_input(press(ctrl(VirtualKeyCode::S)), None));
+        assert_eq!(*fired.borrow(), vec!["save all", "cut"]);
+        // the character typed by a key used by a shortcut is ignored
+        assert!(!registry.received_char());
+        registry.key_input(press(ctrl(VirtualKeyCode::X)), None);
+        assert!(registry.received_char());
+        assert!(!registry.received_char());
+    }
+
+    #[test]
+    fn longer_sequence_in_more_specific_scope() {
+        let (mut registry, fired) = (ShortcutRegistry::new(), Fired::default());
+        let mut parent = Widget::new("parent");
+        let widget = Widget::new("widget");
+        // bound while the scopes don't overlap, then the widget is moved into the subtree
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::K), ShortcutScope::Subtree(parent.clone()), "kill").unwrap();
+        let sequence = vec![ctrl(VirtualKeyCode::K), ctrl(VirtualKeyCode::S)];
+        bind(&mut registry, &fired, sequence, ShortcutScope::Focused(widget.clone()), "save as").unwrap();
+        parent.add_child(widget.clone());
+        assert!(registry.key_input(press(ctrl(VirtualKeyCode::K)), Some(&widget)));
+        assert!(fired.borrow().is_empty());
+        assert!(registry.key_input(press(ctrl(VirtualKeyCode::S)), Some(&widget)));
+        assert!(registry.key_input(press(ctrl(VirtualKeyCode::K)), Some(&parent)));
+        assert_eq!(*fired.borrow(), vec!["save as", "kill"]);
+    }
+
+    #[test]
+    fn removed_widgets() {
+        let (mut registry, fired) = (ShortcutRegistry::new(), Fired::default());
+        let mut parent = Widget::new("parent");
+        let widget = Widget::new("widget");
+        parent.add_child(widget.clone());
+        let other = Widget::new("other");
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::S), ShortcutScope::Global, "save").unwrap();
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::D), ShortcutScope::Focused(widget.clone()), "delete").unwrap();
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::K), ShortcutScope::Subtree(parent.clone()), "kill").unwrap();
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::X), ShortcutScope::Subtree(other), "cut").unwrap();
+        registry.unbind_subtree(&parent);
+        let names: Vec<&str> = registry.bindings().iter().map(|binding| binding.name.as_str()).collect();
+        assert_eq!(names, vec!["save", "cut"]);
+    }
+
+    #[test]
+    fn text_input() {
+        let (mut registry, fired) = (ShortcutRegistry::new(), Fired::default());
+        let mut edit = Widget::new("edit");
+        edit.set_accepts_text(true);
+        let button = Widget::new("button");
+        bind(&mut registry, &fired, KeyChord::new(VirtualKeyCode::A), ShortcutScope::Global, "a").unwrap();
+        bind(&mut registry, &fired, KeyChord::new(VirtualKeyCode::A).shift(), ShortcutScope::Global, "shift a").unwrap();
+        bind(&mut registry, &fired, ctrl(VirtualKeyCode::A), ShortcutScope::Global, "select all").unwrap();
+        bind(&mut registry, &fired, KeyChord::new(VirtualKeyCode::F1), ShortcutScope::Global, "help").unwrap();
+        let sequence = vec![ctrl(VirtualKeyCode::K), KeyChord::new(VirtualKeyCode::B)];
+        bind(&mut registry, &fired, sequence, ShortcutScope::Global, "bold").unwrap();
+
+        // keys that type text go to the widget accepting it
+        assert!(!registry.key_input(press(KeyChord::new(VirtualKeyCode::A)), Some(&edit)));
+        assert!(!registry.key_input(press(KeyChord::new(VirtualKeyCode::A).shift()), Some(&edit)));
+        assert!(!registry.received_char());
+        assert!(fired.borrow().is_empty());
+        assert!(registry.key_input(press(ctrl(VirtualKeyCode::A)), Some(&edit)));
+        assert!(registry.key_input(press(KeyChord::new(VirtualKeyCode::F1)), Some(&edit)));
+        // once a sequence is started, any key can continue it
+        assert!(registry.key_input(press(ctrl(VirtualKeyCode::K)), Some(&edit)));
+        assert!(registry.key_input(press(KeyChord::new(VirtualKeyCode::B)), Some(&edit)));
+        assert!(registry.key_input(press(KeyChord::new(VirtualKeyCode::A)), Some(&button)));
+        assert_eq!(*fired.borrow(), vec!["select all", "help", "bold", "a"]);
+    }
+}
@@ -0,0 +1,96 @@
+//! Touch input handlers.
+//!
+//! Each touch is routed to the widget under the point where it started, which receives every
+//! later event for that touch as a `WidgetTouchEvent`, bubbling up, until the touch ends or is
+//! cancelled. Touch input can be synthesized, eg. for testing gestures, by sending `TouchEvent`s
+//! to the root widget.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use glutin;
+
+use event::{EventHandler, EventArgs};
+use widget::Widget;
+use input::gesture::{GestureTimeout, LongPressTimeout};
+use geometry::Point;
+use app::App;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TouchPhase {
+    Start,
+    Moved,
+    End,
+    Cancelled,
+}
+
+impl From<glutin::TouchPhase> for TouchPhase {
+    fn from(phase: glutin::TouchPhase) -> Self {
+        match phase {
+            glutin::TouchPhase::Started => TouchPhase::Start,
+            glutin::TouchPhase::Moved => TouchPhase::Moved,
+            glutin::TouchPhase::Ended => TouchPhase::End,
+            glutin::TouchPhase::Cancelled => TouchPhase::Cancelled,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct TouchEvent {
+    /// Identifies the touch, unique among the touches currently on the screen
+    pub id: u64,
+    pub phase: TouchPhase,
+    pub position: Point,
+    /// When the event occurred, used by gesture recognizers to measure durations and velocities
+    pub time: Instant,
+}
+
+impl TouchEvent {
+    /// Create a touch event that occurred now.
+    pub fn new(id: u64, phase: TouchPhase, position: Point) -> Self {
+        TouchEvent {
+            id: id,
+            phase: phase,
+            position: position,
+            time: Instant::now(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct WidgetTouchEvent(pub TouchEvent);
+
+/// Tracks the widget each touch started on.
+#[derive(Default)]
+struct TouchController {
+    touches: HashMap<u64, Widget>,
+}
+
+impl EventHandler<TouchEvent> for TouchController {
+    fn handle(&mut self, event: &TouchEvent, args: EventArgs) {
+        if event.phase == TouchPhase::Start {
+            if let Some(widget) = args.ui.widget_under_cursor(event.position) {
+                self.touches.insert(event.id, widget);
+            }
+        }
+        let widget = match event.phase {
+            TouchPhase::End | TouchPhase::Cancelled => self.touches.remove(&event.id),
+            _ => self.touches.get(&event.id).cloned(),
+        };
+        if let Some(widget) = widget {
+            widget.event_bubble_up(WidgetTouchEvent(*event));
+        }
+    }
+}
+
+impl App {
+    pub fn add_touch_handlers(&mut self) {
+        self.add_handler(TouchController::default());
+        // long press timers run on the shared timer thread, which can only send events to the root
+        self.add_handler(|event: &GestureTimeout, args: EventArgs| {
+            if let Some(widget) = args.ui.get_widget(event.widget) {
+                widget.event(LongPressTimeout(event.touch, event.start));
+            }
+        });
+    }
+}
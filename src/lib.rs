@@ -60,10 +60,11 @@ pub extern crate glutin;
 pub extern crate webrender;
 extern crate euclid;
 extern crate linked_hash_map;
-extern crate stable_bst;
 extern crate gleam;
 extern crate app_units;
 extern crate image;
+extern crate gif;
+extern crate xml;
 extern crate font_loader;
 
 #[macro_use]
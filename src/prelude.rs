@@ -7,9 +7,10 @@ pub use glutin;
 
 pub use geometry::{Point, Rect, RectExt, Size, SizeExt, Vector};
 pub use event::{EventHandler, EventArgs};
-pub use event::event_global;
-pub use input::mouse::ClickEvent;
+pub use event::{event_global, event_global_after};
+pub use input::mouse::{ClickEvent, AuxClickEvent};
 pub use widget::Widget;
+pub use widget::transform::Transform;
 pub use widget::draw::{Draw, DrawEventHandler};
 pub use widget::property::Property;
 pub use widget::property::states::*;
@@ -54,6 +54,7 @@ impl WebRenderContext {
         let (mut renderer, sender) = webrender::Renderer::new(gl, notifier, opts).unwrap();
         let api = sender.create_api();
         resources::init_resources(sender);
+        resources::resources().image_loader.hidpi_factor = window.hidpi_factor();
         let document_id = api.add_document(window.size_px(), 0);
 
         renderer.set_external_image_handler(Box::new(LimnExternalImageHandler));
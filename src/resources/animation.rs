@@ -0,0 +1,417 @@
+//! Decoding of animated GIF and APNG images into a sequence of frames.
+//!
+//! Frames are composited as they're decoded, following each frame's disposal and blending
+//! rules, so every frame holds the complete image to show, in premultiplied BGRA pixels.
+
+use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
+
+use failure::Error;
+use gif::{self, SetParameter, DisposalMethod};
+use image;
+
+use resources::image::premultiply;
+
+pub struct AnimationFrame {
+    pub pixels: Vec<u8>,
+    /// How long the frame is shown before the next one
+    pub delay: Duration,
+}
+
+impl fmt::Debug for AnimationFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AnimationFrame").field("delay", &self.delay).finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct Animation {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<AnimationFrame>,
+    /// The number of times the animation is played, forever if `None`
+    pub loop_count: Option<u32>,
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "invalid animated image, {}", reason)]
+struct AnimationError {
+    reason: &'static str,
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+impl Animation {
+    /// Decode an animated GIF or APNG image. Returns `None` for any other image, or an
+    /// image with a single frame, which can be loaded as a still image instead.
+    pub fn decode(data: &[u8]) -> Result<Option<Self>, Error> {
+        let animation = if data.starts_with(b"GIF8") {
+            decode_gif(data)?
+        } else if data.starts_with(PNG_SIGNATURE) && is_apng(data) {
+            decode_apng(data)?
+        } else {
+            return Ok(None);
+        };
+        if animation.frames.len() > 1 {
+            Ok(Some(animation))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Browsers show frames with no delay, or a delay of 10ms or less, for 100ms.
+fn frame_delay(millis: u64) -> Duration {
+    Duration::from_millis(if millis <= 10 { 100 } else { millis })
+}
+
+/// Straight alpha RGBA pixels of the whole image, that frames are drawn onto.
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// The length of the RGBA pixels of an image of the given size, `None` if it overflows.
+fn pixels_len(width: u32, height: u32) -> Option<usize> {
+    (width as usize).checked_mul(height as usize).and_then(|len| len.checked_mul(4))
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Result<Self, Error> {
+        let len = match pixels_len(width, height) {
+            Some(len) => len,
+            None => return Err(AnimationError { reason: "image too large" }.into()),
+        };
+        Ok(Canvas {
+            width: width,
+            height: height,
+            pixels: vec![0; len],
+        })
+    }
+    /// The columns and rows of the canvas covered by a region, cut off at the canvas edges.
+    fn region(&self, left: u32, top: u32, width: u32, height: u32) -> Result<(Range<u32>, Range<u32>), Error> {
+        match (left.checked_add(width), top.checked_add(height)) {
+            (Some(right), Some(bottom)) => Ok((
+                left.min(self.width)..right.min(self.width),
+                top.min(self.height)..bottom.min(self.height),
+            )),
+            _ => Err(AnimationError { reason: "frame offset out of range" }.into()),
+        }
+    }
+    /// Draws the RGBA pixels of a frame at the given offset, replacing the canvas pixels, or
+    /// blending over them, regions outside the canvas are cut off.
+    fn draw(&mut self, pixels: &[u8], left: u32, top: u32, width: u32, height: u32, blend: bool) -> Result<(), Error> {
+        if pixels_len(width, height) != Some(pixels.len()) {
+            return Err(AnimationError { reason: "frame size doesn't match it's pixels" }.into());
+        }
+        let (columns, rows) = self.region(left, top, width, height)?;
+        for canvas_y in rows {
+            for canvas_x in columns.clone() {
+                let (x, y) = ((canvas_x - left) as usize, (canvas_y - top) as usize);
+                let source_index = (y * width as usize + x) * 4;
+                let source = &pixels[source_index..source_index + 4];
+                let index = (canvas_y as usize * self.width as usize + canvas_x as usize) * 4;
+                let dest = &mut self.pixels[index..index + 4];
+                if !blend || source[3] == 255 {
+                    dest.copy_from_slice(source);
+                } else if source[3] > 0 {
+                    let source_alpha = f32::from(source[3]) / 255.0;
+                    let dest_alpha = f32::from(dest[3]) / 255.0 * (1.0 - source_alpha);
+                    let alpha = source_alpha + dest_alpha;
+                    for channel in 0..3 {
+                        let value = f32::from(source[channel]) * source_alpha + f32::from(dest[channel]) * dest_alpha;
+                        dest[channel] = (value / alpha).round() as u8;
+                    }
+                    dest[3] = (alpha * 255.0).round() as u8;
+                }
+            }
+        }
+        Ok(())
+    }
+    fn clear(&mut self, left: u32, top: u32, width: u32, height: u32) -> Result<(), Error> {
+        let (columns, rows) = self.region(left, top, width, height)?;
+        for y in rows {
+            for x in columns.clone() {
+                let index = (y as usize * self.width as usize + x as usize) * 4;
+                for byte in &mut self.pixels[index..index + 4] {
+                    *byte = 0;
+                }
+            }
+        }
+        Ok(())
+    }
+    fn frame(&self, delay: Duration) -> AnimationFrame {
+        let mut pixels = self.pixels.clone();
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+        premultiply(&mut pixels);
+        AnimationFrame {
+            pixels: pixels,
+            delay: delay,
+        }
+    }
+}
+
+fn decode_gif(data: &[u8]) -> Result<Animation, Error> {
+    let mut decoder = gif::Decoder::new(data);
+    decoder.set(gif::ColorOutput::RGBA);
+    let mut reader = decoder.read_info()?;
+    let mut canvas = Canvas::new(u32::from(reader.width()), u32::from(reader.height()))?;
+    let mut frames = Vec::new();
+    while let Some(frame) = reader.read_next_frame()? {
+        let (left, top) = (u32::from(frame.left), u32::from(frame.top));
+        let (width, height) = (u32::from(frame.width), u32::from(frame.height));
+        let previous = if frame.dispose == DisposalMethod::Previous {
+            Some(canvas.pixels.clone())
+        } else {
+            None
+        };
+        // transparent pixels leave what's underneath unchanged, every other pixel is opaque
+        canvas.draw(&frame.buffer, left, top, width, height, true)?;
+        // the delay is in hundredths of a second
+        frames.push(canvas.frame(frame_delay(u64::from(frame.delay) * 10)));
+        match frame.dispose {
+            DisposalMethod::Background => canvas.clear(left, top, width, height)?,
+            DisposalMethod::Previous => canvas.pixels = previous.unwrap(),
+            DisposalMethod::Any | DisposalMethod::Keep => (),
+        }
+    }
+    Ok(Animation {
+        width: canvas.width,
+        height: canvas.height,
+        frames: frames,
+        loop_count: gif_loop_count(data),
+    })
+}
+
+/// Finds the loop count in the Netscape application extension. Without it, the animation
+/// plays once, otherwise it repeats the given number of times, or forever if it's zero.
+fn gif_loop_count(data: &[u8]) -> Option<u32> {
+    let name = b"NETSCAPE2.0";
+    let position = match data.windows(name.len()).position(|window| window == name) {
+        Some(position) => position + name.len(),
+        None => return Some(1),
+    };
+    // sub-block of length 3, with id 1, followed by the count
+    match data.get(position..position + 5) {
+        Some(block) if block[0] == 3 && block[1] == 1 => {
+            let count = u32::from(block[2]) | u32::from(block[3]) << 8;
+            if count == 0 { None } else { Some(count + 1) }
+        }
+        _ => Some(1),
+    }
+}
+
+struct Chunk<'a> {
+    kind: &'a [u8],
+    data: &'a [u8],
+}
+
+/// Splits PNG data into it's chunks, after the signature.
+fn png_chunks(data: &[u8]) -> Result<Vec<Chunk>, Error> {
+    let mut chunks = Vec::new();
+    let mut position = PNG_SIGNATURE.len();
+    while position < data.len() {
+        if data.len() < position + 12 {
+            return Err(AnimationError { reason: "truncated chunk" }.into());
+        }
+        let length = read_u32(&data[position..]) as usize;
+        let end = position + 8 + length;
+        if data.len() < end + 4 {
+            return Err(AnimationError { reason: "truncated chunk" }.into());
+        }
+        let chunk = Chunk {
+            kind: &data[position + 4..position + 8],
+            data: &data[position + 8..end],
+        };
+        let is_end = chunk.kind == b"IEND";
+        chunks.push(chunk);
+        if is_end {
+            break;
+        }
+        position = end + 4;
+    }
+    Ok(chunks)
+}
+
+/// An APNG has an animation control chunk before the image data.
+fn is_apng(data: &[u8]) -> bool {
+    png_chunks(data).ok().map_or(false, |chunks| {
+        chunks.iter().take_while(|chunk| chunk.kind != b"IDAT").any(|chunk| chunk.kind == b"acTL")
+    })
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from(data[0]) << 24 | u32::from(data[1]) << 16 | u32::from(data[2]) << 8 | u32::from(data[3])
+}
+
+fn read_u16(data: &[u8]) -> u16 {
+    u16::from(data[0]) << 8 | u16::from(data[1])
+}
+
+/// A frame control chunk, along with the image data of the frame.
+struct ApngFrame {
+    width: u32,
+    height: u32,
+    left: u32,
+    top: u32,
+    delay: Duration,
+    dispose_op: u8,
+    blend_op: u8,
+    data: Vec<u8>,
+}
+
+fn decode_apng(data: &[u8]) -> Result<Animation, Error> {
+    let chunks = png_chunks(data)?;
+    let mut header = None;
+    let mut loop_count = None;
+    // chunks that apply to every frame, such as the palette
+    let mut shared = Vec::new();
+    let mut frames: Vec<ApngFrame> = Vec::new();
+    for chunk in &chunks {
+        match chunk.kind {
+            b"IHDR" if chunk.data.len() == 13 => header = Some(chunk.data),
+            b"acTL" if chunk.data.len() == 8 => {
+                let plays = read_u32(&chunk.data[4..]);
+                loop_count = if plays == 0 { None } else { Some(plays) };
+            }
+            b"fcTL" if chunk.data.len() == 26 => {
+                let fctl = chunk.data;
+                let numerator = u64::from(read_u16(&fctl[20..]));
+                let denominator = match read_u16(&fctl[22..]) {
+                    0 => 100,
+                    denominator => u64::from(denominator),
+                };
+                frames.push(ApngFrame {
+                    width: read_u32(&fctl[4..]),
+                    height: read_u32(&fctl[8..]),
+                    left: read_u32(&fctl[12..]),
+                    top: read_u32(&fctl[16..]),
+                    delay: frame_delay(numerator * 1000 / denominator),
+                    dispose_op: fctl[24],
+                    blend_op: fctl[25],
+                    data: Vec::new(),
+                });
+            }
+            // the default image is only part of the animation if a frame control chunk precedes it
+            b"IDAT" => if let Some(frame) = frames.last_mut() {
+                frame.data.extend_from_slice(chunk.data);
+            },
+            // frame data starts with a sequence number
+            b"fdAT" if chunk.data.len() >= 4 => if let Some(frame) = frames.last_mut() {
+                frame.data.extend_from_slice(&chunk.data[4..]);
+            },
+            b"PLTE" | b"tRNS" => shared.push(chunk),
+            _ => (),
+        }
+    }
+    let header = match header {
+        Some(header) => header,
+        None => return Err(AnimationError { reason: "missing header" }.into()),
+    };
+
+    let mut canvas = Canvas::new(read_u32(header), read_u32(&header[4..]))?;
+    let mut animation_frames = Vec::new();
+    for (index, frame) in frames.iter().enumerate() {
+        if frame.data.is_empty() {
+            continue;
+        }
+        let image = image::load_from_memory_with_format(&frame_png(header, &shared, frame), image::ImageFormat::PNG)?;
+        let pixels = image.to_rgba().into_raw();
+        // restoring the previous frame before the first is the same as clearing it
+        let dispose_op = if index == 0 && frame.dispose_op == 2 { 1 } else { frame.dispose_op };
+        let previous = if dispose_op == 2 { Some(canvas.pixels.clone()) } else { None };
+        canvas.draw(&pixels, frame.left, frame.top, frame.width, frame.height, frame.blend_op == 1)?;
+        animation_frames.push(canvas.frame(frame.delay));
+        match dispose_op {
+            1 => canvas.clear(frame.left, frame.top, frame.width, frame.height)?,
+            2 => canvas.pixels = previous.unwrap(),
+            _ => (),
+        }
+    }
+    Ok(Animation {
+        width: canvas.width,
+        height: canvas.height,
+        frames: animation_frames,
+        loop_count: loop_count,
+    })
+}
+
+/// Builds a standalone PNG image from a single frame, that can be passed to the PNG decoder.
+fn frame_png(header: &[u8], shared: &[&Chunk], frame: &ApngFrame) -> Vec<u8> {
+    let mut frame_header = header.to_vec();
+    write_u32(&mut frame_header[0..4], frame.width);
+    write_u32(&mut frame_header[4..8], frame.height);
+    let mut png = PNG_SIGNATURE.to_vec();
+    push_chunk(&mut png, b"IHDR", &frame_header);
+    for chunk in shared {
+        push_chunk(&mut png, chunk.kind, chunk.data);
+    }
+    push_chunk(&mut png, b"IDAT", &frame.data);
+    push_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_u32(data: &mut [u8], value: u32) {
+    data[0] = (value >> 24) as u8;
+    data[1] = (value >> 16) as u8;
+    data[2] = (value >> 8) as u8;
+    data[3] = value as u8;
+}
+
+fn push_chunk(png: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+    let mut length = [0; 4];
+    write_u32(&mut length, data.len() as u32);
+    png.extend_from_slice(&length);
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let mut crc = [0; 4];
+    write_u32(&mut crc, crc32(kind, data));
+    png.extend_from_slice(&crc);
+}
+
+/// The CRC of a PNG chunk, over it's type and data.
+fn crc32(kind: &[u8], data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (index, entry) in table.iter_mut().enumerate() {
+        let mut value = index as u32;
+        for _ in 0..8 {
+            value = if value & 1 == 1 { 0xEDB8_8320 ^ (value >> 1) } else { value >> 1 };
+        }
+        *entry = value;
+    }
+    let mut crc = 0xFFFF_FFFF;
+    for byte in kind.iter().chain(data.iter()) {
+        crc = table[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc ^ 0xFFFF_FFFF
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canvas_cuts_off_frames() {
+        let mut canvas = Canvas::new(2, 2).unwrap();
+        let frame = [255, 0, 0, 255, 0, 255, 0, 255];
+        canvas.draw(&frame, 1, 1, 2, 1, false).unwrap();
+        assert_eq!(canvas.pixels, vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 255]);
+        canvas.draw(&frame, 5, 0, 2, 1, false).unwrap();
+        canvas.clear(1, 0, 10, 10).unwrap();
+        assert_eq!(canvas.pixels, vec![0; 16]);
+    }
+
+    #[test]
+    fn canvas_rejects_invalid_frames() {
+        let mut canvas = Canvas::new(2, 2).unwrap();
+        let frame = [255, 0, 0, 255];
+        assert!(canvas.draw(&frame, u32::max_value(), 0, 1, 1, false).is_err());
+        assert!(canvas.draw(&frame, 0, 1, 2, 1, false).is_err());
+        assert!(canvas.clear(0, u32::max_value(), 1, 2).is_err());
+        assert_eq!(canvas.pixels, vec![0; 16]);
+    }
+}
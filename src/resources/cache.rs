@@ -0,0 +1,234 @@
+//! A cache of resources uploaded to WebRender, that evicts the least recently used resources
+//! once their total size exceeds a memory budget.
+//!
+//! Resources are in use, and never evicted, while a `ResourceHandle` to them exists, or if
+//! they've been used since the last eviction, which happens once per frame, so resources
+//! drawn in the current frame stay available.
+
+use std::hash::Hash;
+use std::ops::Add;
+use std::sync::Arc;
+
+use linked_hash_map::LinkedHashMap;
+
+/// Keeps a cached resource from being evicted, for as long as the handle or any clone of it exists.
+#[derive(Debug, Clone)]
+pub struct ResourceHandle(Arc<()>);
+
+/// The contents of a cache, for diagnostics.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct CacheStats {
+    pub count: usize,
+    /// Estimated memory used by the cached resources
+    pub bytes: usize,
+    /// The number of resources that can't be evicted
+    pub in_use: usize,
+}
+
+impl Add for CacheStats {
+    type Output = CacheStats;
+    fn add(self, other: CacheStats) -> CacheStats {
+        CacheStats {
+            count: self.count + other.count,
+            bytes: self.bytes + other.bytes,
+            in_use: self.in_use + other.in_use,
+        }
+    }
+}
+
+struct CacheEntry<V> {
+    value: V,
+    bytes: usize,
+    handle: ResourceHandle,
+    /// Used since the last eviction
+    used: bool,
+}
+
+impl<V> CacheEntry<V> {
+    fn in_use(&self) -> bool {
+        self.used || Arc::strong_count(&self.handle.0) > 1
+    }
+}
+
+pub struct ResourceCache<K: Hash + Eq, V> {
+    /// Ordered from least to most recently used
+    entries: LinkedHashMap<K, CacheEntry<V>>,
+    /// Memory the cached resources can use, in bytes, resources in use can exceed it
+    pub budget: usize,
+    bytes: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> ResourceCache<K, V> {
+    pub fn new(budget: usize) -> Self {
+        ResourceCache {
+            entries: LinkedHashMap::new(),
+            budget: budget,
+            bytes: 0,
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Get a resource, without marking it as used.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    /// Get a resource, marking it as used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        match self.entries.get_refresh(key) {
+            Some(entry) => {
+                entry.used = true;
+                Some(&entry.value)
+            }
+            None => None,
+        }
+    }
+
+    /// Add a resource of the given size in bytes, returning the resource it replaces, if any.
+    pub fn insert(&mut self, key: K, value: V, bytes: usize) -> Option<V> {
+        let previous = self.remove(&key);
+        self.bytes += bytes;
+        self.entries.insert(key, CacheEntry {
+            value: value,
+            bytes: bytes,
+            handle: ResourceHandle(Arc::new(())),
+            used: true,
+        });
+        previous
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.bytes -= entry.bytes;
+                Some(entry.value)
+            }
+            None => None,
+        }
+    }
+
+    /// Get a handle that keeps the resource from being evicted.
+    pub fn handle(&self, key: &K) -> Option<ResourceHandle> {
+        self.entries.get(key).map(|entry| entry.handle.clone())
+    }
+
+    /// Remove the least recently used resources that aren't in use, until the cache fits in
+    /// it's budget, returning them so they can be freed.
+    pub fn evict(&mut self) -> Vec<(K, V)> {
+        let mut evicted = Vec::new();
+        if self.bytes > self.budget {
+            let unused: Vec<K> = self.entries.iter()
+                .filter(|&(_, entry)| !entry.in_use())
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in unused {
+                if self.bytes <= self.budget {
+                    break;
+                }
+                let value = self.remove(&key).unwrap();
+                evicted.push((key, value));
+            }
+        }
+        for (_, entry) in self.entries.iter_mut() {
+            entry.used = false;
+        }
+        evicted
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            count: self.entries.len(),
+            bytes: self.bytes,
+            in_use: self.entries.values().filter(|entry| entry.in_use()).count(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cache holding `keys`, each of 10 bytes, none of them used since the last eviction.
+    fn cache(budget: usize, keys: &[&'static str]) -> ResourceCache<&'static str, u32> {
+        let mut cache = ResourceCache::new(budget);
+        for (value, key) in keys.iter().enumerate() {
+            cache.insert(*key, value as u32, 10);
+        }
+        cache.evict();
+        cache
+    }
+
+    fn keys(evicted: Vec<(&'static str, u32)>) -> Vec<&'static str> {
+        evicted.into_iter().map(|(key, _)| key).collect()
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = cache(30, &["a", "b", "c"]);
+        cache.get(&"a");
+        cache.insert("d", 3, 10);
+        cache.insert("e", 4, 10);
+        // "a" was used most recently of the older resources, "d" and "e" were just inserted
+        assert_eq!(keys(cache.evict()), vec!["b", "c"]);
+        assert_eq!(cache.stats(), CacheStats { count: 3, bytes: 30, in_use: 0 });
+        assert!(cache.contains_key(&"a"));
+    }
+
+    #[test]
+    fn peek_does_not_refresh() {
+        let mut cache = cache(20, &["a", "b"]);
+        assert_eq!(cache.peek(&"a"), Some(&0));
+        cache.insert("c", 2, 10);
+        assert_eq!(keys(cache.evict()), vec!["a"]);
+    }
+
+    #[test]
+    fn stays_within_budget() {
+        let mut cache = cache(100, &["a", "b", "c"]);
+        assert!(cache.evict().is_empty());
+        cache.budget = 15;
+        assert_eq!(keys(cache.evict()), vec!["a", "b"]);
+        assert_eq!(cache.stats().bytes, 10);
+    }
+
+    #[test]
+    fn used_resources_exceed_budget() {
+        let mut cache = ResourceCache::new(10);
+        cache.insert("a", 0, 10);
+        cache.insert("b", 1, 10);
+        // both were used since the last eviction
+        assert!(cache.evict().is_empty());
+        assert_eq!(cache.stats(), CacheStats { count: 2, bytes: 20, in_use: 0 });
+        assert_eq!(keys(cache.evict()), vec!["a"]);
+    }
+
+    #[test]
+    fn handles_pin_resources() {
+        let mut cache = cache(0, &["a", "b"]);
+        let handle = cache.handle(&"a").unwrap();
+        assert_eq!(cache.stats().in_use, 1);
+        assert_eq!(keys(cache.evict()), vec!["b"]);
+        // any clone of the handle pins the resource
+        let clone = handle.clone();
+        drop(handle);
+        assert!(cache.evict().is_empty());
+        drop(clone);
+        assert_eq!(cache.stats().in_use, 0);
+        assert_eq!(keys(cache.evict()), vec!["a"]);
+        assert!(cache.handle(&"a").is_none());
+    }
+
+    #[test]
+    fn insert_replaces() {
+        let mut cache = ResourceCache::new(100);
+        assert_eq!(cache.insert("a", 0, 10), None);
+        assert_eq!(cache.insert("a", 1, 30), Some(0));
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.stats().bytes, 30);
+        assert_eq!(cache.remove(&"a"), Some(1));
+        assert_eq!(cache.stats(), CacheStats::default());
+    }
+}
@@ -1,19 +1,61 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io;
 
 use failure::Error;
-use rusttype;
+use rusttype::{self, GlyphId};
 use font_loader::system_fonts::{self, FontProperty, FontPropertyBuilder};
 use app_units;
 use webrender::api::{RenderApi, ResourceUpdates, FontKey, FontInstanceKey};
 
-use text_layout;
+use text_layout::{self, LayoutFont, LoadedFont};
+use resources::cache::{ResourceCache, ResourceHandle, CacheStats};
+
+/// Memory the cache of font instances can use by default, in bytes.
+const DEFAULT_FONT_INSTANCE_BUDGET: usize = 32 * 1024 * 1024;
+/// The number of glyphs a font instance is assumed to rasterize, to estimate it's memory use.
+const ESTIMATED_GLYPHS: usize = 100;
+/// System fonts tried, in order, for characters missing from a text's fonts. Fonts that aren't
+/// installed are skipped, so this covers common fonts on each platform.
+const DEFAULT_FALLBACK_FONTS: &[&str] = &[
+    "DejaVu Sans",
+    "Noto Sans",
+    "Noto Sans CJK SC",
+    "Arial Unicode MS",
+    "Segoe UI Symbol",
+    "Microsoft YaHei",
+    "Noto Color Emoji",
+    "Segoe UI Emoji",
+    "Apple Color Emoji",
+    "Symbola",
+];
 
 pub type Font = rusttype::Font<'static>;
 
 pub struct FontInfo {
     pub key: FontKey,
-    pub info: Font,
+    /// The font, with the face used to shape text with it
+    pub info: LoadedFont,
+}
+
+impl FontInfo {
+    pub fn font(&self) -> &Font {
+        self.info.font()
+    }
+    /// True if the font has a glyph for `ch`, other than it's missing glyph.
+    fn has_glyph(&self, ch: char) -> bool {
+        self.font().glyph(ch).map_or(false, |glyph| glyph.id() != GlyphId(0))
+    }
+}
+
+/// A font, and the fonts used for characters it's missing, identified by the fonts in a text's style.
+type FontChainKey = (FontDescriptor, Vec<FontDescriptor>);
+
+/// The fonts used to draw text of one style. Fallback fonts are added, in order, once a
+/// character none of the fonts before them have a glyph for is drawn.
+struct FontChain {
+    fonts: Vec<FontDescriptor>,
+    /// The fallback fonts that haven't been needed yet
+    unused: VecDeque<FontDescriptor>,
 }
 
 /// Set of properties used to specify a font
@@ -43,12 +85,31 @@ impl FontDescriptor {
     }
 }
 
-#[derive(Default)]
 pub struct FontLoader {
     pub render: Option<RenderApi>,
     pub font_info: HashMap<FontDescriptor, FontInfo>,
     pub bundled_font_info: HashMap<FontDescriptor, FontInfo>,
-    pub font_instances: HashMap<(FontDescriptor, app_units::Au), FontInstanceKey>,
+    /// Fonts at each size they're drawn at, evicted once they're unused and the cache is over it's budget
+    pub font_instances: ResourceCache<(FontDescriptor, app_units::Au), FontInstanceKey>,
+    /// Fonts used for characters missing from a text's fonts, after the fallbacks in it's style
+    fallback_fonts: Vec<FontDescriptor>,
+    font_chains: HashMap<FontChainKey, FontChain>,
+    /// System fonts that failed to load, so fallback chains don't look them up every time
+    missing_fonts: HashSet<FontDescriptor>,
+}
+
+impl Default for FontLoader {
+    fn default() -> Self {
+        FontLoader {
+            render: None,
+            font_info: HashMap::new(),
+            bundled_font_info: HashMap::new(),
+            font_instances: ResourceCache::new(DEFAULT_FONT_INSTANCE_BUDGET),
+            fallback_fonts: DEFAULT_FALLBACK_FONTS.iter().map(|family| FontDescriptor::from_family(family)).collect(),
+            font_chains: HashMap::new(),
+            missing_fonts: HashSet::new(),
+        }
+    }
 }
 
 impl FontLoader {
@@ -72,26 +133,133 @@ impl FontLoader {
         }
     }
 
+    /// Set the fonts used for characters missing from a text's fonts, after the fallbacks in it's style.
+    pub fn set_fallback_fonts(&mut self, fallback_fonts: Vec<FontDescriptor>) {
+        self.fallback_fonts = fallback_fonts;
+        self.font_chains.clear();
+    }
+
+    /// The fonts to draw `text` styled with `descriptor` with: `descriptor`, then the fonts in
+    /// `fallback`, then the loader's `fallback_fonts`, skipping duplicates and fallback fonts
+    /// that aren't available. Fallback fonts are only loaded once a character is missing from
+    /// the fonts before them, and are kept in the chain for the style, so texts of the same
+    /// style are laid out with the same fonts. Borrow the fonts with `loaded_fonts`.
+    pub fn get_font_chain(&mut self, descriptor: &FontDescriptor, fallback: &[FontDescriptor], text: &str) -> Result<Vec<FontDescriptor>, Error> {
+        let key = (descriptor.clone(), fallback.to_vec());
+        if !self.font_chains.contains_key(&key) {
+            self.get_font(descriptor)?;
+            let mut unused = VecDeque::new();
+            for candidate in fallback.iter().chain(self.fallback_fonts.iter()) {
+                if candidate != descriptor && !unused.contains(candidate) {
+                    unused.push_back(candidate.clone());
+                }
+            }
+            let chain = FontChain {
+                fonts: vec![descriptor.clone()],
+                unused: unused,
+            };
+            self.font_chains.insert(key.clone(), chain);
+        }
+        loop {
+            match self.missing_char(&key, text) {
+                Some(ch) => self.extend_font_chain(&key, ch),
+                None => break,
+            }
+        }
+        Ok(self.font_chains[&key].fonts.clone())
+    }
+
+    /// The first char in `text` that none of the fonts in the chain have a glyph for, if there
+    /// are fallback fonts left to try.
+    fn missing_char(&self, key: &FontChainKey, text: &str) -> Option<char> {
+        let chain = &self.font_chains[key];
+        if chain.unused.is_empty() {
+            return None;
+        }
+        text.chars().find(|&ch| {
+            !ch.is_control() && !chain.fonts.iter().any(|font| self.loaded_font(font).map_or(false, |font| font.has_glyph(ch)))
+        })
+    }
+
+    /// Add fallback fonts to the chain until one of them has a glyph for `ch`, or there are none left.
+    fn extend_font_chain(&mut self, key: &FontChainKey, ch: char) {
+        loop {
+            let candidate = match self.font_chains.get_mut(key).unwrap().unused.pop_front() {
+                Some(candidate) => candidate,
+                None => return,
+            };
+            if self.missing_fonts.contains(&candidate) {
+                continue;
+            }
+            let has_glyph = self.get_font(&candidate).ok().map(|font| font.has_glyph(ch));
+            match has_glyph {
+                Some(has_glyph) => {
+                    self.font_chains.get_mut(key).unwrap().fonts.push(candidate);
+                    if has_glyph {
+                        return;
+                    }
+                }
+                None => {
+                    self.missing_fonts.insert(candidate);
+                }
+            }
+        }
+    }
+
+    fn loaded_font(&self, descriptor: &FontDescriptor) -> Option<&FontInfo> {
+        self.bundled_font_info.get(descriptor).or_else(|| self.font_info.get(descriptor))
+    }
+
+    /// Borrow fonts already loaded by `get_font` or `get_font_chain`, to lay out and shape text with.
+    /// Panics if one of the fonts hasn't been loaded.
+    pub fn loaded_fonts(&self, descriptors: &[FontDescriptor]) -> Vec<LayoutFont> {
+        descriptors.iter().map(|descriptor| {
+            self.loaded_font(descriptor).expect("font not loaded").info.layout_font()
+        }).collect()
+    }
+
     pub fn get_font_instance(&mut self, descriptor: &FontDescriptor, font_size: f32) -> Result<&FontInstanceKey, Error> {
         let font_key = self.get_font(descriptor)?.key;
-        let size = app_units::Au::from_f32_px(text_layout::px_to_pt(font_size));
-        let key = (descriptor.clone(), size);
+        let key = font_instance_key(descriptor, font_size);
         if !self.font_instances.contains_key(&key) {
-            let instance_key = webrender_load_font_instance(self.render_api(), font_key, size);
-            self.font_instances.insert(key.clone(), instance_key);
+            let instance_key = webrender_load_font_instance(self.render_api(), font_key, key.1);
+            self.font_instances.insert(key.clone(), instance_key, font_instance_bytes(font_size));
+        }
+        Ok(self.font_instances.get(&key).unwrap())
+    }
+
+    /// Get a handle that keeps a font instance from being evicted from the cache,
+    /// `None` if it hasn't been created.
+    pub fn font_instance_handle(&self, descriptor: &FontDescriptor, font_size: f32) -> Option<ResourceHandle> {
+        self.font_instances.handle(&font_instance_key(descriptor, font_size))
+    }
+
+    /// The number of font instances cached, and an estimate of the memory their glyphs use.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.font_instances.stats()
+    }
+
+    /// Evict unused font instances until the cache is within it's budget.
+    pub(crate) fn trim(&mut self, updates: &mut ResourceUpdates) {
+        for (_, instance_key) in self.font_instances.evict() {
+            updates.delete_font_instance(instance_key);
         }
-        Ok(&self.font_instances[&key])
     }
 
     fn load_font(&mut self, data: Vec<u8>) -> Result<FontInfo, Error> {
-        let font_info = rusttype_load_font_info(data.clone())?;
-        let key = webrender_load_font(self.render_api(), data)?;
-        Ok(FontInfo { key: key, info: font_info })
+        let info = LoadedFont::new(data)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Bad font format, or font can't be used to shape text"))?;
+        // WebRender rasterizes glyphs on it's own thread, with it's own copy of the data
+        let key = webrender_load_font(self.render_api(), info.data().to_vec())?;
+        Ok(FontInfo { key: key, info: info })
     }
 
     pub fn register_font_data(&mut self, descriptor: FontDescriptor, data: Vec<u8>) -> Result<(), Error> {
         let info = self.load_font(data)?;
         self.bundled_font_info.insert(descriptor.clone(), info);
+        // chains may have skipped the font, or used another font in it's place
+        self.missing_fonts.remove(&descriptor);
+        self.font_chains.clear();
         Ok(())
     }
 
@@ -100,6 +268,17 @@ impl FontLoader {
     }
 }
 
+fn font_instance_key(descriptor: &FontDescriptor, font_size: f32) -> (FontDescriptor, app_units::Au) {
+    (descriptor.clone(), app_units::Au::from_f32_px(text_layout::px_to_pt(font_size)))
+}
+
+/// WebRender rasterizes glyphs as they're drawn, estimate the memory they use as a fixed
+/// number of BGRA glyphs, each the size of an em square.
+fn font_instance_bytes(font_size: f32) -> usize {
+    let em = font_size.ceil().max(1.0) as usize;
+    em * em * 4 * ESTIMATED_GLYPHS
+}
+
 fn webrender_load_font(render_api: &RenderApi, data: Vec<u8>) -> Result<FontKey, io::Error> {
     let key = render_api.generate_font_key();
     let mut resources = ResourceUpdates::new();
@@ -122,10 +301,3 @@ fn system_fonts_load_data(property: &FontProperty) -> Result<Vec<u8>, io::Error>
         .ok_or(io::Error::new(io::ErrorKind::NotFound, "Font not found"));
     font
 }
-
-/// Read font data to get font information, v_metrics, glyph info etc.
-fn rusttype_load_font_info(data: Vec<u8>) -> Result<Font, io::Error> {
-    let collection = rusttype::FontCollection::from_bytes(data);
-    let mut font_iter = collection.into_fonts();
-    font_iter.next().ok_or(io::Error::new(io::ErrorKind::InvalidData, "Bad font format"))
-}
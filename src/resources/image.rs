@@ -1,16 +1,32 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
 
 use failure::Error;
 
 use webrender::api::{RenderApi, ResourceUpdates, ExternalImageId, ExternalImageData, ImageKey, ImageFormat, ImageData, ImageDescriptor};
+use webrender::api::{DeviceUintRect, DeviceUintPoint, DeviceUintSize};
 use image::{self, ImageError, DynamicImage, GenericImage};
 
+use resources::svg::Svg;
+use resources::animation::Animation;
+use resources::cache::{ResourceCache, ResourceHandle, CacheStats};
+use resources::pixels::RawPixels;
+use geometry::Size;
+use color::Color;
+use event;
+
 #[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub enum ImageSource {
     AbsolutePath(PathBuf),
     AssetPath(PathBuf),
     Bundled(String),
+    /// An image created from raw pixels with `ImageLoader::create_raw_image`
+    Raw(String),
 }
 
 impl ImageSource {
@@ -23,6 +39,9 @@ impl ImageSource {
     pub fn bundled<P: Into<String>>(name: P) -> Self {
         ImageSource::Bundled(name.into())
     }
+    pub fn raw<P: Into<String>>(name: P) -> Self {
+        ImageSource::Raw(name.into())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -31,19 +50,166 @@ pub struct ImageInfo {
     pub descriptor: ImageDescriptor,
 }
 
+/// An image loaded from a file, along with it's frames if it's animated, which are evicted with it.
+pub struct CachedImage {
+    pub info: ImageInfo,
+    pub animation: Option<Arc<Animation>>,
+}
+
+/// Memory the cache of images loaded from files can use by default, in bytes.
+const DEFAULT_IMAGE_BUDGET: usize = 256 * 1024 * 1024;
+/// Memory the cache of rasterized SVG images can use by default, in bytes.
+const DEFAULT_SVG_IMAGE_BUDGET: usize = 64 * 1024 * 1024;
+/// Memory the cache of tinted images can use by default, in bytes.
+const DEFAULT_TINTED_IMAGE_BUDGET: usize = 32 * 1024 * 1024;
+
+lazy_static! {
+    static ref RELEASED_IMAGES: Mutex<Vec<ImageKey>> = Mutex::new(Vec::new());
+}
+
+/// Free an image resource that isn't cached by the loader, such as one created with
+/// `ImageLoader::create_image_resource`. It's deleted when the next frame is drawn.
+/// Unlike anything using `resources()`, this can be called while the resources are locked,
+/// for example when dropping a draw state.
+pub fn release_image(key: ImageKey) {
+    RELEASED_IMAGES.lock().unwrap().push(key);
+}
+
 #[derive(Debug, Fail)]
 #[fail(display = "missing bundled image {}", name)]
 struct BundledImageMissingError {
     name: String,
 }
 
-#[derive(Default)]
+#[derive(Debug, Fail)]
+#[fail(display = "missing raw image {}", name)]
+struct RawImageMissingError {
+    name: String,
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "pixels at {}, {} are outside the raw image {}", x, y, name)]
+struct RawImageBoundsError {
+    name: String,
+    x: u32,
+    y: u32,
+}
+
+/// An image resource that belongs to a single draw state, rather than the loader, such as the
+/// rasterized image of a path. It's pixels are replaced in place, and it's freed when dropped.
+#[derive(Debug, Default)]
+pub struct OwnedImage {
+    info: Option<ImageInfo>,
+}
+
+// the image resource isn't shared, a copy of a draw state gets an image of it's own
+impl Clone for OwnedImage {
+    fn clone(&self) -> Self {
+        OwnedImage::default()
+    }
+}
+
+impl Drop for OwnedImage {
+    fn drop(&mut self) {
+        if let Some(ref info) = self.info {
+            release_image(info.key);
+        }
+    }
+}
+
+impl OwnedImage {
+    /// The image, `None` until it's first updated.
+    pub fn info(&self) -> Option<&ImageInfo> {
+        self.info.as_ref()
+    }
+    /// Replace the pixels of the image, creating the image resource the first time.
+    pub fn update(&mut self, loader: &mut ImageLoader, data: ImageData, descriptor: ImageDescriptor) -> &ImageInfo {
+        match self.info {
+            Some(ref mut info) => {
+                loader.update_image(info.key, descriptor, data);
+                info.descriptor = descriptor;
+            }
+            None => self.info = Some(loader.create_image_resource(data, descriptor)),
+        }
+        self.info.as_ref().unwrap()
+    }
+}
+
+/// An image created from raw pixels, the pixels are kept to apply partial updates to.
+struct RawImage {
+    info: ImageInfo,
+    pixels: Arc<Vec<u8>>,
+}
+
 pub struct ImageLoader {
     pub render: Option<RenderApi>,
     pub assets_path: PathBuf,
+    /// Images loaded with `load_image`, these are never evicted, since they can't be reloaded
     pub bundled_images: HashMap<ImageSource, ImageInfo>,
-    pub images: HashMap<ImageSource, ImageInfo>,
+    /// The pixels of bundled raster images, kept to create tinted copies from
+    bundled_pixels: HashMap<ImageSource, ImageData>,
+    /// Images created from raw pixels, these are never evicted, but can be removed with `remove_raw_image`
+    raw_images: HashMap<ImageSource, RawImage>,
+    /// Images loaded from files, evicted once they're unused and the cache is over it's budget
+    pub images: ResourceCache<ImageSource, CachedImage>,
     pub texture_descriptors: HashMap<u64, ImageDescriptor>,
+    /// SVG images, rasterized on demand at each size they're drawn at
+    pub svgs: HashMap<ImageSource, Svg>,
+    pub svg_images: ResourceCache<(ImageSource, u32, u32), ImageInfo>,
+    /// Copies of images with the color of every pixel replaced, by size and tint color
+    pub tinted_images: ResourceCache<(ImageSource, u32, u32, u32), ImageInfo>,
+    /// Scale from `DensityIndependentPixel`s to pixels, that SVG images are rasterized at
+    pub hidpi_factor: f32,
+    /// Decoded frames of animated bundled images, the frames of other images are cached with them
+    bundled_animations: HashMap<ImageSource, Arc<Animation>>,
+    /// Images being decoded on a worker thread
+    pub loading: HashSet<ImageSource>,
+    /// Images that failed to decode on a worker thread, they're loaded again if requested
+    failed: HashSet<ImageSource>,
+    /// The number of threads decoding images loaded with `load_image_async`,
+    /// they're started when the first image is loaded
+    pub worker_threads: usize,
+    workers: Option<Sender<(ImageSource, PathBuf)>>,
+    decoded: Arc<Mutex<Vec<(ImageSource, Result<DecodedImage, Error>)>>>,
+}
+
+/// Sent to the root widget from a worker thread when an image has been decoded,
+/// `ImageLoader::finish_loading` adds it to the loader.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ImageDecoded;
+
+/// An image decoded from a file or bundled data, that hasn't been added to the loader yet.
+pub(crate) enum DecodedImage {
+    Raster {
+        data: ImageData,
+        descriptor: ImageDescriptor,
+        animation: Option<Animation>,
+    },
+    Svg(Svg),
+}
+
+impl Default for ImageLoader {
+    fn default() -> Self {
+        ImageLoader {
+            render: None,
+            assets_path: PathBuf::new(),
+            bundled_images: HashMap::new(),
+            bundled_pixels: HashMap::new(),
+            raw_images: HashMap::new(),
+            images: ResourceCache::new(DEFAULT_IMAGE_BUDGET),
+            texture_descriptors: HashMap::new(),
+            svgs: HashMap::new(),
+            svg_images: ResourceCache::new(DEFAULT_SVG_IMAGE_BUDGET),
+            tinted_images: ResourceCache::new(DEFAULT_TINTED_IMAGE_BUDGET),
+            hidpi_factor: 1.0,
+            bundled_animations: HashMap::new(),
+            loading: HashSet::new(),
+            failed: HashSet::new(),
+            worker_threads: 4,
+            workers: None,
+            decoded: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
 }
 
 impl ImageLoader {
@@ -51,40 +217,250 @@ impl ImageLoader {
         ImageLoader::default()
     }
 
-    pub fn get_image(&mut self, source: &ImageSource) -> Result<&ImageInfo, Error> {
-        let image = self.get_image_internal(source);
+    /// Get an image to be drawn at the given size. Raster images are loaded once, at their
+    /// own size, SVG images are rasterized at the given size, scaled by the HiDPI factor.
+    pub fn get_sized_image(&mut self, source: &ImageSource, size: Size) -> Result<&ImageInfo, Error> {
+        if !self.is_svg(source) {
+            return self.get_image(source);
+        }
+        let image = self.get_svg_image(source, size);
         if let Err(ref error) = image {
-            error!("Failed to load image from source {:?}. {}", source, error);
+            error!("Failed to load SVG image from source {:?}. {}", source, error);
         }
         image
     }
 
-    fn get_image_internal(&mut self, source: &ImageSource) -> Result<&ImageInfo, Error> {
-        if self.images.contains_key(source) {
-            Ok(&self.images[source])
+    /// The size images are drawn at by default, for SVG images, the size in the document.
+    pub fn get_image_size(&mut self, source: &ImageSource) -> Result<Size, Error> {
+        if self.is_svg(source) {
+            Ok(self.get_svg(source)?.size)
         } else {
-            let (data, descriptor) = match *source {
-                ImageSource::AbsolutePath(ref path) => {
-                    prepare_image(image::open(&path)?)?
-                },
-                ImageSource::AssetPath(ref relative_path) => {
-                    let mut path = PathBuf::from(&self.assets_path);
-                    path.push(relative_path);
-                    prepare_image(image::open(&path)?)?
-                },
-                ImageSource::Bundled(ref name) => {
-                    return Err(BundledImageMissingError { name: name.to_owned() }.into())
+            let descriptor = self.get_image(source)?.descriptor;
+            Ok(Size::new(descriptor.width as f32, descriptor.height as f32))
+        }
+    }
+
+    fn is_svg(&self, source: &ImageSource) -> bool {
+        if self.svgs.contains_key(source) {
+            return true;
+        }
+        match *source {
+            ImageSource::AbsolutePath(ref path) | ImageSource::AssetPath(ref path) => has_svg_extension(path),
+            ImageSource::Bundled(_) | ImageSource::Raw(_) => false,
+        }
+    }
+
+    /// The file an image is loaded from, bundled images must be loaded with `load_image` instead.
+    fn source_path(&self, source: &ImageSource) -> Result<PathBuf, Error> {
+        match *source {
+            ImageSource::AbsolutePath(ref path) => Ok(path.clone()),
+            ImageSource::AssetPath(ref relative_path) => Ok(self.assets_path.join(relative_path)),
+            ImageSource::Bundled(ref name) => Err(BundledImageMissingError { name: name.to_owned() }.into()),
+            ImageSource::Raw(ref name) => Err(RawImageMissingError { name: name.to_owned() }.into()),
+        }
+    }
+
+    fn get_svg(&mut self, source: &ImageSource) -> Result<&Svg, Error> {
+        if !self.svgs.contains_key(source) {
+            let path = self.source_path(source)?;
+            let svg = Svg::parse(BufReader::new(File::open(path)?))?;
+            self.svgs.insert(source.clone(), svg);
+        }
+        Ok(&self.svgs[source])
+    }
+
+    /// The key of an SVG image rasterized at the given size.
+    fn svg_image_key(&self, source: &ImageSource, size: Size) -> (ImageSource, u32, u32) {
+        let width = ((size.width * self.hidpi_factor).round() as u32).max(1);
+        let height = ((size.height * self.hidpi_factor).round() as u32).max(1);
+        (source.clone(), width, height)
+    }
+
+    fn get_svg_image(&mut self, source: &ImageSource, size: Size) -> Result<&ImageInfo, Error> {
+        let key = self.svg_image_key(source, size);
+        if !self.svg_images.contains_key(&key) {
+            let (width, height) = (key.1, key.2);
+            let pixels = self.get_svg(source)?.render(width, height);
+            let descriptor = ImageDescriptor::new(width, height, ImageFormat::BGRA8, false);
+            let image_info = self.create_image_resource(ImageData::new(pixels), descriptor);
+            self.svg_images.insert(key.clone(), image_info, image_bytes(&descriptor));
+        }
+        Ok(self.svg_images.get(&key).unwrap())
+    }
+
+    /// Get an image to be drawn at the given size, like `get_sized_image`, with the color of
+    /// every pixel replaced by `tint`, keeping it's alpha, to color monochrome icons.
+    pub fn get_tinted_image(&mut self, source: &ImageSource, size: Size, tint: Color) -> Result<&ImageInfo, Error> {
+        let image = self.get_tinted_image_internal(source, size, tint);
+        if let Err(ref error) = image {
+            error!("Failed to tint image from source {:?}. {}", source, error);
+        }
+        image
+    }
+
+    fn get_tinted_image_internal(&mut self, source: &ImageSource, size: Size, tint: Color) -> Result<&ImageInfo, Error> {
+        let descriptor = self.get_sized_image(source, size)?.descriptor;
+        let key = (source.clone(), descriptor.width, descriptor.height, tint.0);
+        if !self.tinted_images.contains_key(&key) {
+            let alpha = if self.is_svg(source) {
+                let pixels = self.get_svg(source)?.render(descriptor.width, descriptor.height);
+                alpha_channel(&pixels, ImageFormat::BGRA8)
+            } else {
+                let (data, descriptor) = self.raster_pixels(source)?;
+                match data {
+                    ImageData::Raw(ref pixels) => alpha_channel(pixels, descriptor.format),
+                    // decoded and raw images are always in memory
+                    ImageData::Blob(_) | ImageData::External(_) => unreachable!(),
                 }
             };
+            let tinted = tint_alpha(&alpha, tint);
+            let descriptor = ImageDescriptor::new(descriptor.width, descriptor.height, ImageFormat::BGRA8, false);
+            let image_info = self.create_image_resource(ImageData::new(tinted), descriptor);
+            self.tinted_images.insert(key.clone(), image_info, image_bytes(&descriptor));
+        }
+        Ok(self.tinted_images.get(&key).unwrap())
+    }
+
+    /// The pixels of a raster image, decoded again if it's loaded from a file.
+    fn raster_pixels(&self, source: &ImageSource) -> Result<(ImageData, ImageDescriptor), Error> {
+        if let Some(data) = self.bundled_pixels.get(source) {
+            return Ok((data.clone(), self.bundled_images[source].descriptor));
+        }
+        if let Some(image) = self.raw_images.get(source) {
+            return Ok((ImageData::new_shared(image.pixels.clone()), image.info.descriptor));
+        }
+        prepare_image(image::open(self.source_path(source)?)?)
+    }
+
+    /// Get a handle that keeps an image drawn at the given size, and tinted if `tint` is set,
+    /// from being evicted from the cache. `None` if the image isn't loaded, or if it's never
+    /// evicted, like untinted bundled images and images created from raw pixels.
+    pub fn image_handle(&self, source: &ImageSource, size: Size, tint: Option<Color>) -> Option<ResourceHandle> {
+        let (width, height) = if self.svgs.contains_key(source) {
+            let key = self.svg_image_key(source, size);
+            if tint.is_none() {
+                return self.svg_images.handle(&key);
+            }
+            (key.1, key.2)
+        } else {
+            let image = self.images.peek(source).map(|image| &image.info)
+                .or_else(|| self.bundled_images.get(source))
+                .or_else(|| self.raw_images.get(source).map(|image| &image.info));
+            let descriptor = match image {
+                Some(image) => image.descriptor,
+                None => return None,
+            };
+            if tint.is_none() {
+                return self.images.handle(source);
+            }
+            (descriptor.width, descriptor.height)
+        };
+        tint.and_then(|tint| self.tinted_images.handle(&(source.clone(), width, height, tint.0)))
+    }
 
-            Ok(self.put_image(source, data, descriptor))
+    /// Get the frames of an animated GIF or APNG image, marking the image as used. `None` if the
+    /// image isn't animated, or isn't loaded yet, in which case it can be drawn as a still image.
+    pub fn get_animation(&mut self, source: &ImageSource) -> Option<Arc<Animation>> {
+        if let Some(animation) = self.bundled_animations.get(source) {
+            return Some(animation.clone());
         }
+        self.images.get(source).and_then(|image| image.animation.clone())
     }
 
-    fn put_image(&mut self, source: &ImageSource, data: ImageData, descriptor: ImageDescriptor) -> &ImageInfo {
-        let image_info = self.create_image_resource(data, descriptor);
-        self.images.insert(source.clone(), image_info);
-        &self.images[source]
+    /// True if the image can be drawn without loading it first.
+    pub fn is_loaded(&self, source: &ImageSource) -> bool {
+        self.bundled_images.contains_key(source) || self.raw_images.contains_key(source) ||
+            self.images.contains_key(source) || self.svgs.contains_key(source)
+    }
+
+    /// True if the last attempt to load the image with `load_image_async` failed.
+    pub fn failed_to_load(&self, source: &ImageSource) -> bool {
+        self.failed.contains(source)
+    }
+
+    /// Start decoding an image on a worker thread, unless it's already loaded or loading.
+    /// Once it's finished, an `ImageDecoded` event is sent to the root widget.
+    pub fn load_image_async(&mut self, source: &ImageSource) {
+        if self.is_loaded(source) || self.loading.contains(source) {
+            return;
+        }
+        self.loading.insert(source.clone());
+        self.failed.remove(source);
+        match self.source_path(source) {
+            Ok(path) => {
+                if self.workers.is_none() {
+                    self.workers = Some(spawn_image_workers(self.worker_threads, self.decoded.clone()));
+                }
+                self.workers.as_ref().unwrap().send((source.clone(), path)).unwrap();
+            }
+            Err(error) => {
+                self.decoded.lock().unwrap().push((source.clone(), Err(error)));
+                event::event_global(ImageDecoded);
+            }
+        }
+    }
+
+    /// Add the images decoded on worker threads since the last call, returning whether
+    /// each one loaded successfully.
+    pub(crate) fn finish_loading(&mut self) -> Vec<(ImageSource, Result<(), Error>)> {
+        let decoded: Vec<_> = self.decoded.lock().unwrap().drain(..).collect();
+        decoded.into_iter().map(|(source, image)| {
+            self.loading.remove(&source);
+            let result = image.map(|image| self.insert_decoded(source.clone(), image));
+            if let Err(ref error) = result {
+                error!("Failed to load image from source {:?}. {}", source, error);
+                self.failed.insert(source.clone());
+            }
+            (source, result)
+        }).collect()
+    }
+
+    fn insert_decoded(&mut self, source: ImageSource, image: DecodedImage) {
+        match image {
+            DecodedImage::Raster { data, descriptor, animation } => {
+                let image_info = self.create_image_resource(data.clone(), descriptor);
+                if let ImageSource::Bundled(_) = source {
+                    self.bundled_images.insert(source.clone(), image_info);
+                    self.bundled_pixels.insert(source.clone(), data);
+                    if let Some(animation) = animation {
+                        self.bundled_animations.insert(source.clone(), Arc::new(animation));
+                    }
+                } else {
+                    let bytes = image_bytes(&descriptor) + animation.as_ref().map_or(0, animation_bytes);
+                    let image = CachedImage {
+                        info: image_info,
+                        animation: animation.map(Arc::new),
+                    };
+                    self.images.insert(source.clone(), image, bytes);
+                }
+            }
+            DecodedImage::Svg(svg) => {
+                self.svgs.insert(source, svg);
+            }
+        }
+    }
+
+    pub fn get_image(&mut self, source: &ImageSource) -> Result<&ImageInfo, Error> {
+        let image = self.get_image_internal(source);
+        if let Err(ref error) = image {
+            error!("Failed to load image from source {:?}. {}", source, error);
+        }
+        image
+    }
+
+    fn get_image_internal(&mut self, source: &ImageSource) -> Result<&ImageInfo, Error> {
+        if self.bundled_images.contains_key(source) {
+            Ok(&self.bundled_images[source])
+        } else if self.raw_images.contains_key(source) {
+            Ok(&self.raw_images[source].info)
+        } else if self.images.contains_key(source) {
+            Ok(&self.images.get(source).unwrap().info)
+        } else {
+            // the frames of animated images are decoded along with the still image
+            let image = decode_raster(&read_file(&self.source_path(source)?)?)?;
+            self.insert_decoded(source.clone(), image);
+            Ok(&self.images.get(source).unwrap().info)
+        }
     }
 
     pub fn create_image_resource(&mut self, data: ImageData, descriptor: ImageDescriptor) -> ImageInfo {
@@ -95,6 +471,12 @@ impl ImageLoader {
         ImageInfo { key: key, descriptor: descriptor }
     }
 
+    pub fn update_image(&mut self, key: ImageKey, descriptor: ImageDescriptor, data: ImageData) {
+        let mut resources = ResourceUpdates::new();
+        resources.update_image(key, descriptor, data, None);
+        self.render_api().update_resources(resources);
+    }
+
     pub fn update_texture(&mut self, key: ImageKey, descriptor: ImageDescriptor, data: ExternalImageData) {
         let mut resources = ResourceUpdates::new();
         resources.update_image(key, descriptor, ImageData::External(data), None);
@@ -110,17 +492,225 @@ impl ImageLoader {
     }
 
     fn load_image_internal(&mut self, name: &str, data: Vec<u8>) -> Result<(), Error> {
-        let (data, descriptor) = prepare_image(image::load_from_memory(&data)?)?;
-        let image_info = self.create_image_resource(data, descriptor);
-        self.images.insert(ImageSource::bundled(name), image_info);
+        let image = decode_image(&data)?;
+        self.insert_decoded(ImageSource::bundled(name), image);
+        Ok(())
+    }
+
+    /// Create an image from raw pixels, drawn with `ImageSource::raw(name)`, replacing any image
+    /// created with the same name. Unless the resources are dropped, the image is kept until
+    /// it's removed with `remove_raw_image`.
+    pub fn create_raw_image(&mut self, name: &str, pixels: RawPixels) -> Result<(), Error> {
+        let (width, height) = (pixels.width, pixels.height);
+        let pixels = Arc::new(pixels.into_bgra()?);
+        let descriptor = ImageDescriptor::new(width, height, ImageFormat::BGRA8, is_image_opaque(ImageFormat::BGRA8, &pixels));
+        self.remove_raw_image(name);
+        let info = self.create_image_resource(ImageData::new_shared(pixels.clone()), descriptor);
+        self.raw_images.insert(ImageSource::raw(name), RawImage { info: info, pixels: pixels });
+        Ok(())
+    }
+
+    /// Replace the pixels of an image created with `create_raw_image`, the size can change,
+    /// but widgets drawing the image aren't resized. Widgets aren't redrawn either,
+    /// call `Ui::redraw` once the image has been updated.
+    pub fn update_raw_image(&mut self, name: &str, pixels: RawPixels) -> Result<(), Error> {
+        let key = self.get_raw_image(name)?.info.key;
+        let (width, height) = (pixels.width, pixels.height);
+        let pixels = Arc::new(pixels.into_bgra()?);
+        let descriptor = ImageDescriptor::new(width, height, ImageFormat::BGRA8, is_image_opaque(ImageFormat::BGRA8, &pixels));
+        let mut resources = ResourceUpdates::new();
+        resources.update_image(key, descriptor, ImageData::new_shared(pixels.clone()), None);
+        self.render_api().update_resources(resources);
+        self.raw_images.insert(ImageSource::raw(name), RawImage {
+            info: ImageInfo { key: key, descriptor: descriptor },
+            pixels: pixels,
+        });
+        Ok(())
+    }
+
+    /// Replace a rect of pixels in an image created with `create_raw_image`, with it's top left
+    /// corner at `x`, `y` pixels from the top left of the image. Only the rect is uploaded,
+    /// which is faster than updating the whole image, for content that changes gradually.
+    pub fn update_raw_image_rect(&mut self, name: &str, x: u32, y: u32, pixels: RawPixels) -> Result<(), Error> {
+        let (width, height) = (pixels.width, pixels.height);
+        let image_size = self.get_raw_image(name)?.info.descriptor;
+        let right = x.checked_add(width);
+        let bottom = y.checked_add(height);
+        if right.map_or(true, |right| right > image_size.width) || bottom.map_or(true, |bottom| bottom > image_size.height) {
+            return Err(RawImageBoundsError { name: name.to_owned(), x: x, y: y }.into());
+        }
+        let rect_pixels = pixels.into_bgra()?;
+        let (key, descriptor, data) = {
+            let image = self.raw_images.get_mut(&ImageSource::raw(name)).unwrap();
+            let row_len = width as usize * 4;
+            let stride = image.info.descriptor.width as usize * 4;
+            {
+                // copied if WebRender hasn't finished with the previous pixels
+                let image_pixels = Arc::make_mut(&mut image.pixels);
+                for (row, rect_row) in rect_pixels.chunks(row_len).enumerate() {
+                    let start = (y as usize + row) * stride + x as usize * 4;
+                    image_pixels[start..start + row_len].copy_from_slice(rect_row);
+                }
+            }
+            if image.info.descriptor.is_opaque {
+                image.info.descriptor.is_opaque = is_image_opaque(ImageFormat::BGRA8, &rect_pixels);
+            }
+            (image.info.key, image.info.descriptor, ImageData::new_shared(image.pixels.clone()))
+        };
+        let dirty_rect = DeviceUintRect::new(DeviceUintPoint::new(x, y), DeviceUintSize::new(width, height));
+        let mut resources = ResourceUpdates::new();
+        resources.update_image(key, descriptor, data, Some(dirty_rect));
+        self.render_api().update_resources(resources);
         Ok(())
     }
 
+    /// Remove an image created with `create_raw_image`, it's freed when the next frame is drawn.
+    pub fn remove_raw_image(&mut self, name: &str) {
+        if let Some(image) = self.raw_images.remove(&ImageSource::raw(name)) {
+            release_image(image.info.key);
+        }
+    }
+
+    fn get_raw_image(&self, name: &str) -> Result<&RawImage, Error> {
+        self.raw_images.get(&ImageSource::raw(name))
+            .ok_or_else(|| RawImageMissingError { name: name.to_owned() }.into())
+    }
+
+    /// The number of images cached, and the memory they use, including rasterized SVG images
+    /// but not bundled images.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.images.stats() + self.svg_images.stats() + self.tinted_images.stats()
+    }
+
+    /// Evict unused images until the caches are within their budgets, and free released images.
+    pub(crate) fn trim(&mut self, updates: &mut ResourceUpdates) {
+        for (_, image) in self.images.evict() {
+            updates.delete_image(image.info.key);
+        }
+        for (_, image) in self.svg_images.evict() {
+            updates.delete_image(image.key);
+        }
+        for (_, image) in self.tinted_images.evict() {
+            updates.delete_image(image.key);
+        }
+        for key in RELEASED_IMAGES.lock().unwrap().drain(..) {
+            updates.delete_image(key);
+        }
+    }
+
     fn render_api(&self) -> &RenderApi {
         self.render.as_ref().unwrap()
     }
 }
 
+/// The memory used by an image, in bytes.
+fn image_bytes(descriptor: &ImageDescriptor) -> usize {
+    descriptor.width as usize * descriptor.height as usize * descriptor.format.bytes_per_pixel() as usize
+}
+
+/// The alpha of every pixel of an image.
+fn alpha_channel(pixels: &[u8], format: ImageFormat) -> Vec<u8> {
+    match format {
+        ImageFormat::BGRA8 => pixels.chunks(4).map(|pixel| pixel[3]).collect(),
+        ImageFormat::A8 => pixels.to_vec(),
+        ImageFormat::RGB8 => vec![255; pixels.len() / 3],
+        ImageFormat::RG8 => vec![255; pixels.len() / 2],
+        ImageFormat::Invalid | ImageFormat::RGBAF32 => unreachable!(),
+    }
+}
+
+/// Premultiplied BGRA pixels of the tint color, with the tint's alpha scaled by each pixel's alpha.
+fn tint_alpha(alpha: &[u8], tint: Color) -> Vec<u8> {
+    let channel = |offset: u32| (tint.0 >> offset & 0xFF) as u32;
+    let (r, g, b, a) = (channel(24), channel(16), channel(8), channel(0));
+    let mut pixels = Vec::with_capacity(alpha.len() * 4);
+    for &pixel_alpha in alpha {
+        let alpha = (a * pixel_alpha as u32 + 128) / 255;
+        pixels.extend_from_slice(&[
+            ((b * alpha + 128) / 255) as u8,
+            ((g * alpha + 128) / 255) as u8,
+            ((r * alpha + 128) / 255) as u8,
+            alpha as u8,
+        ]);
+    }
+    pixels
+}
+
+/// Premultiplied BGRA pixels of the tint color, keeping the alpha of each of the given BGRA pixels.
+pub(crate) fn tint_pixels(pixels: &[u8], tint: Color) -> Vec<u8> {
+    tint_alpha(&alpha_channel(pixels, ImageFormat::BGRA8), tint)
+}
+
+/// The memory used by the decoded frames of an animated image, in bytes.
+fn animation_bytes(animation: &Animation) -> usize {
+    animation.frames.iter().map(|frame| frame.pixels.len()).sum()
+}
+
+/// Starts the threads that decode images for `load_image_async`, returning the sender for
+/// their jobs. Decoded images are added to the `decoded` queue.
+fn spawn_image_workers(count: usize, decoded: Arc<Mutex<Vec<(ImageSource, Result<DecodedImage, Error>)>>>) -> Sender<(ImageSource, PathBuf)> {
+    let (sender, receiver) = mpsc::channel::<(ImageSource, PathBuf)>();
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..count.max(1) {
+        let receiver = receiver.clone();
+        let decoded = decoded.clone();
+        thread::spawn(move || loop {
+            let job = receiver.lock().unwrap().recv();
+            // the sender is gone when the loader is dropped
+            let (source, path) = match job {
+                Ok(job) => job,
+                Err(_) => return,
+            };
+            let image = read_file(&path).and_then(|data| decode_image(&data));
+            decoded.lock().unwrap().push((source, image));
+            event::event_global(ImageDecoded);
+        });
+    }
+    sender
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Decodes an image of any supported format, the frames of animated images are decoded as
+/// well as the first frame.
+fn decode_image(data: &[u8]) -> Result<DecodedImage, Error> {
+    if is_svg_data(data) {
+        return Ok(DecodedImage::Svg(Svg::parse(data)?));
+    }
+    decode_raster(data)
+}
+
+/// Decodes a raster image, if it's frames can't be decoded, it's shown as a still image.
+fn decode_raster(data: &[u8]) -> Result<DecodedImage, Error> {
+    let animation = match Animation::decode(data) {
+        Ok(animation) => animation,
+        Err(error) => {
+            error!("Failed to decode the frames of an animated image, showing the first frame. {}", error);
+            None
+        }
+    };
+    let (data, descriptor) = prepare_image(image::load_from_memory(data)?)?;
+    Ok(DecodedImage::Raster {
+        data: data,
+        descriptor: descriptor,
+        animation: animation,
+    })
+}
+
+fn has_svg_extension(path: &Path) -> bool {
+    path.extension().and_then(|extension| extension.to_str())
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("svg"))
+}
+
+/// SVG documents are XML, unlike any supported raster format they start with text.
+fn is_svg_data(data: &[u8]) -> bool {
+    data.iter().find(|byte| !(**byte as char).is_whitespace()) == Some(&b'<')
+}
+
 fn prepare_image(image: DynamicImage) -> Result<(ImageData, ImageDescriptor), Error> {
     let image_dims = image.dimensions();
     let format = match image {
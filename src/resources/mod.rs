@@ -2,12 +2,16 @@
 pub mod id;
 pub mod font;
 pub mod image;
+pub mod svg;
+pub mod animation;
+pub mod cache;
+pub mod pixels;
 
 use std::sync::{Mutex, MutexGuard};
 use std::collections::HashMap;
 use std::default::Default;
 
-use webrender::api::RenderApiSender;
+use webrender::api::{RenderApiSender, ResourceUpdates};
 
 use self::id::{Id, IdGen};
 use self::font::FontLoader;
@@ -90,6 +94,14 @@ impl Resources {
         self.widget_id.next_id()
     }
 
+    /// Evict unused images and font instances from caches that are over their budget, adding
+    /// the resources to delete to `updates`. Called after drawing each frame, so resources
+    /// drawn in the frame aren't evicted.
+    pub(crate) fn trim_caches(&mut self, updates: &mut ResourceUpdates) {
+        self.image_loader.trim(updates);
+        self.font_loader.trim(updates);
+    }
+
     fn set_render_api(&mut self, render: RenderApiSender) {
         self.font_loader.render = Some(render.create_api());
         self.image_loader.render = Some(render.create_api());
@@ -0,0 +1,87 @@
+//! Buffers of raw pixels, used to create images at runtime, for procedurally generated or
+//! streamed content, without encoding them in an image format.
+
+use resources::image::premultiply;
+
+/// The order of the channels of a pixel, each a byte.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PixelFormat {
+    Rgba8,
+    Bgra8,
+}
+
+/// A rect of pixels, stored row by row from the top left.
+#[derive(Debug, Clone)]
+pub struct RawPixels {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// The number of bytes from the start of one row to the next, if rows are padded
+    pub stride: Option<u32>,
+    pub format: PixelFormat,
+    /// If false, the color channels are multiplied by alpha when the image is uploaded
+    pub premultiplied: bool,
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "invalid pixel buffer, {}", reason)]
+pub struct PixelBufferError {
+    reason: &'static str,
+}
+
+impl RawPixels {
+    pub fn new(data: Vec<u8>, width: u32, height: u32, format: PixelFormat) -> Self {
+        RawPixels {
+            data: data,
+            width: width,
+            height: height,
+            stride: None,
+            format: format,
+            premultiplied: false,
+        }
+    }
+    pub fn stride(mut self, stride: u32) -> Self {
+        self.stride = Some(stride);
+        self
+    }
+    pub fn premultiplied(mut self, premultiplied: bool) -> Self {
+        self.premultiplied = premultiplied;
+        self
+    }
+
+    /// Convert to the packed, premultiplied BGRA pixels WebRender draws, without copying them
+    /// if they're already in that format.
+    pub(crate) fn into_bgra(self) -> Result<Vec<u8>, PixelBufferError> {
+        let row_len = self.width as usize * 4;
+        let stride = self.stride.map_or(row_len, |stride| stride as usize);
+        if self.width == 0 || self.height == 0 {
+            return Err(PixelBufferError { reason: "the size is zero" });
+        }
+        if stride < row_len {
+            return Err(PixelBufferError { reason: "the stride is shorter than a row" });
+        }
+        if self.data.len() < stride * (self.height as usize - 1) + row_len {
+            return Err(PixelBufferError { reason: "the buffer is smaller than the image" });
+        }
+        let mut data = if stride == row_len {
+            let mut data = self.data;
+            data.truncate(row_len * self.height as usize);
+            data
+        } else {
+            let mut data = Vec::with_capacity(row_len * self.height as usize);
+            for row in self.data.chunks(stride).take(self.height as usize) {
+                data.extend_from_slice(&row[..row_len]);
+            }
+            data
+        };
+        if self.format == PixelFormat::Rgba8 {
+            for pixel in data.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        if !self.premultiplied {
+            premultiply(&mut data);
+        }
+        Ok(data)
+    }
+}
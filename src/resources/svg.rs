@@ -0,0 +1,1025 @@
+//! Loads SVG images as vector paths, so they can be rasterized at the size they're drawn at.
+//!
+//! Supports the subset of SVG used by icons: paths, basic shapes, groups, transforms, and solid
+//! fills and strokes, set with presentation attributes or the `style` attribute. Text, gradients,
+//! patterns, clipping, masks, filters and `use` references aren't supported and are skipped.
+//! Lengths in absolute units are converted to pixels, at 96 pixels per inch, percentages aren't
+//! supported.
+
+use std::collections::HashMap;
+use std::f32;
+use std::io::Read;
+
+use failure::Error;
+use xml::reader::{EventReader, XmlEvent};
+
+use draw::path::{self, Path, PathEvent, StyledPath, Stroke, LineJoin, LineCap};
+use geometry::{Point, Rect, Size, Vector};
+use color::Color;
+
+#[derive(Debug, Fail)]
+#[fail(display = "invalid SVG, {}", reason)]
+struct SvgError {
+    reason: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Svg {
+    /// The intrinsic size, from the width and height of the root element, or it's view box
+    pub size: Size,
+    view_box: Rect,
+    paths: Vec<StyledPath>,
+}
+
+impl Svg {
+    pub fn parse<R: Read>(source: R) -> Result<Self, Error> {
+        let mut parser = SvgParser::default();
+        for event in EventReader::new(source) {
+            match event? {
+                XmlEvent::StartElement { name, attributes, .. } => {
+                    let attributes = attributes.into_iter()
+                        .map(|attribute| (attribute.name.local_name, attribute.value))
+                        .collect();
+                    parser.start_element(&name.local_name, attributes)?;
+                }
+                XmlEvent::EndElement { .. } => parser.end_element(),
+                _ => (),
+            }
+        }
+        match parser.root {
+            Some((size, view_box)) => Ok(Svg {
+                size: size,
+                view_box: view_box,
+                paths: parser.paths,
+            }),
+            None => Err(SvgError { reason: "no svg element".to_owned() }.into()),
+        }
+    }
+
+    /// Rasterizes the image into premultiplied BGRA pixels. The view box is scaled to fit the
+    /// given size, preserving it's aspect ratio, and centered.
+    pub fn render(&self, width: u32, height: u32) -> Vec<u8> {
+        let scale = (width as f32 / self.view_box.size.width).min(height as f32 / self.view_box.size.height);
+        let offset = Vector::new(
+            (width as f32 - self.view_box.size.width * scale) / 2.0 - self.view_box.origin.x * scale,
+            (height as f32 - self.view_box.size.height * scale) / 2.0 - self.view_box.origin.y * scale);
+        let transform = Affine([scale, 0.0, 0.0, scale, offset.x, offset.y]);
+        let paths: Vec<StyledPath> = self.paths.iter().map(|styled| {
+            StyledPath {
+                path: transform.path(&styled.path),
+                fill: styled.fill,
+                stroke: styled.stroke.as_ref().map(|stroke| transform.stroke(stroke)),
+            }
+        }).collect();
+        path::rasterize(&paths, width, height)
+    }
+}
+
+/// A 2D affine transform, the matrix `[a c e; b d f; 0 0 1]`, stored as `[a, b, c, d, e, f]`
+/// like the SVG `matrix` transform.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Affine([f32; 6]);
+
+impl Affine {
+    fn identity() -> Self {
+        Affine([1.0, 0.0, 0.0, 1.0, 0.0, 0.0])
+    }
+    /// The transform applying `other`, then `self`.
+    fn pre_mul(&self, other: &Affine) -> Self {
+        let (m, o) = (self.0, other.0);
+        Affine([
+            m[0] * o[0] + m[2] * o[1],
+            m[1] * o[0] + m[3] * o[1],
+            m[0] * o[2] + m[2] * o[3],
+            m[1] * o[2] + m[3] * o[3],
+            m[0] * o[4] + m[2] * o[5] + m[4],
+            m[1] * o[4] + m[3] * o[5] + m[5],
+        ])
+    }
+    fn point(&self, point: Point) -> Point {
+        let m = self.0;
+        Point::new(m[0] * point.x + m[2] * point.y + m[4], m[1] * point.x + m[3] * point.y + m[5])
+    }
+    /// Average scale factor, used for stroke widths
+    fn scale(&self) -> f32 {
+        let m = self.0;
+        (m[0] * m[3] - m[1] * m[2]).abs().sqrt()
+    }
+    fn path(&self, path: &Path) -> Path {
+        let events = path.events.iter().map(|event| match *event {
+            PathEvent::MoveTo(point) => PathEvent::MoveTo(self.point(point)),
+            PathEvent::LineTo(point) => PathEvent::LineTo(self.point(point)),
+            PathEvent::QuadraticTo(control, point) => PathEvent::QuadraticTo(self.point(control), self.point(point)),
+            PathEvent::CubicTo(control_1, control_2, point) => {
+                PathEvent::CubicTo(self.point(control_1), self.point(control_2), self.point(point))
+            }
+            // arcs are converted to curves when parsing, only exact for uniform scaling
+            PathEvent::Arc { center, radius, start_angle, end_angle } => PathEvent::Arc {
+                center: self.point(center),
+                radius: radius * self.scale(),
+                start_angle: start_angle,
+                end_angle: end_angle,
+            },
+            PathEvent::Close => PathEvent::Close,
+        }).collect();
+        Path { events: events }
+    }
+    fn stroke(&self, stroke: &Stroke) -> Stroke {
+        let scale = self.scale();
+        Stroke {
+            width: stroke.width * scale,
+            dashes: stroke.dashes.iter().map(|length| length * scale).collect(),
+            dash_offset: stroke.dash_offset * scale,
+            .. stroke.clone()
+        }
+    }
+}
+
+/// Inherited style properties, and the current transform.
+#[derive(Debug, Clone)]
+struct Style {
+    fill: Option<Color>,
+    stroke: Option<Color>,
+    stroke_width: f32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    dashes: Vec<f32>,
+    dash_offset: f32,
+    opacity: f32,
+    fill_opacity: f32,
+    stroke_opacity: f32,
+    transform: Affine,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            fill: Some(Color(0x000000FF)),
+            stroke: None,
+            stroke_width: 1.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: 4.0,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+            opacity: 1.0,
+            fill_opacity: 1.0,
+            stroke_opacity: 1.0,
+            transform: Affine::identity(),
+        }
+    }
+}
+
+impl Style {
+    fn apply(&mut self, name: &str, value: &str) {
+        let value = value.trim();
+        match name {
+            "fill" => if let Some(color) = parse_paint(value, self.fill) { self.fill = color },
+            "stroke" => if let Some(color) = parse_paint(value, self.stroke) { self.stroke = color },
+            "stroke-width" => if let Some(width) = parse_length(value) { self.stroke_width = width },
+            "stroke-linecap" => match value {
+                "butt" => self.line_cap = LineCap::Butt,
+                "round" => self.line_cap = LineCap::Round,
+                "square" => self.line_cap = LineCap::Square,
+                _ => (),
+            },
+            "stroke-linejoin" => match value {
+                "miter" => self.line_join = LineJoin::Miter,
+                "round" => self.line_join = LineJoin::Round,
+                "bevel" => self.line_join = LineJoin::Bevel,
+                _ => (),
+            },
+            "stroke-miterlimit" => if let Some(limit) = parse_number(value) { self.miter_limit = limit },
+            "stroke-dasharray" => {
+                self.dashes = if value == "none" { Vec::new() } else { parse_numbers(value) };
+            }
+            "stroke-dashoffset" => if let Some(offset) = parse_length(value) { self.dash_offset = offset },
+            // group opacity is approximated by applying it to each shape in the group
+            "opacity" => if let Some(opacity) = parse_number(value) { self.opacity *= opacity },
+            "fill-opacity" => if let Some(opacity) = parse_number(value) { self.fill_opacity = opacity },
+            "stroke-opacity" => if let Some(opacity) = parse_number(value) { self.stroke_opacity = opacity },
+            _ => (),
+        }
+    }
+
+    fn styled_path(&self, path: Path) -> StyledPath {
+        let fill = self.fill.map(|color| with_opacity(color, self.opacity * self.fill_opacity));
+        let stroke = match self.stroke {
+            Some(color) if self.stroke_width > 0.0 => {
+                Some(self.transform.stroke(&Stroke {
+                    width: self.stroke_width,
+                    color: with_opacity(color, self.opacity * self.stroke_opacity),
+                    join: self.line_join,
+                    cap: self.line_cap,
+                    miter_limit: self.miter_limit,
+                    dashes: self.dashes.clone(),
+                    dash_offset: self.dash_offset,
+                }))
+            }
+            _ => None,
+        };
+        StyledPath {
+            path: self.transform.path(&path),
+            fill: fill,
+            stroke: stroke,
+        }
+    }
+}
+
+#[derive(Default)]
+struct SvgParser {
+    /// Size and view box of the root svg element
+    root: Option<(Size, Rect)>,
+    styles: Vec<Style>,
+    /// Depth of unsupported or hidden elements being skipped, with their children
+    skip_depth: usize,
+    paths: Vec<StyledPath>,
+}
+
+impl SvgParser {
+    fn start_element(&mut self, name: &str, mut attributes: HashMap<String, String>) -> Result<(), Error> {
+        if self.skip_depth > 0 {
+            self.skip_depth += 1;
+            return Ok(());
+        }
+        if let Some(style) = attributes.remove("style") {
+            for declaration in style.split(';') {
+                let mut parts = declaration.splitn(2, ':');
+                if let (Some(property), Some(value)) = (parts.next(), parts.next()) {
+                    attributes.insert(property.trim().to_owned(), value.trim().to_owned());
+                }
+            }
+        }
+        let hidden = attributes.get("display").map_or(false, |display| display.trim() == "none");
+        match name {
+            "defs" | "clipPath" | "mask" | "symbol" | "marker" | "pattern" | "linearGradient"
+            | "radialGradient" | "filter" | "text" | "style" | "title" | "desc" | "metadata" => {
+                self.skip_depth = 1;
+                return Ok(());
+            }
+            _ if hidden => {
+                self.skip_depth = 1;
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        let mut style = self.styles.last().cloned().unwrap_or_default();
+        if name == "svg" && self.root.is_none() {
+            self.root = Some(parse_root(&attributes)?);
+        }
+        if let Some(transform) = attributes.get("transform") {
+            style.transform = style.transform.pre_mul(&parse_transform(transform));
+        }
+        for (attribute, value) in &attributes {
+            style.apply(attribute, value);
+        }
+
+        let number = |attribute: &str| attributes.get(attribute).and_then(|value| parse_length(value));
+        let path = match name {
+            "path" => attributes.get("d").map(|data| parse_path_data(data)),
+            "rect" => {
+                let rect = Rect::new(
+                    Point::new(number("x").unwrap_or(0.0), number("y").unwrap_or(0.0)),
+                    Size::new(number("width").unwrap_or(0.0), number("height").unwrap_or(0.0)));
+                let (rx, ry) = match (number("rx"), number("ry")) {
+                    (Some(rx), Some(ry)) => (rx, ry),
+                    (Some(radius), None) | (None, Some(radius)) => (radius, radius),
+                    (None, None) => (0.0, 0.0),
+                };
+                if rect.size.width > 0.0 && rect.size.height > 0.0 {
+                    Some(rounded_rect(rect, rx.min(rect.size.width / 2.0), ry.min(rect.size.height / 2.0)))
+                } else {
+                    None
+                }
+            }
+            "circle" => number("r").map(|radius| {
+                ellipse(Point::new(number("cx").unwrap_or(0.0), number("cy").unwrap_or(0.0)), radius, radius)
+            }),
+            "ellipse" => match (number("rx"), number("ry")) {
+                (Some(rx), Some(ry)) => {
+                    Some(ellipse(Point::new(number("cx").unwrap_or(0.0), number("cy").unwrap_or(0.0)), rx, ry))
+                }
+                _ => None,
+            },
+            "line" => Some(Path::line(
+                Point::new(number("x1").unwrap_or(0.0), number("y1").unwrap_or(0.0)),
+                Point::new(number("x2").unwrap_or(0.0), number("y2").unwrap_or(0.0)))),
+            "polyline" | "polygon" => attributes.get("points").map(|points| {
+                let points: Vec<Point> = parse_numbers(points).chunks(2)
+                    .filter(|pair| pair.len() == 2)
+                    .map(|pair| Point::new(pair[0], pair[1]))
+                    .collect();
+                if name == "polygon" { Path::polygon(&points) } else { Path::polyline(&points) }
+            }),
+            _ => None,
+        };
+        if let Some(path) = path {
+            self.paths.push(style.styled_path(path));
+        }
+        self.styles.push(style);
+        Ok(())
+    }
+
+    fn end_element(&mut self) {
+        if self.skip_depth > 0 {
+            self.skip_depth -= 1;
+        } else {
+            self.styles.pop();
+        }
+    }
+}
+
+fn parse_root(attributes: &HashMap<String, String>) -> Result<(Size, Rect), Error> {
+    let width = attributes.get("width").and_then(|value| parse_length(value));
+    let height = attributes.get("height").and_then(|value| parse_length(value));
+    let view_box = attributes.get("viewBox").map(|value| parse_numbers(value));
+    let view_box = match view_box {
+        Some(ref values) if values.len() == 4 && values[2] > 0.0 && values[3] > 0.0 => {
+            Some(Rect::new(Point::new(values[0], values[1]), Size::new(values[2], values[3])))
+        }
+        _ => None,
+    };
+    match (width, height, view_box) {
+        (Some(width), Some(height), view_box) if width > 0.0 && height > 0.0 => {
+            let size = Size::new(width, height);
+            Ok((size, view_box.unwrap_or_else(|| Rect::new(Point::zero(), size))))
+        }
+        (_, _, Some(view_box)) => Ok((view_box.size, view_box)),
+        _ => Err(SvgError { reason: "missing size and view box".to_owned() }.into()),
+    }
+}
+
+/// Parses a fill or stroke value, `None` if it's invalid, `Some(None)` if it's `none`.
+fn parse_paint(value: &str, current: Option<Color>) -> Option<Option<Color>> {
+    match value {
+        "none" => Some(None),
+        "inherit" => Some(current),
+        // paint servers aren't supported, use the fallback color if there is one
+        _ if value.starts_with("url(") => {
+            match value.find(')') {
+                Some(end) if !value[end + 1..].trim().is_empty() => parse_paint(value[end + 1..].trim(), current),
+                _ => Some(None),
+            }
+        }
+        _ => parse_color(value).map(Some),
+    }
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim();
+    if value.starts_with('#') {
+        let hex = &value[1..];
+        let rgb = match hex.len() {
+            3 => u32::from_str_radix(hex, 16).ok().map(|rgb| {
+                let (r, g, b) = ((rgb >> 8) & 0xF, (rgb >> 4) & 0xF, rgb & 0xF);
+                (r * 17) << 16 | (g * 17) << 8 | b * 17
+            }),
+            6 => u32::from_str_radix(hex, 16).ok(),
+            _ => None,
+        };
+        return rgb.map(|rgb| Color(rgb << 8 | 0xFF));
+    }
+    if value.starts_with("rgb(") && value.ends_with(')') {
+        let channels: Vec<u32> = value[4..value.len() - 1].split(',').filter_map(|channel| {
+            let channel = channel.trim();
+            if channel.ends_with('%') {
+                channel[..channel.len() - 1].trim().parse::<f32>().ok().map(|percent| percent * 2.55)
+            } else {
+                channel.parse::<f32>().ok()
+            }
+        }).map(|channel| channel.max(0.0).min(255.0).round() as u32).collect();
+        if channels.len() == 3 {
+            return Some(Color(channels[0] << 24 | channels[1] << 16 | channels[2] << 8 | 0xFF));
+        }
+        return None;
+    }
+    let rgb = match value {
+        "black" | "currentColor" => 0x000000,
+        "white" => 0xFFFFFF,
+        "red" => 0xFF0000,
+        "lime" => 0x00FF00,
+        "green" => 0x008000,
+        "blue" => 0x0000FF,
+        "yellow" => 0xFFFF00,
+        "cyan" | "aqua" => 0x00FFFF,
+        "magenta" | "fuchsia" => 0xFF00FF,
+        "gray" | "grey" => 0x808080,
+        "silver" => 0xC0C0C0,
+        "maroon" => 0x800000,
+        "olive" => 0x808000,
+        "navy" => 0x000080,
+        "purple" => 0x800080,
+        "teal" => 0x008080,
+        "orange" => 0xFFA500,
+        "transparent" => return Some(Color(0x00000000)),
+        _ => return None,
+    };
+    Some(Color(rgb << 8 | 0xFF))
+}
+
+fn with_opacity(color: Color, opacity: f32) -> Color {
+    let alpha = ((color.0 & 0xFF) as f32 * opacity.max(0.0).min(1.0)).round() as u32;
+    Color(color.0 & 0xFFFF_FF00 | alpha)
+}
+
+/// Parses a number, ignoring anything after it.
+fn parse_number(value: &str) -> Option<f32> {
+    let mut parser = NumberParser::new(value);
+    parser.number()
+}
+
+/// Parses a length in pixels, `None` if it's a percentage or the unit isn't known.
+fn parse_length(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let unit_start = value.rfind(|ch: char| ch.is_digit(10) || ch == '.').map_or(0, |end| end + 1);
+    let scale = match value[unit_start..].trim() {
+        "" | "px" => 1.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        "pt" => 96.0 / 72.0,
+        "pc" => 96.0 / 6.0,
+        // relative to the default font size
+        "em" => 16.0,
+        "ex" => 8.0,
+        _ => return None,
+    };
+    parse_number(&value[..unit_start]).map(|number| number * scale)
+}
+
+fn parse_numbers(value: &str) -> Vec<f32> {
+    let mut parser = NumberParser::new(value);
+    let mut numbers = Vec::new();
+    while let Some(number) = parser.number() {
+        numbers.push(number);
+    }
+    numbers
+}
+
+fn parse_transform(value: &str) -> Affine {
+    let mut transform = Affine::identity();
+    for item in value.split(')') {
+        let mut parts = item.splitn(2, '(');
+        let (name, args) = match (parts.next(), parts.next()) {
+            (Some(name), Some(args)) => (name.trim().trim_left_matches(','), parse_numbers(args)),
+            _ => continue,
+        };
+        let arg = |index: usize, default: f32| args.get(index).cloned().unwrap_or(default);
+        let item = match name.trim() {
+            "matrix" if args.len() == 6 => Affine([args[0], args[1], args[2], args[3], args[4], args[5]]),
+            "translate" => Affine([1.0, 0.0, 0.0, 1.0, arg(0, 0.0), arg(1, 0.0)]),
+            "scale" => {
+                let x = arg(0, 1.0);
+                Affine([x, 0.0, 0.0, arg(1, x), 0.0, 0.0])
+            }
+            "rotate" => {
+                let (sin, cos) = arg(0, 0.0).to_radians().sin_cos();
+                let (x, y) = (arg(1, 0.0), arg(2, 0.0));
+                Affine([1.0, 0.0, 0.0, 1.0, x, y])
+                    .pre_mul(&Affine([cos, sin, -sin, cos, 0.0, 0.0]))
+                    .pre_mul(&Affine([1.0, 0.0, 0.0, 1.0, -x, -y]))
+            }
+            "skewX" => Affine([1.0, 0.0, arg(0, 0.0).to_radians().tan(), 1.0, 0.0, 0.0]),
+            "skewY" => Affine([1.0, arg(0, 0.0).to_radians().tan(), 0.0, 1.0, 0.0, 0.0]),
+            _ => continue,
+        };
+        transform = transform.pre_mul(&item);
+    }
+    transform
+}
+
+/// Reads numbers and flags from path data, points lists and other attributes.
+struct NumberParser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// Units are skipped after numbers, except in path data where letters are commands
+    skip_units: bool,
+}
+
+impl<'a> NumberParser<'a> {
+    fn new(value: &'a str) -> Self {
+        NumberParser { bytes: value.as_bytes(), position: 0, skip_units: true }
+    }
+    fn path_data(data: &'a str) -> Self {
+        NumberParser { bytes: data.as_bytes(), position: 0, skip_units: false }
+    }
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).cloned()
+    }
+    fn skip_separators(&mut self) {
+        while let Some(byte) = self.peek() {
+            if byte == b',' || (byte as char).is_whitespace() {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        match self.peek() {
+            Some(byte) if (byte as char).is_alphabetic() && byte != b'e' && byte != b'E' => {
+                self.position += 1;
+                Some(byte)
+            }
+            _ => None,
+        }
+    }
+    fn has_number(&mut self) -> bool {
+        self.skip_separators();
+        match self.peek() {
+            Some(byte) => (byte as char).is_digit(10) || byte == b'-' || byte == b'+' || byte == b'.',
+            None => false,
+        }
+    }
+    /// Reads a number, skipping any unit after it.
+    fn number(&mut self) -> Option<f32> {
+        if !self.has_number() {
+            return None;
+        }
+        let start = self.position;
+        if let Some(b'-') | Some(b'+') = self.peek() {
+            self.position += 1;
+        }
+        self.digits();
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            self.digits();
+        }
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            let mantissa_end = self.position;
+            self.position += 1;
+            if let Some(b'-') | Some(b'+') = self.peek() {
+                self.position += 1;
+            }
+            if self.digits() == 0 {
+                self.position = mantissa_end;
+            }
+        }
+        let number = ::std::str::from_utf8(&self.bytes[start..self.position]).ok()
+            .and_then(|number| number.parse::<f32>().ok())
+            .and_then(|number| if number.is_finite() { Some(number) } else { None });
+        if number.is_none() {
+            // skip the invalid character, so callers reading a list don't get stuck on it
+            self.position = start + 1;
+        }
+        while self.skip_units && self.peek().map_or(false, |byte| byte == b'%' || (byte as char).is_alphabetic()) {
+            self.position += 1;
+        }
+        number
+    }
+    fn digits(&mut self) -> usize {
+        let start = self.position;
+        while self.peek().map_or(false, |byte| (byte as char).is_digit(10)) {
+            self.position += 1;
+        }
+        self.position - start
+    }
+    /// Arc flags can be written without separators, ex. `a1 1 0 00 10 10`.
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        match self.peek() {
+            Some(b'0') => { self.position += 1; Some(false) }
+            Some(b'1') => { self.position += 1; Some(true) }
+            _ => None,
+        }
+    }
+    fn point(&mut self) -> Option<Point> {
+        match (self.number(), self.number()) {
+            (Some(x), Some(y)) => Some(Point::new(x, y)),
+            _ => None,
+        }
+    }
+}
+
+/// Parses path data, stopping at the first error, as SVG renderers do.
+fn parse_path_data(data: &str) -> Path {
+    let mut parser = NumberParser::path_data(data);
+    let mut path = Path::new();
+    let mut current = Point::zero();
+    let mut subpath_start = Point::zero();
+    // control point of the last curve, reflected by smooth curve commands
+    let mut last_cubic_control: Option<Point> = None;
+    let mut last_quadratic_control: Option<Point> = None;
+    let mut command = None;
+    loop {
+        if let Some(next) = parser.command() {
+            command = Some(next);
+        } else if !parser.has_number() {
+            break;
+        }
+        let command_byte = match command {
+            Some(command) => command,
+            None => break,
+        };
+        let relative = (command_byte as char).is_lowercase();
+        let offset = if relative { current.to_vector() } else { Vector::zero() };
+        let mut cubic_control = None;
+        let mut quadratic_control = None;
+        match command_byte.to_ascii_uppercase() {
+            b'M' => match parser.point() {
+                Some(point) => {
+                    current = point + offset;
+                    subpath_start = current;
+                    path.events.push(PathEvent::MoveTo(current));
+                    // further coordinate pairs are implicit line commands
+                    command = Some(if relative { b'l' } else { b'L' });
+                }
+                None => break,
+            },
+            b'L' => match parser.point() {
+                Some(point) => {
+                    current = point + offset;
+                    path.events.push(PathEvent::LineTo(current));
+                }
+                None => break,
+            },
+            b'H' => match parser.number() {
+                Some(x) => {
+                    current = Point::new(x + offset.x, current.y);
+                    path.events.push(PathEvent::LineTo(current));
+                }
+                None => break,
+            },
+            b'V' => match parser.number() {
+                Some(y) => {
+                    current = Point::new(current.x, y + offset.y);
+                    path.events.push(PathEvent::LineTo(current));
+                }
+                None => break,
+            },
+            b'C' => match (parser.point(), parser.point(), parser.point()) {
+                (Some(control_1), Some(control_2), Some(point)) => {
+                    let control_2 = control_2 + offset;
+                    current = point + offset;
+                    path.events.push(PathEvent::CubicTo(control_1 + offset, control_2, current));
+                    cubic_control = Some(control_2);
+                }
+                _ => break,
+            },
+            b'S' => match (parser.point(), parser.point()) {
+                (Some(control_2), Some(point)) => {
+                    let control_1 = reflect(last_cubic_control, current);
+                    let control_2 = control_2 + offset;
+                    current = point + offset;
+                    path.events.push(PathEvent::CubicTo(control_1, control_2, current));
+                    cubic_control = Some(control_2);
+                }
+                _ => break,
+            },
+            b'Q' => match (parser.point(), parser.point()) {
+                (Some(control), Some(point)) => {
+                    let control = control + offset;
+                    current = point + offset;
+                    path.events.push(PathEvent::QuadraticTo(control, current));
+                    quadratic_control = Some(control);
+                }
+                _ => break,
+            },
+            b'T' => match parser.point() {
+                Some(point) => {
+                    let control = reflect(last_quadratic_control, current);
+                    current = point + offset;
+                    path.events.push(PathEvent::QuadraticTo(control, current));
+                    quadratic_control = Some(control);
+                }
+                None => break,
+            },
+            b'A' => {
+                let radii = (parser.number(), parser.number(), parser.number());
+                let flags = (parser.flag(), parser.flag());
+                match (radii, flags, parser.point()) {
+                    ((Some(rx), Some(ry), Some(rotation)), (Some(large_arc), Some(sweep)), Some(point)) => {
+                        let end = point + offset;
+                        arc_to(&mut path, current, rx, ry, rotation, large_arc, sweep, end);
+                        current = end;
+                    }
+                    _ => break,
+                }
+            }
+            b'Z' => {
+                path.events.push(PathEvent::Close);
+                current = subpath_start;
+                // numbers can't follow a close command without another command before them
+                command = None;
+            }
+            _ => break,
+        }
+        last_cubic_control = cubic_control;
+        last_quadratic_control = quadratic_control;
+    }
+    path
+}
+
+fn reflect(control: Option<Point>, current: Point) -> Point {
+    match control {
+        Some(control) => current + (current - control),
+        None => current,
+    }
+}
+
+/// Adds an elliptical arc, in SVG endpoint parameterization, as cubic Bézier curves.
+/// See the SVG spec, "Elliptical arc implementation notes".
+fn arc_to(path: &mut Path, start: Point, rx: f32, ry: f32, rotation: f32, large_arc: bool, sweep: bool, end: Point) {
+    if start == end {
+        return;
+    }
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx == 0.0 || ry == 0.0 {
+        path.events.push(PathEvent::LineTo(end));
+        return;
+    }
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let half = (start - end) / 2.0;
+    let x1 = cos * half.x + sin * half.y;
+    let y1 = -sin * half.x + cos * half.y;
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let sign = if large_arc == sweep { -1.0 } else { 1.0 };
+    let coefficient = sign * (numerator / denominator).max(0.0).sqrt();
+    let center_x1 = coefficient * rx * y1 / ry;
+    let center_y1 = -coefficient * ry * x1 / rx;
+    let center = Point::new(
+        cos * center_x1 - sin * center_y1 + (start.x + end.x) / 2.0,
+        sin * center_x1 + cos * center_y1 + (start.y + end.y) / 2.0);
+
+    let angle = |ux: f32, uy: f32, vx: f32, vy: f32| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    let start_angle = angle(1.0, 0.0, (x1 - center_x1) / rx, (y1 - center_y1) / ry);
+    let mut sweep_angle = angle((x1 - center_x1) / rx, (y1 - center_y1) / ry,
+                                (-x1 - center_x1) / rx, (-y1 - center_y1) / ry);
+    if !(center.x.is_finite() && center.y.is_finite() && start_angle.is_finite() && sweep_angle.is_finite()) {
+        path.events.push(PathEvent::LineTo(end));
+        return;
+    }
+    if !sweep && sweep_angle > 0.0 {
+        sweep_angle -= 2.0 * f32::consts::PI;
+    } else if sweep && sweep_angle < 0.0 {
+        sweep_angle += 2.0 * f32::consts::PI;
+    }
+
+    let point = |angle: f32| Point::new(
+        center.x + rx * angle.cos() * cos - ry * angle.sin() * sin,
+        center.y + rx * angle.cos() * sin + ry * angle.sin() * cos);
+    let derivative = |angle: f32| Vector::new(
+        -rx * angle.sin() * cos - ry * angle.cos() * sin,
+        -rx * angle.sin() * sin + ry * angle.cos() * cos);
+    // split into segments of at most a quarter turn, which cubics approximate closely
+    let segments = (sweep_angle.abs() / f32::consts::FRAC_PI_2).ceil().max(1.0) as usize;
+    let delta = sweep_angle / segments as f32;
+    let t = 4.0 / 3.0 * (delta / 4.0).tan();
+    for segment in 0..segments {
+        let from = start_angle + delta * segment as f32;
+        let to = from + delta;
+        let segment_end = if segment == segments - 1 { end } else { point(to) };
+        path.events.push(PathEvent::CubicTo(
+            point(from) + derivative(from) * t, point(to) - derivative(to) * t, segment_end));
+    }
+}
+
+fn ellipse(center: Point, rx: f32, ry: f32) -> Path {
+    let right = Point::new(center.x + rx, center.y);
+    let left = Point::new(center.x - rx, center.y);
+    let mut path = Path::new().move_to(right);
+    arc_to(&mut path, right, rx, ry, 0.0, false, true, left);
+    arc_to(&mut path, left, rx, ry, 0.0, false, true, right);
+    path.close()
+}
+
+fn rounded_rect(rect: Rect, rx: f32, ry: f32) -> Path {
+    if rx <= 0.0 || ry <= 0.0 {
+        return Path::rect(rect);
+    }
+    let (left, top) = (rect.origin.x, rect.origin.y);
+    let (right, bottom) = (left + rect.size.width, top + rect.size.height);
+    let corners = [
+        (Point::new(right - rx, top), Point::new(right, top + ry)),
+        (Point::new(right, bottom - ry), Point::new(right - rx, bottom)),
+        (Point::new(left + rx, bottom), Point::new(left, bottom - ry)),
+        (Point::new(left, top + ry), Point::new(left + rx, top)),
+    ];
+    let mut path = Path::new().move_to(Point::new(left + rx, top));
+    for &(from, to) in &corners {
+        path.events.push(PathEvent::LineTo(from));
+        arc_to(&mut path, from, rx, ry, 0.0, false, true, to);
+    }
+    path.close()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(data: &str) -> Vec<PathEvent> {
+        parse_path_data(data).events
+    }
+
+    fn point(x: f32, y: f32) -> Point {
+        Point::new(x, y)
+    }
+
+    fn assert_close(a: Point, b: Point) {
+        assert!((a - b).length() < 0.001, "{:?} != {:?}", a, b);
+    }
+
+    fn end_point(event: &PathEvent) -> Point {
+        match *event {
+            PathEvent::MoveTo(point) | PathEvent::LineTo(point) => point,
+            PathEvent::QuadraticTo(_, point) | PathEvent::CubicTo(_, _, point) => point,
+            _ => panic!("{:?} has no end point", event),
+        }
+    }
+
+    #[test]
+    fn path_absolute_and_relative() {
+        assert_eq!(events("M10 20 L30 40 l10 10 H0 h5 V3 v-3 Z"), vec![
+            PathEvent::MoveTo(point(10.0, 20.0)),
+            PathEvent::LineTo(point(30.0, 40.0)),
+            PathEvent::LineTo(point(40.0, 50.0)),
+            PathEvent::LineTo(point(0.0, 50.0)),
+            PathEvent::LineTo(point(5.0, 50.0)),
+            PathEvent::LineTo(point(5.0, 3.0)),
+            PathEvent::LineTo(point(5.0, 0.0)),
+            PathEvent::Close,
+        ]);
+        // a relative move after a close is relative to the start of the closed subpath
+        assert_eq!(events("m1 1 l5 0 z m1 1"), vec![
+            PathEvent::MoveTo(point(1.0, 1.0)),
+            PathEvent::LineTo(point(6.0, 1.0)),
+            PathEvent::Close,
+            PathEvent::MoveTo(point(2.0, 2.0)),
+        ]);
+    }
+
+    #[test]
+    fn path_implicit_repeats() {
+        // coordinates after a move are lines, relative if the move is
+        assert_eq!(events("M0 0 10 0 10 10"), vec![
+            PathEvent::MoveTo(point(0.0, 0.0)),
+            PathEvent::LineTo(point(10.0, 0.0)),
+            PathEvent::LineTo(point(10.0, 10.0)),
+        ]);
+        assert_eq!(events("m1 1 2 2"), vec![
+            PathEvent::MoveTo(point(1.0, 1.0)),
+            PathEvent::LineTo(point(3.0, 3.0)),
+        ]);
+        assert_eq!(events("M0 0 c1 1 2 2 3 3 1 1 2 2 3 3"), vec![
+            PathEvent::MoveTo(point(0.0, 0.0)),
+            PathEvent::CubicTo(point(1.0, 1.0), point(2.0, 2.0), point(3.0, 3.0)),
+            PathEvent::CubicTo(point(4.0, 4.0), point(5.0, 5.0), point(6.0, 6.0)),
+        ]);
+        // numbers needn't be separated when it's unambiguous
+        assert_eq!(events("M.5.5-1-1 1e1,2E-1"), vec![
+            PathEvent::MoveTo(point(0.5, 0.5)),
+            PathEvent::LineTo(point(-1.0, -1.0)),
+            PathEvent::LineTo(point(10.0, 0.2)),
+        ]);
+    }
+
+    #[test]
+    fn path_smooth_curves() {
+        assert_eq!(events("M0 0 C0 10 10 10 10 0 S20 -10 20 0")[2],
+                   PathEvent::CubicTo(point(10.0, -10.0), point(20.0, -10.0), point(20.0, 0.0)));
+        assert_eq!(events("M0 0 Q5 10 10 0 T20 0")[2],
+                   PathEvent::QuadraticTo(point(15.0, -10.0), point(20.0, 0.0)));
+        // without a curve before it, the first control point is the current point
+        assert_eq!(events("M0 0 S10 10 10 0")[1],
+                   PathEvent::CubicTo(point(0.0, 0.0), point(10.0, 10.0), point(10.0, 0.0)));
+    }
+
+    #[test]
+    fn path_arcs() {
+        // a half circle, split into two quarter turns
+        let arc = events("M0 0 A10 10 0 0 1 20 0");
+        assert_eq!(arc.len(), 3);
+        assert_close(end_point(&arc[1]), point(10.0, -10.0));
+        assert_eq!(end_point(&arc[2]), point(20.0, 0.0));
+        // flags can be written without separators, the sweep flag picks the other side
+        let arc = events("M0 0 a10 10 0 0020 0");
+        assert_close(end_point(&arc[1]), point(10.0, 10.0));
+        assert_eq!(end_point(&arc[2]), point(20.0, 0.0));
+        // radii too small to reach the end point are scaled up
+        let arc = events("M0 0 A1 1 0 0 1 20 0");
+        assert_close(end_point(&arc[1]), point(10.0, -10.0));
+        // a large arc goes the long way around, a sixth of a turn or five sixths here
+        assert_eq!(events("M0 0 A10 10 0 0 1 10 0").len(), 2);
+        assert_eq!(events("M0 0 A10 10 0 1 1 10 0").len(), 5);
+        // an arc with a zero radius is a line
+        assert_eq!(events("M0 0 A0 5 0 0 1 10 0")[1], PathEvent::LineTo(point(10.0, 0.0)));
+    }
+
+    #[test]
+    fn path_errors() {
+        // path data is drawn up to the first error
+        assert_eq!(events("M0 0 L10 0 L5"), vec![
+            PathEvent::MoveTo(point(0.0, 0.0)),
+            PathEvent::LineTo(point(10.0, 0.0)),
+        ]);
+        assert_eq!(events("M0 0 Z 5 5"), vec![PathEvent::MoveTo(point(0.0, 0.0)), PathEvent::Close]);
+        assert_eq!(events("M0 0 X 5 5"), vec![PathEvent::MoveTo(point(0.0, 0.0))]);
+        assert_eq!(events("M0 0 A1 1 0 2 0 1 1"), vec![PathEvent::MoveTo(point(0.0, 0.0))]);
+        assert!(events("M1e40 0").is_empty());
+        for data in &["", "M", "M1", "M1,", "M--1", "M1..2", "M1e", "M\u{e9}1 2", "Z Z Z",
+                      "M0 0 A1e38 1e38 0 1 1 1 1", "M0 0 A1 1 1e38 1 1 3e38 0", "M0 0 a1 1 0 1 1 0 0"] {
+            let path = parse_path_data(data);
+            for event in &path.events {
+                if let PathEvent::CubicTo(control_1, control_2, point) = *event {
+                    assert!(control_1.x.is_finite() && control_2.x.is_finite() && point.x.is_finite());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn transforms() {
+        assert_eq!(parse_transform("translate(10 20)"), Affine([1.0, 0.0, 0.0, 1.0, 10.0, 20.0]));
+        assert_eq!(parse_transform("scale(2)"), Affine([2.0, 0.0, 0.0, 2.0, 0.0, 0.0]));
+        assert_eq!(parse_transform("matrix(1 2 3 4 5 6)"), Affine([1.0, 2.0, 3.0, 4.0, 5.0, 6.0]));
+        // transforms in a list are applied from right to left
+        assert_eq!(parse_transform("translate(10) scale(2, 3)").point(point(1.0, 1.0)), point(12.0, 3.0));
+        assert_eq!(parse_transform("scale(2, 3),translate(10)").point(point(1.0, 1.0)), point(22.0, 3.0));
+        assert_close(parse_transform("rotate(90)").point(point(1.0, 0.0)), point(0.0, 1.0));
+        assert_close(parse_transform("rotate(90 10 10)").point(point(20.0, 10.0)), point(10.0, 20.0));
+        assert_close(parse_transform("skewX(45)").point(point(0.0, 1.0)), point(1.0, 1.0));
+        assert_close(parse_transform("skewY(45)").point(point(1.0, 0.0)), point(1.0, 1.0));
+        // unknown or invalid transforms are ignored
+        assert_eq!(parse_transform("spin(90) matrix(1 2)"), Affine::identity());
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#f00"), Some(Color(0xFF0000FF)));
+        assert_eq!(parse_color("#00ff80"), Some(Color(0x00FF80FF)));
+        assert_eq!(parse_color("rgb(255, 0, 128)"), Some(Color(0xFF0080FF)));
+        assert_eq!(parse_color("rgb(100%, 0%, 0%)"), Some(Color(0xFF0000FF)));
+        assert_eq!(parse_color("rgb(300, -5, 0)"), Some(Color(0xFF0000FF)));
+        assert_eq!(parse_color("teal"), Some(Color(0x008080FF)));
+        assert_eq!(parse_color("transparent"), Some(Color(0x00000000)));
+        for invalid in &["#12", "#ggg", "#\u{e9}\u{e9}\u{e9}", "rgb(1, 2)", "rgb(", "notacolor", ""] {
+            assert_eq!(parse_color(invalid), None);
+        }
+        let red = Some(Color(0xFF0000FF));
+        assert_eq!(parse_paint("none", red), Some(None));
+        assert_eq!(parse_paint("inherit", red), Some(red));
+        assert_eq!(parse_paint("url(#gradient) red", None), Some(red));
+        assert_eq!(parse_paint("url(#gradient)", red), Some(None));
+        assert_eq!(parse_paint("bogus", red), None);
+        assert_eq!(with_opacity(Color(0xFF0000FF), 0.5), Color(0xFF000080));
+    }
+
+    #[test]
+    fn lengths() {
+        assert_eq!(parse_length("10"), Some(10.0));
+        assert_eq!(parse_length(" 10px "), Some(10.0));
+        assert_eq!(parse_length("1in"), Some(96.0));
+        assert_eq!(parse_length("72pt"), Some(96.0));
+        assert_eq!(parse_length("6pc"), Some(96.0));
+        assert_eq!(parse_length("1em"), Some(16.0));
+        assert!((parse_length("2.54cm").unwrap() - 96.0).abs() < 0.001);
+        assert!((parse_length("25.4mm").unwrap() - 96.0).abs() < 0.001);
+        assert_eq!(parse_length("-1.5e1"), Some(-15.0));
+        for invalid in &["50%", "10furlongs", "px", ""] {
+            assert_eq!(parse_length(invalid), None);
+        }
+        assert_eq!(parse_numbers("1,2 3-4.5.5"), vec![1.0, 2.0, 3.0, -4.5, 0.5]);
+    }
+
+    #[test]
+    fn parse_document() {
+        let svg = Svg::parse(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="2in" height="1in" viewBox="0 0 20 10">
+                <defs><rect width="1" height="1"/></defs>
+                <g transform="translate(5 5)" style="fill: red; stroke: none">
+                    <rect width="10" height="5"/>
+                </g>
+                <circle r="1" display="none"/>
+                <path d="M0 0 H 4" stroke="blue" stroke-width="2" fill="none"/>
+            </svg>"#.as_bytes()).unwrap();
+        assert_eq!(svg.size, Size::new(192.0, 96.0));
+        assert_eq!(svg.view_box, Rect::new(Point::zero(), Size::new(20.0, 10.0)));
+        assert_eq!(svg.paths.len(), 2);
+        assert_eq!(svg.paths[0].fill, Some(Color(0xFF0000FF)));
+        assert_eq!(svg.paths[0].stroke, None);
+        assert_eq!(svg.paths[0].path.events[0], PathEvent::MoveTo(point(5.0, 5.0)));
+        assert_eq!(svg.paths[1].fill, None);
+        assert_eq!(svg.paths[1].stroke.as_ref().map(|stroke| stroke.width), Some(2.0));
+
+        // without a size, the view box is the size
+        let svg = Svg::parse(r#"<svg viewBox="0 0 24 12"/>"#.as_bytes()).unwrap();
+        assert_eq!(svg.size, Size::new(24.0, 12.0));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for invalid in &["", "not xml", "<g/>", "<svg/>", "<svg width=\"50%\" height=\"10\"/>",
+                         "<svg viewBox=\"0 0 0 10\"/>", "<svg width=\"1\" height=\"1\"><g>"] {
+            assert!(Svg::parse(invalid.as_bytes()).is_err(), "{} parsed", invalid);
+        }
+    }
+}
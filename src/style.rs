@@ -49,6 +49,20 @@ The theme will prioritize which values to use by (currently) simple specificity
 -Styles registered in the theme for named style classes that can be applied to widgets, eg. "alert_button"
 -The base style for the type, ie. `ButtonStyle`, registered in the theme
 -In values are found no where else, the default values specified in `component_style!`, in this example, `RectStyle::default()` and `None`
+
+Fields that a component needs while it's drawn, but that shouldn't be styled, can be declared after the style fields
+in a `state` block. They're left out of the style struct, and set to their defaults whenever the component is
+created from a style:
+
+```
+# #[macro_use] extern crate limn; fn main() {
+component_style!{pub struct Spinner<name="spinner", style=SpinnerStyle> {
+    speed: f32 = 1.0,
+} state {
+    angle: f32 = 0.0,
+}}
+# }
+```
 */
 
 use std::fmt::Debug;
@@ -250,13 +264,19 @@ impl <T: WidgetModifier + Component + 'static, C: ComponentStyle<Component = T>
 
 #[macro_export]
 macro_rules! component_style {
-    ( pub struct $component:ident <name=$name:expr, style=$style:ident> { $ ( $field:ident : $field_type:ty = $default:expr, ) * } ) => {
+    ( pub struct $component:ident <name=$name:expr, style=$style:ident> { $ ( $(#[$attr:meta])* $field:ident : $field_type:ty = $default:expr, ) * }
+      state { $ ( $(#[$state_attr:meta])* $state_field:ident : $state_type:ty = $state_default:expr, ) * } ) => {
         #[allow(missing_copy_implementations)]
         #[derive(Clone, Debug)]
         pub struct $component {
             $(
+                $(#[$attr])*
                 pub $field: $field_type,
             )*
+            $(
+                $(#[$state_attr])*
+                pub $state_field: $state_type,
+            )*
         }
         impl Default for $component {
             fn default() -> Self {
@@ -264,6 +284,9 @@ macro_rules! component_style {
                     $(
                         $field: $default,
                     )*
+                    $(
+                        $state_field: $state_default,
+                    )*
                 }
             }
         }
@@ -293,10 +316,21 @@ macro_rules! component_style {
                     $(
                         $field: self.$field.unwrap_or($default),
                     )*
+                    $(
+                        $state_field: $state_default,
+                    )*
                 }
             }
         }
     };
+    ( pub struct $component:ident <name=$name:expr, style=$style:ident> { $ ( $(#[$attr:meta])* $field:ident : $field_type:ty = $default:expr, ) * } ) => {
+        component_style!{pub struct $component<name=$name, style=$style> {
+            $(
+                $(#[$attr])*
+                $field: $field_type = $default,
+            )*
+        } state {}}
+    };
 }
 
 #[macro_export]
@@ -16,9 +16,11 @@ use widget::Widget;
 use layout::{LimnSolver, LayoutChanged, LayoutVars, ExactFrame};
 use layout::constraint::*;
 use geometry::{Point, Rect, Size};
-use resources::WidgetId;
+use resources::{resources, WidgetId};
 use event::{Target, EventArgs};
 use render::WebRenderContext;
+use input::shortcut::ShortcutRegistry;
+use input::mouse::{ClickSettings, MouseCaptureLost};
 
 /// If true, the constraint that matches the root layout size to the window size
 /// is required. This can be useful for debugging but can result in panics from resizing the window.
@@ -36,6 +38,17 @@ pub struct Ui {
     debug_draw_bounds: bool,
     pub window: Rc<RefCell<Window>>,
     window_constraints: Vec<Constraint>,
+    /// Layers of the currently open modal dialogs, the last is the topmost
+    pub(crate) modals: Vec<Widget>,
+    /// Last known position of the mouse cursor, `None` if it is outside the window
+    pub(crate) cursor: Option<Point>,
+    /// The widget with keyboard focus, kept up to date by the `FocusHandler`
+    pub(crate) focused: Option<Widget>,
+    /// The widget receiving all mouse input, see `capture_mouse`
+    pub(crate) mouse_capture: Option<Widget>,
+    pub shortcuts: ShortcutRegistry,
+    pub click_settings: ClickSettings,
+    ime_cursor_area: Option<Rect>,
 }
 
 impl Ui {
@@ -56,6 +69,13 @@ impl Ui {
             debug_draw_bounds: false,
             window: Rc::new(RefCell::new(window)),
             window_constraints: Vec::new(),
+            modals: Vec::new(),
+            cursor: None,
+            focused: None,
+            mouse_capture: None,
+            shortcuts: ShortcutRegistry::new(),
+            click_settings: ClickSettings::default(),
+            ime_cursor_area: None,
         }
     }
 
@@ -79,6 +99,59 @@ impl Ui {
         self.should_close
     }
 
+    /// Returns true if a modal dialog is open
+    pub fn has_modal(&self) -> bool {
+        !self.modals.is_empty()
+    }
+
+    /// The position of the mouse cursor, if it is inside the window
+    pub fn cursor_position(&self) -> Option<Point> {
+        self.cursor
+    }
+
+    /// The widget that currently has keyboard focus, if any
+    pub fn focused_widget(&self) -> Option<Widget> {
+        self.focused.clone()
+    }
+
+    /// Send all mouse input to `widget`, regardless of the widget under the cursor, until all mouse
+    /// buttons are released, or `release_mouse` is called. To capture the mouse when a button is pressed
+    /// on a widget, eg. to keep dragging it when the cursor moves off it, use `Widget::set_captures_mouse`,
+    /// which takes the capture before the press is sent to any handlers.
+    ///
+    /// While captured, `widget` stays hovered, and receives `WidgetMouseMoved`, `WidgetMouseButton` and
+    /// `WidgetMouseWheel` events. If the cursor leaves the window, or another widget captures the mouse,
+    /// it receives `MouseCaptureLost`.
+    pub fn capture_mouse(&mut self, widget: Widget) {
+        if let Some(ref capture) = self.mouse_capture {
+            if *capture != widget {
+                capture.event(MouseCaptureLost);
+            }
+        }
+        self.mouse_capture = Some(widget);
+    }
+
+    pub fn release_mouse(&mut self) {
+        self.mouse_capture = None;
+    }
+
+    /// The widget that has captured the mouse, if any
+    pub fn mouse_capture(&self) -> Option<Widget> {
+        self.mouse_capture.clone()
+    }
+
+    /// The caret rectangle of the focused text input, where an input method should place it's
+    /// candidate window. glutin can't position the candidate window yet, so this is provided for
+    /// platform integrations that can.
+    pub fn ime_cursor_area(&self) -> Option<Rect> {
+        self.ime_cursor_area
+    }
+
+    /// Set by text inputs when they gain focus or their caret moves, and cleared when they lose focus.
+    pub fn set_ime_cursor_area(&mut self, area: Option<Rect>) {
+        self.ime_cursor_area = area;
+    }
+
     pub(super) fn resize_window_to_fit(&mut self) {
         let window_dims = self.root.bounds().size;
         self.window.borrow_mut().resize(window_dims.width as u32, window_dims.height as u32);
@@ -138,6 +211,8 @@ impl Ui {
             let mut renderer = self.render.render_builder(window_size);
             let crop_to = Rect::new(Point::zero(), Size::new(::std::f32::MAX, ::std::f32::MAX));
             self.root.draw(crop_to, &mut renderer, self.debug_draw_bounds);
+            // deleted in the same transaction as the display list, which doesn't use them
+            resources().trim_caches(&mut renderer.resources);
             (renderer.builder, renderer.resources)
         };
         self.render.set_display_list(builder, resources, window_size);
@@ -219,6 +294,14 @@ impl Ui {
         }
     }
 
+    pub fn debug_resource_caches(&self) {
+        println!("RESOURCE CACHES");
+        let res = resources();
+        println!("images {:?}", res.image_loader.cache_stats());
+        println!("bundled images {}", res.image_loader.bundled_images.len());
+        println!("font instances {:?}", res.font_loader.cache_stats());
+    }
+
     pub fn print_widgets(&self) {
         for widget_ref in self.widgets_bfs() {
             let draw_state = &widget_ref.widget().draw_state;
@@ -245,6 +328,7 @@ impl App {
             args.ui.solver.remove_layout(widget_ref.id().0);
             args.ui.check_layout_changes();
             args.ui.widget_map.remove(&widget_ref.id());
+            args.ui.shortcuts.unbind_subtree(&widget_ref);
         });
     }
 }
@@ -13,6 +13,11 @@ pub trait Draw: ::std::fmt::Debug {
     fn is_under_cursor(&self, bounds: Rect, cursor: Point) -> bool {
         bounds.contains(&cursor)
     }
+    /// The area drawn to for the given widget bounds, drawing is clipped to this area.
+    /// Can be larger than `bounds` for effects that extend outside the widget, like shadows.
+    fn draw_bounds(&self, bounds: Rect) -> Rect {
+        bounds
+    }
 }
 
 pub trait DrawComponent: Draw {
@@ -40,6 +45,9 @@ impl Draw for DrawWrapper {
     fn draw(&mut self, bounds: Rect, crop_to: Rect, renderer: &mut RenderBuilder) {
         self.wrapper.draw(bounds, crop_to, renderer);
     }
+    fn draw_bounds(&self, bounds: Rect) -> Rect {
+        self.wrapper.draw_bounds(bounds)
+    }
 }
 
 impl <D: Draw + Component + 'static> DrawComponent for D {
@@ -12,6 +12,7 @@
 
 pub mod property;
 pub mod draw;
+pub mod transform;
 
 use std::any::{TypeId, Any};
 use std::collections::HashMap;
@@ -22,6 +23,8 @@ use std::ops::{Deref, DerefMut};
 use std::fmt;
 use std::fmt::Debug;
 
+use webrender::api::{PrimitiveInfo, ScrollPolicy, PropertyBinding, TransformStyle, MixBlendMode, FilterOp};
+
 use render::RenderBuilder;
 use event::{self, EventHandler, EventArgs, EventHandlerWrapper};
 use layout::{Layout, LayoutVars, LayoutRef, LayoutUpdated, VarType};
@@ -36,6 +39,7 @@ use style::*;
 
 use self::property::{PropSet, Property};
 use self::draw::*;
+use self::transform::Transform;
 
 #[derive(Clone, Copy)]
 pub struct StateUpdated;
@@ -76,6 +80,8 @@ impl Widget {
     }
 
     pub fn set_draw_state<T: Draw + Component + 'static>(&mut self, draw_state: T) -> &mut Self {
+        // replaces any draw style, which would otherwise be restored when the props change
+        self.widget_mut().style_type = None;
         self.widget_mut().draw_state = Some(DrawWrapper::new(draw_state));
         self.props_updated();
         self
@@ -204,6 +210,91 @@ impl Widget {
     pub fn bounds(&self) -> Rect {
         self.0.borrow().bounds
     }
+    /// Sets the transform applied to this widget and it's subtree when drawing and hit-testing.
+    /// Doesn't affect layout, the transform is applied relative to the widgets laid out bounds.
+    pub fn set_transform(&mut self, transform: Transform) -> &mut Self {
+        self.widget_mut().transform = if transform.is_identity() { None } else { Some(transform) };
+        self.set_updated(true);
+        self.event(StateUpdated);
+        self
+    }
+    pub fn clear_transform(&mut self) -> &mut Self {
+        self.set_transform(Transform::default())
+    }
+    pub fn transform(&self) -> Option<Transform> {
+        self.0.borrow().transform
+    }
+    /// Sets the opacity of this widget and it's subtree, from 0.0, fully transparent, to 1.0, opaque.
+    /// The subtree is drawn as a group, so overlapping children don't show through each other.
+    pub fn set_opacity(&mut self, opacity: f32) -> &mut Self {
+        self.widget_mut().opacity = opacity.max(0.0).min(1.0);
+        self.set_updated(true);
+        self.event(StateUpdated);
+        self
+    }
+    pub fn opacity(&self) -> f32 {
+        self.0.borrow().opacity
+    }
+    /// Blurs this widget and it's subtree by the given radius, 0.0 for no blur. WebRender can only
+    /// filter what's drawn inside a widget, to blur the background behind a panel, blur the widget
+    /// drawing the background, eg. the content behind a modal dialog.
+    pub fn set_blur(&mut self, radius: f32) -> &mut Self {
+        self.widget_mut().blur = radius.max(0.0);
+        self.set_updated(true);
+        self.event(StateUpdated);
+        self
+    }
+    pub fn blur(&self) -> f32 {
+        self.0.borrow().blur
+    }
+    /// If set, a mouse button pressed on this widget, or on a descendant that doesn't capture the mouse
+    /// itself, captures the mouse for this widget until the button is released, see `Ui::capture_mouse`.
+    pub fn set_captures_mouse(&mut self, captures_mouse: bool) -> &mut Self {
+        self.widget_mut().captures_mouse = captures_mouse;
+        self
+    }
+    pub fn captures_mouse(&self) -> bool {
+        self.0.borrow().captures_mouse
+    }
+    /// If set, keys that type or edit text, pressed while this widget is focused, don't start
+    /// shortcuts, see `ShortcutRegistry`.
+    pub fn set_accepts_text(&mut self, accepts_text: bool) -> &mut Self {
+        self.widget_mut().accepts_text = accepts_text;
+        self
+    }
+    pub fn accepts_text(&self) -> bool {
+        self.0.borrow().accepts_text
+    }
+    /// Set the position of this focusable widget or focus group in the tab order,
+    /// lower tab indices come first, the default is 0.
+    pub fn set_tab_index(&mut self, tab_index: i32) -> &mut Self {
+        self.widget_mut().tab_index = tab_index;
+        self
+    }
+    pub fn tab_index(&self) -> i32 {
+        self.0.borrow().tab_index
+    }
+    /// Maps a point in window coordinates to the untransformed coordinate space this widget
+    /// is laid out in, undoing the transforms of this widget and all it's ancestors.
+    /// Returns `None` if any of those transforms can't be inverted.
+    pub fn to_local(&self, point: Point) -> Option<Point> {
+        let mut ancestors = vec![self.clone()];
+        let mut parent = self.parent();
+        while let Some(widget) = parent {
+            parent = widget.parent();
+            ancestors.push(widget);
+        }
+        let mut point = point;
+        for widget in ancestors.iter().rev() {
+            if let Some(transform) = widget.transform() {
+                match transform.inverse_transform_point(widget.bounds(), point) {
+                    Some(local) => point = local,
+                    None => return None,
+                }
+            }
+        }
+        Some(point)
+    }
 
     pub fn update<F, T: Draw + 'static>(&mut self, f: F)
         where F: FnOnce(&mut T)
@@ -233,7 +324,7 @@ impl Widget {
             widget.children.remove(index);
         }
         self.event(::ui::ChildrenUpdatedEvent::Removed(child.clone()));
-        child.event(::ui::WidgetDetachedEvent);
+        child.event_subtree(::ui::WidgetDetachedEvent);
         event::event(Target::Root, ::ui::RemoveWidget(child.clone()));
     }
 
@@ -255,6 +346,18 @@ impl Widget {
         self.children().iter().find(|child| child.name() == name).cloned()
     }
 
+    /// Returns true if this widget is `root`, or one of its descendants.
+    pub fn is_in_subtree(&self, root: &Widget) -> bool {
+        let mut widget = Some(self.clone());
+        while let Some(current) = widget {
+            if current == *root {
+                return true;
+            }
+            widget = current.parent();
+        }
+        false
+    }
+
     pub fn event<T: 'static>(&self, data: T) {
         event::event(Target::Widget(self.clone()), data);
     }
@@ -299,7 +402,10 @@ impl Widget {
     pub fn is_under_cursor(&self, cursor: Point) -> bool {
         self.update_draw_state();
         if let Some(ref draw_state) = self.widget().draw_state {
-            draw_state.is_under_cursor(self.bounds(), cursor)
+            match self.to_local(cursor) {
+                Some(cursor) => draw_state.is_under_cursor(self.bounds(), cursor),
+                None => false,
+            }
         } else {
             false
         }
@@ -308,17 +414,48 @@ impl Widget {
     fn draw_widget(&mut self, crop_to: Rect, renderer: &mut RenderBuilder) {
         self.update_draw_state();
         let bounds = self.bounds();
-        let clip_id = renderer.builder.define_clip(None, bounds, vec![], None);
-        renderer.builder.push_clip_id(clip_id);
+        let transform = self.transform();
+        let opacity = self.opacity();
+        let blur = self.blur();
+        let needs_stacking_context = transform.is_some() || opacity < 1.0 || blur > 0.0;
+        if needs_stacking_context {
+            let mut filters = Vec::new();
+            if opacity < 1.0 {
+                filters.push(FilterOp::Opacity(PropertyBinding::Value(opacity)));
+            }
+            if blur > 0.0 {
+                filters.push(FilterOp::Blur(blur));
+            }
+            renderer.builder.push_stacking_context(
+                &PrimitiveInfo::new(Rect::zero()),
+                ScrollPolicy::Fixed,
+                transform.map(|transform| PropertyBinding::Value(transform.matrix(bounds))),
+                TransformStyle::Flat,
+                None,
+                MixBlendMode::Normal,
+                filters,
+            );
+        }
+        // crop_to is in window coordinates, which don't line up with the transformed subtree
+        let crop_to = if transform.is_some() { bounds } else { crop_to };
         if let Some(draw_state) = self.widget_mut().draw_state.as_mut() {
+            let draw_bounds = draw_state.draw_bounds(bounds);
+            let clip_id = renderer.builder.define_clip(None, draw_bounds, vec![], None);
+            renderer.builder.push_clip_id(clip_id);
             draw_state.draw(bounds, crop_to, renderer);
+            renderer.builder.pop_clip_id();
         }
         if let Some(crop_to) = crop_to.intersection(&bounds) {
+            let clip_id = renderer.builder.define_clip(None, bounds, vec![], None);
+            renderer.builder.push_clip_id(clip_id);
             for child in &mut self.children() {
                 child.draw_widget(crop_to, renderer);
             }
+            renderer.builder.pop_clip_id();
+        }
+        if needs_stacking_context {
+            renderer.builder.pop_stacking_context();
         }
-        renderer.builder.pop_clip_id();
     }
     fn draw_debug(&mut self, renderer: &mut RenderBuilder) {
         let color = self.debug_color().unwrap_or(::color::GREEN);
@@ -451,6 +588,12 @@ pub(super) struct WidgetInner {
     props_updated: bool,
     pub(super) layout: Layout,
     pub(super) bounds: Rect,
+    transform: Option<Transform>,
+    opacity: f32,
+    blur: f32,
+    captures_mouse: bool,
+    accepts_text: bool,
+    tab_index: i32,
     name: String,
     debug_color: Option<Color>,
     children: Vec<Widget>,
@@ -473,6 +616,12 @@ impl WidgetInner {
             style_updated: false,
             props_updated: true,
             bounds: Rect::zero(),
+            transform: None,
+            opacity: 1.0,
+            blur: 0.0,
+            captures_mouse: false,
+            accepts_text: false,
+            tab_index: 0,
             name: name,
             debug_color: None,
             children: Vec::new(),
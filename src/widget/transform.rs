@@ -0,0 +1,83 @@
+//! 2D transforms, applied to a widget and it's subtree after layout.
+//!
+//! A transform doesn't affect the layout of the widget, or any other widget, it only changes
+//! where the widget and it's descendants are drawn, and where they receive mouse and touch input.
+
+use euclid;
+use webrender::api::LayoutTransform;
+
+use geometry::{Rect, Point, Vector};
+
+pub type Radians = euclid::Radians<f32>;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    pub translation: Vector,
+    /// Clockwise rotation about the origin
+    pub rotation: Radians,
+    /// Horizontal and vertical scale factors, applied about the origin
+    pub scale: (f32, f32),
+    /// The point rotation and scaling are applied about, relative to the widget bounds,
+    /// where (0.0, 0.0) is the top left corner and (1.0, 1.0) is the bottom right
+    pub origin: (f32, f32),
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            translation: Vector::zero(),
+            rotation: Radians::new(0.0),
+            scale: (1.0, 1.0),
+            origin: (0.5, 0.5),
+        }
+    }
+}
+
+impl Transform {
+    pub fn translate(mut self, x: f32, y: f32) -> Self {
+        self.translation = Vector::new(x, y);
+        self
+    }
+    pub fn rotate(mut self, rotation: Radians) -> Self {
+        self.rotation = rotation;
+        self
+    }
+    pub fn scale(mut self, x: f32, y: f32) -> Self {
+        self.scale = (x, y);
+        self
+    }
+    pub fn origin(mut self, x: f32, y: f32) -> Self {
+        self.origin = (x, y);
+        self
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.translation == Vector::zero() && self.rotation.get() == 0.0 && self.scale == (1.0, 1.0)
+    }
+
+    /// The origin in window coordinates, for a widget with the given bounds.
+    pub fn origin_point(&self, bounds: Rect) -> Point {
+        Point::new(bounds.origin.x + bounds.size.width * self.origin.0,
+                   bounds.origin.y + bounds.size.height * self.origin.1)
+    }
+
+    /// The transform matrix for a widget with the given bounds, mapping points in the
+    /// untransformed widget to the window coordinates they're drawn at.
+    pub fn matrix(&self, bounds: Rect) -> LayoutTransform {
+        let origin = self.origin_point(bounds);
+        let pre_transform = LayoutTransform::create_translation(
+            origin.x + self.translation.x, origin.y + self.translation.y, 0.0);
+        let post_transform = LayoutTransform::create_translation(-origin.x, -origin.y, 0.0);
+        // y increases moving down, so a negative angle is clockwise on screen
+        let transform = LayoutTransform::identity()
+            .pre_rotate(0.0, 0.0, 1.0, -self.rotation)
+            .pre_scale(self.scale.0, self.scale.1, 1.0);
+        pre_transform.pre_mul(&transform).pre_mul(&post_transform)
+    }
+
+    /// Maps a point in window coordinates back to the untransformed widget, the inverse of `matrix`.
+    /// Returns `None` if the transform can't be inverted, ie. if either scale factor is zero.
+    pub fn inverse_transform_point(&self, bounds: Rect, point: Point) -> Option<Point> {
+        self.matrix(bounds).inverse().map(|inverse| inverse.transform_point2d(&point))
+    }
+}
@@ -0,0 +1,19 @@
+use widget::Widget;
+use geometry::Size;
+pub use draw::canvas::CanvasState;
+pub use draw::path::{Painter, Path, Stroke, LineJoin, LineCap};
+
+#[derive(Debug, Copy, Clone)]
+pub struct CanvasBuilder;
+
+impl CanvasBuilder {
+    /// Creates a new canvas `Widget`, drawn by `draw` whenever the UI is redrawn.
+    /// `draw` receives the size of the widget, paths are relative to it's top left corner.
+    /// To redraw the canvas after state captured by `draw` changes, update the widget,
+    /// ex. `widget.update(|_: &mut CanvasState| {})`
+    pub fn new<F: Fn(&mut Painter, Size) + 'static>(draw: F) -> Widget {
+        let mut widget = Widget::new("canvas");
+        widget.set_draw_state(CanvasState::new(draw));
+        widget
+    }
+}
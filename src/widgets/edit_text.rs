@@ -1,10 +1,14 @@
+use std::cmp;
+
 use cassowary::Constraint;
+use glutin;
 
 use layout::constraint::ConstraintBuilder;
 use layout::constraint::*;
 use widget::{Widget, StyleUpdated};
-use ui::{WidgetAttachedEvent, WidgetDetachedEvent};
-use input::keyboard::{WidgetReceivedCharacter, KeyboardInputEvent};
+use ui::{Ui, WidgetAttachedEvent, WidgetDetachedEvent};
+use input::keyboard::{WidgetReceivedCharacter, WidgetKeyboardInput, WidgetCompositionEvent, CompositionEvent, KeyboardInputEvent};
+use input::keyboard::{FocusGained, FocusLost};
 use draw::rect::RectStyle;
 use draw::text::{TextState, TextStyle};
 use event::{EventHandler, EventArgs};
@@ -19,46 +23,153 @@ pub struct TextUpdated(pub String);
 
 multi_event!{impl EventHandler<EditTextEvent> for EditTextHandler {
     WidgetReceivedCharacter => received_char,
+    WidgetKeyboardInput => key_input,
+    WidgetCompositionEvent => composition,
     TextUpdated => text_updated,
     StyleUpdated => style_updated,
+    FocusGained => focus_gained,
+    FocusLost => focus_lost,
 }}
 
 struct EditTextHandler {
     text_box: Widget,
     text: String,
+    /// The char index of the caret in `text`, the left and right arrow keys move it visually
+    cursor: usize,
+    focused: bool,
+    /// The preedit text of an input method composition, and the caret position within it
+    composition: Option<(String, Option<usize>)>,
 }
 
 impl EditTextHandler {
     fn update_text(&mut self) {
-        let text = self.text.clone();
+        let mut text = self.text.clone();
+        let mut cursor = self.cursor;
+        // the preedit text is shown underlined at the caret
+        let underline = self.composition.as_ref().map(|&(ref preedit, caret)| {
+            text.insert_str(byte_index(&self.text, self.cursor), preedit);
+            cursor = self.cursor + caret.unwrap_or_else(|| preedit.chars().count());
+            self.cursor..self.cursor + preedit.chars().count()
+        });
+        let cursor = if self.focused { Some(cursor) } else { None };
         self.text_box.update(|state: &mut TextState| {
             state.text = text;
+            state.underline = underline;
+            state.cursor = cursor;
         });
     }
 
+    /// Update the area an input method's candidate window is placed next to, with the caret rectangle.
+    fn update_ime_cursor_area(&self, ui: &mut Ui) {
+        let mut char_index = self.cursor;
+        if let Some((ref preedit, caret)) = self.composition {
+            char_index += caret.unwrap_or_else(|| preedit.chars().count());
+        }
+        let bounds = self.text_box.bounds();
+        let draw_state = self.text_box.draw_state();
+        let text_draw_state = draw_state.downcast_ref::<TextState>().unwrap();
+        ui.set_ime_cursor_area(text_draw_state.cursor_rect(bounds, char_index));
+    }
+
+    fn text_fits(&self, text: &str) -> bool {
+        let bounds = self.text_box.bounds();
+        let draw_state = self.text_box.draw_state();
+        let text_draw_state = draw_state.downcast_ref::<TextState>().unwrap();
+        text_draw_state.text_fits(text, bounds)
+    }
+
     fn received_char(&mut self, event: &WidgetReceivedCharacter, args: EventArgs) {
         let &WidgetReceivedCharacter(char) = event;
         match char {
             BACKSPACE => {
-                self.text.pop();
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    let byte = byte_index(&self.text, self.cursor);
+                    self.text.remove(byte);
+                }
             }
             _ => {
-                self.text.push(char);
-                let bounds = self.text_box.bounds();
-                let draw_state = self.text_box.draw_state();
-                let text_draw_state = draw_state.downcast_ref::<TextState>().unwrap();
-                if !text_draw_state.text_fits(&self.text, bounds) {
-                    self.text.pop();
+                let mut new_text = self.text.clone();
+                new_text.insert(byte_index(&self.text, self.cursor), char);
+                if self.text_fits(&new_text) {
+                    self.text = new_text;
+                    self.cursor += 1;
                 }
             }
         }
         self.update_text();
+        self.update_ime_cursor_area(args.ui);
         args.widget.event(TextUpdated(self.text.clone()));
     }
 
+    fn key_input(&mut self, event: &WidgetKeyboardInput, args: EventArgs) {
+        let &WidgetKeyboardInput(input) = event;
+        // while composing, the input method moves it's own caret
+        if input.state != glutin::ElementState::Pressed || self.composition.is_some() {
+            return;
+        }
+        let right = match input.virtual_keycode {
+            Some(glutin::VirtualKeyCode::Left) => false,
+            Some(glutin::VirtualKeyCode::Right) => true,
+            _ => return,
+        };
+        let cursor = {
+            let bounds = self.text_box.bounds();
+            let draw_state = self.text_box.draw_state();
+            let text_draw_state = draw_state.downcast_ref::<TextState>().unwrap();
+            text_draw_state.move_cursor(bounds, self.cursor, right)
+        };
+        if let Some(cursor) = cursor {
+            self.cursor = cursor;
+            self.update_text();
+            self.update_ime_cursor_area(args.ui);
+        }
+    }
+
+    fn composition(&mut self, event: &WidgetCompositionEvent, args: EventArgs) {
+        let &WidgetCompositionEvent(ref composition) = event;
+        match *composition {
+            CompositionEvent::Start => {
+                self.composition = Some((String::new(), None));
+            }
+            CompositionEvent::Update(ref preedit, caret) => {
+                self.composition = Some((preedit.clone(), caret));
+            }
+            CompositionEvent::Commit(ref text) => {
+                self.composition = None;
+                let mut new_text = self.text.clone();
+                new_text.insert_str(byte_index(&self.text, self.cursor), text);
+                if self.text_fits(&new_text) {
+                    self.text = new_text;
+                    self.cursor += text.chars().count();
+                }
+                args.widget.event(TextUpdated(self.text.clone()));
+            }
+            CompositionEvent::Cancel => {
+                self.composition = None;
+            }
+        }
+        self.update_text();
+        self.update_ime_cursor_area(args.ui);
+    }
+
+    fn focus_gained(&mut self, _: &FocusGained, args: EventArgs) {
+        self.focused = true;
+        self.update_text();
+        self.update_ime_cursor_area(args.ui);
+    }
+
+    fn focus_lost(&mut self, _: &FocusLost, args: EventArgs) {
+        self.focused = false;
+        self.composition = None;
+        self.update_text();
+        args.ui.set_ime_cursor_area(None);
+    }
+
     fn text_updated(&mut self, event: &TextUpdated, _: EventArgs) {
         let &TextUpdated(ref text) = event;
         self.text = text.clone();
+        self.cursor = cmp::min(self.cursor, self.text.chars().count());
         self.update_text();
     }
 
@@ -67,6 +178,11 @@ impl EditTextHandler {
     }
 }
 
+/// The byte index of the char at `char_index` in `text`, or the end of the text.
+fn byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices().nth(char_index).map_or(text.len(), |(byte, _)| byte)
+}
+
 component_style!{pub struct EditText<name="scroll", style=EditTextStyle> {
     rect: RectStyle = style!(RectStyle {
         border: Some((1.0, GRAY_70)),
@@ -91,8 +207,12 @@ impl WidgetModifier for EditText {
             .add_handler(EditTextHandler {
                 text_box: text_widget.clone(),
                 text: "".to_owned(),
+                cursor: 0,
+                focused: false,
+                composition: None,
             })
-            .make_focusable();
+            .make_focusable()
+            .set_accepts_text(true);
         EditTextHandler::add_adapters(widget);
 
         if let Some(ref focused_rect) = self.focused_rect {
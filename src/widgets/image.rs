@@ -1,19 +1,105 @@
-use widget::Widget;
-use draw::image::ImageState;
+//! Images, still or animated.
+//!
+//! Images that aren't loaded yet are decoded on a worker thread. Until then, the widget draws
+//! a rect with the `image_loading` style class, sized by `Image::placeholder_size` if it's set.
+//! Once the image is ready, the widget is resized to fit it, if it fails to load, the rect is
+//! drawn with the `image_error` class instead. Either way, the widget is sent an `ImageLoadEvent`.
+//!
+//! Images are styled with an `ImageStyle`, from the theme, the widget's style class and
+//! `Image::style`. The widget prefers the size of the image, if it's constrained to another size,
+//! the style's `fit` decides how the image is scaled.
+//!
+//! Animated GIF and APNG images play when the widget is created, unless `autoplay` is disabled,
+//! and can be controlled by sending the widget an `AnimationEvent`. Each frame is shown for
+//! it's delay, timed with `event_global_after`. Playback is suspended while the widget or one of it's
+//! ancestors is hidden, and stops when the widget is removed.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cassowary::Constraint;
+use cassowary::strength::*;
+
+use app::App;
+use event::{self, EventArgs, EventHandler, Target};
+use widget::{Widget, StateUpdated, StyleUpdated};
+use draw::image::{ImageState, ImageStyle};
+use draw::rect::RectStyle;
+use geometry::Size;
+use layout::LayoutUpdated;
 use layout::constraint::*;
-use resources::image::ImageSource;
+use resources::{resources, WidgetId};
+use resources::image::{ImageSource, ImageDecoded};
+use resources::animation::Animation;
+use ui::{Ui, WidgetDetachedEvent};
 
 use style::*;
 
 #[derive(Debug, Clone)]
 pub struct Image {
     source: ImageSource,
+    style: ImageStyle,
+    style_class: Option<String>,
+    autoplay: bool,
+    looping: Option<bool>,
+    placeholder_size: Option<Size>,
 }
 
 impl Image {
     pub fn new(source: ImageSource) -> Self {
         Image {
             source: source,
+            style: ImageStyle::default(),
+            style_class: None,
+            autoplay: true,
+            looping: None,
+            placeholder_size: None,
+        }
+    }
+    /// Style the image, fields that aren't set are taken from the theme.
+    pub fn style(mut self, style: ImageStyle) -> Self {
+        self.style = style;
+        self
+    }
+    /// The theme class of the image's style.
+    pub fn style_class(mut self, class: &str) -> Self {
+        self.style_class = Some(class.to_owned());
+        self
+    }
+    /// If false, an animated image shows it's first frame until it's sent `AnimationEvent::Play`.
+    pub fn autoplay(mut self, autoplay: bool) -> Self {
+        self.autoplay = autoplay;
+        self
+    }
+    /// Play an animated image forever, or only once, instead of the number of times set in the image.
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = Some(looping);
+        self
+    }
+    /// The size of the widget while the image is loading, or if it fails to load.
+    pub fn placeholder_size(mut self, size: Size) -> Self {
+        self.placeholder_size = Some(size);
+        self
+    }
+
+    /// Draw the image, once it's loaded.
+    fn show(&self, widget: &mut Widget) {
+        if let Some(ref class) = self.style_class {
+            widget.set_style_class(TypeId::of::<ImageStyle>(), class);
+        }
+        widget.set_draw_style(ImageStyle {
+            image: Some(self.source.clone()),
+            ..self.style.clone()
+        });
+        widget.add_handler(ImageSizeHandler::default());
+        let animation = resources().image_loader.get_animation(&self.source);
+        if let Some(animation) = animation {
+            widget.add_handler(AnimationHandler::new(animation, self.looping));
+            AnimationHandler::add_adapters(widget);
+            if self.autoplay {
+                widget.event(AnimationEvent::Play);
+            }
         }
     }
 }
@@ -26,10 +112,302 @@ impl Component for Image {
 
 impl WidgetModifier for Image {
     fn apply(&self, widget: &mut Widget) {
-        let image_draw_state = ImageState::new(self.source.clone());
-        let image_size = image_draw_state.measure();
         widget.set_name("image");
-        widget.set_draw_state(image_draw_state);
-        widget.layout().add(size(image_size));
+        let loaded = resources().image_loader.is_loaded(&self.source);
+        if loaded {
+            self.show(widget);
+            return;
+        }
+        widget
+            .set_style_class(TypeId::of::<RectStyle>(), "image_loading")
+            .set_draw_style(RectStyle::default());
+        let placeholder = match self.placeholder_size {
+            Some(placeholder_size) => widget.layout().create_constraint(size(placeholder_size)),
+            None => Vec::new(),
+        };
+        widget.layout().add(placeholder.clone());
+        let image = self.clone();
+        widget.add_handler(move |event: &ImageLoadEvent, mut args: EventArgs| {
+            match *event {
+                ImageLoadEvent::Loaded => {
+                    args.widget.layout().remove_constraints(placeholder.clone());
+                    image.show(&mut args.widget);
+                }
+                ImageLoadEvent::Failed(_) => {
+                    args.widget
+                        .set_style_class(TypeId::of::<RectStyle>(), "image_error")
+                        .set_draw_style(RectStyle::default());
+                }
+            }
+            args.ui.redraw();
+        });
+        event::event(Target::Root, LoadImage(self.source.clone(), widget.id()));
+    }
+}
+
+/// Prefers the size of the image once it's styled, which changes if it's rotated.
+#[derive(Default)]
+struct ImageSizeHandler {
+    measured_size: Option<Size>,
+    size_constraints: Vec<Constraint>,
+}
+
+impl EventHandler<StateUpdated> for ImageSizeHandler {
+    fn handle(&mut self, _: &StateUpdated, mut args: EventArgs) {
+        let image_size = {
+            let draw_state = args.widget.draw_state();
+            match draw_state.downcast_ref::<ImageState>() {
+                Some(image_draw_state) => image_draw_state.measure(),
+                None => return,
+            }
+        };
+        let image_size = match image_size {
+            Some(image_size) => image_size,
+            None => return,
+        };
+        if self.measured_size != Some(image_size) {
+            let size_constraints = size(image_size).strength(STRONG).build(&args.widget.layout_vars());
+            let mut layout = args.widget.layout();
+            for constraint in self.size_constraints.drain(..) {
+                layout.remove_constraint(constraint);
+            }
+            layout.add(size_constraints.clone());
+            self.size_constraints = size_constraints;
+            self.measured_size = Some(image_size);
+        }
+    }
+}
+
+/// Sent to an image widget when it's image has been loaded on a worker thread,
+/// or failed to load, with a description of the error.
+#[derive(Debug, Clone)]
+pub enum ImageLoadEvent {
+    Loaded,
+    Failed(String),
+}
+
+/// Sent by an image widget to the root widget, to load it's image.
+#[derive(Debug, Clone)]
+struct LoadImage(ImageSource, WidgetId);
+
+multi_event!{impl EventHandler<ImageLoadHandlerEvent> for ImageLoadHandler {
+    LoadImage => load_image,
+    ImageDecoded => image_decoded,
+}}
+
+/// Starts loading images, and notifies the widgets waiting for them once they're decoded.
+#[derive(Default)]
+struct ImageLoadHandler {
+    waiting: HashMap<ImageSource, Vec<WidgetId>>,
+}
+
+impl ImageLoadHandler {
+    fn load_image(&mut self, event: &LoadImage, args: EventArgs) {
+        let LoadImage(ref source, widget_id) = *event;
+        let mut res = resources();
+        if res.image_loader.is_loaded(source) {
+            // loaded since the widget was created
+            if let Some(widget) = args.ui.get_widget(widget_id) {
+                widget.event(ImageLoadEvent::Loaded);
+            }
+            return;
+        }
+        self.waiting.entry(source.clone()).or_insert_with(Vec::new).push(widget_id);
+        res.image_loader.load_image_async(source);
+    }
+
+    fn image_decoded(&mut self, _: &ImageDecoded, args: EventArgs) {
+        let results = resources().image_loader.finish_loading();
+        // images drawn by draw states, like nine-patches, are drawn once they're loaded
+        if results.iter().any(|&(_, ref result)| result.is_ok()) {
+            args.ui.redraw();
+        }
+        for (source, result) in results {
+            let event = match result {
+                Ok(()) => ImageLoadEvent::Loaded,
+                Err(error) => ImageLoadEvent::Failed(error.to_string()),
+            };
+            for widget_id in self.waiting.remove(&source).unwrap_or_default() {
+                if let Some(widget) = args.ui.get_widget(widget_id) {
+                    widget.event(event.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Controls the playback of an animated image.
+#[derive(Debug, Copy, Clone)]
+pub enum AnimationEvent {
+    /// Starts or resumes playback, from the first frame if the animation has finished
+    Play,
+    /// Stops playback, keeping the current frame
+    Pause,
+    /// Shows the first frame, playback continues from there if the animation is playing
+    Rewind,
+}
+
+/// Sent from the timer thread to the root widget, and forwarded to the widget as `FrameTimeout`.
+#[derive(Debug, Copy, Clone)]
+struct AnimationTimeout {
+    widget: WidgetId,
+    timer: u64,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct FrameTimeout(u64);
+
+multi_event!{impl EventHandler<AnimationHandlerEvent> for AnimationHandler {
+    AnimationEvent => animation_event,
+    FrameTimeout => frame_timeout,
+    StyleUpdated => style_updated,
+    LayoutUpdated => layout_updated,
+    WidgetDetachedEvent => detached,
+}}
+
+struct AnimationHandler {
+    animation: Arc<Animation>,
+    looping: Option<bool>,
+    frame: usize,
+    /// The number of times the animation has played to the end
+    plays: u32,
+    playing: bool,
+    /// Set while playing if the widget isn't visible, no timer is running until it's shown again
+    suspended: bool,
+    /// Incremented whenever playback is paused or restarted, so earlier timers are ignored
+    timer: u64,
+}
+
+impl AnimationHandler {
+    fn new(animation: Arc<Animation>, looping: Option<bool>) -> Self {
+        AnimationHandler {
+            animation: animation,
+            looping: looping,
+            frame: 0,
+            plays: 0,
+            playing: false,
+            suspended: false,
+            timer: 0,
+        }
+    }
+
+    /// Starts a timer for the current frame's delay.
+    fn schedule(&mut self, widget: WidgetId) {
+        self.timer += 1;
+        self.suspended = false;
+        let timeout = AnimationTimeout {
+            widget: widget,
+            timer: self.timer,
+        };
+        event::event_global_after(self.animation.frames[self.frame].delay, timeout);
+    }
+
+    fn show_frame(&mut self, frame: usize, widget: &mut Widget) {
+        self.frame = frame;
+        widget.update(|state: &mut ImageState| state.frame = frame);
+    }
+
+    fn repeats(&self) -> bool {
+        match self.looping {
+            Some(looping) => looping,
+            None => self.animation.loop_count.map_or(true, |count| self.plays < count),
+        }
+    }
+
+    fn animation_event(&mut self, event: &AnimationEvent, mut args: EventArgs) {
+        match *event {
+            AnimationEvent::Play => {
+                if !self.playing {
+                    if self.frame == self.animation.frames.len() - 1 && !self.repeats() {
+                        self.plays = 0;
+                        self.show_frame(0, &mut args.widget);
+                    }
+                    self.playing = true;
+                    self.schedule(args.widget.id());
+                }
+            }
+            AnimationEvent::Pause => {
+                self.playing = false;
+                self.suspended = false;
+                self.timer += 1;
+            }
+            AnimationEvent::Rewind => {
+                self.plays = 0;
+                self.show_frame(0, &mut args.widget);
+                if self.playing {
+                    self.schedule(args.widget.id());
+                }
+            }
+        }
+    }
+
+    fn frame_timeout(&mut self, event: &FrameTimeout, mut args: EventArgs) {
+        let &FrameTimeout(timer) = event;
+        if timer != self.timer || !self.playing {
+            return;
+        }
+        if !is_visible(&args.widget, args.ui) {
+            self.suspended = true;
+            return;
+        }
+        let mut frame = self.frame + 1;
+        if frame == self.animation.frames.len() {
+            self.plays += 1;
+            if !self.repeats() {
+                // the last frame stays visible
+                self.playing = false;
+                return;
+            }
+            frame = 0;
+        }
+        self.show_frame(frame, &mut args.widget);
+        self.schedule(args.widget.id());
+    }
+
+    /// Restyling the image creates a new draw state, showing the first frame.
+    fn style_updated(&mut self, _: &StyleUpdated, mut args: EventArgs) {
+        let frame = self.frame;
+        self.show_frame(frame, &mut args.widget);
+    }
+
+    fn layout_updated(&mut self, _: &LayoutUpdated, args: EventArgs) {
+        if self.suspended && is_visible(&args.widget, args.ui) {
+            self.schedule(args.widget.id());
+        }
+    }
+
+    fn detached(&mut self, _: &WidgetDetachedEvent, _: EventArgs) {
+        self.playing = false;
+        self.suspended = false;
+        self.timer += 1;
+    }
+}
+
+/// True if the widget is attached to the root, and neither it or any of it's ancestors are hidden.
+fn is_visible(widget: &Widget, ui: &Ui) -> bool {
+    let root = ui.get_root();
+    let mut current = Some(widget.clone());
+    while let Some(mut widget) = current {
+        if widget.layout().hidden {
+            return false;
+        }
+        if widget == root {
+            return true;
+        }
+        current = widget.parent();
+    }
+    false
+}
+
+impl App {
+    pub fn add_image_handlers(&mut self) {
+        self.add_handler(ImageLoadHandler::default());
+        ImageLoadHandler::add_adapters(&mut self.get_root());
+        // frame timers run on the timer thread, which can only send events to the root
+        self.add_handler(|event: &AnimationTimeout, args: EventArgs| {
+            if let Some(widget) = args.ui.get_widget(event.widget) {
+                widget.event(FrameTimeout(event.timer));
+            }
+        });
     }
 }
@@ -0,0 +1,809 @@
+//! Menu bars and context menus.
+//!
+//! A menu is described by a list of `MenuEntry`s. A `MenuBar` displays a row of titles that open
+//! drop down menus when clicked, and any widget can be given a menu that opens when it is right
+//! clicked, with `Widget::set_context_menu`.
+//!
+//! Open menus are displayed in a modal layer, so clicking outside of them closes them. They can be
+//! navigated with the arrow keys, Enter selects the highlighted item and Esc closes the innermost
+//! open menu. Selecting an item sends a `MenuCommand`, bubbling up from the menu bar or the widget
+//! owning the context menu.
+
+use std::any::TypeId;
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use glutin;
+
+use event::{self, EventArgs, EventHandler, Target};
+use widget::{Widget, WidgetWeak};
+use widget::property::Property;
+use widget::property::states::*;
+use widgets::modal::{ModalResult, ModalStyle};
+use widgets::text::StaticTextStyle;
+use input::keyboard::{WidgetKeyboardInput, KeyboardInputEvent};
+use input::mouse::{ClickEvent, MouseOverEvent, WidgetMouseButton};
+use layout::constraint::*;
+use layout::linear_layout::{LinearLayoutSettings, Orientation, ItemAlignment};
+use draw::rect::RectStyle;
+use draw::text::TextStyle;
+use geometry::{Point, Size, RectExt};
+use ui::WidgetDetachedEvent;
+use color::*;
+use style::*;
+
+/// An entry in a menu.
+#[derive(Debug, Clone)]
+pub enum MenuEntry {
+    Item(MenuItem),
+    /// An entry with the given label, that opens a nested menu
+    Submenu(String, Vec<MenuEntry>),
+    Separator,
+}
+
+impl MenuEntry {
+    pub fn item(label: &str, command: &str) -> Self {
+        MenuEntry::Item(MenuItem::new(label, command))
+    }
+    pub fn submenu(label: &str, entries: Vec<MenuEntry>) -> Self {
+        MenuEntry::Submenu(label.to_owned(), entries)
+    }
+    fn is_separator(&self) -> bool {
+        match *self {
+            MenuEntry::Separator => true,
+            _ => false,
+        }
+    }
+    fn is_submenu(&self) -> bool {
+        match *self {
+            MenuEntry::Submenu(..) => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<MenuItem> for MenuEntry {
+    fn from(item: MenuItem) -> Self {
+        MenuEntry::Item(item)
+    }
+}
+
+/// A menu entry that sends a `MenuCommand` when selected.
+#[derive(Debug, Clone)]
+pub struct MenuItem {
+    pub label: String,
+    /// Identifies the item in the `MenuCommand` it sends
+    pub command: String,
+    /// Shortcut displayed next to the label, eg. "Ctrl+S"
+    pub accelerator: Option<String>,
+    /// `Some` if the item is checkable, it then displays a check mark while `true`,
+    /// and is toggled when selected
+    pub checked: Option<bool>,
+    /// Disabled items get the `Inactive` property, which keeps them from being selected
+    pub enabled: bool,
+}
+
+impl MenuItem {
+    pub fn new(label: &str, command: &str) -> Self {
+        MenuItem {
+            label: label.to_owned(),
+            command: command.to_owned(),
+            accelerator: None,
+            checked: None,
+            enabled: true,
+        }
+    }
+    pub fn accelerator(mut self, accelerator: &str) -> Self {
+        self.accelerator = Some(accelerator.to_owned());
+        self
+    }
+    pub fn checkable(mut self, checked: bool) -> Self {
+        self.checked = Some(checked);
+        self
+    }
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+}
+
+/// Sent from a menu bar, or a widget with a context menu, when one of it's menu items is selected.
+#[derive(Debug, Clone)]
+pub struct MenuCommand {
+    pub command: String,
+    /// The new checked state, if the item is checkable
+    pub checked: Option<bool>,
+}
+
+/// Sent to a menu bar or widget with a context menu, to check or uncheck the item with the given command.
+#[derive(Debug, Clone)]
+pub struct SetMenuItemChecked(pub String, pub bool);
+
+/// Sent to a menu bar or widget with a context menu, to enable or disable the item with the given command.
+#[derive(Debug, Clone)]
+pub struct SetMenuItemEnabled(pub String, pub bool);
+
+type MenuResult = ModalResult<MenuCommand>;
+
+fn find_item<'a>(entries: &'a mut [MenuEntry], command: &str) -> Option<&'a mut MenuItem> {
+    for entry in entries.iter_mut() {
+        match *entry {
+            MenuEntry::Item(ref mut item) => {
+                if item.command == command {
+                    return Some(item);
+                }
+            }
+            MenuEntry::Submenu(_, ref mut entries) => {
+                if let Some(item) = find_item(entries, command) {
+                    return Some(item);
+                }
+            }
+            MenuEntry::Separator => (),
+        }
+    }
+    None
+}
+
+component_style!{pub struct Menu<name="menu", style=MenuStyle> {
+    entries: Vec<MenuEntry> = Vec::new(),
+    rect: RectStyle = style!(RectStyle {
+        background_color: WHITE,
+        border: Some((1.0, GRAY_60)),
+    }),
+    item_rect: RectStyle = style!(RectStyle {
+        background_color: TRANSPARENT,
+    }),
+    highlight_rect: RectStyle = style!(RectStyle {
+        background_color: GRAY_80,
+    }),
+    text: TextStyle = style!(TextStyle {
+        font_size: 18.0,
+    }),
+    inactive_text: TextStyle = style!(TextStyle {
+        text_color: GRAY_60,
+    }),
+    accelerator_text: TextStyle = style!(TextStyle {
+        font_size: 18.0,
+        text_color: GRAY_40,
+    }),
+    check: RectStyle = style!(RectStyle {
+        background_color: GRAY_30,
+        corner_radius: Some(2.0),
+    }),
+    separator: RectStyle = style!(RectStyle {
+        background_color: GRAY_80,
+    }),
+}}
+
+impl MenuStyle {
+    pub fn from_entries(entries: Vec<MenuEntry>) -> Self {
+        MenuStyle {
+            entries: Some(entries),
+            ..MenuStyle::default()
+        }
+    }
+}
+
+impl Menu {
+    fn text_widget(&self, name: &str, text: &str, style: &TextStyle) -> Widget {
+        let mut text_widget = Widget::new(name);
+        text_widget.set_style_class(TypeId::of::<TextStyle>(), name);
+        let style = style!(TextStyle {
+            text: text.to_owned(),
+        }).merge(style);
+        StaticTextStyle::from_style(style).component().apply(&mut text_widget);
+        text_widget.set_draw_style_prop(INACTIVE.clone(), self.inactive_text.clone());
+        text_widget
+    }
+
+    fn item_widget(&self, label: &str, accelerator: Option<&str>, checked: Option<bool>, enabled: bool) -> Widget {
+        let mut item = Widget::new("menu_item");
+        item
+            .set_style_class(TypeId::of::<RectStyle>(), "menu_item_rect")
+            .set_draw_style(self.item_rect.clone())
+            .set_draw_style_prop(SELECTED.clone(), self.highlight_rect.clone());
+        item.layout().no_container();
+
+        let mut label_widget = self.text_widget("menu_item_text", label, &self.text);
+        label_widget.layout().add(constraints![
+            align_left(&item).padding(28.0),
+            bound_right(&item).padding(20.0),
+            bound_top(&item).padding(4.0),
+            bound_bottom(&item).padding(4.0),
+            center_vertical(&item),
+        ]);
+        if let Some(accelerator) = accelerator {
+            let mut accelerator_widget = self.text_widget("menu_item_accelerator", accelerator, &self.accelerator_text);
+            accelerator_widget.layout().add(constraints![
+                align_right(&item).padding(10.0),
+                to_right_of(&label_widget).padding(30.0),
+                center_vertical(&item),
+            ]);
+            item.add_child(accelerator_widget);
+        }
+        if let Some(true) = checked {
+            let mut check = Widget::new("menu_item_check");
+            check
+                .set_style_class(TypeId::of::<RectStyle>(), "menu_item_check")
+                .set_draw_style(self.check.clone());
+            check.layout().add(constraints![
+                size(Size::new(10.0, 10.0)),
+                align_left(&item).padding(9.0),
+                center_vertical(&item),
+            ]);
+            item.add_child(check);
+        }
+        item.add_child(label_widget);
+        if !enabled {
+            item.add_prop(Property::Inactive);
+        }
+        item
+    }
+
+    fn separator_widget(&self) -> Widget {
+        let mut separator = Widget::new("menu_separator");
+        separator.layout().no_container();
+        separator.layout().add(height(9.0));
+        let mut line = Widget::new("menu_separator_line");
+        line
+            .set_style_class(TypeId::of::<RectStyle>(), "menu_separator")
+            .set_draw_style(self.separator.clone());
+        line.layout().add(constraints![
+            height(1.0),
+            center_vertical(&separator),
+            align_left(&separator).padding(4.0),
+            align_right(&separator).padding(4.0),
+        ]);
+        separator.add_child(line);
+        separator
+    }
+}
+
+impl WidgetModifier for Menu {
+    fn apply(&self, widget: &mut Widget) {
+        widget
+            .set_style_class(TypeId::of::<RectStyle>(), "menu_rect")
+            .set_draw_style(self.rect.clone());
+        let mut layout_settings = LinearLayoutSettings::new(Orientation::Vertical);
+        layout_settings.item_align = ItemAlignment::Fill;
+        widget.linear_layout(layout_settings);
+        widget.layout().add(constraints![
+            min_width(120.0),
+            shrink(),
+        ]);
+        for entry in &self.entries {
+            let entry_widget = match *entry {
+                MenuEntry::Item(ref item) => {
+                    let accelerator = item.accelerator.as_ref().map(|accelerator| accelerator.as_str());
+                    self.item_widget(&item.label, accelerator, item.checked, item.enabled)
+                }
+                MenuEntry::Submenu(ref label, _) => self.item_widget(label, Some(">"), None, true),
+                MenuEntry::Separator => self.separator_widget(),
+            };
+            widget.add_child(entry_widget);
+        }
+    }
+}
+
+/// State shared by the popups of an open menu, and the widget that owns the menu.
+#[derive(Clone)]
+struct MenuContext {
+    entries: Rc<RefCell<Vec<MenuEntry>>>,
+    style: MenuStyle,
+    /// The menu bar the menu belongs to, if any
+    bar: Option<WidgetWeak>,
+}
+
+impl MenuContext {
+    fn new(entries: Vec<MenuEntry>, style: MenuStyle, bar: Option<WidgetWeak>) -> Self {
+        MenuContext {
+            entries: Rc::new(RefCell::new(entries)),
+            style: style,
+            bar: bar,
+        }
+    }
+
+    fn bar(&self) -> Option<Widget> {
+        self.bar.as_ref().and_then(|bar| bar.upgrade())
+    }
+
+    /// Create the command sent by selecting `item`, toggling it if it's checkable.
+    fn select_item(&self, item: &MenuItem) -> MenuCommand {
+        let checked = item.checked.map(|checked| !checked);
+        if let Some(checked) = checked {
+            if let Some(item) = find_item(&mut self.entries.borrow_mut(), &item.command) {
+                item.checked = Some(checked);
+            }
+        }
+        MenuCommand {
+            command: item.command.clone(),
+            checked: checked,
+        }
+    }
+
+    /// Create a popup listing `entries`, with it's top left corner at `position`.
+    fn popup(&self, entries: Vec<MenuEntry>, parent: Option<Widget>, position: Point) -> Widget {
+        let mut style = self.style.clone();
+        style.entries = Some(entries.clone());
+        let mut popup = Widget::from_modifier_style(style);
+        popup.set_name("menu_popup");
+        popup.layout().edit_left().set(position.x);
+        popup.layout().edit_top().set(position.y);
+        let items = popup.children();
+        for (index, item) in items.iter().enumerate() {
+            let mut item = item.clone();
+            item.add_handler(move |event: &MouseOverEvent, args: EventArgs| {
+                if let MouseOverEvent::Over = *event {
+                    if let Some(popup) = args.widget.parent() {
+                        popup.event(MenuAction::Hover(index));
+                    }
+                }
+            });
+            item.add_handler(move |_: &ClickEvent, args: EventArgs| {
+                if let Some(popup) = args.widget.parent() {
+                    popup.event(MenuAction::Activate(index));
+                }
+                *args.handled = true;
+            });
+        }
+        popup.add_handler(MenuPopupHandler {
+            context: self.clone(),
+            entries: entries,
+            items: items,
+            selected: None,
+            parent: parent,
+            submenu: None,
+        });
+        MenuPopupHandler::add_adapters(&mut popup);
+        // the focused popup receives the keyboard input used for navigation
+        popup.add_handler(|_: &WidgetDetachedEvent, args: EventArgs| {
+            args.ui.event(KeyboardInputEvent::RemoveFocusable(args.widget));
+        });
+        event::event(Target::Root, KeyboardInputEvent::AddFocusable(popup.clone()));
+        popup
+    }
+
+    /// Open the top level popup of a menu, with `entries` at `position`. The `MenuResult` is sent to `owner`.
+    fn open(&self, owner: &Widget, entries: Vec<MenuEntry>, position: Point) -> Widget {
+        let popup = self.popup(entries, None, position);
+        let modal_style = style!(ModalStyle {
+            overlay: style!(RectStyle {
+                background_color: TRANSPARENT,
+            }),
+            close_on_click_outside: true,
+            close_on_esc: false,
+        });
+        owner.open_modal_layer::<MenuCommand>(popup.clone(), modal_style, false);
+        popup
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum MenuAction {
+    /// The mouse moved over the entry at the given index
+    Hover(usize),
+    /// The entry at the given index was clicked
+    Activate(usize),
+    /// Highlight the first selectable entry
+    SelectFirst,
+    /// Take keyboard focus
+    Focus,
+    /// Close the open submenu, and take back focus
+    CloseSubmenu,
+    /// Close this popup and it's submenus
+    Close,
+}
+
+multi_event!{impl EventHandler<MenuPopupEvent> for MenuPopupHandler {
+    WidgetKeyboardInput => key_input,
+    MenuAction => action,
+}}
+
+struct MenuPopupHandler {
+    context: MenuContext,
+    entries: Vec<MenuEntry>,
+    items: Vec<Widget>,
+    selected: Option<usize>,
+    /// The popup this is a submenu of, if any
+    parent: Option<Widget>,
+    /// The open submenu, and the index of the entry that opened it
+    submenu: Option<(usize, Widget)>,
+}
+
+impl MenuPopupHandler {
+    /// Entries whose widget has the `Inactive` property can't be highlighted or activated.
+    fn is_selectable(&self, index: usize) -> bool {
+        !self.entries[index].is_separator() && !self.items[index].props().contains(&Property::Inactive)
+    }
+
+    fn select(&mut self, index: Option<usize>) {
+        if index != self.selected {
+            if let Some(selected) = self.selected {
+                self.items[selected].remove_prop(Property::Selected);
+            }
+            self.selected = index;
+            if let Some(selected) = self.selected {
+                self.items[selected].add_prop(Property::Selected);
+            }
+        }
+    }
+
+    /// Highlight the next selectable entry after, or before the highlighted entry, wrapping around.
+    fn select_next(&mut self, forward: bool) {
+        let len = self.entries.len();
+        if len == 0 {
+            return;
+        }
+        let start = self.selected.unwrap_or(if forward { len - 1 } else { 0 });
+        for step in 1..(len + 1) {
+            let index = if forward { (start + step) % len } else { (start + len - step) % len };
+            if self.is_selectable(index) {
+                self.select(Some(index));
+                return;
+            }
+        }
+    }
+
+    fn open_submenu(&mut self, index: usize, focus: bool, popup: &Widget) {
+        let already_open = self.submenu.as_ref().map_or(false, |&(open_index, _)| open_index == index);
+        if !already_open {
+            self.close_submenu();
+            let entries = match self.entries[index] {
+                MenuEntry::Submenu(_, ref entries) => entries.clone(),
+                _ => return,
+            };
+            let bounds = self.items[index].bounds();
+            let position = Point::new(bounds.right(), bounds.top());
+            let mut submenu = self.context.popup(entries, Some(popup.clone()), position);
+            if let Some(mut layer) = popup.parent() {
+                submenu.layout().add(bound_by(&layer));
+                layer.add_child(submenu.clone());
+            }
+            self.submenu = Some((index, submenu));
+        }
+        if focus {
+            if let Some((_, ref submenu)) = self.submenu {
+                submenu.event(MenuAction::SelectFirst);
+                submenu.event(MenuAction::Focus);
+            }
+        }
+    }
+
+    fn close_submenu(&mut self) {
+        if let Some((_, submenu)) = self.submenu.take() {
+            submenu.event(MenuAction::Close);
+        }
+    }
+
+    /// Switch to the menu to the left or right in the menu bar, if this menu belongs to one.
+    fn step_bar(&self, step: isize) {
+        if let Some(bar) = self.context.bar() {
+            bar.event(MenuBarEvent::Step(step));
+        }
+    }
+
+    fn activate(&mut self, index: usize, args: EventArgs) {
+        if !self.is_selectable(index) {
+            return;
+        }
+        if self.entries[index].is_submenu() {
+            self.open_submenu(index, true, &args.widget);
+            return;
+        }
+        let command = match self.entries[index] {
+            MenuEntry::Item(ref item) => self.context.select_item(item),
+            _ => return,
+        };
+        args.widget.event_bubble_up(MenuResult::Custom(command));
+    }
+
+    fn key_input(&mut self, event: &WidgetKeyboardInput, args: EventArgs) {
+        let &WidgetKeyboardInput(input) = event;
+        if input.state != glutin::ElementState::Pressed {
+            return;
+        }
+        match input.virtual_keycode {
+            Some(glutin::VirtualKeyCode::Down) => self.select_next(true),
+            Some(glutin::VirtualKeyCode::Up) => self.select_next(false),
+            Some(glutin::VirtualKeyCode::Right) => {
+                match self.selected {
+                    Some(index) if self.entries[index].is_submenu() => {
+                        self.open_submenu(index, true, &args.widget);
+                    }
+                    _ => self.step_bar(1),
+                }
+            }
+            Some(glutin::VirtualKeyCode::Left) => {
+                if let Some(ref parent) = self.parent {
+                    parent.event(MenuAction::CloseSubmenu);
+                } else {
+                    self.step_bar(-1);
+                }
+            }
+            Some(glutin::VirtualKeyCode::Return) => {
+                if let Some(index) = self.selected {
+                    self.activate(index, args);
+                }
+            }
+            Some(glutin::VirtualKeyCode::Escape) => {
+                if let Some(ref parent) = self.parent {
+                    parent.event(MenuAction::CloseSubmenu);
+                } else {
+                    args.widget.event_bubble_up(MenuResult::Cancel);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn action(&mut self, event: &MenuAction, mut args: EventArgs) {
+        match *event {
+            MenuAction::Hover(index) => {
+                if self.is_selectable(index) {
+                    self.select(Some(index));
+                }
+                if self.entries[index].is_submenu() {
+                    self.open_submenu(index, false, &args.widget);
+                } else {
+                    self.close_submenu();
+                }
+                args.ui.event(KeyboardInputEvent::FocusChange(Some(args.widget.clone())));
+            }
+            MenuAction::Activate(index) => self.activate(index, args),
+            MenuAction::SelectFirst => {
+                if self.selected.is_none() {
+                    self.select_next(true);
+                }
+            }
+            MenuAction::Focus => {
+                args.ui.event(KeyboardInputEvent::FocusChange(Some(args.widget.clone())));
+            }
+            MenuAction::CloseSubmenu => {
+                self.close_submenu();
+                args.ui.event(KeyboardInputEvent::FocusChange(Some(args.widget.clone())));
+            }
+            MenuAction::Close => {
+                self.close_submenu();
+                args.widget.remove_widget();
+            }
+        }
+    }
+}
+
+impl Widget {
+    /// Keep the entries of a menu owned by this widget up to date with `SetMenuItemChecked`
+    /// and `SetMenuItemEnabled` events.
+    fn add_menu_entry_handlers(&mut self, context: &MenuContext) {
+        let entries = context.entries.clone();
+        self.add_handler(move |event: &SetMenuItemChecked, _: EventArgs| {
+            let SetMenuItemChecked(ref command, checked) = *event;
+            if let Some(item) = find_item(&mut entries.borrow_mut(), command) {
+                item.checked = Some(checked);
+            }
+        });
+        let entries = context.entries.clone();
+        self.add_handler(move |event: &SetMenuItemEnabled, _: EventArgs| {
+            let SetMenuItemEnabled(ref command, enabled) = *event;
+            if let Some(item) = find_item(&mut entries.borrow_mut(), command) {
+                item.enabled = enabled;
+            }
+        });
+    }
+
+    /// Open a menu listing `entries` when this widget is right clicked, styled by the theme's `MenuStyle`.
+    /// Selecting an item sends a `MenuCommand`, bubbling up from this widget.
+    pub fn set_context_menu(&mut self, entries: Vec<MenuEntry>) -> &mut Self {
+        self.set_context_menu_style(entries, MenuStyle::default())
+    }
+
+    /// Open a context menu using the given `MenuStyle`, with missing fields inherited from the theme.
+    pub fn set_context_menu_style(&mut self, entries: Vec<MenuEntry>, style: MenuStyle) -> &mut Self {
+        let context = MenuContext::new(entries, style, None);
+        self.add_menu_entry_handlers(&context);
+        self.add_handler(|event: &MenuResult, args: EventArgs| {
+            if let ModalResult::Custom(ref command) = *event {
+                args.widget.event_bubble_up(command.clone());
+            }
+        });
+        self.add_handler(move |event: &WidgetMouseButton, args: EventArgs| {
+            if let WidgetMouseButton(glutin::ElementState::Released, glutin::MouseButton::Right) = *event {
+                if let Some(position) = args.ui.cursor_position() {
+                    let entries = context.entries.borrow().clone();
+                    context.open(&args.widget, entries, position);
+                }
+                // only open the innermost context menu
+                *args.handled = true;
+            }
+        })
+    }
+}
+
+component_style!{pub struct MenuBar<name="menu_bar", style=MenuBarStyle> {
+    entries: Vec<MenuEntry> = Vec::new(),
+    rect: RectStyle = style!(RectStyle {
+        background_color: GRAY_90,
+    }),
+    title_rect: RectStyle = style!(RectStyle {
+        background_color: TRANSPARENT,
+    }),
+    title_highlight_rect: RectStyle = style!(RectStyle {
+        background_color: GRAY_80,
+    }),
+    title_text: TextStyle = style!(TextStyle {
+        font_size: 18.0,
+    }),
+    inactive_title_text: TextStyle = style!(TextStyle {
+        text_color: GRAY_60,
+    }),
+    menu: MenuStyle = MenuStyle::default(),
+}}
+
+impl MenuBarStyle {
+    pub fn from_entries(entries: Vec<MenuEntry>) -> Self {
+        MenuBarStyle {
+            entries: Some(entries),
+            ..MenuBarStyle::default()
+        }
+    }
+}
+
+impl WidgetModifier for MenuBar {
+    fn apply(&self, widget: &mut Widget) {
+        widget
+            .set_style_class(TypeId::of::<RectStyle>(), "menu_bar_rect")
+            .set_draw_style(self.rect.clone());
+        let mut layout_settings = LinearLayoutSettings::new(Orientation::Horizontal);
+        layout_settings.item_align = ItemAlignment::Fill;
+        widget.linear_layout(layout_settings);
+        widget.layout().add(shrink_vertical());
+
+        let mut titles = Vec::new();
+        for (entry_index, entry) in self.entries.iter().enumerate() {
+            let label = match *entry {
+                MenuEntry::Item(ref item) => item.label.clone(),
+                MenuEntry::Submenu(ref label, _) => label.clone(),
+                MenuEntry::Separator => continue,
+            };
+            let mut title = Widget::new("menu_bar_item");
+            title
+                .set_style_class(TypeId::of::<RectStyle>(), "menu_bar_item_rect")
+                .set_draw_style(self.title_rect.clone())
+                .set_draw_style_prop(SELECTED.clone(), self.title_highlight_rect.clone());
+            title.layout().add(shrink());
+
+            let mut title_text = Widget::new("menu_bar_item_text");
+            title_text.set_style_class(TypeId::of::<TextStyle>(), "menu_bar_item_text");
+            let style = style!(TextStyle {
+                text: label,
+            }).merge(&self.title_text);
+            StaticTextStyle::from_style(style).component().apply(&mut title_text);
+            title_text.set_draw_style_prop(INACTIVE.clone(), self.inactive_title_text.clone());
+            title_text.layout().add(constraints![
+                bound_left(&title).padding(10.0),
+                bound_right(&title).padding(10.0),
+                bound_top(&title).padding(4.0),
+                bound_bottom(&title).padding(4.0),
+                center(&title),
+            ]);
+            title.add_child(title_text);
+            if let MenuEntry::Item(ref item) = *entry {
+                if !item.enabled {
+                    title.add_prop(Property::Inactive);
+                }
+                let command = item.command.clone();
+                let mut item_title = title.clone();
+                widget.add_handler(move |event: &SetMenuItemEnabled, _: EventArgs| {
+                    let SetMenuItemEnabled(ref event_command, enabled) = *event;
+                    if *event_command == command {
+                        if enabled {
+                            item_title.remove_prop(Property::Inactive);
+                        } else {
+                            item_title.add_prop(Property::Inactive);
+                        }
+                    }
+                });
+            }
+
+            let title_index = titles.len();
+            title.add_handler(move |_: &ClickEvent, args: EventArgs| {
+                if let Some(bar) = args.widget.parent() {
+                    bar.event(MenuBarEvent::Open(title_index, false));
+                }
+                *args.handled = true;
+            });
+            widget.add_child(title.clone());
+            titles.push((entry_index, title));
+        }
+
+        let context = MenuContext::new(self.entries.clone(), self.menu.clone(), Some(widget.downgrade()));
+        widget.add_menu_entry_handlers(&context);
+        widget.add_handler(MenuBarHandler {
+            context: context,
+            titles: titles,
+            open: None,
+            pending: None,
+        });
+        MenuBarHandler::add_adapters(widget);
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum MenuBarEvent {
+    /// Open the menu of the title at the given index, highlighting it's first entry if true
+    Open(usize, bool),
+    /// Switch from the open menu to the one the given number of titles to the right
+    Step(isize),
+}
+
+multi_event!{impl EventHandler<MenuBarHandlerEvent> for MenuBarHandler {
+    MenuBarEvent => bar_event,
+    MenuResult => menu_result,
+}}
+
+struct MenuBarHandler {
+    context: MenuContext,
+    /// The index of the entry for each title, and the title widget
+    titles: Vec<(usize, Widget)>,
+    /// The index of the title whose menu is open, and the menu's popup
+    open: Option<(usize, Widget)>,
+    /// The title to open once the open menu has closed
+    pending: Option<usize>,
+}
+
+impl MenuBarHandler {
+    fn open_menu(&mut self, index: usize, select_first: bool, bar: &Widget) {
+        let (entry_index, mut title) = self.titles[index].clone();
+        if title.props().contains(&Property::Inactive) {
+            return;
+        }
+        let entry = self.context.entries.borrow()[entry_index].clone();
+        match entry {
+            MenuEntry::Submenu(_, entries) => {
+                let bounds = title.bounds();
+                let popup = self.context.open(bar, entries, Point::new(bounds.left(), bounds.bottom()));
+                if select_first {
+                    popup.event(MenuAction::SelectFirst);
+                }
+                title.add_prop(Property::Selected);
+                self.open = Some((index, popup));
+            }
+            MenuEntry::Item(ref item) => {
+                bar.event_bubble_up(self.context.select_item(item));
+            }
+            _ => (),
+        }
+    }
+
+    fn bar_event(&mut self, event: &MenuBarEvent, args: EventArgs) {
+        match *event {
+            MenuBarEvent::Open(index, select_first) => {
+                if self.open.is_none() {
+                    self.open_menu(index, select_first, &args.widget);
+                }
+            }
+            MenuBarEvent::Step(step) => {
+                if self.pending.is_some() {
+                    return;
+                }
+                if let Some((open_index, ref popup)) = self.open {
+                    let len = self.titles.len() as isize;
+                    let next = ((open_index as isize + step) % len + len) % len;
+                    self.pending = Some(next as usize);
+                    popup.event_bubble_up(MenuResult::Cancel);
+                }
+            }
+        }
+    }
+
+    fn menu_result(&mut self, event: &MenuResult, args: EventArgs) {
+        if let Some((index, _)) = self.open.take() {
+            self.titles[index].1.remove_prop(Property::Selected);
+        }
+        if let ModalResult::Custom(ref command) = *event {
+            args.widget.event_bubble_up(command.clone());
+        }
+        if let Some(index) = self.pending.take() {
+            args.widget.event(MenuBarEvent::Open(index, true));
+        }
+    }
+}
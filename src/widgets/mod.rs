@@ -7,4 +7,8 @@ pub mod slider;
 pub mod edit_text;
 pub mod image;
 pub mod glcanvas;
+pub mod canvas;
 pub mod text;
+pub mod modal;
+pub mod menu;
+pub mod tooltip;
@@ -0,0 +1,172 @@
+//! Modal dialogs, displayed above the rest of the UI.
+//!
+//! A modal dialog is any widget opened with `Widget::open_modal`. It is placed in a layer that
+//! covers the whole window, dimming and blocking mouse input to the widgets below it, and keyboard
+//! focus is trapped inside the dialog until it is closed.
+//!
+//! The dialog is closed by sending a `ModalResult` from any widget inside it,
+//! eg. `args.widget.event_bubble_up(ModalResult::<()>::Ok)`, or by pressing Esc, which closes the
+//! topmost dialog with `ModalResult::Cancel`, unless disabled with `ModalStyle::close_on_esc`.
+//! The result is then sent to the widget that opened the dialog.
+
+use std::any::TypeId;
+
+use glutin;
+
+use app::App;
+use event::{self, EventArgs, Target};
+use widget::Widget;
+use input::keyboard::{KeyboardInput, KeyboardInputEvent};
+use input::mouse::{ClickEvent, AuxClickEvent, WidgetMouseButton, WidgetMouseWheel};
+use layout::constraint::*;
+use geometry::Point;
+use draw::rect::RectStyle;
+use color::*;
+use style::*;
+
+component_style!{pub struct Modal<name="modal", style=ModalStyle> {
+    overlay: RectStyle = style!(RectStyle {
+        background_color: Color(0x00000080),
+    }),
+    close_on_click_outside: bool = false,
+    close_on_esc: bool = true,
+}}
+
+/// The result of a modal dialog, sent to the widget that opened it when the dialog closes.
+#[derive(Debug, Clone)]
+pub enum ModalResult<T> {
+    Ok,
+    Cancel,
+    Custom(T),
+}
+
+/// Sent to a modal layer to close it with `ModalResult::Cancel`.
+#[derive(Debug, Copy, Clone)]
+struct CloseModal;
+
+/// Sent to the topmost modal layer when Esc is released.
+#[derive(Debug, Copy, Clone)]
+struct ModalEscape;
+
+#[derive(Clone)]
+enum ModalEvent {
+    Open(Widget),
+    Close(Widget),
+}
+
+impl WidgetModifier for Modal {
+    fn apply(&self, widget: &mut Widget) {
+        widget
+            .set_style_class(TypeId::of::<RectStyle>(), "modal_overlay")
+            .set_draw_style(self.overlay.clone());
+        // the dialog positions itself, see `open_modal_layer`
+        widget.layout().no_container();
+        // input is not passed on to the widgets below the modal
+        widget.add_handler(|_: &WidgetMouseButton, args: EventArgs| {
+            *args.handled = true;
+        });
+        widget.add_handler(|_: &WidgetMouseWheel, args: EventArgs| {
+            *args.handled = true;
+        });
+        if self.close_on_click_outside {
+            widget.add_handler(|event: &ClickEvent, args: EventArgs| {
+                close_if_outside(event.position, args);
+            });
+            widget.add_handler(|event: &AuxClickEvent, args: EventArgs| {
+                close_if_outside(event.position, args);
+            });
+        } else {
+            widget.add_handler(|_: &ClickEvent, args: EventArgs| {
+                *args.handled = true;
+            });
+            widget.add_handler(|_: &AuxClickEvent, args: EventArgs| {
+                *args.handled = true;
+            });
+        }
+        if self.close_on_esc {
+            widget.add_handler(|_: &ModalEscape, args: EventArgs| {
+                args.widget.event(CloseModal);
+            });
+        }
+    }
+}
+
+/// Closes the modal layer if a click on it missed the dialog.
+fn close_if_outside(position: Point, args: EventArgs) {
+    let inside = args.widget.children().iter().any(|child| child.bounds().contains(&position));
+    if !inside {
+        args.widget.event(CloseModal);
+    }
+    *args.handled = true;
+}
+
+impl Widget {
+    /// Open `dialog` as a modal dialog, above the rest of the UI, styled by the theme's `ModalStyle`.
+    /// When the dialog closes, this widget receives a `ModalResult<T>`.
+    pub fn open_modal<T: Clone + 'static>(&self, dialog: Widget) {
+        self.open_modal_style::<T>(dialog, ModalStyle::default());
+    }
+
+    /// Open `dialog` as a modal dialog, using the given `ModalStyle`, with
+    /// missing fields inherited from the theme.
+    pub fn open_modal_style<T: Clone + 'static>(&self, dialog: Widget, style: ModalStyle) {
+        self.open_modal_layer::<T>(dialog, style, true);
+    }
+
+    /// Open `dialog` in a modal layer. If `centered` is false, the dialog is only kept within
+    /// the window, and is expected to position itself, eg. a popup menu.
+    pub(crate) fn open_modal_layer<T: Clone + 'static>(&self, mut dialog: Widget, style: ModalStyle, centered: bool) {
+        let mut layer = Widget::from_modifier_style(style);
+        layer.set_name("modal_layer");
+        let opener = self.clone();
+        layer.add_handler(move |event: &ModalResult<T>, args: EventArgs| {
+            opener.event(event.clone());
+            event::event(Target::Root, ModalEvent::Close(args.widget));
+            *args.handled = true;
+        });
+        layer.add_handler(|_: &CloseModal, args: EventArgs| {
+            args.widget.event(ModalResult::<T>::Cancel);
+        });
+        if centered {
+            dialog.layout().add(constraints![
+                center(&layer),
+                shrink(),
+            ]);
+        }
+        dialog.layout().add(bound_by(&layer));
+        layer.add_child(dialog);
+        event::event(Target::Root, ModalEvent::Open(layer));
+    }
+}
+
+impl App {
+    pub fn add_modal_handlers(&mut self) {
+        self.add_handler(|event: &ModalEvent, args: EventArgs| {
+            match *event {
+                ModalEvent::Open(ref layer) => {
+                    args.ui.modals.push(layer.clone());
+                    args.ui.get_root().add_child(layer.clone());
+                    args.ui.event(KeyboardInputEvent::PushFocusScope(layer.clone()));
+                }
+                ModalEvent::Close(ref layer) => {
+                    if let Some(index) = args.ui.modals.iter().position(|modal| modal == layer) {
+                        args.ui.modals.remove(index);
+                        args.ui.event(KeyboardInputEvent::PopFocusScope(layer.clone()));
+                        layer.clone().remove_widget();
+                    }
+                }
+            }
+        });
+        // close on release, so the key press that closes a dialog isn't also handled
+        // by handlers on the root that check `Ui::has_modal`, like `EscKeyCloseHandler`
+        self.add_handler(|event: &KeyboardInput, args: EventArgs| {
+            let &KeyboardInput(input) = event;
+            if input.state == glutin::ElementState::Released &&
+                input.virtual_keycode == Some(glutin::VirtualKeyCode::Escape) {
+                if let Some(modal) = args.ui.modals.last() {
+                    modal.event(ModalEscape);
+                }
+            }
+        });
+    }
+}
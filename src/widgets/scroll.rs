@@ -9,6 +9,7 @@ use widgets::slider::{SliderStyle, SliderEvent, SetSliderValue, Orientation};
 use geometry::{Size, Vector, Rect, RectExt};
 use layout::{LayoutUpdated, LAYOUT};
 use input::mouse::WidgetMouseWheel;
+use input::gesture::{PanEvent, PanRecognizer};
 use draw::rect::RectStyle;
 use color::*;
 use style::WidgetModifier;
@@ -92,6 +93,8 @@ impl WidgetModifier for ScrollContainer {
         }
         content_holder.add_handler(scroll_parent_handler);
         ScrollParent::add_adapters(&mut content_holder);
+        // touches on the content bubble up to the holder, for touch panning
+        content_holder.add_handler(PanRecognizer::default());
 
         content_holder.add_child(content);
         if scrollbars.is_some() {
@@ -147,6 +150,7 @@ multi_event!{impl EventHandler<ScrollParentEvent> for ScrollParent {
     LayoutUpdated => container_layout_updated,
     ContentLayoutUpdated => content_layout_updated,
     WidgetMouseWheel => widget_mouse_wheel,
+    PanEvent => pan,
     ScrollBarMoved => scrollbar_moved,
 }}
 
@@ -259,7 +263,14 @@ impl ScrollParent {
 
     fn widget_mouse_wheel(&mut self, event: &WidgetMouseWheel, _: EventArgs) {
         let &WidgetMouseWheel(mouse_wheel) = event;
-        let scroll = get_scroll(mouse_wheel);
+        self.scroll_by(get_scroll(mouse_wheel));
+    }
+
+    fn pan(&mut self, event: &PanEvent, _: EventArgs) {
+        self.scroll_by(event.change);
+    }
+
+    fn scroll_by(&mut self, scroll: Vector) {
         if self.scrollable_area.width > 0.0 {
             self.offset.x = f32::min(0.0, f32::max(-self.scrollable_area.width, self.offset.x + scroll.x));
             self.move_content_x();
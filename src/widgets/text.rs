@@ -1,7 +1,7 @@
 use cassowary::Constraint;
 
 use widget::{Widget, StateUpdated, StyleUpdated};
-use draw::text::{TextState, TextStyle};
+use draw::text::{TextState, TextStyle, RichTextState, RichTextStyle, AttributedText};
 use event::{EventHandler, EventArgs};
 use layout::constraint::*;
 use geometry::Size;
@@ -29,7 +29,7 @@ impl StaticTextStyle {
 
 impl WidgetModifier for StaticText {
     fn apply(&self, widget: &mut Widget) {
-        widget.add_handler(TextSizeHandler::default());
+        widget.add_handler(TextSizeHandler::new(measure_text));
         widget.add_handler(TextUpdateHandler::default());
         widget.add_handler(|_: &StyleUpdated, args: EventArgs| {
             args.widget.event(StaticTextUpdate::StyleUpdated);
@@ -41,6 +41,66 @@ impl WidgetModifier for StaticText {
     }
 }
 
+/// Text made up of spans with their own fonts, sizes, colors and decorations, eg. for labels
+/// with bold parts or highlighted search results. Attributes that spans don't set are taken
+/// from the `RichTextStyle`. The text can be replaced by sending the widget a `RichTextUpdated`.
+#[derive(Debug, Clone)]
+pub struct RichText {
+    text: AttributedText,
+    style: RichTextStyle,
+}
+
+impl RichText {
+    pub fn new(text: AttributedText) -> Self {
+        RichText {
+            text: text,
+            style: RichTextStyle::default(),
+        }
+    }
+    /// Style the text, fields that aren't set are taken from the theme.
+    pub fn style(mut self, style: RichTextStyle) -> Self {
+        self.style = style;
+        self
+    }
+}
+
+impl WidgetModifier for RichText {
+    fn apply(&self, widget: &mut Widget) {
+        widget.set_name("rich_text");
+        widget.add_handler(TextSizeHandler::new(measure_rich_text));
+        let mut text = None;
+        widget.add_handler(move |event: &RichTextUpdate, mut args: EventArgs| {
+            if let RichTextUpdate::TextUpdated(ref event) = *event {
+                text = Some(event.0.clone());
+            }
+            if let Some(ref text) = text {
+                args.widget.update(|state: &mut RichTextState| {
+                    state.text = text.clone();
+                });
+            }
+        });
+        widget.add_handler(|_: &StyleUpdated, args: EventArgs| {
+            args.widget.event(RichTextUpdate::StyleUpdated);
+        });
+        widget.add_handler(|event: &RichTextUpdated, args: EventArgs| {
+            args.widget.event(RichTextUpdate::TextUpdated(event.clone()));
+        });
+        widget.set_draw_style(RichTextStyle {
+            text: Some(self.text.clone()),
+            ..self.style.clone()
+        });
+    }
+}
+
+/// Replaces the text of a `RichText` widget.
+#[derive(Debug, Clone)]
+pub struct RichTextUpdated(pub AttributedText);
+
+enum RichTextUpdate {
+    StyleUpdated,
+    TextUpdated(RichTextUpdated),
+}
+
 #[derive(Default)]
 struct TextUpdateHandler {
     text: Option<String>,
@@ -71,19 +131,38 @@ impl EventHandler<StaticTextUpdate> for TextUpdateHandler {
     }
 }
 
-#[derive(Default)]
+/// Sizes the widget to fit it's text, using `measure` to measure the widget's draw state.
 struct TextSizeHandler {
+    measure: fn(&mut Widget) -> Size,
     measured_size: Option<Size>,
     size_constraints: Vec<Constraint>,
 }
 
+impl TextSizeHandler {
+    fn new(measure: fn(&mut Widget) -> Size) -> Self {
+        TextSizeHandler {
+            measure: measure,
+            measured_size: None,
+            size_constraints: Vec::new(),
+        }
+    }
+}
+
+fn measure_text(widget: &mut Widget) -> Size {
+    let draw_state = widget.draw_state();
+    let text_size = draw_state.downcast_ref::<TextState>().unwrap().measure();
+    text_size
+}
+
+fn measure_rich_text(widget: &mut Widget) -> Size {
+    let draw_state = widget.draw_state();
+    let text_size = draw_state.downcast_ref::<RichTextState>().unwrap().measure();
+    text_size
+}
+
 impl EventHandler<StateUpdated> for TextSizeHandler {
     fn handle(&mut self, _: &StateUpdated, mut args: EventArgs) {
-        let text_size = {
-            let draw_state = args.widget.draw_state();
-            let text_draw_state = draw_state.downcast_ref::<TextState>().unwrap();
-            text_draw_state.measure()
-        };
+        let text_size = (self.measure)(&mut args.widget);
         if self.measured_size.is_none() || self.measured_size.unwrap() != text_size {
             let size_constraints = size(text_size).build(&args.widget.layout_vars());
             let mut layout = args.widget.layout();
@@ -0,0 +1,252 @@
+//! Tooltips, shown when the cursor rests on a widget.
+//!
+//! Any widget can be given a tooltip with `Widget::set_tooltip`, or `Widget::set_tooltip_widget`
+//! for content other than text. The tooltip is shown near the cursor once it has stayed over the
+//! widget for the `TooltipStyle`'s delay, and hidden when the cursor leaves the widget, or on
+//! a click, scroll or key press.
+
+use std::any::TypeId;
+use std::rc::Rc;
+use std::time::Duration;
+
+use app::App;
+use event::{self, EventArgs, EventHandler};
+use widget::Widget;
+use widgets::text::StaticTextStyle;
+use input::mouse::{MouseOverEvent, MouseButton, MouseWheel};
+use input::keyboard::KeyboardInput;
+use layout::constraint::*;
+use draw::rect::{RectStyle, BoxShadow};
+use draw::text::TextStyle;
+use geometry::{Point, Vector};
+use ui::{Ui, WidgetDetachedEvent};
+use resources::resources;
+use color::*;
+use style::*;
+
+component_style!{pub struct Tooltip<name="tooltip", style=TooltipStyle> {
+    rect: RectStyle = style!(RectStyle {
+        background_color: Color(0xFFFFE1FF),
+        border: Some((1.0, GRAY_50)),
+        shadow: BoxShadow::new(Vector::new(2.0, 2.0), 4.0, Color(0x00000040)),
+    }),
+    text: TextStyle = style!(TextStyle {
+        font_size: 16.0,
+    }),
+    delay: Duration = Duration::from_millis(500),
+    offset: Vector = Vector::new(10.0, 20.0),
+    padding: f32 = 5.0,
+}}
+
+impl WidgetModifier for Tooltip {
+    fn apply(&self, widget: &mut Widget) {
+        widget
+            .set_style_class(TypeId::of::<RectStyle>(), "tooltip_rect")
+            .set_draw_style(self.rect.clone());
+        widget.layout().no_container();
+        widget.layout().add(shrink());
+    }
+}
+
+impl TooltipStyle {
+    /// Merge with the `TooltipStyle` registered in the theme, to find the values in effect.
+    fn resolve(self) -> Tooltip {
+        let style = resources().theme.get_modifier_style(Box::new(self), TypeId::of::<TooltipStyle>(), None);
+        style.as_any().downcast_ref::<TooltipStyle>().unwrap().clone().component()
+    }
+}
+
+#[derive(Clone)]
+enum TooltipContent {
+    Text(String),
+    Widget(Rc<Fn() -> Widget>),
+}
+
+struct TooltipSource {
+    content: TooltipContent,
+    style: TooltipStyle,
+}
+
+#[derive(Clone)]
+enum TooltipEvent {
+    /// The cursor moved over a widget with a tooltip
+    Hover(Widget, Rc<TooltipSource>),
+    /// The cursor moved out of a widget with a tooltip, or it was removed
+    Leave(Widget),
+}
+
+/// Sent from the timer thread once the cursor has rested on a widget for the tooltip delay.
+#[derive(Debug, Copy, Clone)]
+struct TooltipTimeout(u64);
+
+impl Widget {
+    /// Show `text` in a tooltip when the cursor rests on this widget, styled by the theme's `TooltipStyle`.
+    pub fn set_tooltip(&mut self, text: &str) -> &mut Self {
+        self.set_tooltip_style(text, TooltipStyle::default())
+    }
+
+    /// Show a text tooltip using the given `TooltipStyle`, with missing fields inherited from the theme.
+    pub fn set_tooltip_style(&mut self, text: &str, style: TooltipStyle) -> &mut Self {
+        self.add_tooltip(TooltipContent::Text(text.to_owned()), style)
+    }
+
+    /// Show a tooltip containing the widget returned by `build`, which is called each time the
+    /// tooltip is shown.
+    pub fn set_tooltip_widget<F: Fn() -> Widget + 'static>(&mut self, build: F) -> &mut Self {
+        self.set_tooltip_widget_style(build, TooltipStyle::default())
+    }
+
+    /// Show a widget tooltip using the given `TooltipStyle`, with missing fields inherited from the theme.
+    pub fn set_tooltip_widget_style<F: Fn() -> Widget + 'static>(&mut self, build: F, style: TooltipStyle) -> &mut Self {
+        self.add_tooltip(TooltipContent::Widget(Rc::new(build)), style)
+    }
+
+    fn add_tooltip(&mut self, content: TooltipContent, style: TooltipStyle) -> &mut Self {
+        let source = Rc::new(TooltipSource {
+            content: content,
+            style: style,
+        });
+        self.add_handler(move |event: &MouseOverEvent, args: EventArgs| {
+            let event = match *event {
+                MouseOverEvent::Over => TooltipEvent::Hover(args.widget.clone(), source.clone()),
+                MouseOverEvent::Out => TooltipEvent::Leave(args.widget.clone()),
+            };
+            args.ui.event(event);
+        });
+        self.add_handler(|_: &WidgetDetachedEvent, args: EventArgs| {
+            args.ui.event(TooltipEvent::Leave(args.widget.clone()));
+        })
+    }
+}
+
+struct Hovered {
+    widget: Widget,
+    source: Rc<TooltipSource>,
+    tooltip: Tooltip,
+}
+
+multi_event!{impl EventHandler<TooltipHandlerEvent> for TooltipHandler {
+    TooltipEvent => tooltip_event,
+    TooltipTimeout => timeout,
+    MouseButton => hide_on_input,
+    MouseWheel => hide_on_input,
+    KeyboardInput => hide_on_input,
+}}
+
+/// Tracks the widget with a tooltip under the cursor, and shows it's tooltip.
+#[derive(Default)]
+struct TooltipHandler {
+    hovered: Option<Hovered>,
+    /// Incremented whenever the hovered widget changes, so timers started for
+    /// a previously hovered widget are ignored
+    hover_id: u64,
+    /// Set by a click, scroll or key press, the tooltip isn't shown again until the cursor leaves the widget
+    suppressed: bool,
+    /// The layer containing the visible tooltip
+    visible: Option<Widget>,
+}
+
+impl TooltipHandler {
+    fn hide(&mut self) {
+        if let Some(mut layer) = self.visible.take() {
+            layer.remove_widget();
+        }
+    }
+
+    /// True if the widget under the cursor is `widget`, one of it's descendants, or the visible tooltip
+    fn cursor_inside(&self, widget: &Widget, ui: &mut Ui) -> bool {
+        let under_cursor = ui.cursor_position().and_then(|cursor| ui.widget_under_cursor(cursor));
+        under_cursor.map_or(false, |under_cursor| {
+            under_cursor.is_in_subtree(widget) ||
+                self.visible.as_ref().map_or(false, |layer| under_cursor.is_in_subtree(layer))
+        })
+    }
+
+    fn tooltip_event(&mut self, event: &TooltipEvent, args: EventArgs) {
+        match *event {
+            TooltipEvent::Hover(ref widget, ref source) => {
+                if let Some(ref hovered) = self.hovered {
+                    // the hover event bubbles up to ancestors with tooltips,
+                    // the innermost widget's tooltip is the one shown
+                    if hovered.widget.is_in_subtree(widget) {
+                        return;
+                    }
+                }
+                self.hide();
+                self.suppressed = false;
+                self.hover_id += 1;
+                let tooltip = source.style.clone().resolve();
+                event::event_global_after(tooltip.delay, TooltipTimeout(self.hover_id));
+                self.hovered = Some(Hovered {
+                    widget: widget.clone(),
+                    source: source.clone(),
+                    tooltip: tooltip,
+                });
+            }
+            TooltipEvent::Leave(ref widget) => {
+                let is_hovered = self.hovered.as_ref().map_or(false, |hovered| hovered.widget == *widget);
+                // moving between the children of a widget sends `Out` followed by `Over`
+                if is_hovered && !self.cursor_inside(widget, args.ui) {
+                    self.hide();
+                    self.hovered = None;
+                    self.suppressed = false;
+                }
+            }
+        }
+    }
+
+    fn timeout(&mut self, event: &TooltipTimeout, args: EventArgs) {
+        let &TooltipTimeout(hover_id) = event;
+        if hover_id != self.hover_id || self.suppressed || self.visible.is_some() {
+            return;
+        }
+        if let (Some(ref hovered), Some(cursor)) = (self.hovered.as_ref(), args.ui.cursor_position()) {
+            self.visible = Some(show_tooltip(hovered, cursor, args.ui));
+        }
+    }
+
+    fn hide_on_input<E>(&mut self, _: &E, _: EventArgs) {
+        self.hide();
+        if self.hovered.is_some() {
+            self.suppressed = true;
+        }
+    }
+}
+
+/// Add the tooltip for `hovered` to the root widget, returning the layer containing it.
+fn show_tooltip(hovered: &Hovered, cursor: Point, ui: &mut Ui) -> Widget {
+    let tooltip = &hovered.tooltip;
+    let mut layer = Widget::new("tooltip_layer");
+    layer.layout().no_container();
+
+    let mut tooltip_widget = Widget::from_modifier(tooltip.clone());
+    let mut content = match hovered.source.content {
+        TooltipContent::Text(ref text) => {
+            let mut text_widget = Widget::new("tooltip_text");
+            text_widget.set_style_class(TypeId::of::<TextStyle>(), "tooltip_text");
+            let style = style!(TextStyle {
+                text: text.clone(),
+            }).merge(&tooltip.text);
+            StaticTextStyle::from_style(style).component().apply(&mut text_widget);
+            text_widget
+        }
+        TooltipContent::Widget(ref build) => build(),
+    };
+    content.layout().add(bound_by(&tooltip_widget).padding(tooltip.padding));
+    tooltip_widget.add_child(content);
+
+    let position = cursor + tooltip.offset;
+    tooltip_widget.layout().edit_left().set(position.x);
+    tooltip_widget.layout().edit_top().set(position.y);
+    tooltip_widget.layout().add(bound_by(&layer));
+    layer.add_child(tooltip_widget);
+    ui.get_root().add_child(layer.clone());
+    layer
+}
+
+impl App {
+    pub fn add_tooltip_handlers(&mut self) {
+        self.add_handler(TooltipHandler::default());
+        TooltipHandler::add_adapters(&mut self.get_root());
+    }
+}
@@ -1,23 +1,24 @@
 /// Logic related to the positioning of the cursor within text.
+///
+/// The cursor is placed between the clusters of shaped chars on each line, so it never splits
+/// a ligature, or a char from the marks attached to it.
+///
+/// A cursor `Index` is a logical position in the text. In mixed left to right and right to left
+/// text, logical order differs from visual order, so the cursor can be moved either through the
+/// text, with `previous` and `next`, or across the screen, with `left` and `right`.
 
 use std;
 use types::{Range, Align, Rect, RectExt, Point};
-use rusttype;
-use rusttype::LayoutIter;
 
 use super::line::{LineRects, LineInfo};
-use super::Font;
 
 /// Every possible cursor position within each line of text yielded by the given iterator.
 ///
 /// Yields `(xs, y_range)`, where `y_range` is the `Range` occupied by the line across the *y*
 /// axis and `xs` is every possible cursor position along the *x* axis
 #[derive(Clone)]
-pub struct XysPerLine<'a, I> {
+pub struct XysPerLine<I> {
     lines_with_rects: I,
-    font: &'a Font,
-    text: &'a str,
-    font_size: f32,
 }
 
 /// Similarly to `XysPerLine`, yields every possible cursor position within each line of text
@@ -31,18 +32,17 @@ pub struct XysPerLine<'a, I> {
 /// axis and `xs` is every possible cursor position along the *x* axis.
 #[derive(Clone)]
 pub struct XysPerLineFromText<'a> {
-        xys_per_line: XysPerLine<'a,
+        xys_per_line: XysPerLine<
             std::iter::Zip<std::iter::Cloned<std::slice::Iter<'a, LineInfo>>,
             LineRects<std::iter::Cloned<std::slice::Iter<'a, LineInfo>>>>
         >,
     }
 
-/// Each possible cursor position along the *x* axis within a line of text.
+/// Each possible cursor position along the *x* axis within a line of text, in logical order.
 ///
 /// `Xs` iterators are produced by the `XysPerLine` iterator.
-pub struct Xs<'a, 'b> {
-    next_x: Option<f32>,
-    layout: LayoutIter<'a, 'b>,
+pub struct Xs {
+    xs: std::vec::IntoIter<f32>,
 }
 
 /// An index representing the position of a cursor within some text.
@@ -50,11 +50,13 @@ pub struct Xs<'a, 'b> {
 pub struct Index {
     /// The index of the line upon which the cursor is situated.
     pub line: usize,
-    /// The index within all possible cursor positions for the line.
+    /// The index within all possible cursor positions for the line, the number of clusters
+    /// before the cursor.
     ///
-    /// For example, for the line `foo`, a `char` of `1` would indicate the cursor's position
-    /// as `f|oo` where `|` is the cursor.
-    pub char: usize,
+    /// For example, for the line `foo`, a `cluster` of `1` would indicate the cursor's position
+    /// as `f|oo` where `|` is the cursor. For a line starting with `e` followed by a combining
+    /// accent, a `cluster` of `1` would be after both chars.
+    pub cluster: usize,
 }
 
 
@@ -68,33 +70,29 @@ impl Index {
     /// the start of the word that precedes the whitespace
     ///
     /// If `self` is in the middle or end of a word, return the index of the start of that word
-    pub fn previous_word_start<I>(self, text: &str, mut line_infos: I) -> Option<Self>
+    pub fn previous_word_start<I>(self, mut line_infos: I) -> Option<Self>
         where I: Iterator<Item = LineInfo>
     {
-        let Index { line, char } = self;
-        if char > 0 {
-            line_infos.nth(line).and_then(|line_info| {
-                let line_count = line_info.char_range().count();
-                let mut chars_rev = (&text[line_info.byte_range()]).chars().rev();
-                if char != line_count {
-                    chars_rev.nth(line_count - char - 1);
-                }
-                let mut new_char = 0;
+        let Index { line, cluster } = self;
+        if cluster > 0 {
+            line_infos.nth(line).map(|line_info| {
+                let clusters = &line_info.clusters[..std::cmp::min(cluster, line_info.clusters.len())];
+                let mut new_cluster = 0;
                 let mut hit_non_whitespace = false;
-                for (i, char_) in chars_rev.enumerate() {
+                for (i, cluster) in clusters.iter().enumerate().rev() {
                     // loop until word starts, then continue until the word ends
-                    if !char_.is_whitespace() {
+                    if !cluster.whitespace {
                         hit_non_whitespace = true;
                     }
-                    if char_.is_whitespace() && hit_non_whitespace {
-                        new_char = char - i;
+                    if cluster.whitespace && hit_non_whitespace {
+                        new_cluster = i + 1;
                         break;
                     }
                 }
-                Some(Index {
+                Index {
                     line: line,
-                    char: new_char,
-                })
+                    cluster: new_cluster,
+                }
             })
         } else {
             self.previous(line_infos)
@@ -112,39 +110,35 @@ impl Index {
     /// the end of the word after the whitespace
     ///
     /// If `self` is in the middle or start of a word, return the index of the end of that word
-    pub fn next_word_end<I>(self, text: &str, mut line_infos: I) -> Option<Self>
+    pub fn next_word_end<I>(self, mut line_infos: I) -> Option<Self>
         where I: Iterator<Item = LineInfo>
     {
-        let Index { line, char } = self;
+        let Index { line, cluster } = self;
         line_infos.nth(line)
             .and_then(|line_info| {
-                let line_count = line_info.char_range().count();
-                if char < line_count {
-                    let mut chars = (&text[line_info.byte_range()]).chars();
-                    let mut new_char = line_count;
+                let line_count = line_info.clusters.len();
+                if cluster < line_count {
+                    let mut new_cluster = line_count;
                     let mut hit_non_whitespace = false;
-                    if char != 0 {
-                        chars.nth(char - 1);
-                    }
-                    for (i, char_) in chars.enumerate() {
+                    for (i, cluster) in line_info.clusters.iter().enumerate().skip(cluster) {
                         // loop until word starts, then continue until the word ends
-                        if !char_.is_whitespace() {
+                        if !cluster.whitespace {
                             hit_non_whitespace = true;
                         }
-                        if char_.is_whitespace() && hit_non_whitespace {
-                            new_char = char + i;
+                        if cluster.whitespace && hit_non_whitespace {
+                            new_cluster = i;
                             break;
                         }
                     }
                     Some(Index {
                         line: line,
-                        char: new_char,
+                        cluster: new_cluster,
                     })
                 } else {
                     line_infos.next().map(|_| {
                         Index {
                             line: line + 1,
-                            char: 0,
+                            cluster: 0,
                         }
                     })
                 }
@@ -159,18 +153,18 @@ impl Index {
     /// index position of the previous line.
     ///
     /// If `self` is a position other than the start of a line, it will return the position
-    /// that is immediately to the left.
+    /// before the previous cluster.
     pub fn previous<I>(self, mut line_infos: I) -> Option<Self>
         where I: Iterator<Item = LineInfo>
     {
-        let Index { line, char } = self;
-        if char > 0 {
-            let new_char = char - 1;
+        let Index { line, cluster } = self;
+        if cluster > 0 {
+            let new_cluster = cluster - 1;
             line_infos.nth(line)
-                .and_then(|info| if new_char <= info.char_range().count() {
+                .and_then(|info| if new_cluster <= info.clusters.len() {
                     Some(Index {
                         line: line,
-                        char: new_char,
+                        cluster: new_cluster,
                     })
                 } else {
                     None
@@ -179,10 +173,9 @@ impl Index {
             let new_line = line - 1;
             line_infos.nth(new_line)
                 .map(|info| {
-                    let new_char = info.end_char() - info.start_char;
                     Index {
                         line: new_line,
-                        char: new_char,
+                        cluster: info.clusters.len(),
                     }
                 })
         } else {
@@ -197,45 +190,105 @@ impl Index {
     /// If `self` is at the end of a line other than the last, this returns the first index of
     /// the next line.
     ///
-    /// If `self` is a position other than the end of a line, it will return the position that
-    /// is immediately to the right.
+    /// If `self` is a position other than the end of a line, it will return the position after
+    /// the next cluster.
     pub fn next<I>(self, mut line_infos: I) -> Option<Self>
         where I: Iterator<Item = LineInfo>
     {
-        let Index { line, char } = self;
+        let Index { line, cluster } = self;
         line_infos.nth(line)
-            .and_then(|info| if char >= info.char_range().count() {
+            .and_then(|info| if cluster >= info.clusters.len() {
                 line_infos.next().map(|_| {
                     Index {
                         line: line + 1,
-                        char: 0,
+                        cluster: 0,
                     }
                 })
             } else {
                 Some(Index {
                     line: line,
-                    char: char + 1,
+                    cluster: cluster + 1,
                 })
             })
     }
 
+    /// The cursor index visually to the left of `self`.
+    ///
+    /// Within a line, this is the closest cursor position to the left, which in right to left
+    /// text comes after `self`. From the left edge of a line, this moves to the right edge of the
+    /// previous line, or of the next line if the line is right to left, or returns `None` if there
+    /// is no such line.
+    pub fn left<I>(self, line_infos: I) -> Option<Self>
+        where I: Iterator<Item = LineInfo>
+    {
+        self.move_visually(line_infos, false)
+    }
+
+    /// The cursor index visually to the right of `self`.
+    ///
+    /// Within a line, this is the closest cursor position to the right, which in right to left
+    /// text comes before `self`. From the right edge of a line, this moves to the left edge of the
+    /// next line, or of the previous line if the line is right to left, or returns `None` if there
+    /// is no such line.
+    pub fn right<I>(self, line_infos: I) -> Option<Self>
+        where I: Iterator<Item = LineInfo>
+    {
+        self.move_visually(line_infos, true)
+    }
+
+    fn move_visually<I>(self, line_infos: I, right: bool) -> Option<Self>
+        where I: Iterator<Item = LineInfo>
+    {
+        let line_infos: Vec<LineInfo> = line_infos.collect();
+        let line_info = match line_infos.get(self.line) {
+            Some(line_info) => line_info,
+            None => return None,
+        };
+        // move to the closest caret position that's further left or right on the screen, skipping
+        // positions that are in the same place, like either side of a change in direction
+        let xs = caret_xs(line_info);
+        let x = xs[std::cmp::min(self.cluster, xs.len() - 1)];
+        let carets = visual_order(line_info);
+        let next = if right {
+            carets.iter().find(|&&cluster| xs[cluster] > x)
+        } else {
+            carets.iter().rev().find(|&&cluster| xs[cluster] < x)
+        };
+        if let Some(&cluster) = next {
+            return Some(Index {
+                line: self.line,
+                cluster: cluster,
+            });
+        }
+        // past the edge of the line, continue from the opposite edge of the line before or after
+        // it, depending on the direction of the line
+        let forward = right != (line_info.level % 2 == 1);
+        let line = if forward { Some(self.line + 1) } else { self.line.checked_sub(1) };
+        line.and_then(|line| line_infos.get(line).map(|line_info| {
+            let carets = visual_order(line_info);
+            Index {
+                line: line,
+                cluster: if right { carets[0] } else { carets[carets.len() - 1] },
+            }
+        }))
+    }
+
     /// Clamps `self` to the given lines.
     ///
     /// If `self` would lie after the end of the last line, return the index at the end of the
     /// last line.
     ///
-    /// If `line_infos` is empty, returns cursor at line=0 char=0.
+    /// If `line_infos` is empty, returns cursor at line=0 cluster=0.
     pub fn clamp_to_lines<I>(self, line_infos: I) -> Self
         where I: Iterator<Item = LineInfo>
     {
         let mut last = None;
         for (i, info) in line_infos.enumerate() {
             if i == self.line {
-                let num_chars = info.char_range().len();
-                let char = std::cmp::min(self.char, num_chars);
+                let cluster = std::cmp::min(self.cluster, info.clusters.len());
                 return Index {
                     line: i,
-                    char: char,
+                    cluster: cluster,
                 };
             }
             last = Some((i, info));
@@ -244,10 +297,10 @@ impl Index {
             Some((i, info)) => {
                 Index {
                     line: i,
-                    char: info.char_range().len(),
+                    cluster: info.clusters.len(),
                 }
             }
-            None => Index { line: 0, char: 0 },
+            None => Index { line: 0, cluster: 0 },
         }
     }
 }
@@ -257,16 +310,9 @@ impl Index {
 ///
 /// Yields `(xs, y_range)`, where `y_range` is the `Range` occupied by the line across the *y*
 /// axis and `xs` is every possible cursor position along the *x* axis
-pub fn xys_per_line<'a, I>(lines_with_rects: I,
-                           font: &'a Font,
-                           text: &'a str,
-                           font_size: f32)
-                           -> XysPerLine<'a, I> {
+pub fn xys_per_line<I>(lines_with_rects: I) -> XysPerLine<I> {
     XysPerLine {
         lines_with_rects: lines_with_rects,
-        font: font,
-        text: text,
-        font_size: font_size,
     }
 }
 
@@ -279,9 +325,7 @@ pub fn xys_per_line<'a, I>(lines_with_rects: I,
 ///
 /// Yields `(xs, y_range)`, where `y_range` is the `Range` occupied by the line across the *y*
 /// axis and `xs` is every possible cursor position along the *x* axis.
-pub fn xys_per_line_from_text<'a>(text: &'a str,
-                                  line_infos: &'a [LineInfo],
-                                  font: &'a Font,
+pub fn xys_per_line_from_text<'a>(line_infos: &'a [LineInfo],
                                   font_size: f32,
                                   align: Align,
                                   line_spacing: f32,
@@ -296,11 +340,12 @@ pub fn xys_per_line_from_text<'a>(text: &'a str,
     let lines = line_infos.clone();
     let lines_with_rects = lines.zip(line_rects.clone());
     XysPerLineFromText {
-        xys_per_line: xys_per_line(lines_with_rects, font, text, font_size),
+        xys_per_line: xys_per_line(lines_with_rects),
     }
 }
 
-/// Convert the given character index into a cursor `Index`.
+/// Convert the given character index into a cursor `Index`, the position before the cluster
+/// containing the character.
 pub fn index_before_char<I>(line_infos: I, char_index: usize) -> Option<Index>
     where I: Iterator<Item = LineInfo>
 {
@@ -308,23 +353,37 @@ pub fn index_before_char<I>(line_infos: I, char_index: usize) -> Option<Index>
         let start_char = line_info.start_char;
         let end_char = line_info.end_char();
         if start_char <= char_index && char_index <= end_char {
+            let cluster = line_info.clusters
+                .iter()
+                .position(|cluster| char_index < cluster.char + cluster.len_chars)
+                .unwrap_or(line_info.clusters.len());
             return Some(Index {
                 line: i,
-                char: char_index - start_char,
+                cluster: cluster,
             });
         }
     }
     None
 }
 
+/// Convert the given cursor `Index` into the index of the character after it, or of the end of
+/// it's line if it's after the last cluster on the line.
+pub fn char_index<I>(mut line_infos: I, index: Index) -> Option<usize>
+    where I: Iterator<Item = LineInfo>
+{
+    line_infos.nth(index.line).map(|line_info| {
+        line_info.clusters.get(index.cluster).map_or(line_info.end_char(), |cluster| cluster.char)
+    })
+}
+
 /// Determine the *xy* location of the cursor at the given cursor `Index`.
-pub fn xy_at<'a, I>(xys_per_line: I, idx: Index) -> Option<(f32, Range)>
-    where I: Iterator<Item = (Xs<'a, 'a>, Range)>
+pub fn xy_at<I>(xys_per_line: I, idx: Index) -> Option<(f32, Range)>
+    where I: Iterator<Item = (Xs, Range)>
 {
     for (i, (xs, y)) in xys_per_line.enumerate() {
         if i == idx.line {
             for (j, x) in xs.enumerate() {
-                if j == idx.char {
+                if j == idx.cluster {
                     return Some((x, y));
                 }
             }
@@ -337,8 +396,8 @@ pub fn xy_at<'a, I>(xys_per_line: I, idx: Index) -> Option<(f32, Range)>
 /// return the line index, Xs iterator, and y-range of that line
 ///
 /// Returns `None` if there are no lines
-pub fn closest_line<'a, I>(y_pos: f32, xys_per_line: I) -> Option<(usize, Xs<'a, 'a>, Range)>
-    where I: Iterator<Item = (Xs<'a, 'a>, Range)>
+pub fn closest_line<I>(y_pos: f32, xys_per_line: I) -> Option<(usize, Xs, Range)>
+    where I: Iterator<Item = (Xs, Range)>
 {
     let mut xys_per_line_enumerated = xys_per_line.enumerate();
     xys_per_line_enumerated.next().and_then(|(first_line_idx, (first_line_xs, first_line_y))| {
@@ -366,16 +425,16 @@ pub fn closest_line<'a, I>(y_pos: f32, xys_per_line: I) -> Option<(usize, Xs<'a,
 /// cursor.
 ///
 /// Returns `None` if the given `text` is empty.
-pub fn closest_cursor_index_and_xy<'a, I>(point: Point, xys_per_line: I) -> Option<(Index, Point)>
-    where I: Iterator<Item = (Xs<'a, 'a>, Range)>
+pub fn closest_cursor_index_and_xy<I>(point: Point, xys_per_line: I) -> Option<(Index, Point)>
+    where I: Iterator<Item = (Xs, Range)>
 {
-    closest_line(point.x, xys_per_line)
+    closest_line(point.y, xys_per_line)
         .and_then(|(closest_line_idx, closest_line_xs, closest_line_y)| {
-            let (closest_char_idx, closest_x) = closest_cursor_index_on_line(point.y,
-                                                                             closest_line_xs);
+            let (closest_cluster_idx, closest_x) = closest_cursor_index_on_line(point.x,
+                                                                                closest_line_xs);
             let index = Index {
                 line: closest_line_idx,
-                char: closest_char_idx,
+                cluster: closest_cluster_idx,
             };
             let point = Point::new(closest_x, closest_line_y.middle());
             Some((index, point))
@@ -384,70 +443,150 @@ pub fn closest_cursor_index_and_xy<'a, I>(point: Point, xys_per_line: I) -> Opti
 
 /// Find the closest cursor index to the given `x` position on the given line along with the
 /// `x` position of that cursor.
-pub fn closest_cursor_index_on_line<'a>(x_pos: f32, line_xs: Xs<'a, 'a>) -> (usize, f32) {
+pub fn closest_cursor_index_on_line(x_pos: f32, line_xs: Xs) -> (usize, f32) {
     let mut xs_enumerated = line_xs.enumerate();
     // `xs` always yields at least one `x` (the start of the line).
     let (first_idx, first_x) = xs_enumerated.next().unwrap();
     let first_diff = (x_pos - first_x).abs();
     let mut closest = (first_idx, first_x);
     let mut closest_diff = first_diff;
+    // right to left clusters move the cursor left, so the xs aren't sorted
     for (i, x) in xs_enumerated {
         let diff = (x_pos - x).abs();
         if diff < closest_diff {
             closest = (i, x);
             closest_diff = diff;
-        } else {
-            break;
         }
     }
     closest
 }
 
 
-impl<'a, I> Iterator for XysPerLine<'a, I>
+impl<I> Iterator for XysPerLine<I>
     where I: Iterator<Item = (LineInfo, Rect)>
 {
     // The `Range` occupied by the line across the *y* axis, along with an iterator yielding
     // each possible cursor position along the *x* axis.
-    type Item = (Xs<'a, 'a>, Range);
+    type Item = (Xs, Range);
     fn next(&mut self) -> Option<Self::Item> {
-        let XysPerLine { ref mut lines_with_rects, font, text, font_size } = *self;
-        let scale = super::pt_to_scale(font_size);
-        lines_with_rects.next().map(|(line_info, line_rect)| {
-            let line = &text[line_info.byte_range()];
-            let (x, y) = (line_rect.left(), line_rect.top());
-            let point = rusttype::Point { x: x, y: y };
-            let y = line_rect.y_range();
-            let layout = font.layout(line, scale, point);
+        self.lines_with_rects.next().map(|(line_info, line_rect)| {
+            let left = line_rect.left();
+            let xs: Vec<f32> = caret_xs(&line_info).iter().map(|x| left + x).collect();
             let xs = Xs {
-                next_x: Some(line_rect.left()),
-                layout: layout,
+                xs: xs.into_iter(),
             };
-            (xs, y)
+            (xs, line_rect.y_range())
         })
     }
 }
 
 impl<'a> Iterator for XysPerLineFromText<'a> {
-    type Item = (Xs<'a, 'a>, Range);
+    type Item = (Xs, Range);
     fn next(&mut self) -> Option<Self::Item> {
         self.xys_per_line.next()
     }
 }
 
-impl<'a, 'b> Iterator for Xs<'a, 'b> {
+impl Iterator for Xs {
     // Each possible cursor position along the *x* axis.
     type Item = f32;
     fn next(&mut self) -> Option<Self::Item> {
-        self.next_x.map(|x| {
-            self.next_x = self.layout
-                .next()
-                .map(|g| {
-                    g.pixel_bounding_box()
-                        .map(|r| r.max.x as f32)
-                        .unwrap_or_else(|| x + g.unpositioned().h_metrics().advance_width)
-                });
-            x
-        })
+        self.xs.next()
+    }
+}
+
+/// The *x* position of each cursor position on a line, relative to the left of the line, in
+/// logical order: before each cluster, on it's leading edge, then after the last cluster.
+fn caret_xs(line_info: &LineInfo) -> Vec<f32> {
+    let mut xs: Vec<f32> = line_info.clusters.iter().map(|cluster| cluster.leading_x()).collect();
+    xs.push(line_info.clusters.last().map_or(0.0, |cluster| cluster.trailing_x()));
+    xs
+}
+
+/// The cursor positions on a line, as cluster indices, from left to right.
+fn visual_order(line_info: &LineInfo) -> Vec<usize> {
+    let xs = caret_xs(line_info);
+    let mut carets: Vec<usize> = (0..xs.len()).collect();
+    carets.sort_by(|&a, &b| xs[a].partial_cmp(&xs[b]).unwrap_or(std::cmp::Ordering::Equal).then(a.cmp(&b)));
+    carets
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32;
+    use super::*;
+    use line::LineInfos;
+    use fallback::{FontFallback, LoadedFont};
+    use Wrap;
+
+    fn line_infos(text: &str) -> Vec<LineInfo> {
+        // the font has no Hebrew glyphs, they're shaped with it's missing glyph
+        let font = LoadedFont::new(include_bytes!("../../assets/fonts/NotoSans/NotoSans-Regular.ttf").to_vec()).unwrap();
+        let fonts = [font.layout_font()];
+        LineInfos::new(text, FontFallback::new(&fonts), 20.0, Wrap::NoWrap, f32::MAX).collect()
+    }
+
+    /// The clusters visited moving the cursor from `start` until it can't move any further.
+    fn moves(line_infos: &[LineInfo], start: Index, right: bool) -> Vec<(usize, usize)> {
+        let mut index = start;
+        let mut moves = Vec::new();
+        loop {
+            let next = if right {
+                index.right(line_infos.iter().cloned())
+            } else {
+                index.left(line_infos.iter().cloned())
+            };
+            match next {
+                Some(next) => {
+                    moves.push((next.line, next.cluster));
+                    index = next;
+                }
+                None => return moves,
+            }
+        }
+    }
+
+    #[test]
+    fn visual_order_mixed() {
+        let lines = line_infos("ab \u{5d0}\u{5d1} cd");
+        assert_eq!(lines.len(), 1);
+        // the caret before the first right to left char is on it's right, where the caret
+        // after the last right to left char is too
+        assert_eq!(visual_order(&lines[0]), vec![0, 1, 2, 4, 3, 5, 6, 7, 8]);
+        let xs = caret_xs(&lines[0]);
+        assert_eq!(xs[3], xs[5]);
+    }
+
+    #[test]
+    fn move_visually_mixed() {
+        let lines = line_infos("ab \u{5d0}\u{5d1} cd");
+        let start = Index { line: 0, cluster: 0 };
+        let right = moves(&lines, start, true);
+        assert_eq!(right, vec![(0, 1), (0, 2), (0, 4), (0, 3), (0, 6), (0, 7), (0, 8)]);
+        let end = Index { line: 0, cluster: 8 };
+        let left = moves(&lines, end, false);
+        assert_eq!(left, vec![(0, 7), (0, 6), (0, 5), (0, 4), (0, 2), (0, 1), (0, 0)]);
+    }
+
+    #[test]
+    fn move_visually_across_lines() {
+        let lines = line_infos("ab\ncd");
+        let right = moves(&lines, Index { line: 0, cluster: 1 }, true);
+        assert_eq!(right, vec![(0, 2), (1, 0), (1, 1), (1, 2)]);
+        // right to left lines are moved through from right to left, from their start to their end
+        let lines = line_infos("\u{5d0}\u{5d1}\n\u{5d2}\u{5d3}");
+        let left = moves(&lines, Index { line: 0, cluster: 0 }, false);
+        assert_eq!(left, vec![(0, 1), (0, 2), (1, 0), (1, 1), (1, 2)]);
+        let right = moves(&lines, Index { line: 1, cluster: 1 }, true);
+        assert_eq!(right, vec![(1, 0), (0, 2), (0, 1), (0, 0)]);
+    }
+
+    #[test]
+    fn cursor_char_index() {
+        let lines = line_infos("ab \u{5d0}\u{5d1}\ncd");
+        for char in 0..9 {
+            let index = index_before_char(lines.iter().cloned(), char).unwrap();
+            assert_eq!(char_index(lines.iter().cloned(), index), Some(char));
+        }
     }
 }
@@ -0,0 +1,101 @@
+//! Fonts to fall back to, for characters a font has no glyph for.
+//!
+//! Each character is drawn with the first font in the list that has a glyph for it, so text
+//! mixing scripts, or containing symbols and emoji, can be drawn with fonts that cover them.
+
+use std::sync::Arc;
+
+use rusttype::{GlyphId, Glyph, FontCollection, SharedBytes};
+use rustybuzz::Face;
+use super::Font;
+
+/// A font, and the data it was loaded from, which faces to shape text with are parsed from.
+pub struct LoadedFont {
+    font: Font,
+    data: Arc<Box<[u8]>>,
+}
+
+impl LoadedFont {
+    /// Returns `None` if the data isn't a font that can be used to shape text.
+    pub fn new(data: Vec<u8>) -> Option<Self> {
+        let data = Arc::new(data.into_boxed_slice());
+        if Face::from_slice(&data, 0).is_none() {
+            return None;
+        }
+        let font = FontCollection::from_bytes(SharedBytes::ByArc(data.clone())).into_fonts().next();
+        font.map(|font| LoadedFont {
+            font: font,
+            data: data,
+        })
+    }
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+    /// The font along with a face to shape text with, parsing the face is cheap, it only
+    /// reads the font's table directory.
+    pub fn layout_font(&self) -> LayoutFont {
+        LayoutFont {
+            font: &self.font,
+            face: Face::from_slice(&self.data, 0).expect("font checked when it was loaded"),
+        }
+    }
+    /// The data the font was loaded from.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// A font used to lay out text, along with the face used to shape text with it, borrowed from a `LoadedFont`.
+pub struct LayoutFont<'a> {
+    pub font: &'a Font,
+    pub face: Face<'a>,
+}
+
+/// A list of fonts, the first font is the font the text is styled with, the rest are used,
+/// in order, for characters it's missing.
+#[derive(Copy, Clone)]
+pub struct FontFallback<'a> {
+    fonts: &'a [LayoutFont<'a>],
+}
+
+impl<'a> FontFallback<'a> {
+    /// Panics if `fonts` is empty.
+    pub fn new(fonts: &'a [LayoutFont<'a>]) -> Self {
+        assert!(!fonts.is_empty(), "a font fallback list needs at least one font");
+        FontFallback {
+            fonts: fonts,
+        }
+    }
+
+    /// The font the text is styled with, used for the vertical metrics of lines.
+    pub fn first(&self) -> &'a Font {
+        self.fonts[0].font
+    }
+
+    /// The font at `index` in the list.
+    pub fn font(&self, index: usize) -> &'a Font {
+        self.fonts[index].font
+    }
+
+    /// The face used to shape text with the font at `index` in the list.
+    pub fn face(&self, index: usize) -> &'a Face<'a> {
+        &self.fonts[index].face
+    }
+
+    /// True if the font at `index` in the list has a glyph for `ch`.
+    pub fn has_glyph(&self, index: usize, ch: char) -> bool {
+        // glyph 0 is the font's missing glyph
+        self.fonts[index].font.glyph(ch).map_or(false, |glyph| glyph.id() != GlyphId(0))
+    }
+
+    /// The glyph used to draw `ch`, along with the index of the font it's from. If none of the
+    /// fonts have a glyph for it, the first font's missing glyph is used.
+    pub fn glyph(&self, ch: char) -> (usize, Glyph<'a>) {
+        for index in 0..self.fonts.len() {
+            if self.has_glyph(index, ch) {
+                return (index, self.font(index).glyph(ch).unwrap());
+            }
+        }
+        (0, self.first().glyph(ch).unwrap())
+    }
+}
@@ -1,97 +1,84 @@
 /// Logic and types specific to individual glyph layout.
 
-use super::Font;
 use types::{Range, Rect, RectExt};
 use std;
-use rusttype;
-use rusttype::LayoutIter;
 use super::line::LineInfo;
+use super::shape::Cluster;
 
-/// An iterator yielding the `Rect` for each `char`'s `Glyph` in the given `text`.
-pub struct GlyphRects<'a, 'b> {
-    /// The *y* axis `Range` of the `Line` for which character `Rect`s are being yielded.
+/// An iterator yielding the `Rect` for each cluster of shaped chars in a line of text.
+pub struct GlyphRects {
+    /// The *y* axis `Range` of the `Line` for which cluster `Rect`s are being yielded.
     ///
     /// Every yielded `Rect` will use this as its `y` `Range`.
     y: Range,
-    /// The position of the next `Rect`'s left edge along the *x* axis.
-    next_left: f32,
-    /// `PositionedGlyphs` yielded by the RustType `LayoutIter`.
-    layout: LayoutIter<'a, 'b>,
+    /// The left edge of the line along the *x* axis.
+    left: f32,
+    /// The clusters of the line, in logical order.
+    clusters: std::vec::IntoIter<Cluster>,
 }
 
 
-impl<'a, 'b> Iterator for GlyphRects<'a, 'b> {
+impl Iterator for GlyphRects {
     type Item = Rect;
     fn next(&mut self) -> Option<Self::Item> {
-        let GlyphRects { ref mut next_left, ref mut layout, y } = *self;
-        layout.next().map(|g| {
-            let left = *next_left;
-            let right = g.pixel_bounding_box()
-                .map(|bb| bb.max.x as f32)
-                .unwrap_or_else(|| left + g.unpositioned().h_metrics().advance_width);
-            *next_left = right;
-            let x = Range::new(left, right);
+        let GlyphRects { ref mut clusters, left, y } = *self;
+        clusters.next().map(|cluster| {
+            let x = Range::new(left + cluster.x, left + cluster.x + cluster.width);
             Rect::from_ranges(x, y)
         })
     }
 }
 
-/// An iterator that, for every `(line, line_rect)` pair yielded by the given iterator,
-/// produces an iterator that yields a `Rect` for every character in that line.
-pub struct GlyphRectsPerLine<'a, I> {
+/// An iterator that, for every `(line_info, line_rect)` pair yielded by the given iterator,
+/// produces an iterator that yields a `Rect` for every cluster in that line.
+pub struct GlyphRectsPerLine<I> {
     lines_with_rects: I,
-    font: &'a Font,
-    font_size: f32,
 }
 
-impl<'a, I> GlyphRectsPerLine<'a, I>
-    where I: Iterator<Item = (&'a str, Rect)>
+impl<I> GlyphRectsPerLine<I>
+    where I: Iterator<Item = (LineInfo, Rect)>
 {
-    /// Produce an iterator that, for every `(line, line_rect)` pair yielded by the given iterator,
-    /// produces an iterator that yields a `Rect` for every character in that line.
+    /// Produce an iterator that, for every `(line_info, line_rect)` pair yielded by the given
+    /// iterator, produces an iterator that yields a `Rect` for every cluster in that line, in
+    /// logical order.
     ///
     /// This is useful when information about character positioning is needed when reasoning about
     /// text layout.
-    pub fn new(lines_with_rects: I, font: &'a Font, font_size: f32) -> GlyphRectsPerLine<'a, I> {
+    pub fn new(lines_with_rects: I) -> GlyphRectsPerLine<I> {
         GlyphRectsPerLine {
             lines_with_rects: lines_with_rects,
-            font: font,
-            font_size: font_size,
         }
     }
 }
-impl<'a, I> Iterator for GlyphRectsPerLine<'a, I>
-    where I: Iterator<Item = (&'a str, Rect)>
+impl<I> Iterator for GlyphRectsPerLine<I>
+    where I: Iterator<Item = (LineInfo, Rect)>
 {
-    type Item = GlyphRects<'a, 'a>;
+    type Item = GlyphRects;
     fn next(&mut self) -> Option<Self::Item> {
-        let GlyphRectsPerLine { ref mut lines_with_rects, font, font_size } = *self;
-        let scale = super::pt_to_scale(font_size);
-        lines_with_rects.next().map(|(line_text, line_rect)| {
-            let (x, y) = (line_rect.left(), line_rect.top());
-            let point = rusttype::Point { x: x, y: y };
+        self.lines_with_rects.next().map(|(line_info, line_rect)| {
             GlyphRects {
-                next_left: line_rect.left(),
-                layout: font.layout(line_text, scale, point),
+                left: line_rect.left(),
+                clusters: line_info.clusters.into_iter(),
                 y: line_rect.y_range(),
             }
         })
     }
 }
 
-/// Yields a `Rect` for each selected character in a single line of text.
+/// Yields a `Rect` for each selected cluster in a single line of text, in logical order. In
+/// mixed left to right and right to left text, the rects needn't be next to each other.
 ///
-/// This iterator can only be produced by the `SelectedCharRectsPerLine` iterator.
-pub struct SelectedGlyphRects<'a, 'b> {
-    enumerated_rects: std::iter::Enumerate<GlyphRects<'a, 'b>>,
-    end_char_idx: usize,
+/// This iterator can only be produced by the `SelectedGlyphRectsPerLine` iterator.
+pub struct SelectedGlyphRects {
+    enumerated_rects: std::iter::Enumerate<GlyphRects>,
+    end_cluster_idx: usize,
 }
-impl<'a, 'b> Iterator for SelectedGlyphRects<'a, 'b> {
+impl Iterator for SelectedGlyphRects {
     type Item = Rect;
     fn next(&mut self) -> Option<Self::Item> {
-        let SelectedGlyphRects { ref mut enumerated_rects, end_char_idx } = *self;
+        let SelectedGlyphRects { ref mut enumerated_rects, end_cluster_idx } = *self;
         enumerated_rects.next()
-            .and_then(|(i, rect)| if i < end_char_idx { Some(rect) } else { None })
+            .and_then(|(i, rect)| if i < end_cluster_idx { Some(rect) } else { None })
     }
 }
 
@@ -103,14 +90,14 @@ impl<'a, 'b> Iterator for SelectedGlyphRects<'a, 'b> {
 /// will be produced.
 ///
 /// All lines that have no selected `Rect`s will be skipped.
-pub struct SelectedGlyphRectsPerLine<'a, I> {
-    enumerated_rects_per_line: std::iter::Enumerate<GlyphRectsPerLine<'a, I>>,
+pub struct SelectedGlyphRectsPerLine<I> {
+    enumerated_rects_per_line: std::iter::Enumerate<GlyphRectsPerLine<I>>,
     start_cursor_idx: super::cursor::Index,
     end_cursor_idx: super::cursor::Index,
 }
 
-impl<'a, I> SelectedGlyphRectsPerLine<'a, I>
-    where I: Iterator<Item = (&'a str, Rect)>
+impl<I> SelectedGlyphRectsPerLine<I>
+    where I: Iterator<Item = (LineInfo, Rect)>
 {
     /// Produces an iterator that yields iteraors yielding `Rect`s for each selected character in
     /// each line of text within the given iterator yielding char `Rect`s.
@@ -120,60 +107,58 @@ impl<'a, I> SelectedGlyphRectsPerLine<'a, I>
     ///
     /// All lines that have no selected `Rect`s will be skipped.
     pub fn new(lines_with_rects: I,
-               font: &'a Font,
-               font_size: f32,
                start: super::cursor::Index,
                end: super::cursor::Index)
-               -> SelectedGlyphRectsPerLine<'a, I> {
+               -> SelectedGlyphRectsPerLine<I> {
         SelectedGlyphRectsPerLine {
-            enumerated_rects_per_line: GlyphRectsPerLine::new(lines_with_rects, font, font_size)
-                .enumerate(),
+            enumerated_rects_per_line: GlyphRectsPerLine::new(lines_with_rects).enumerate(),
             start_cursor_idx: start,
             end_cursor_idx: end,
         }
     }
 }
-impl<'a, I> Iterator for SelectedGlyphRectsPerLine<'a, I>
-    where I: Iterator<Item = (&'a str, Rect)>
+impl<I> Iterator for SelectedGlyphRectsPerLine<I>
+    where I: Iterator<Item = (LineInfo, Rect)>
 {
-    type Item = SelectedGlyphRects<'a, 'a>;
+    type Item = SelectedGlyphRects;
     fn next(&mut self) -> Option<Self::Item> {
         let SelectedGlyphRectsPerLine { ref mut enumerated_rects_per_line,
                                         start_cursor_idx,
                                         end_cursor_idx } = *self;
 
         enumerated_rects_per_line.next().map(|(i, rects)| {
-            let end_char_idx =
-                    // If this is the last line, the end is the char after the final selected char.
+            let end_cluster_idx =
+                    // If this is the last line, the end is the cluster after the final selected cluster.
                     if i == end_cursor_idx.line {
-                        end_cursor_idx.char
-                    // Otherwise if in range, every char in the line is selected.
+                        end_cursor_idx.cluster
+                    // Otherwise if in range, every cluster in the line is selected.
                     } else if start_cursor_idx.line <= i && i < end_cursor_idx.line {
                         std::u32::MAX as usize
-                    // Otherwise if out of range, no chars are selected.
+                    // Otherwise if out of range, no clusters are selected.
                     } else {
                         0
                     };
 
             let mut enumerated_rects = rects.enumerate();
 
-            // If this is the first line, skip all non-selected chars.
+            // If this is the first line, skip all non-selected clusters.
             if i == start_cursor_idx.line {
-                for _ in 0..start_cursor_idx.char {
+                for _ in 0..start_cursor_idx.cluster {
                     enumerated_rects.next();
                 }
             }
 
             SelectedGlyphRects {
                 enumerated_rects: enumerated_rects,
-                end_char_idx: end_char_idx,
+                end_cluster_idx: end_cluster_idx,
             }
         })
     }
 }
 
 
-/// Find the index of the character that directly follows the cursor at the given `cursor_idx`.
+/// Find the index of the character that directly follows the cursor at the given `cursor_idx`,
+/// the first char of the cluster after it.
 ///
 /// Returns `None` if either the given `cursor::Index` `line` or `idx` fields are out of bounds
 /// of the line information yielded by the `line_infos` iterator.
@@ -182,13 +167,10 @@ pub fn index_after_cursor<I>(mut line_infos: I, cursor_idx: super::cursor::Index
 {
     line_infos.nth(cursor_idx.line)
         .and_then(|line_info| {
-            let start_char = line_info.start_char;
-            let end_char = line_info.end_char();
-            let char_index = start_char + cursor_idx.char;
-            if char_index <= end_char {
-                Some(char_index)
-            } else {
-                None
+            match line_info.clusters.get(cursor_idx.cluster) {
+                Some(cluster) => Some(cluster.char),
+                None if cursor_idx.cluster == line_info.clusters.len() => Some(line_info.end_char()),
+                None => None,
             }
         })
 }
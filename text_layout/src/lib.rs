@@ -37,14 +37,20 @@
 
 extern crate rusttype;
 extern crate euclid;
+extern crate rustybuzz;
+extern crate unicode_bidi;
+extern crate unicode_linebreak;
 
 pub mod types;
 pub mod cursor;
 pub mod glyph;
 pub mod line;
+pub mod fallback;
+pub mod rich;
+pub mod shape;
 
 use std::f32;
-use rusttype::Scale;
+use rusttype::{Scale, point};
 use self::line::{LineRects, LineInfo, LineInfos};
 use self::types::*;
 
@@ -56,6 +62,8 @@ pub type PositionedGlyph = rusttype::PositionedGlyph<'static>;
 pub type Font = rusttype::Font<'static>;
 
 pub use types::Align;
+pub use fallback::{FontFallback, LayoutFont, LoadedFont};
+pub use rich::Span;
 
 /// The way in which text should wrap around the width.
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -65,6 +73,11 @@ pub enum Wrap {
     Character,
     /// Wrap at the first word that exceeds the width.
     Whitespace,
+    /// Wrap at the last line break opportunity, found with the Unicode line breaking algorithm,
+    /// before the first character that exceeds the width. Unlike `Whitespace`, this wraps text
+    /// without spaces, like Chinese or Japanese, and after hyphens and slashes. A segment with
+    /// no break opportunity that exceeds the width by itself is wrapped by character.
+    Unicode,
 }
 
 impl Default for Wrap {
@@ -74,79 +87,72 @@ impl Default for Wrap {
 }
 
 pub fn get_text_size(text: &str,
-                     font: &Font,
+                     fonts: FontFallback,
                      font_size: f32,
                      line_height: f32,
                      wrap: Wrap) -> Size {
 
-    let line_infos = LineInfos::new(text, font, font_size, wrap, f32::MAX);
-    let max_width = line_infos.fold(0.0, |max, line_info| f32::max(max, line_info.width));
-    Size::new(max_width, line_infos.count() as f32 * line_height)
+    let line_infos: Vec<LineInfo> = LineInfos::new(text, fonts, font_size, wrap, f32::MAX).collect();
+    let max_width = line_infos.iter().fold(0.0, |max, line_info| f32::max(max, line_info.width));
+    Size::new(max_width, line_infos.len() as f32 * line_height)
 }
 
 pub fn get_text_height(text: &str,
-                        font: &Font,
+                        fonts: FontFallback,
                         font_size: f32,
                         line_height: f32,
                         wrap: Wrap,
                         width: f32)
                         -> f32 {
-    let line_infos = LineInfos::new(text, font, font_size, wrap, width);
+    let line_infos = LineInfos::new(text, fonts, font_size, wrap, width);
     line_infos.count() as f32 * line_height
 }
 
 pub fn get_line_rects(text: &str,
                       rect: Rect,
-                      font: &Font,
+                      fonts: FontFallback,
                       font_size: f32,
                       line_height: f32,
                       line_wrap: Wrap,
                       align: Align)
                       -> Vec<Rect> {
 
-    let line_infos: Vec<LineInfo> = LineInfos::new(text, font, font_size, line_wrap, rect.width())
+    let line_infos: Vec<LineInfo> = LineInfos::new(text, fonts, font_size, line_wrap, rect.width())
         .collect();
     let line_infos = line_infos.iter().cloned();
     let line_rects = LineRects::new(line_infos, font_size, rect, align, line_height);
     line_rects.collect()
 }
 
+/// Position the glyphs of `text` within `rect`, each along with the index of the font in
+/// `fonts` it's from, so consecutive glyphs from the same font can be drawn as a run.
 pub fn get_positioned_glyphs(text: &str,
                              rect: Rect,
-                             font: &Font,
+                             fonts: FontFallback,
                              font_size: f32,
                              line_height: f32,
                              line_wrap: Wrap,
                              align: Align)
-                             -> Vec<PositionedGlyph>
+                             -> Vec<(usize, PositionedGlyph)>
 {
-    let line_infos: Vec<LineInfo> = LineInfos::new(text, font, font_size, line_wrap, rect.width())
+    let line_infos: Vec<LineInfo> = LineInfos::new(text, fonts, font_size, line_wrap, rect.width())
         .collect();
     let line_infos = line_infos.iter().cloned();
-    let line_texts = line_infos.clone().map(|info| &text[info.byte_range()]);
-    let line_rects = LineRects::new(line_infos, font_size, rect, align, line_height);
+    let line_rects = LineRects::new(line_infos.clone(), font_size, rect, align, line_height);
     let scale = Scale::uniform(font_size);
 
     let mut positioned_glyphs = Vec::new();
-    for (line_text, line_rect) in line_texts.zip(line_rects) {
-        // point specifies bottom left corner of text line
-        let point = rusttype::Point {
-            x: line_rect.left(),
-            y: line_rect.top() + font_size,
-        };
-
-        positioned_glyphs.extend(font.glyphs_for(line_text.chars())
-            .scan((None, 0.0), |state, g| {
-                let &mut (last, x) = state;
-                let g = g.scaled(scale);
-
-                let kern = last.map(|last| font.pair_kerning(scale, last, g.id())).unwrap_or(0.0);
-                let width = g.h_metrics().advance_width;
-
-                let next = g.positioned(point + rusttype::vector(x, 0.0));
-                *state = (Some(next.id()), x + width + kern);
-                Some(next.standalone())
-            }));
+    for (line_info, line_rect) in line_infos.zip(line_rects) {
+        // glyphs are positioned relative to the bottom left corner of the text line
+        let (left, baseline) = (line_rect.left(), line_rect.top() + font_size);
+        positioned_glyphs.extend(line_info.glyphs.iter().map(|glyph| {
+            let positioned = fonts.font(glyph.font)
+                .glyph(glyph.id)
+                .unwrap()
+                .scaled(scale)
+                .positioned(point(left + glyph.x, baseline + glyph.y));
+            (glyph.font, positioned.standalone())
+        }));
     }
     positioned_glyphs
 }
@@ -1,17 +1,14 @@
-
 /// Text handling logic related to individual lines of text.
 ///
 /// This module is the core of multi-line text handling.
-use rusttype;
-use super::Font;
-use rusttype::Scale;
+use super::FontFallback;
 use types::{Range, Align, Rect, RectExt};
 use std;
-use rusttype::GlyphId;
-use std::str::CharIndices;
-use std::iter::Peekable;
 use super::Wrap;
 use super::glyph::SelectedGlyphRectsPerLine;
+use super::rich::Span;
+use super::shape::{self, Cluster, Measure, ShapedGlyph};
+use unicode_linebreak::linebreaks;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 enum BreakType {
@@ -21,9 +18,10 @@ enum BreakType {
         /// character to use as the beginning of the next line.
         len_bytes: usize,
     },
-    /// A break caused by a newline character.
+    /// A break caused by a newline character, or another char that always breaks a line, like
+    /// a carriage return or U+2028 LINE SEPARATOR.
     Newline {
-        /// The width of the "newline" token in bytes.
+        /// The width of the "newline" token in bytes, 2 for a carriage return followed by a newline.
         len_bytes: usize,
     },
     End,
@@ -51,7 +49,7 @@ impl Break {
 /// `Info` is a minimal amount of information that can be stored for efficient reasoning about
 /// blocks of text given some `&str`. The `start` and `end_break` can be used for indexing into
 /// the `&str`, and the `width` can be used for calculating line `Rect`s, alignment, etc.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct LineInfo {
     /// The index into the `&str` that represents the first character within the line.
     pub start_byte: usize,
@@ -63,6 +61,14 @@ pub struct LineInfo {
     pub end_break: Break,
     /// The total width of all characters within the line.
     pub width: f32,
+    /// The clusters of shaped chars on the line, in logical order, the cursor is placed between
+    /// them. Their `x` positions are in visual order, relative to the left of the line.
+    pub clusters: Vec<Cluster>,
+    /// The glyphs of the line's clusters, taken from the shaped paragraph the line is in, with
+    /// their `x` positions relative to the left of the line.
+    pub glyphs: Vec<ShapedGlyph>,
+    /// The base bidi embedding level of the paragraph the line is in, odd if it's right to left.
+    pub level: u8,
 }
 
 impl LineInfo {
@@ -77,12 +83,12 @@ impl LineInfo {
     }
 
     /// The index range for indexing (via bytes) into the original str slice.
-    pub fn byte_range(self) -> std::ops::Range<usize> {
+    pub fn byte_range(&self) -> std::ops::Range<usize> {
         self.start_byte..self.end_byte()
     }
 
     /// The index range for indexing into a `char` iterator over the original str slice.
-    pub fn char_range(self) -> std::ops::Range<usize> {
+    pub fn char_range(&self) -> std::ops::Range<usize> {
         self.start_char..self.end_char()
     }
 }
@@ -95,11 +101,10 @@ impl LineInfo {
 /// Construct an `Infos` iterator via the [`infos` function](./fn.infos.html) and its two builder
 /// methods, [`wrap_by_character`](./struct.Infos.html#method.wrap_by_character) and
 /// [`wrap_by_whitespace`](./struct.Infos.html#method.wrap_by_whitespace).
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct LineInfos<'a> {
     text: &'a str,
-    font: &'a Font,
-    font_size: f32,
+    measure: Measure<'a>,
     max_width: f32,
     line_wrap: Wrap,
     /// The index that indicates the start of the next line to be yielded.
@@ -108,24 +113,108 @@ pub struct LineInfos<'a> {
     start_char: usize,
     /// The break type of the previously yielded line
     last_break: Option<Break>,
+    /// The shaped paragraph the next line is in
+    paragraph: Option<Paragraph>,
+    /// The index of the first cluster of the next line in the paragraph.
+    cluster: usize,
 }
 
 impl<'a> LineInfos<'a> {
     pub fn new(text: &'a str,
-               font: &'a Font,
+               fonts: FontFallback<'a>,
                font_size: f32,
                line_wrap: Wrap,
                max_width: f32)
                -> Self {
+        LineInfos::with_measure(text, Measure::Uniform(fonts, font_size), line_wrap, max_width)
+    }
+
+    /// Lines of text made up of `spans`, each shaped with the fonts and size of the span it's in.
+    pub fn from_spans(text: &'a str,
+                      spans: &'a [Span<'a>],
+                      line_wrap: Wrap,
+                      max_width: f32)
+                      -> Self {
+        LineInfos::with_measure(text, Measure::Spans(spans), line_wrap, max_width)
+    }
+
+    fn with_measure(text: &'a str, measure: Measure<'a>, line_wrap: Wrap, max_width: f32) -> Self {
         LineInfos {
             text: text,
-            font: font,
-            font_size: font_size,
+            measure: measure,
             max_width: max_width,
             line_wrap: line_wrap,
             start_byte: 0,
             start_char: 0,
             last_break: None,
+            paragraph: None,
+            cluster: 0,
+        }
+    }
+}
+
+/// The text up to a mandatory line break, or the end of the text, shaped as a whole so it's only shaped once
+/// however many lines it's wrapped into.
+#[derive(Clone)]
+struct Paragraph {
+    clusters: Vec<Cluster>,
+    /// The glyphs of the paragraph in the logical order of their clusters, lines take the glyphs
+    /// of their clusters rather than being shaped again, so they're positioned the same way their
+    /// widths were measured.
+    glyphs: Vec<ShapedGlyph>,
+    /// The index of the first glyph of each cluster, followed by the number of glyphs, the glyphs
+    /// of the cluster at `i` are `glyphs[glyph_starts[i]..glyph_starts[i + 1]]`.
+    glyph_starts: Vec<usize>,
+    /// The base bidi embedding level of the paragraph.
+    level: u8,
+    /// The byte index of the line break ending the paragraph, or the end of the text.
+    end: usize,
+    /// The length of the line break ending the paragraph in bytes, 0 at the end of the text.
+    newline_len: usize,
+    /// The byte indices lines can start at, when wrapping with the Unicode line breaking
+    /// algorithm.
+    breaks: Vec<usize>,
+}
+
+impl Paragraph {
+    fn shape(text: &str, start_byte: usize, start_char: usize, measure: Measure, line_wrap: Wrap) -> Self {
+        let (end, newline_len) = match text[start_byte..].char_indices().find(|&(_, ch)| is_mandatory_break(ch)) {
+            Some((newline, ch)) => {
+                let newline = start_byte + newline;
+                if ch == '\r' && text[newline + 1..].starts_with('\n') {
+                    (newline, 2)
+                } else {
+                    (newline, ch.len_utf8())
+                }
+            }
+            None => (text.len(), 0),
+        };
+        let mut shaped = shape::shape(text, start_byte..end, start_char, measure);
+        // right to left runs are shaped into glyphs in visual order, sorting them by cluster keeps
+        // the order of the glyphs within a cluster, and makes the glyphs of each line contiguous
+        shaped.glyphs.sort_by_key(|glyph| glyph.cluster);
+        let mut glyph_starts = Vec::with_capacity(shaped.clusters.len() + 1);
+        let mut glyph = 0;
+        for cluster in &shaped.clusters {
+            while glyph < shaped.glyphs.len() && shaped.glyphs[glyph].cluster < cluster.byte {
+                glyph += 1;
+            }
+            glyph_starts.push(glyph);
+        }
+        glyph_starts.push(shaped.glyphs.len());
+        let breaks = if line_wrap == Wrap::Unicode {
+            linebreaks(&text[start_byte..end]).map(|(byte, _)| start_byte + byte).collect()
+        } else {
+            Vec::new()
+        };
+        Paragraph {
+            clusters: shaped.clusters,
+            glyphs: shaped.glyphs,
+            glyph_starts: glyph_starts,
+            level: shaped.level,
+            end: end,
+            newline_len: newline_len,
+            breaks: breaks,
         }
     }
 }
@@ -133,76 +222,100 @@ impl<'a> LineInfos<'a> {
 impl<'a> Iterator for LineInfos<'a> {
     type Item = LineInfo;
     fn next(&mut self) -> Option<Self::Item> {
-        let LineInfos { text,
-                        font,
-                        font_size,
-                        max_width,
-                        line_wrap,
-                        ref mut start_byte,
-                        ref mut start_char,
-                        ref mut last_break } = *self;
-
-        let text_line = &text[*start_byte..];
-        let (next, width) = match line_wrap {
-            Wrap::NoWrap => next_break(text_line, font, font_size),
-            Wrap::Character => next_break_by_character(text_line, font, font_size, max_width),
-            Wrap::Whitespace => next_break_by_whitespace(text_line, font, font_size, max_width),
+        if let Some(Break { break_type: BreakType::End, .. }) = self.last_break {
+            return None;
+        }
+        let shape_paragraph = match self.paragraph {
+            Some(ref paragraph) => self.start_byte > paragraph.end,
+            None => true,
         };
-        match next.break_type {
-            BreakType::Newline { len_bytes } |
-            BreakType::Wrap { len_bytes } => {
-                if next.byte == 0 && len_bytes == 0 {
-                    None
-                } else {
-                    let next_break = Break::new(*start_byte + next.byte,
-                                                *start_char + next.char,
-                                                next.break_type);
-                    let info = LineInfo {
-                        start_byte: *start_byte,
-                        start_char: *start_char,
-                        end_break: next_break,
-                        width: width,
-                    };
-                    *start_byte = info.start_byte + next.byte + len_bytes;
-                    *start_char = info.start_char + next.char + 1;
-                    *last_break = Some(next_break);
-                    Some(info)
+        if shape_paragraph {
+            self.paragraph = Some(Paragraph::shape(self.text, self.start_byte, self.start_char, self.measure, self.line_wrap));
+            self.cluster = 0;
+        }
+        let start_byte = self.start_byte;
+        let start_char = self.start_char;
+        let first = self.cluster;
+        let (info, next_byte, next_char, next_cluster) = {
+            let paragraph = self.paragraph.as_ref().unwrap();
+            let clusters = &paragraph.clusters[first..];
+            let wrap = match self.line_wrap {
+                Wrap::NoWrap => None,
+                Wrap::Character => break_by_character(clusters, self.max_width).map(|count| (count, 0)),
+                Wrap::Whitespace => break_by_whitespace(clusters, self.max_width),
+                Wrap::Unicode => break_by_line_break(clusters, self.max_width, &paragraph.breaks),
+            };
+            match wrap {
+                // a wrap that skips the last clusters of the paragraph leaves them hanging at the
+                // end of the line, rather than starting an empty line
+                Some((count, skip)) if count + skip < clusters.len() => {
+                    let end = clusters[count];
+                    let next = clusters[count + skip];
+                    let break_type = BreakType::Wrap { len_bytes: next.byte - end.byte };
+                    let info = line_info(start_byte, start_char, Break::new(end.byte, end.char, break_type), first..first + count, paragraph);
+                    (info, next.byte, next.char, first + count + skip)
                 }
-            }
-            BreakType::End => {
-                let char = next.char;
-                // if the last line ends in a new line, or the entire text is empty,
-                // return an empty line Info
-                let empty_line = {
-                    match *last_break {
-                        Some(last_break_) => {
-                            match last_break_.break_type {
-                                BreakType::Newline { .. } => true,
-                                _ => false,
-                            }
-                        }
-                        None => true,
-                    }
-                };
-                if *start_byte < text.len() || empty_line {
-                    let total_bytes = text.len();
-                    let total_chars = *start_char + char;
-                    let end_break = Break::new(total_bytes, total_chars, BreakType::End);
-                    let info = LineInfo {
-                        start_byte: *start_byte,
-                        start_char: *start_char,
-                        end_break: end_break,
-                        width: width,
+                wrap => {
+                    let end_char = clusters.last().map_or(start_char, |cluster| cluster.char + cluster.len_chars);
+                    let mut info = if paragraph.newline_len > 0 {
+                        let break_type = BreakType::Newline { len_bytes: paragraph.newline_len };
+                        line_info(start_byte, start_char, Break::new(paragraph.end, end_char, break_type), first..paragraph.clusters.len(), paragraph)
+                    } else {
+                        line_info(start_byte, start_char, Break::new(paragraph.end, end_char, BreakType::End), first..paragraph.clusters.len(), paragraph)
                     };
-                    *start_byte = total_bytes;
-                    *start_char = total_chars;
-                    *last_break = Some(end_break);
-                    Some(info)
-                } else {
-                    None
+                    if let Some((count, _)) = wrap {
+                        info.width = clusters[..count].iter().map(|cluster| cluster.width).sum();
+                    }
+                    let newline = &self.text[paragraph.end..paragraph.end + paragraph.newline_len];
+                    (info, paragraph.end + paragraph.newline_len, end_char + newline.chars().count(), paragraph.clusters.len())
                 }
             }
-        }
+        };
+        self.start_byte = next_byte;
+        self.start_char = next_char;
+        self.cluster = next_cluster;
+        self.last_break = Some(info.end_break);
+        Some(info)
+    }
+}
+
+/// True if a line always ends after `ch`, the chars of the mandatory break class of the Unicode
+/// line breaking algorithm, along with carriage returns, newlines and U+0085 NEXT LINE.
+pub fn is_mandatory_break(ch: char) -> bool {
+    match ch {
+        '\n' | '\r' | '\u{b}' | '\u{c}' | '\u{85}' | '\u{2028}' | '\u{2029}' => true,
+        _ => false,
+    }
+}
+
+/// A line made up of the `clusters` at a range of indices in `paragraph`, reordered for display at the paragraph's bidi
+/// embedding level, with their positions, and the positions of their glyphs, relative to the
+/// left of the line.
+fn line_info(start_byte: usize, start_char: usize, end_break: Break, clusters: std::ops::Range<usize>, paragraph: &Paragraph) -> LineInfo {
+    let first = clusters.start;
+    let clusters = &paragraph.clusters[clusters];
+    let mut line_clusters = clusters.to_vec();
+    shape::reorder(&mut line_clusters, paragraph.level);
+    let glyph_starts = &paragraph.glyph_starts[first..first + clusters.len() + 1];
+    let mut glyphs = Vec::with_capacity(glyph_starts[clusters.len()] - glyph_starts[0]);
+    for (i, cluster) in clusters.iter().enumerate() {
+        // the glyphs move along with their cluster
+        let offset = line_clusters[i].x - cluster.x;
+        glyphs.extend(paragraph.glyphs[glyph_starts[i]..glyph_starts[i + 1]].iter().map(|glyph| {
+            ShapedGlyph {
+                x: glyph.x + offset,
+                ..*glyph
+            }
+        }));
+    }
+    LineInfo {
+        start_byte: start_byte,
+        start_char: start_char,
+        end_break: end_break,
+        width: line_clusters.iter().map(|cluster| cluster.width).sum(),
+        clusters: line_clusters,
+        glyphs: glyphs,
+        level: paragraph.level,
     }
 }
 
@@ -286,15 +399,18 @@ impl<I> Iterator for LineRects<I>
 
 /// An iterator yielding a `Rect` for each selected line in a block of text.
 ///
-/// The yielded `Rect`s represent the selected range within each line of text.
+/// The yielded `Rect`s represent the selected range within each line of text. In mixed left to
+/// right and right to left text, a selected range can be split into several `Rect`s on a line.
 ///
 /// Lines that do not contain any selected text will be skipped.
-pub struct SelectedLineRects<'a, I> {
-    selected_glyph_rects_per_line: SelectedGlyphRectsPerLine<'a, I>,
+pub struct SelectedLineRects<I> {
+    selected_glyph_rects_per_line: SelectedGlyphRectsPerLine<I>,
+    /// The rects of the line being yielded.
+    line_rects: std::vec::IntoIter<Rect>,
 }
 
-impl<'a, I> SelectedLineRects<'a, I>
-    where I: Iterator<Item = (&'a str, Rect)>
+impl<I> SelectedLineRects<I>
+    where I: Iterator<Item = (LineInfo, Rect)>
 {
     /// Produces an iterator yielding a `Rect` for the selected range in each
     /// selected line in a block of text.
@@ -303,212 +419,298 @@ impl<'a, I> SelectedLineRects<'a, I>
     ///
     /// Lines that do not contain any selected text will be skipped.
     pub fn new(lines_with_rects: I,
-               font: &'a Font,
-               font_size: f32,
                start: super::cursor::Index,
                end: super::cursor::Index)
-               -> SelectedLineRects<'a, I> {
+               -> SelectedLineRects<I> {
         SelectedLineRects {
             selected_glyph_rects_per_line: SelectedGlyphRectsPerLine::new(lines_with_rects,
-                                                                          font,
-                                                                          font_size,
                                                                           start,
                                                                           end),
+            line_rects: Vec::new().into_iter(),
         }
     }
 }
-impl<'a, I> Iterator for SelectedLineRects<'a, I>
-    where I: Iterator<Item = (&'a str, Rect)>
+impl<I> Iterator for SelectedLineRects<I>
+    where I: Iterator<Item = (LineInfo, Rect)>
 {
     type Item = Rect;
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(mut rects) = self.selected_glyph_rects_per_line.next() {
-            if let Some(first_rect) = rects.next() {
-                let total_selected_rect = rects.fold(first_rect, |mut total, next| {
-                    // TODO ?
-                    total.size.width = next.width();
-                    total
-                });
-                return Some(total_selected_rect);
+        loop {
+            if let Some(rect) = self.line_rects.next() {
+                return Some(rect);
+            }
+            match self.selected_glyph_rects_per_line.next() {
+                Some(rects) => self.line_rects = join_rects(rects.collect()).into_iter(),
+                None => return None,
             }
         }
-        None
     }
 }
 
-/// A function for finding the advance width between the given character that also considers
-/// the kerning for some previous glyph.
-///
-/// This also updates the `last_glyph` with the glyph produced for the given `char`.
-///
-/// This is primarily for use within the `next_break` functions below.
-///
-/// The following code is adapted from the `rusttype::LayoutIter::next` src.
-fn advance_width(ch: char, font: &Font, scale: Scale, last_glyph: &mut Option<GlyphId>) -> f32 {
-    let g = font.glyph(ch).unwrap().scaled(scale);
-    let kern = last_glyph.map(|last| font.pair_kerning(scale, last, g.id()))
-        .unwrap_or(0.0);
-    let advance_width = g.h_metrics().advance_width;
-    *last_glyph = Some(g.id());
-    (kern + advance_width)
+/// Join the rects of clusters on a line that are next to each other, from left to right.
+fn join_rects(mut rects: Vec<Rect>) -> Vec<Rect> {
+    rects.sort_by(|a, b| a.left().partial_cmp(&b.left()).unwrap_or(std::cmp::Ordering::Equal));
+    let mut joined: Vec<Rect> = Vec::new();
+    for rect in rects {
+        if let Some(last) = joined.last_mut() {
+            // allow for rounding between the positions of adjacent clusters
+            if rect.left() - last.right() < 0.01 {
+                last.size.width = f32::max(last.right(), rect.right()) - last.left();
+                continue;
+            }
+        }
+        joined.push(rect);
+    }
+    joined
 }
 
-fn peek_next_char(char_indices: &mut Peekable<CharIndices>, next_char_expected: char) -> bool {
-    if let Some(&(_, next_char)) = char_indices.peek() {
-        next_char == next_char_expected
-    } else {
-        false
+/// The number of clusters that fit on a line, if they don't all fit, wrapping at the first
+/// cluster exceeding `max_width`. There's always at least one cluster on a line.
+fn break_by_character(clusters: &[Cluster], max_width: f32) -> Option<usize> {
+    let mut width = 0.0;
+    for (i, cluster) in clusters.iter().enumerate() {
+        width += cluster.width;
+        if width > max_width {
+            return Some(std::cmp::max(i, 1));
+        }
     }
+    None
 }
 
-/// Returns the next index at which the text naturally breaks via a newline character,
-/// along with the width of the line.
-fn next_break(text: &str, font: &Font, font_size: f32) -> (Break, f32) {
-    let scale = super::pt_to_scale(font_size);
+/// The number of clusters that fit on a line, along with the number of whitespace clusters
+/// to skip before the next line, if they don't all fit. Wraps at the start of the whitespace
+/// that preceeds the first word exceeding the `max_width`, or at the first cluster exceeding
+/// the `max_width` if no whitespace appears before it.
+fn break_by_whitespace(clusters: &[Cluster], max_width: f32) -> Option<(usize, usize)> {
     let mut width = 0.0;
-    let mut char_i = 0;
-    let mut char_indices = text.char_indices().peekable();
-    let mut last_glyph = None;
-    while let Some((byte_i, ch)) = char_indices.next() {
-        // Check for a newline.
-        if ch == '\r' && peek_next_char(&mut char_indices, '\n') {
-            let break_ = Break::new(byte_i, char_i, BreakType::Newline { len_bytes: 2 });
-            return (break_, width);
-        } else if ch == '\n' {
-            let break_ = Break::new(byte_i, char_i, BreakType::Newline { len_bytes: 1 });
-            return (break_, width);
+    let mut last_whitespace = None;
+    for (i, cluster) in clusters.iter().enumerate() {
+        width += cluster.width;
+        if width > max_width {
+            if cluster.whitespace {
+                return Some((i, 1));
+            }
+            return match last_whitespace {
+                Some(whitespace) => Some((whitespace, 1)),
+                None => break_by_character(clusters, max_width).map(|count| (count, 0)),
+            };
+        }
+        if cluster.whitespace {
+            last_whitespace = Some(i);
         }
-
-        // Update the width.
-        width += advance_width(ch, font, scale, &mut last_glyph);
-        char_i += 1;
     }
-    let break_ = Break::new(text.len(), char_i, BreakType::End);
-    (break_, width)
+    None
 }
-/// Returns the next index at which the text will break by either:
-/// - A newline character.
-/// - A line wrap at the beginning of the first character exceeding the `max_width`.
-///
-/// Also returns the width of each line alongside the Break.
-fn next_break_by_character(text: &str,
-                           font: &Font,
-                           font_size: f32,
-                           max_width: f32)
-                           -> (Break, f32) {
-    let scale = super::pt_to_scale(font_size);
+
+/// The number of clusters that fit on a line, along with the number of whitespace clusters
+/// to skip before the next line, if they don't all fit. Wraps at the last of the `breaks`,
+/// the byte indices lines can start at, before the first cluster exceeding the `max_width`, or
+/// at that cluster if there's no break before it. Whitespace at the end of a line doesn't count
+/// towards it's width.
+fn break_by_line_break(clusters: &[Cluster], max_width: f32, breaks: &[usize]) -> Option<(usize, usize)> {
     let mut width = 0.0;
-    let mut char_i = 0;
-    let mut char_indices = text.char_indices().peekable();
-    let mut last_glyph = None;
-    while let Some((byte_i, ch)) = char_indices.next() {
-        // Check for a newline.
-        if ch == '\r' && peek_next_char(&mut char_indices, '\n') {
-            let break_ = Break::new(byte_i, char_i, BreakType::Newline { len_bytes: 2 });
-            return (break_, width);
-        } else if ch == '\n' {
-            let break_ = Break::new(byte_i, char_i, BreakType::Newline { len_bytes: 1 });
-            return (break_, width);
+    let mut last_break = None;
+    for (i, cluster) in clusters.iter().enumerate() {
+        if i > 0 && breaks.binary_search(&cluster.byte).is_ok() {
+            last_break = Some(i);
         }
+        width += cluster.width;
+        if width > max_width && !cluster.whitespace {
+            return match last_break {
+                Some(line_break) => {
+                    // whitespace before the break is skipped, unless the line is only whitespace
+                    let skip = clusters[..line_break].iter().rev().take_while(|cluster| cluster.whitespace).count();
+                    let skip = std::cmp::min(skip, line_break - 1);
+                    Some((line_break - skip, skip))
+                }
+                None => break_by_character(clusters, max_width).map(|count| (count, 0)),
+            };
+        }
+    }
+    None
+}
 
-        // Add the character's width to the width so far.
-        let new_width = width + advance_width(ch, font, scale, &mut last_glyph);
+/// Produce the width of the given line of text including spaces (i.e. ' ').
+pub fn width(text: &str, fonts: FontFallback, font_size: f32) -> f32 {
+    shape::shape_text(text, fonts, font_size).width
+}
 
-        // Check for a line wrap.
-        if new_width > max_width {
-            let break_ = Break::new(byte_i, char_i, BreakType::Wrap { len_bytes: 0 });
-            return (break_, width);
-        }
+#[cfg(test)]
+mod tests {
+    use std::f32;
+    use super::*;
+    use fallback::LoadedFont;
+    use shape::shape_text;
 
-        width = new_width;
-        char_i += 1;
+    const FONT_SIZE: f32 = 20.0;
+
+    fn font() -> LoadedFont {
+        LoadedFont::new(include_bytes!("../../assets/fonts/NotoSans/NotoSans-Regular.ttf").to_vec()).unwrap()
     }
 
-    let break_ = Break::new(text.len(), char_i, BreakType::End);
-    (break_, width)
-}
+    fn text_width(text: &str) -> f32 {
+        let font = font();
+        let fonts = [font.layout_font()];
+        width(text, FontFallback::new(&fonts), FONT_SIZE)
+    }
 
-/// Returns the next index at which the text will break by either:
-/// - A newline character.
-/// - A line wrap at the beginning of the whitespace that preceeds the first word
-/// exceeding the `max_width`.
-/// - A line wrap at the beginning of the first character exceeding the `max_width`,
-/// if no whitespace appears for `max_width` characters.
-///
-/// Also returns the width the line alongside the Break.
-fn next_break_by_whitespace(text: &str,
-                            font: &Font,
-                            font_size: f32,
-                            max_width: f32)
-                            -> (Break, f32) {
-    struct Last {
-        byte: usize,
-        char: usize,
-        width_before: f32,
-    }
-    let scale = super::pt_to_scale(font_size);
-    let mut last_whitespace_start = None;
-    let mut width = 0.0;
-    let mut char_i = 0;
-    let mut char_indices = text.char_indices().peekable();
-    let mut last_glyph = None;
-    while let Some((byte_i, ch)) = char_indices.next() {
-
-        // Check for a newline.
-        if ch == '\r' && peek_next_char(&mut char_indices, '\n') {
-            let break_ = Break::new(byte_i, char_i, BreakType::Newline { len_bytes: 2 });
-            return (break_, width);
-        } else if ch == '\n' {
-            let break_ = Break::new(byte_i, char_i, BreakType::Newline { len_bytes: 1 });
-            return (break_, width);
+    fn line_infos(text: &str, wrap: Wrap, max_width: f32) -> Vec<LineInfo> {
+        let font = font();
+        let fonts = [font.layout_font()];
+        LineInfos::new(text, FontFallback::new(&fonts), FONT_SIZE, wrap, max_width).collect()
+    }
+
+    fn lines(text: &str, wrap: Wrap, max_width: f32) -> Vec<&str> {
+        line_infos(text, wrap, max_width).iter().map(|info| &text[info.byte_range()]).collect()
+    }
+
+    #[test]
+    fn clusters() {
+        // a combining acute accent is shaped with the char before it
+        let text = "a\u{301}b c";
+        let font = font();
+        let fonts = [font.layout_font()];
+        let shaped = shape_text(text, FontFallback::new(&fonts), FONT_SIZE);
+        let ranges: Vec<_> = shaped.clusters.iter().map(|cluster| (cluster.byte_range(), cluster.char_range())).collect();
+        assert_eq!(ranges, vec![(0..3, 0..2), (3..4, 2..3), (4..5, 3..4), (5..6, 4..5)]);
+        let whitespace: Vec<bool> = shaped.clusters.iter().map(|cluster| cluster.whitespace).collect();
+        assert_eq!(whitespace, vec![false, false, true, false]);
+        let mut x = 0.0;
+        for cluster in &shaped.clusters {
+            assert!(!cluster.rtl());
+            assert_eq!(cluster.x, x);
+            assert!(cluster.width > 0.0);
+            x += cluster.width;
         }
+        assert_eq!(shaped.width, x);
+    }
 
-        // Add the character's width to the width so far.
-        let new_width = width + advance_width(ch, font, scale, &mut last_glyph);
+    #[test]
+    fn line_glyphs_from_paragraph() {
+        let text = "aaa bbb";
+        let font = font();
+        let fonts = [font.layout_font()];
+        let shaped = shape_text(text, FontFallback::new(&fonts), FONT_SIZE);
+        let infos = line_infos(text, Wrap::Whitespace, f32::MAX);
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].glyphs, shaped.glyphs);
+        assert_eq!(infos[0].width, shaped.width);
+
+        // the glyphs of a wrapped line are positioned from the left of the line
+        let infos = line_infos(text, Wrap::Whitespace, text_width("aaa b"));
+        assert_eq!(infos.len(), 2);
+        let second = &infos[1];
+        assert_eq!(second.glyphs.len(), 3);
+        assert!(second.glyphs.iter().all(|glyph| glyph.cluster >= 4));
+        assert_eq!(second.glyphs[0].x, 0.0);
+        let advance: f32 = second.glyphs.iter().map(|glyph| glyph.advance).sum();
+        assert_eq!(second.width, advance);
+    }
 
-        // Check for a line wrap.
-        if new_width > max_width {
-            match last_whitespace_start {
-                Some(Last { byte, char, width_before }) => {
-                    let break_ = Break::new(byte, char, BreakType::Wrap { len_bytes: 1 });
-                    return (break_, width_before);
-                }
-                None => {
-                    let break_ = Break::new(byte_i, char_i, BreakType::Wrap { len_bytes: 0 });
-                    return (break_, width);
-                }
+    #[test]
+    fn glyphs_of_each_line() {
+        let text = "ab ab ab\nab ab";
+        let infos = line_infos(text, Wrap::Whitespace, text_width("ab a"));
+        assert_eq!(infos.len(), 5);
+        for info in &infos {
+            assert_eq!(info.glyphs.len(), info.clusters.len());
+            let line = info.start_byte..info.start_byte + text[info.byte_range()].trim_right().len();
+            for (glyph, cluster) in info.glyphs.iter().zip(&info.clusters) {
+                assert_eq!(glyph.cluster, cluster.byte);
+                assert!(glyph.cluster >= line.start && glyph.cluster < line.end);
             }
         }
+    }
 
-        // Check for a new whitespace.
-        if ch.is_whitespace() {
-            last_whitespace_start = Some(Last {
-                byte: byte_i,
-                char: char_i,
-                width_before: width,
-            });
-        }
+    #[test]
+    fn no_wrap() {
+        let text = "aaa bbb\nccc";
+        assert_eq!(lines(text, Wrap::NoWrap, 1.0), vec!["aaa bbb", "ccc"]);
+        assert_eq!(lines("", Wrap::NoWrap, 1.0), vec![""]);
+    }
 
-        width = new_width;
-        char_i += 1;
+    #[test]
+    fn wrap_by_character() {
+        let text = "abcdef";
+        assert_eq!(lines(text, Wrap::Character, text_width("abc") + 0.5), vec!["abc", "def"]);
+        // there's always at least one cluster on a line
+        assert_eq!(lines("ab", Wrap::Character, 1.0), vec!["a", "b"]);
     }
 
-    let break_ = Break::new(text.len(), char_i, BreakType::End);
-    (break_, width)
-}
+    #[test]
+    fn wrap_by_whitespace() {
+        let text = "aaa bbb ccc";
+        let infos = line_infos(text, Wrap::Whitespace, text_width("aaa bbb") + 0.5);
+        let lines: Vec<&str> = infos.iter().map(|info| &text[info.byte_range()]).collect();
+        assert_eq!(lines, vec!["aaa bbb", "ccc"]);
+        assert_eq!(infos[0].end_break.break_type, BreakType::Wrap { len_bytes: 1 });
+        assert_eq!(infos[1].start_char, 8);
+        assert_eq!(infos[1].end_break.break_type, BreakType::End);
+        // a word too long for a line by itself is wrapped by character
+        assert_eq!(lines("aaaaaa", Wrap::Whitespace, text_width("aaa") + 0.5), vec!["aaa", "aaa"]);
+    }
 
-/// Produce the width of the given line of text including spaces (i.e. ' ').
-pub fn width(text: &str, font: &Font, font_size: f32) -> f32 {
-    let scale = Scale::uniform(font_size);
-    let point = rusttype::Point { x: 0.0, y: 0.0 };
-
-    let mut total_w = 0.0;
-    for g in font.layout(text, scale, point) {
-        match g.pixel_bounding_box() {
-            Some(bb) => total_w = bb.max.x as f32,
-            None => total_w += g.unpositioned().h_metrics().advance_width,
+    #[test]
+    fn wrap_by_line_break() {
+        // lines can break after a hyphen, as well as at whitespace
+        let text = "aaa-bbb ccc";
+        assert_eq!(lines(text, Wrap::Unicode, text_width("aaa-bb")), vec!["aaa-", "bbb", "ccc"]);
+        assert_eq!(lines(text, Wrap::Whitespace, text_width("aaa-bb")), vec!["aaa-bb", "b ccc"]);
+    }
+
+    #[test]
+    fn mandatory_breaks() {
+        let text = "a\u{2028}b\u{85}c\rd\r\ne";
+        for &wrap in &[Wrap::NoWrap, Wrap::Whitespace, Wrap::Unicode] {
+            assert_eq!(lines(text, wrap, f32::MAX), vec!["a", "b", "c", "d", "e"]);
         }
+        let infos = line_infos(text, Wrap::Unicode, f32::MAX);
+        let breaks: Vec<BreakType> = infos.iter().map(|info| info.end_break.break_type).collect();
+        assert_eq!(breaks, vec![
+            BreakType::Newline { len_bytes: 3 },
+            BreakType::Newline { len_bytes: 2 },
+            BreakType::Newline { len_bytes: 1 },
+            BreakType::Newline { len_bytes: 2 },
+            BreakType::End,
+        ]);
+        let starts: Vec<usize> = infos.iter().map(|info| info.start_char).collect();
+        assert_eq!(starts, vec![0, 2, 4, 6, 9]);
+        assert_eq!(lines("a\r\rb", Wrap::NoWrap, f32::MAX), vec!["a", "", "b"]);
     }
 
-    total_w
+    #[test]
+    fn wrap_cjk() {
+        // the font has no CJK glyphs, they're shaped with it's missing glyph, so they're all
+        // the same width
+        let text = "\u{4e2d}\u{6587}\u{4e2d}\u{6587}\u{4e2d}\u{6587}";
+        let max_width = text_width("\u{4e2d}\u{6587}\u{4e2d}") + 0.5;
+        assert_eq!(lines(text, Wrap::Unicode, max_width), vec!["\u{4e2d}\u{6587}\u{4e2d}", "\u{6587}\u{4e2d}\u{6587}"]);
+        // an ideographic full stop isn't put at the start of a line
+        let text = "\u{4e2d}\u{6587}\u{3002}\u{4e2d}\u{6587}";
+        let max_width = text_width("\u{4e2d}\u{6587}") + 0.5;
+        assert_eq!(lines(text, Wrap::Unicode, max_width), vec!["\u{4e2d}", "\u{6587}\u{3002}", "\u{4e2d}\u{6587}"]);
+    }
+
+    #[test]
+    fn wrap_long_url() {
+        let url = "https://example.com/a/very/long/path/to/somewhere";
+        let text = format!("see {}", url);
+        let max_width = text_width(url) / 3.0;
+        let infos = line_infos(&text, Wrap::Unicode, max_width);
+        // the url starts a new line, and is split into lines that fit
+        assert_eq!(&text[infos[0].byte_range()], "see");
+        assert!(infos.len() > 3);
+        let url_lines: Vec<&str> = infos[1..].iter().map(|info| &text[info.byte_range()]).collect();
+        assert_eq!(url_lines.concat(), url);
+        for info in &infos {
+            assert!(info.width <= max_width);
+        }
+    }
+
+    #[test]
+    fn wrap_by_line_break_falls_back_to_character() {
+        let max_width = text_width("aaa") + 0.5;
+        assert_eq!(lines("aaaaaaaa", Wrap::Unicode, max_width), vec!["aaa", "aaa", "aa"]);
+        assert_eq!(lines("ab aaaaaa", Wrap::Unicode, max_width), vec!["ab", "aaa", "aaa"]);
+    }
 }
@@ -0,0 +1,221 @@
+//! Layout of rich text, made up of spans that each have their own fonts and size.
+//!
+//! Lines are broken the same way as text in a single font, then each line is made tall enough
+//! for the largest span on it, with the spans on a line sharing a baseline.
+
+use std::f32;
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use rusttype::{self, Scale};
+use types::{Align, Rect, RectExt, Size};
+use types::Range as AxisRange;
+use super::line::{LineInfo, LineInfos};
+use super::{FontFallback, PositionedGlyph, Wrap};
+
+/// A range of the text, laid out with it's own fonts and size.
+#[derive(Copy, Clone)]
+pub struct Span<'a> {
+    /// The start of the span, as a byte index into the text. Each span ends where the next one
+    /// starts, the last span ends at the end of the text.
+    pub start: usize,
+    pub fonts: FontFallback<'a>,
+    pub font_size: f32,
+}
+
+impl<'a> Span<'a> {
+    pub fn new(start: usize, fonts: FontFallback<'a>, font_size: f32) -> Self {
+        Span {
+            start: start,
+            fonts: fonts,
+            font_size: font_size,
+        }
+    }
+}
+
+/// A line of rich text, positioned within the text's bounds.
+#[derive(Clone, Debug)]
+pub struct RichLine {
+    pub info: LineInfo,
+    /// The bounds of the line, the height of the largest span on it.
+    pub rect: Rect,
+    /// The y position the glyphs of each span on the line are drawn on.
+    pub baseline: f32,
+}
+
+/// A glyph of rich text, along with the index of the span it's in and the index of the font
+/// in the span's fonts it's from.
+#[derive(Clone)]
+pub struct SpanGlyph {
+    pub span: usize,
+    pub font: usize,
+    pub glyph: PositionedGlyph,
+}
+
+/// The part of a line covered by a span, used to draw the span's background and underline.
+#[derive(Copy, Clone, Debug)]
+pub struct SpanRect {
+    pub span: usize,
+    pub rect: Rect,
+    pub baseline: f32,
+}
+
+/// The index of the span containing the char at `byte`. Panics if `spans` is empty.
+pub fn span_index(spans: &[Span], byte: usize) -> usize {
+    spans.iter().rposition(|span| span.start <= byte).unwrap_or(0)
+}
+
+/// The size of the text made up of `spans`, laid out with no maximum width.
+pub fn get_rich_text_size(text: &str, spans: &[Span], wrap: Wrap) -> Size {
+    let line_infos: Vec<LineInfo> = LineInfos::from_spans(text, spans, wrap, f32::MAX).collect();
+    let width = line_infos.iter().fold(0.0, |max, line_info| f32::max(max, line_info.width));
+    let height: f32 = line_infos.iter().map(|line_info| line_height(text, spans, line_info)).sum();
+    Size::new(width, height)
+}
+
+/// The height of the text made up of `spans`, wrapped to `width`.
+pub fn get_rich_text_height(text: &str, spans: &[Span], wrap: Wrap, width: f32) -> f32 {
+    LineInfos::from_spans(text, spans, wrap, width).map(|line_info| line_height(text, spans, &line_info)).sum()
+}
+
+/// Break the text made up of `spans` into lines and position them within `rect`.
+pub fn get_rich_lines(text: &str, spans: &[Span], rect: Rect, wrap: Wrap, align: Align) -> Vec<RichLine> {
+    let mut top = rect.top();
+    LineInfos::from_spans(text, spans, wrap, rect.width()).map(|info| {
+        let (ascent, descent, line_gap) = line_metrics(text, spans, &info);
+        let x = AxisRange::new(0.0, info.width);
+        let x = match align {
+            Align::Start => x.align_start_of(rect.x_range()),
+            Align::Middle => x.align_middle_of(rect.x_range()),
+            Align::End => x.align_end_of(rect.x_range()),
+        };
+        let y = AxisRange::new(top, top + ascent - descent + line_gap);
+        top = y.end;
+        RichLine {
+            info: info,
+            rect: Rect::from_ranges(x, y),
+            baseline: y.start + ascent,
+        }
+    }).collect()
+}
+
+/// Position the glyphs of `lines`, laid out from the text made up of `spans`.
+pub fn get_rich_positioned_glyphs(spans: &[Span], lines: &[RichLine]) -> Vec<SpanGlyph> {
+    let mut glyphs = Vec::new();
+    for line in lines {
+        layout_line(spans, line, |span, font, glyph| {
+            glyphs.push(SpanGlyph {
+                span: span,
+                font: font,
+                glyph: glyph.standalone(),
+            });
+        });
+    }
+    glyphs
+}
+
+/// The rects covered by each span on each of `lines`, laid out from the text made up of `spans`,
+/// from left to right on each line. In mixed left to right and right to left text, the clusters
+/// of a span needn't be next to each other, so a span can cover several rects on a line.
+pub fn get_span_rects(spans: &[Span], lines: &[RichLine]) -> Vec<SpanRect> {
+    let mut span_rects: Vec<SpanRect> = Vec::new();
+    for line in lines {
+        let mut clusters: Vec<(usize, AxisRange)> = line.info.clusters.iter().map(|cluster| {
+            let left = line.rect.left() + cluster.x;
+            (span_index(spans, cluster.byte), AxisRange::new(left, left + cluster.width))
+        }).collect();
+        clusters.sort_by(|a, b| a.1.start.partial_cmp(&b.1.start).unwrap_or(Ordering::Equal));
+        let first_rect = span_rects.len();
+        for (span, x) in clusters {
+            if span_rects.len() > first_rect {
+                let last = span_rects.last_mut().unwrap();
+                // join clusters of the same span that are next to each other, allowing for
+                // rounding between their positions
+                if last.span == span && x.start - last.rect.right() < 0.01 {
+                    let right = f32::max(last.rect.right(), x.end);
+                    last.rect = Rect::from_ranges(AxisRange::new(last.rect.left(), right), line.rect.y_range());
+                    continue;
+                }
+            }
+            span_rects.push(SpanRect {
+                span: span,
+                rect: Rect::from_ranges(x, line.rect.y_range()),
+                baseline: line.baseline,
+            });
+        }
+    }
+    span_rects
+}
+
+/// Call `f` with each glyph of `line`, positioned on it's baseline, along with the index of it's
+/// span and the index of it's font.
+fn layout_line<'a, F>(spans: &[Span<'a>], line: &RichLine, mut f: F)
+    where F: FnMut(usize, usize, rusttype::PositionedGlyph<'a>)
+{
+    let left = line.rect.left();
+    for glyph in &line.info.glyphs {
+        let span = &spans[glyph.span];
+        let positioned = span.fonts.font(glyph.font)
+            .glyph(glyph.id)
+            .unwrap()
+            .scaled(Scale::uniform(span.font_size))
+            .positioned(rusttype::point(left + glyph.x, line.baseline + glyph.y));
+        f(glyph.span, glyph.font, positioned);
+    }
+}
+
+/// The largest ascent, descent and line gap of the spans on a line, or of the span the line
+/// starts in if it's empty.
+fn line_metrics(text: &str, spans: &[Span], line_info: &LineInfo) -> (f32, f32, f32) {
+    let first = span_index(spans, line_info.start_byte);
+    let last = if line_info.end_byte() > line_info.start_byte {
+        span_index(spans, last_char_byte(text, line_info))
+    } else {
+        first
+    };
+    spans[first..last + 1].iter().fold((0.0, 0.0, 0.0), |(ascent, descent, line_gap), span| {
+        let v_metrics = span.fonts.first().v_metrics(Scale::uniform(span.font_size));
+        (f32::max(ascent, v_metrics.ascent),
+         f32::min(descent, v_metrics.descent),
+         f32::max(line_gap, v_metrics.line_gap))
+    })
+}
+
+fn line_height(text: &str, spans: &[Span], line_info: &LineInfo) -> f32 {
+    let (ascent, descent, line_gap) = line_metrics(text, spans, line_info);
+    ascent - descent + line_gap
+}
+
+/// The byte index of the last char in a non empty line.
+fn last_char_byte(text: &str, line_info: &LineInfo) -> usize {
+    let Range { start, end } = line_info.byte_range();
+    text[start..end].char_indices().last().map_or(start, |(byte, _)| start + byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fallback::LoadedFont;
+    use types::Point;
+
+    #[test]
+    fn span_rects_mixed_direction() {
+        let font = LoadedFont::new(include_bytes!("../../assets/fonts/NotoSans/NotoSans-Regular.ttf").to_vec()).unwrap();
+        let fonts = [font.layout_font()];
+        let fonts = FontFallback::new(&fonts);
+        // the second span starts at the second Hebrew char, the Hebrew is reversed on screen so
+        // each span covers two rects
+        let text = "ab \u{5d0}\u{5d1} cd";
+        let spans = [Span::new(0, fonts, 20.0), Span::new(5, fonts, 20.0)];
+        let rect = Rect::new(Point::new(0.0, 0.0), Size::new(1000.0, 100.0));
+        let lines = get_rich_lines(text, &spans, rect, Wrap::NoWrap, Align::Start);
+        let span_rects = get_span_rects(&spans, &lines);
+        let order: Vec<usize> = span_rects.iter().map(|span_rect| span_rect.span).collect();
+        assert_eq!(order, vec![0, 1, 0, 1]);
+        for pair in span_rects.windows(2) {
+            assert!(pair[0].rect.right() <= pair[1].rect.left() + 0.01);
+        }
+        let width: f32 = span_rects.iter().map(|span_rect| span_rect.rect.width()).sum();
+        assert!((width - lines[0].info.width).abs() < 0.01);
+    }
+}
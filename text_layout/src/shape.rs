@@ -0,0 +1,330 @@
+//! Shaping text into positioned glyphs, so ligatures, marks and the contextual forms of
+//! scripts like Arabic and Devanagari are drawn correctly.
+//!
+//! Text is split into runs of chars drawn with the same font and bidi embedding level, each run
+//! is shaped with `rustybuzz`. Shaping groups chars into clusters, chars that are drawn together
+//! by one or more glyphs, the cursor is placed between clusters, never inside one.
+//!
+//! Embedding levels are resolved with the Unicode bidi algorithm over the paragraph the text is
+//! in, then the clusters of each line are reordered for display, so right to left text, like
+//! Hebrew or Arabic, can be mixed with left to right text.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use rusttype::GlyphId;
+use rustybuzz::{self, UnicodeBuffer, Direction};
+use unicode_bidi::BidiInfo;
+
+use super::FontFallback;
+use super::line::is_mandatory_break;
+use super::rich::{self, Span};
+
+/// A glyph positioned by shaping.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShapedGlyph {
+    /// The index of the span the glyph is in, 0 for text in a single style.
+    pub span: usize,
+    /// The index of the glyph's font in the span's `FontFallback`.
+    pub font: usize,
+    pub id: GlyphId,
+    /// The byte index of the first char of the glyph's cluster.
+    pub cluster: usize,
+    /// The position of the glyph's origin, relative to the start of the shaped text and it's
+    /// baseline, with y pointing down.
+    pub x: f32,
+    pub y: f32,
+    pub advance: f32,
+}
+
+/// Chars that are drawn together by one or more glyphs.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cluster {
+    /// The byte index of the cluster's first char.
+    pub byte: usize,
+    pub len_bytes: usize,
+    /// The char index of the cluster's first char.
+    pub char: usize,
+    pub len_chars: usize,
+    /// The left edge of the cluster in visual order, relative to the start of it's line once
+    /// the text is broken into lines, otherwise to the start of the shaped text.
+    pub x: f32,
+    pub width: f32,
+    /// True if the cluster starts with whitespace, lines are wrapped at whitespace clusters.
+    pub whitespace: bool,
+    /// The bidi embedding level of the cluster, odd levels are right to left.
+    pub level: u8,
+}
+
+impl Cluster {
+    /// True if the cluster was shaped right to left, it's glyphs are in visual order.
+    pub fn rtl(&self) -> bool {
+        self.level % 2 == 1
+    }
+    /// The byte range of the cluster's chars.
+    pub fn byte_range(&self) -> Range<usize> {
+        self.byte..self.byte + self.len_bytes
+    }
+    /// The char range of the cluster's chars.
+    pub fn char_range(&self) -> Range<usize> {
+        self.char..self.char + self.len_chars
+    }
+    /// The x position of a caret placed before the cluster, in logical order.
+    pub fn leading_x(&self) -> f32 {
+        if self.rtl() { self.x + self.width } else { self.x }
+    }
+    /// The x position of a caret placed after the cluster, in logical order.
+    pub fn trailing_x(&self) -> f32 {
+        if self.rtl() { self.x } else { self.x + self.width }
+    }
+}
+
+/// The glyphs and clusters of a range of shaped text.
+#[derive(Clone, Debug, Default)]
+pub struct ShapedText {
+    /// The glyphs, in the order their runs were shaped.
+    pub glyphs: Vec<ShapedGlyph>,
+    /// The clusters in logical order.
+    pub clusters: Vec<Cluster>,
+    pub width: f32,
+    /// The base embedding level of the paragraph the text is in, 1 if it's first strong char is
+    /// right to left.
+    pub level: u8,
+}
+
+/// The fonts and sizes used to shape text.
+#[derive(Copy, Clone)]
+pub enum Measure<'a> {
+    /// All chars are in the same fonts and size.
+    Uniform(FontFallback<'a>, f32),
+    /// Each char is in the fonts and size of the span it's in.
+    Spans(&'a [Span<'a>]),
+}
+
+/// Shape `text`, all in the same fonts and size.
+pub fn shape_text(text: &str, fonts: FontFallback, font_size: f32) -> ShapedText {
+    shape(text, 0..text.len(), 0, Measure::Uniform(fonts, font_size))
+}
+
+/// Shape the chars in the byte range `range` of `text`, which must not contain line breaks,
+/// laid out in visual order as a single line. `start_char` is the char index of the start of the
+/// range, the glyphs and clusters have byte and char indices into the whole text.
+pub fn shape(text: &str, range: Range<usize>, start_char: usize, measure: Measure) -> ShapedText {
+    let (paragraph_start, bidi_info) = paragraph_bidi_info(text, range.clone());
+    let level_at = |byte: usize| bidi_info.levels[byte - paragraph_start].number();
+    let mut glyphs = Vec::new();
+    // the left edge, width and level of each cluster, by the byte it starts at
+    let mut clusters: BTreeMap<usize, (f32, f32, u8)> = BTreeMap::new();
+    let mut pen = 0.0;
+    for (span, span_range) in span_ranges(range.clone(), measure) {
+        let (fonts, font_size) = match measure {
+            Measure::Uniform(fonts, font_size) => (fonts, font_size),
+            Measure::Spans(spans) => (spans[span].fonts, spans[span].font_size),
+        };
+        for (font, run) in font_runs(text, span_range, fonts, &level_at) {
+            let level = level_at(run.start);
+            let face = fonts.face(font);
+            let mut buffer = UnicodeBuffer::new();
+            buffer.push_str(&text[run.clone()]);
+            buffer.guess_segment_properties();
+            buffer.set_direction(if level % 2 == 1 { Direction::RightToLeft } else { Direction::LeftToRight });
+            let output = rustybuzz::shape(face, &[], buffer);
+            let scale = font_size / f32::from(face.units_per_em());
+            for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+                let cluster = run.start + info.cluster as usize;
+                let advance = position.x_advance as f32 * scale;
+                glyphs.push(ShapedGlyph {
+                    span: span,
+                    font: font,
+                    id: GlyphId(info.glyph_id),
+                    cluster: cluster,
+                    x: pen + position.x_offset as f32 * scale,
+                    y: -position.y_offset as f32 * scale,
+                    advance: advance,
+                });
+                let entry = clusters.entry(cluster).or_insert((pen, 0.0, level));
+                entry.0 = entry.0.min(pen);
+                entry.1 += advance;
+                pen += advance;
+            }
+        }
+    }
+    let starts: Vec<usize> = clusters.keys().cloned().collect();
+    let mut char_index = start_char;
+    let mut clusters: Vec<Cluster> = starts.iter().enumerate().map(|(i, &byte)| {
+        let end = starts.get(i + 1).cloned().unwrap_or(range.end);
+        let (x, width, level) = clusters[&byte];
+        let chars = &text[byte..end];
+        let cluster = Cluster {
+            byte: byte,
+            len_bytes: end - byte,
+            char: char_index,
+            len_chars: chars.chars().count(),
+            x: x,
+            width: width,
+            whitespace: chars.chars().next().map_or(false, char::is_whitespace),
+            level: level,
+        };
+        char_index += cluster.len_chars;
+        cluster
+    }).collect();
+    // move each glyph along with it's cluster
+    let shaped_x: Vec<f32> = clusters.iter().map(|cluster| cluster.x).collect();
+    let level = bidi_info.paragraphs.first().map_or(0, |paragraph| paragraph.level.number());
+    reorder(&mut clusters, level);
+    for glyph in &mut glyphs {
+        let i = starts.binary_search(&glyph.cluster).unwrap();
+        glyph.x += clusters[i].x - shaped_x[i];
+    }
+    ShapedText {
+        glyphs: glyphs,
+        clusters: clusters,
+        width: pen,
+        level: level,
+    }
+}
+
+/// Position the clusters of a line from left to right in visual order, reversing the runs of
+/// right to left clusters, and runs nested within them, as in rules L1 and L2 of the Unicode
+/// bidi algorithm. Whitespace at the end of the line is given the paragraph's `level`, so it
+/// stays at the end the line starts from.
+pub fn reorder(clusters: &mut [Cluster], level: u8) {
+    for cluster in clusters.iter_mut().rev().take_while(|cluster| cluster.whitespace) {
+        cluster.level = level;
+    }
+    let mut order: Vec<usize> = (0..clusters.len()).collect();
+    let max_level = clusters.iter().map(|cluster| cluster.level).max().unwrap_or(0);
+    let min_odd_level = clusters.iter().map(|cluster| cluster.level).filter(|level| level % 2 == 1).min();
+    if let Some(min_odd_level) = min_odd_level {
+        // from the highest level down to the lowest odd level, reverse every run of clusters at
+        // that level or higher
+        for run_level in (min_odd_level..max_level + 1).rev() {
+            let mut i = 0;
+            while i < order.len() {
+                let start = i;
+                while i < order.len() && clusters[order[i]].level >= run_level {
+                    i += 1;
+                }
+                order[start..i].reverse();
+                i += 1;
+            }
+        }
+    }
+    let mut x = 0.0;
+    for i in order {
+        clusters[i].x = x;
+        x += clusters[i].width;
+    }
+}
+
+/// The byte index of the start of the paragraph containing `range`, the text between mandatory
+/// line breaks, along with the resolved bidi embedding levels of the paragraph.
+fn paragraph_bidi_info(text: &str, range: Range<usize>) -> (usize, BidiInfo) {
+    let start = text[..range.start].char_indices().rev().find(|&(_, ch)| is_mandatory_break(ch))
+        .map_or(0, |(newline, ch)| newline + ch.len_utf8());
+    let end = text[range.end..].char_indices().find(|&(_, ch)| is_mandatory_break(ch))
+        .map_or(text.len(), |(newline, _)| range.end + newline);
+    (start, BidiInfo::new(&text[start..end], None))
+}
+
+/// Split `range` into the ranges of each span it covers, along with the span's index.
+fn span_ranges(range: Range<usize>, measure: Measure) -> Vec<(usize, Range<usize>)> {
+    match measure {
+        Measure::Uniform(..) => vec![(0, range)],
+        Measure::Spans(spans) => {
+            let mut ranges = Vec::new();
+            let mut start = range.start;
+            while start < range.end {
+                let span = rich::span_index(spans, start);
+                let end = spans.get(span + 1).map_or(range.end, |next| next.start.min(range.end));
+                ranges.push((span, start..end));
+                start = end;
+            }
+            ranges
+        }
+    }
+}
+
+/// Split `range` into runs of chars drawn with the same font from `fonts` and at the same bidi
+/// embedding level, along with the font's index. Chars that aren't letters or digits, like
+/// marks, joiners, variation selectors and spaces, stay in the current run if it's font has
+/// glyphs for them, so they're shaped with the chars they're attached to.
+fn font_runs<L>(text: &str, range: Range<usize>, fonts: FontFallback, level_at: &L) -> Vec<(usize, Range<usize>)>
+    where L: Fn(usize) -> u8
+{
+    let mut runs: Vec<(usize, Range<usize>)> = Vec::new();
+    for (byte, ch) in text[range.clone()].char_indices() {
+        let byte = range.start + byte;
+        let (font, _) = fonts.glyph(ch);
+        if let Some(&mut (run_font, ref mut run)) = runs.last_mut() {
+            let same_font = font == run_font || (!ch.is_alphanumeric() && fonts.has_glyph(run_font, ch));
+            if same_font && level_at(byte) == level_at(run.start) {
+                run.end = byte + ch.len_utf8();
+                continue;
+            }
+        }
+        runs.push((font, byte..byte + ch.len_utf8()));
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clusters(levels: &[u8], whitespace: &[usize]) -> Vec<Cluster> {
+        levels.iter().enumerate().map(|(i, &level)| Cluster {
+            byte: i,
+            len_bytes: 1,
+            char: i,
+            len_chars: 1,
+            x: 0.0,
+            width: 1.0,
+            whitespace: whitespace.contains(&i),
+            level: level,
+        }).collect()
+    }
+
+    /// The indices of the clusters from left to right.
+    fn visual(clusters: &[Cluster]) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..clusters.len()).collect();
+        order.sort_by(|&a, &b| clusters[a].x.partial_cmp(&clusters[b].x).unwrap());
+        order
+    }
+
+    #[test]
+    fn reorder_left_to_right() {
+        let mut line = clusters(&[0, 0, 0], &[]);
+        reorder(&mut line, 0);
+        assert_eq!(visual(&line), vec![0, 1, 2]);
+        let xs: Vec<f32> = line.iter().map(|cluster| cluster.x).collect();
+        assert_eq!(xs, vec![0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn reorder_right_to_left_run() {
+        let mut line = clusters(&[0, 0, 1, 1, 1, 0], &[1]);
+        reorder(&mut line, 0);
+        assert_eq!(visual(&line), vec![0, 1, 4, 3, 2, 5]);
+    }
+
+    #[test]
+    fn reorder_nested_runs() {
+        // numbers in right to left text stay left to right
+        let mut line = clusters(&[1, 1, 2, 2, 1], &[1]);
+        reorder(&mut line, 1);
+        assert_eq!(visual(&line), vec![4, 2, 3, 1, 0]);
+    }
+
+    #[test]
+    fn reorder_trailing_whitespace() {
+        // whitespace at the end of a line stays at the end the paragraph starts from
+        let mut line = clusters(&[1, 1, 1], &[2]);
+        reorder(&mut line, 0);
+        assert_eq!(visual(&line), vec![1, 0, 2]);
+        let mut line = clusters(&[2, 2, 2], &[2]);
+        reorder(&mut line, 1);
+        assert_eq!(visual(&line), vec![2, 0, 1]);
+        assert_eq!(line[2].level, 1);
+    }
+}